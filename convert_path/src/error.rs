@@ -1,11 +1,15 @@
 use std::error::Error;
 use std::fmt::{self, Display, Formatter};
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 
 #[derive(Debug, Ord, PartialOrd, Eq, PartialEq)]
 pub enum PathConvertError {
     InvalidUtf8Path(PathBuf),
     InvalidPath(PathBuf),
+    InvalidName(PathBuf, String),
+
+    /// The name has no letters or digits, so it has no words to be converted by.
+    Wordless(PathBuf),
 }
 
 impl Display for PathConvertError {
@@ -17,14 +21,18 @@ impl Display for PathConvertError {
             PathConvertError::InvalidPath(path) => {
                 write!(f, "paths must container either a stem or a path or both: '{}'", path.to_string_lossy())
             }
+            PathConvertError::InvalidName(path, reason) => {
+                write!(f, "converted name '{}' {}", path.to_string_lossy(), reason)
+            }
+            PathConvertError::Wordless(path) => {
+                write!(f, "name '{}' has no letters or digits to convert", path.to_string_lossy())
+            }
         }
     }
 }
 
 impl Error for PathConvertError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
-        match self {
-            _ => None,
-        }
+        None
     }
 }
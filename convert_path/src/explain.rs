@@ -0,0 +1,311 @@
+//! Explains how a single name is converted: the words found in it, why it was split
+//! between each of them, and which rules decided the result.
+//!
+//! The words are found the same way as by `convert_case`, which does not expose them.
+
+use std::ffi::OsStr;
+use std::fmt::{self, Display, Formatter};
+use std::path::Path;
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::error::PathConvertError;
+use crate::stem::{self, Extensions};
+use crate::{convert_component_with, is_trivially_conforming, recase, Convention};
+
+/// Why a name was split before a word.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Boundary {
+    /// A separator between the words, which is dropped.
+    Separator(char),
+
+    /// A lowercase letter followed by an uppercase one, like 'someFile'.
+    LowerUpper,
+
+    /// An acronym followed by a capitalized word, like 'HTMLFile'.
+    Acronym,
+
+    /// A digit next to a letter, like 'file2'.
+    Digit,
+}
+
+impl Display for Boundary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Boundary::Separator(c) => write!(f, "separator {:?}", c),
+            Boundary::LowerUpper => write!(f, "lower to upper case"),
+            Boundary::Acronym => write!(f, "end of acronym"),
+            Boundary::Digit => write!(f, "digit"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Word {
+    pub text: String,
+
+    /// Why the name was split before this word, which is only missing for the first.
+    pub boundary: Option<Boundary>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Explanation {
+    /// The words of the stem, which are all that is converted.
+    pub words: Vec<Word>,
+
+    /// Every rule beyond splitting and joining the words which affected the result.
+    pub rules: Vec<String>,
+
+    pub converted: String,
+}
+
+/// Explain how `component` is converted from `from_convention` to `to_convention`, keeping
+/// the extension recognized by `extensions`.
+pub fn explain_component(
+    component: &OsStr,
+    from_convention: Option<Convention>,
+    to_convention: Convention,
+    extensions: &Extensions,
+) -> Result<Explanation, PathConvertError> {
+    let converted = convert_component_with(component, from_convention, to_convention, extensions)?;
+
+    // a successful conversion means the name is valid utf-8
+    let path: &Path = component.as_ref();
+    let original = path.to_str().unwrap();
+    let (stem, ext) = stem::split(original, extensions);
+    let stem = stem.unwrap_or_default();
+
+    let mut rules = vec![];
+
+    let normalized: String = stem.nfc().collect();
+    if normalized != stem {
+        rules.push(String::from(
+            "the stem was composed into NFC before splitting",
+        ));
+    }
+
+    if let Some(from_convention) = from_convention.filter(|from| *from != Convention::Keep) {
+        rules.push(format!(
+            "words were only split as {} words are",
+            from_convention.name()
+        ));
+    }
+
+    if let Some(ext) = ext {
+        rules.push(format!("the extension '.{}' was kept as is", ext));
+    }
+
+    if to_convention == Convention::Keep {
+        rules.push(String::from("the case of the stem was kept as is"));
+    } else if from_convention.is_none()
+        && !stem.is_empty()
+        && is_trivially_conforming(stem, to_convention)
+    {
+        rules.push(format!(
+            "the stem already conforms to {}, so it was kept as is",
+            to_convention.name()
+        ));
+    } else if converted == original
+        && join(&normalized, ext, from_convention, to_convention) != original
+    {
+        rules.push(String::from(
+            "the result only differs in its normalization form, so the name was kept as is",
+        ));
+    }
+
+    Ok(Explanation {
+        words: words(&normalized, from_convention),
+        rules,
+        converted,
+    })
+}
+
+/// The name `stem` would be given before checking whether it changed at all.
+fn join(
+    stem: &str,
+    ext: Option<&str>,
+    from_convention: Option<Convention>,
+    to_convention: Convention,
+) -> String {
+    let new_stem = recase(stem, from_convention, to_convention);
+
+    match ext {
+        Some(ext) => format!("{}.{}", new_stem, ext),
+        None => new_stem,
+    }
+}
+
+/// Split `stem` into words like `convert_case` does, given its convention if it is known.
+fn words(stem: &str, from_convention: Option<Convention>) -> Vec<Word> {
+    let mut words = vec![];
+
+    match from_convention {
+        None | Some(Convention::Keep) => split_separated(stem, "-_ ", true, &mut words),
+        Some(Convention::TitleCase) => split_separated(stem, " \t\n\x0c\r", false, &mut words),
+        Some(Convention::KebabCase) => split_separated(stem, "-", false, &mut words),
+        Some(Convention::SnakeCase) | Some(Convention::UpperSnakeCase) => {
+            split_separated(stem, "_", false, &mut words)
+        }
+        Some(Convention::CamelCase) | Some(Convention::UpperCamelCase) => {
+            split_camel(stem, None, &mut words)
+        }
+        Some(Convention::FlatCase) | Some(Convention::UpperFlatCase) => words.push(Word {
+            text: String::from(stem),
+            boundary: None,
+        }),
+    }
+
+    words
+}
+
+/// Split `stem` on any of `separators`, and on changes in case when `camel` is true.
+fn split_separated(stem: &str, separators: &str, camel: bool, words: &mut Vec<Word>) {
+    let mut boundary = None;
+    let mut rest = stem;
+
+    loop {
+        let end = rest.find(|c| separators.contains(c)).unwrap_or(rest.len());
+        let piece = &rest[..end];
+
+        if camel {
+            split_camel(piece, boundary.take(), words);
+        } else if !piece.is_empty() {
+            words.push(Word {
+                text: String::from(piece),
+                boundary: boundary.take(),
+            });
+        }
+
+        match rest[end..].chars().next() {
+            Some(separator) => {
+                // separators before the first word, or repeated ones, only split once
+                if !words.is_empty() {
+                    boundary = Some(Boundary::Separator(separator));
+                }
+
+                rest = &rest[end + separator.len_utf8()..];
+            }
+            None => break,
+        }
+    }
+}
+
+/// Split `name` on changes in case and between digits and letters.
+fn split_camel(name: &str, boundary: Option<Boundary>, words: &mut Vec<Word>) {
+    let chars: Vec<(usize, char)> = name.char_indices().collect();
+    let mut splits = vec![];
+
+    for window in chars.windows(3) {
+        let (first, second, third) = (window[0].1, window[1].1, window[2].1);
+
+        if first.is_uppercase() && second.is_uppercase() && third.is_lowercase() {
+            splits.push((window[1].0, Boundary::Acronym));
+        } else if let Some(boundary) = two_char_boundary(first, second) {
+            splits.push((window[1].0, boundary));
+        }
+    }
+
+    if let [.., (_, first), (i, second)] = chars[..] {
+        if let Some(boundary) = two_char_boundary(first, second) {
+            splits.push((i, boundary));
+        }
+    }
+
+    let mut start = 0;
+    let mut boundary = boundary;
+
+    for (end, next) in splits {
+        words.push(Word {
+            text: String::from(&name[start..end]),
+            boundary: boundary.take(),
+        });
+
+        start = end;
+        boundary = Some(next);
+    }
+
+    if start < name.len() {
+        words.push(Word {
+            text: String::from(&name[start..]),
+            boundary,
+        });
+    }
+}
+
+fn two_char_boundary(first: char, second: char) -> Option<Boundary> {
+    let is_digit_or_punct = |c: char| c.is_ascii_digit() || c.is_ascii_punctuation();
+
+    if first.is_lowercase() && second.is_uppercase() {
+        Some(Boundary::LowerUpper)
+    } else if (first.is_ascii_digit() && !is_digit_or_punct(second))
+        || (!is_digit_or_punct(first) && second.is_ascii_digit())
+    {
+        Some(Boundary::Digit)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::OsStr;
+
+    use convert_case::{Case, Casing};
+
+    use crate::explain::{explain_component, words, Boundary, Word};
+    use crate::stem::Extensions;
+    use crate::Convention;
+
+    #[test]
+    fn test_words_agree_with_conversion() {
+        let stems = [
+            "Some File",
+            "someHTMLFile2",
+            "__some--file  name__",
+            "file2go",
+            "ABC",
+            "a1B2c3",
+            "Already_snake_Case",
+        ];
+
+        for stem in stems.iter() {
+            let found: Vec<String> = words(stem, None)
+                .into_iter()
+                .map(|word| word.text.to_lowercase())
+                .collect();
+
+            assert_eq!(stem.to_case(Case::Lower), found.join(" "), "{}", stem);
+        }
+    }
+
+    #[test]
+    fn test_explain_component() {
+        let explanation = explain_component(
+            OsStr::new("some HTMLFile2.txt"),
+            None,
+            Convention::SnakeCase,
+            &Extensions::LastDot,
+        )
+        .unwrap();
+
+        let word = |text: &str, boundary| Word {
+            text: String::from(text),
+            boundary,
+        };
+
+        assert_eq!(
+            vec![
+                word("some", None),
+                word("HTML", Some(Boundary::Separator(' '))),
+                word("File", Some(Boundary::Acronym)),
+                word("2", Some(Boundary::Digit)),
+            ],
+            explanation.words
+        );
+        assert_eq!(
+            vec!["the extension '.txt' was kept as is"],
+            explanation.rules
+        );
+        assert_eq!("some_html_file_2.txt", explanation.converted);
+    }
+}
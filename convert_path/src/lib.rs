@@ -1,12 +1,22 @@
 pub mod error;
+pub mod explain;
+pub mod plan;
+pub mod plan_file;
+pub mod sanitize;
+pub mod stem;
+pub mod template;
+pub mod transform;
+pub mod walk;
 
 use std::convert::TryFrom;
 use std::ffi::OsStr;
 use std::path::{Component, Path, PathBuf};
 
 use convert_case::{Case, Casing};
+use unicode_normalization::UnicodeNormalization;
 
 use crate::error::PathConvertError;
+use crate::stem::Extensions;
 
 /// Describes the supported file naming conventions.
 ///
@@ -44,19 +54,40 @@ pub enum Convention {
 
     /// In kebab case words are separated by underscores (ie '-') and all are lowercase.
     KebabCase,
+
+    /// Not a convention at all, every name keeps the case it is already written in so that
+    /// only the other adjustments to a name, like sanitizing it, are made.
+    Keep,
 }
 
-impl From<Convention> for Case {
-    fn from(convention: Convention) -> Self {
-        match convention {
-            Convention::TitleCase => Case::Title,
-            Convention::FlatCase => Case::Flat,
-            Convention::UpperFlatCase => Case::UpperFlat,
-            Convention::CamelCase => Case::Camel,
-            Convention::UpperCamelCase => Case::UpperCamel,
-            Convention::SnakeCase => Case::Snake,
-            Convention::UpperSnakeCase => Case::UpperSnake,
-            Convention::KebabCase => Case::Kebab,
+impl Convention {
+    /// The case names are converted into, or none if they keep their case.
+    fn case(self) -> Option<Case> {
+        match self {
+            Convention::TitleCase => Some(Case::Title),
+            Convention::FlatCase => Some(Case::Flat),
+            Convention::UpperFlatCase => Some(Case::UpperFlat),
+            Convention::CamelCase => Some(Case::Camel),
+            Convention::UpperCamelCase => Some(Case::UpperCamel),
+            Convention::SnakeCase => Some(Case::Snake),
+            Convention::UpperSnakeCase => Some(Case::UpperSnake),
+            Convention::KebabCase => Some(Case::Kebab),
+            Convention::Keep => None,
+        }
+    }
+
+    /// The name the convention is parsed from, like 'snake' or 'CAMEL'.
+    pub fn name(self) -> &'static str {
+        match self {
+            Convention::TitleCase => "title",
+            Convention::FlatCase => "flat",
+            Convention::UpperFlatCase => "FLAT",
+            Convention::CamelCase => "camel",
+            Convention::UpperCamelCase => "CAMEL",
+            Convention::SnakeCase => "snake",
+            Convention::UpperSnakeCase => "SNAKE",
+            Convention::KebabCase => "kebab",
+            Convention::Keep => "keep",
         }
     }
 }
@@ -74,6 +105,7 @@ impl TryFrom<&str> for Convention {
             "snake" => Ok(Convention::SnakeCase),
             "SNAKE" => Ok(Convention::UpperSnakeCase),
             "kebab" => Ok(Convention::KebabCase),
+            "keep" => Ok(Convention::Keep),
             _ => Err(format!(
                 "Unsupported naming convention '{}'",
                 <str as AsRef<str>>::as_ref(s)
@@ -82,47 +114,265 @@ impl TryFrom<&str> for Convention {
     }
 }
 
+/// Every convention, in the order they are listed to users.
+pub const CONVENTIONS: [Convention; 9] = [
+    Convention::TitleCase,
+    Convention::FlatCase,
+    Convention::UpperFlatCase,
+    Convention::CamelCase,
+    Convention::UpperCamelCase,
+    Convention::SnakeCase,
+    Convention::UpperSnakeCase,
+    Convention::KebabCase,
+    Convention::Keep,
+];
+
+/// Convert `name` into every convention, in the order of [`CONVENTIONS`], to show what each
+/// of them looks like.
+///
+/// # Examples
+/// ```
+/// # use convert_path::Convention;
+///
+/// # fn main() {
+/// let examples = convert_path::examples("some file.txt").unwrap();
+///
+/// assert_eq!((Convention::TitleCase, String::from("Some File.txt")), examples[0]);
+/// assert_eq!((Convention::SnakeCase, String::from("some_file.txt")), examples[5]);
+/// # }
+/// ```
+pub fn examples(name: &str) -> Result<Vec<(Convention, String)>, PathConvertError> {
+    CONVENTIONS
+        .iter()
+        .map(|convention| {
+            convert_component(OsStr::new(name), None, *convention)
+                .map(|converted| (*convention, converted))
+        })
+        .collect()
+}
+
+/// Whether every byte of `word` is an ascii letter accepted by `is_letter`, or every byte is a digit.
+fn is_word(word: &[u8], is_letter: fn(&u8) -> bool) -> bool {
+    !word.is_empty() && (word.iter().all(is_letter) || word.iter().all(u8::is_ascii_digit))
+}
+
+/// Whether `stem` is written as `separator` delimited words of letters accepted by `is_letter` or digits.
+fn is_separated(stem: &str, separator: u8, is_letter: fn(&u8) -> bool) -> bool {
+    stem.as_bytes()
+        .split(|b| *b == separator)
+        .all(|word| is_word(word, is_letter))
+}
+
+/// Whether `stem` is written as camel case words of only letters, starting with a letter
+/// accepted by `is_first`.
+fn is_camel(stem: &str, is_first: fn(&u8) -> bool) -> bool {
+    let bytes = stem.as_bytes();
+
+    bytes.first().is_some_and(is_first)
+        && bytes.iter().all(u8::is_ascii_alphabetic)
+        && !bytes
+            .windows(2)
+            .any(|pair| pair[0].is_ascii_uppercase() && pair[1].is_ascii_uppercase())
+}
+
+/// Whether converting `stem` to `convention` would certainly leave it unchanged, checked
+/// without allocating.
+///
+/// Only plain ascii names are recognized, anything else returns false so that the caller
+/// falls back to a full conversion.
+fn is_trivially_conforming(stem: &str, convention: Convention) -> bool {
+    match convention {
+        Convention::TitleCase => stem.as_bytes().split(|b| *b == b' ').all(|word| {
+            word.split_first().is_some_and(|(first, rest)| {
+                first.is_ascii_uppercase() && rest.iter().all(u8::is_ascii_lowercase)
+            })
+        }),
+        Convention::FlatCase => stem
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit()),
+        Convention::UpperFlatCase => stem
+            .bytes()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit()),
+        Convention::CamelCase => is_camel(stem, u8::is_ascii_lowercase),
+        Convention::UpperCamelCase => is_camel(stem, u8::is_ascii_uppercase),
+        Convention::SnakeCase => is_separated(stem, b'_', u8::is_ascii_lowercase),
+        Convention::UpperSnakeCase => is_separated(stem, b'_', u8::is_ascii_uppercase),
+        Convention::KebabCase => is_separated(stem, b'-', u8::is_ascii_lowercase),
+        Convention::Keep => true,
+    }
+}
+
+/// Convert `stem` into `to_convention`, splitting it into words as `from_convention` does
+/// if it is known.
+fn recase(stem: &str, from_convention: Option<Convention>, to_convention: Convention) -> String {
+    let to_case = match to_convention.case() {
+        Some(to_case) => to_case,
+        None => return String::from(stem),
+    };
+
+    match from_convention.and_then(Convention::case) {
+        Some(from_case) => stem.from_case(from_case).to_case(to_case),
+        None => stem.to_case(to_case),
+    }
+}
+
+/// The order conventions are detected in, so that names written in several of them are
+/// attributed to the simplest, ie 'file' is flat case rather than snake case.
+const DETECTION_ORDER: [Convention; 8] = [
+    Convention::FlatCase,
+    Convention::UpperFlatCase,
+    Convention::SnakeCase,
+    Convention::UpperSnakeCase,
+    Convention::KebabCase,
+    Convention::CamelCase,
+    Convention::UpperCamelCase,
+    Convention::TitleCase,
+];
+
+/// Detect the convention the basename of a path is written in, ignoring its extension.
+///
+/// Only plain ascii names are recognized, anything else is not considered to be written in
+/// any convention.
+///
+/// # Examples
+/// ```
+/// # use convert_path::Convention;
+///
+/// # fn main() {
+/// assert_eq!(Some(Convention::KebabCase), convert_path::detect_convention("dir/some-file.txt"));
+/// assert_eq!(Some(Convention::FlatCase), convert_path::detect_convention("file"));
+/// assert_eq!(None, convert_path::detect_convention("some_File"));
+/// # }
+/// ```
+pub fn detect_convention<P: AsRef<Path>>(path: P) -> Option<Convention> {
+    let stem = path.as_ref().file_stem()?.to_str()?;
+
+    if stem.is_empty() {
+        return None;
+    }
+
+    DETECTION_ORDER
+        .iter()
+        .copied()
+        .find(|convention| is_trivially_conforming(stem, *convention))
+}
+
+/// Every convention the basename of a path is written in, ignoring its extension, in the
+/// order they are detected. Names of a single word are often written in several at once.
+///
+/// # Examples
+/// ```
+/// # use convert_path::Convention;
+///
+/// # fn main() {
+/// assert_eq!(
+///     vec![Convention::UpperCamelCase, Convention::TitleCase],
+///     convert_path::conforming_conventions("File.txt")
+/// );
+/// assert!(convert_path::conforming_conventions("some_File").is_empty());
+/// # }
+/// ```
+pub fn conforming_conventions<P: AsRef<Path>>(path: P) -> Vec<Convention> {
+    let stem = match path.as_ref().file_stem().and_then(OsStr::to_str) {
+        Some(stem) if !stem.is_empty() => stem,
+        _ => return vec![],
+    };
+
+    DETECTION_ORDER
+        .iter()
+        .copied()
+        .filter(|convention| is_trivially_conforming(stem, *convention))
+        .collect()
+}
+
 /// Convert a component of a path into the desired case.
 fn convert_component(
     component: &OsStr,
     from_convention: Option<Convention>,
     to_convention: Convention,
+) -> Result<String, PathConvertError> {
+    convert_component_with(
+        component,
+        from_convention,
+        to_convention,
+        &Extensions::LastDot,
+    )
+}
+
+/// Convert a component of a path into the desired case, keeping the extension recognized
+/// by `extensions` as is.
+fn convert_component_with(
+    component: &OsStr,
+    from_convention: Option<Convention>,
+    to_convention: Convention,
+    extensions: &Extensions,
 ) -> Result<String, PathConvertError> {
     let path: &Path = component.as_ref();
 
-    // allow remaining code to safely call 'OsStr::toStr' without checks for valid utf-8
-    if path.to_str().is_none() {
-        return Err(PathConvertError::InvalidUtf8Path(path.to_path_buf()));
-    }
+    let original = match path.to_str() {
+        Some(original) => original,
+        None => return Err(PathConvertError::InvalidUtf8Path(path.to_path_buf())),
+    };
 
-    let stem = path.file_stem();
-    let ext = path.extension();
+    // the alternate data stream of a file is named as is, so is never mangled into its name
+    let (original, stream) = stem::split_stream(original);
+
+    convert_name(original, path, from_convention, to_convention, extensions)
+        .map(|converted| converted + stream)
+}
+
+/// Convert the name `original` of the component `path`.
+fn convert_name(
+    original: &str,
+    path: &Path,
+    from_convention: Option<Convention>,
+    to_convention: Convention,
+    extensions: &Extensions,
+) -> Result<String, PathConvertError> {
+    let (stem, ext) = stem::split(original, extensions);
 
     if stem.is_none() && ext.is_none() {
         Err(PathConvertError::InvalidPath(path.to_path_buf()))
     } else if stem.is_none() {
-        Ok(String::from(ext.unwrap().to_str().unwrap()))
+        Ok(String::from(ext.unwrap()))
     } else {
-        let new_stem = if from_convention.is_some() {
-            stem.unwrap()
-                .to_str()
-                .unwrap()
-                .from_case(from_convention.unwrap().into())
-                .to_case(to_convention.into())
+        let stem = stem.unwrap();
+
+        // skip converting, and allocating for, names which are already conforming
+        if from_convention.is_none()
+            && !stem.is_empty()
+            && is_trivially_conforming(stem, to_convention)
+        {
+            return Ok(String::from(original));
+        }
+
+        // decomposed characters (ie from HFS+) would otherwise be split into separate words
+        let stem: String = if stem.is_ascii() {
+            String::from(stem)
         } else {
-            stem.unwrap()
-                .to_str()
-                .unwrap()
-                .to_case(to_convention.into())
+            stem.nfc().collect()
         };
 
-        match ext {
-            Some(ext) => Ok(String::from(format!(
-                "{}.{}",
-                new_stem,
-                ext.to_str().unwrap()
-            ))),
-            None => Ok(new_stem),
+        let new_stem = recase(&stem, from_convention, to_convention);
+
+        let converted = match ext {
+            Some(ext) => {
+                let mut converted = new_stem;
+                converted.reserve(ext.len() + 1);
+                converted.push('.');
+                converted.push_str(ext);
+
+                converted
+            }
+            None => new_stem,
+        };
+
+        // names differing only by their normalization form are the same name to the user and
+        // to normalization insensitive filesystems, so don't report them as a change
+        if converted.nfc().eq(original.nfc()) {
+            Ok(String::from(original))
+        } else {
+            Ok(converted)
         }
     }
 }
@@ -150,21 +400,53 @@ pub fn convert_basename<P: AsRef<Path>>(
     path: P,
     from_convention: Option<Convention>,
     to_convention: Convention,
+) -> Result<PathBuf, PathConvertError> {
+    convert_basename_with(path, from_convention, to_convention, &Extensions::LastDot)
+}
+
+/// The same as `convert_basename`, except keeping the extension recognized by `extensions`.
+///
+/// # Examples
+/// ```
+/// # use std::path::PathBuf;
+/// # use convert_path::stem::Extensions;
+/// # use convert_path::Convention;
+///
+/// # fn main() {
+/// assert_eq!(
+///     Ok(PathBuf::from("notes/v_1.2.3_release_notes")),
+///     convert_path::convert_basename_with(
+///         "notes/v1.2.3 Release Notes",
+///         None,
+///         Convention::SnakeCase,
+///         &Extensions::Short,
+///     )
+/// );
+/// # }
+/// ```
+pub fn convert_basename_with<P: AsRef<Path>>(
+    path: P,
+    from_convention: Option<Convention>,
+    to_convention: Convention,
+    extensions: &Extensions,
 ) -> Result<PathBuf, PathConvertError> {
     let parent = path.as_ref().parent();
     let basename = path.as_ref().file_name();
 
-    // if the path is either the root path or '..'
-    if parent.is_none() || basename.is_none() {
-        Ok(path.as_ref().to_path_buf())
-    } else {
-        let base = convert_component(basename.unwrap(), from_convention, to_convention)?;
+    match (parent, basename) {
+        (Some(parent), Some(basename)) => {
+            let base =
+                convert_component_with(basename, from_convention, to_convention, extensions)?;
 
-        let mut path = path.as_ref().to_path_buf();
-        path.pop();
-        path.push(base);
+            // the parent is rebuilt from its components so that it is written with the
+            // platform separator, even if given mixing '/' and '\' on Windows
+            let mut path: PathBuf = parent.components().collect();
+            path.push(base);
 
-        Ok(path)
+            Ok(path)
+        }
+        // if the path is either the root path or '..'
+        _ => Ok(path.as_ref().to_path_buf()),
     }
 }
 
@@ -191,6 +473,16 @@ pub fn convert_full<P: AsRef<Path>>(
     path: P,
     from_convention: Option<Convention>,
     to_convention: Convention,
+) -> Result<PathBuf, PathConvertError> {
+    convert_full_with(path, from_convention, to_convention, &Extensions::LastDot)
+}
+
+/// The same as `convert_full`, except keeping the extensions recognized by `extensions`.
+pub fn convert_full_with<P: AsRef<Path>>(
+    path: P,
+    from_convention: Option<Convention>,
+    to_convention: Convention,
+    extensions: &Extensions,
 ) -> Result<PathBuf, PathConvertError> {
     let mut converted_path: PathBuf = PathBuf::new();
 
@@ -198,7 +490,7 @@ pub fn convert_full<P: AsRef<Path>>(
         match component {
             Component::Normal(path) => {
                 let converted_component: String =
-                    convert_component(path, from_convention, to_convention)?;
+                    convert_component_with(path, from_convention, to_convention, extensions)?;
 
                 converted_path.push(converted_component);
             }
@@ -209,6 +501,55 @@ pub fn convert_full<P: AsRef<Path>>(
     Ok(converted_path)
 }
 
+/// The same as `convert_full_with`, except components which cannot be converted, like those
+/// which are not valid utf-8, are kept as they are rather than failing the whole path. The
+/// error of every kept component is returned along with the path.
+///
+/// # Examples
+/// ```
+/// # use std::path::PathBuf;
+/// # use convert_path::stem::Extensions;
+/// # use convert_path::Convention;
+///
+/// # fn main() {
+/// let (converted, errors) = convert_path::convert_full_keeping_invalid_with(
+///     "Some Dir/Some File.txt",
+///     None,
+///     Convention::SnakeCase,
+///     &Extensions::LastDot,
+/// );
+///
+/// assert_eq!(PathBuf::from("some_dir/some_file.txt"), converted);
+/// assert!(errors.is_empty());
+/// # }
+/// ```
+pub fn convert_full_keeping_invalid_with<P: AsRef<Path>>(
+    path: P,
+    from_convention: Option<Convention>,
+    to_convention: Convention,
+    extensions: &Extensions,
+) -> (PathBuf, Vec<PathConvertError>) {
+    let mut converted_path: PathBuf = PathBuf::new();
+    let mut errors = vec![];
+
+    for component in path.as_ref().components() {
+        match component {
+            Component::Normal(path) => {
+                match convert_component_with(path, from_convention, to_convention, extensions) {
+                    Ok(converted_component) => converted_path.push(converted_component),
+                    Err(err) => {
+                        errors.push(err);
+                        converted_path.push(path);
+                    }
+                }
+            }
+            _ => converted_path.push(component),
+        }
+    }
+
+    (converted_path, errors)
+}
+
 /// The same as convert_full except ignoring the given prefix when converting.
 ///
 /// If the prefix is not present in teh given path, the result is the same as
@@ -243,23 +584,38 @@ pub fn convert_full_except_prefix<P: AsRef<Path>, Q: AsRef<Path>>(
     from_convention: Option<Convention>,
     to_convention: Convention,
 ) -> Result<PathBuf, PathConvertError>  {
+    convert_full_except_prefix_with(
+        path,
+        prefix,
+        from_convention,
+        to_convention,
+        &Extensions::LastDot,
+    )
+}
+
+/// The same as `convert_full_except_prefix`, except keeping the extensions recognized by
+/// `extensions`.
+pub fn convert_full_except_prefix_with<P: AsRef<Path>, Q: AsRef<Path>>(
+    path: P,
+    prefix: Q,
+    from_convention: Option<Convention>,
+    to_convention: Convention,
+    extensions: &Extensions,
+) -> Result<PathBuf, PathConvertError> {
     let prefix = prefix.as_ref();
     let base = path.as_ref();
 
     if base.starts_with(prefix) {
-        let new_base = convert_full(
+        let new_base = convert_full_with(
             base.strip_prefix(prefix).unwrap(),
             from_convention,
             to_convention,
-        );
+            extensions,
+        )?;
 
-        if new_base.is_ok() {
-            Ok(prefix.join(new_base.unwrap()))
-        } else {
-            new_base
-        }
+        Ok(prefix.join(new_base))
     } else {
-        convert_full(base, from_convention, to_convention)
+        convert_full_with(base, from_convention, to_convention, extensions)
     }
 }
 
@@ -270,8 +626,47 @@ mod test {
 
     use crate::{
         Convention, convert_basename, convert_component, convert_full, convert_full_except_prefix,
+        convert_full_keeping_invalid_with, is_trivially_conforming, recase,
     };
 
+    #[test]
+    fn test_is_trivially_conforming_agrees_with_conversion() {
+        let alphabet = ['a', 'b', 'A', 'B', '1', '_', '-', ' '];
+        let conventions = [
+            Convention::TitleCase,
+            Convention::FlatCase,
+            Convention::UpperFlatCase,
+            Convention::CamelCase,
+            Convention::UpperCamelCase,
+            Convention::SnakeCase,
+            Convention::UpperSnakeCase,
+            Convention::KebabCase,
+            Convention::Keep,
+        ];
+
+        let mut stems = vec![String::new()];
+        let mut checked = 0;
+
+        for _ in 0..4 {
+            stems = stems
+                .iter()
+                .flat_map(|stem| alphabet.iter().map(move |c| format!("{}{}", stem, c)))
+                .collect();
+
+            for stem in &stems {
+                for convention in conventions {
+                    if is_trivially_conforming(stem, convention) {
+                        assert_eq!(*stem, recase(stem, None, convention), "{:?}", convention);
+                        checked += 1;
+                    }
+                }
+            }
+        }
+
+        // make sure the fast path is actually taken
+        assert!(checked > 100);
+    }
+
     #[test]
     fn test_convert_component_kebab_to_snake_no_from_case() {
         let expected = Ok(String::from("some_file.jpg"));
@@ -294,6 +689,46 @@ mod test {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn test_convert_component_decomposed_already_conforming() {
+        let component = "cafe\u{301}_file.txt";
+
+        let actual = convert_component(OsStr::new(component), None, Convention::SnakeCase);
+
+        assert_eq!(Ok(String::from(component)), actual);
+    }
+
+    #[test]
+    fn test_convert_component_keep() {
+        let component = "Some file-NAME.txt";
+
+        assert_eq!(
+            Ok(String::from(component)),
+            convert_component(OsStr::new(component), None, Convention::Keep)
+        );
+        assert_eq!(
+            Ok(String::from(component)),
+            convert_component(
+                OsStr::new(component),
+                Some(Convention::SnakeCase),
+                Convention::Keep
+            )
+        );
+    }
+
+    #[test]
+    fn test_convert_component_decomposed_to_snake() {
+        let expected = Ok(String::from("caf\u{e9}_file.txt"));
+
+        let actual = convert_component(
+            OsStr::new("Cafe\u{301} File.txt"),
+            None,
+            Convention::SnakeCase,
+        );
+
+        assert_eq!(expected, actual);
+    }
+
     #[test]
     fn test_convert_basename_title_to_camel_no_from_case() {
         let expected = Ok(PathBuf::from("/An Absolute/Path To/someFile.jpg"));
@@ -346,6 +781,35 @@ mod test {
         assert_eq!(expected, actual)
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_convert_full_keeping_invalid() {
+        use std::os::unix::ffi::OsStrExt;
+
+        use crate::error::PathConvertError;
+        use crate::stem::Extensions;
+
+        let invalid = OsStr::from_bytes(b"Bad \xff Dir");
+        let path = Path::new("Some Dir").join(invalid).join("Some File.txt");
+
+        let (converted, errors) = convert_full_keeping_invalid_with(
+            &path,
+            None,
+            Convention::SnakeCase,
+            &Extensions::LastDot,
+        );
+
+        assert_eq!(
+            Path::new("some_dir").join(invalid).join("some_file.txt"),
+            converted
+        );
+        assert_eq!(
+            vec![PathConvertError::InvalidUtf8Path(PathBuf::from(invalid))],
+            errors
+        );
+        assert!(convert_full(&path, None, Convention::SnakeCase).is_err());
+    }
+
     #[test]
     fn test_convert_except_prefix_no_prefix_match() {
         let expected = Ok(PathBuf::from("/SOME_PATH/PREFIX/AND_A/CHILD"));
@@ -373,4 +837,44 @@ mod test {
 
         assert_eq!(expected, actual);
     }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_convert_drive_relative() {
+        // a drive-relative path is relative to the current directory of its drive
+        let path = Path::new(r"C:Some Folder\Some File.txt");
+
+        assert_eq!(
+            Ok(PathBuf::from(r"C:some_folder\some_file.txt")),
+            convert_full(path, None, Convention::SnakeCase)
+        );
+        assert_eq!(
+            Ok(PathBuf::from(r"C:Some Folder\some_file.txt")),
+            convert_basename(path, None, Convention::SnakeCase)
+        );
+        assert_eq!(
+            Ok(PathBuf::from("C:some_file.txt")),
+            convert_basename("C:Some File.txt", None, Convention::SnakeCase)
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_convert_mixed_separators() {
+        let path = Path::new(r"C:\Some Folder/Other Folder\Some File.txt");
+
+        // both are written with the platform separator rather than the given ones
+        assert_eq!(
+            r"C:\some_folder\other_folder\some_file.txt",
+            convert_full(path, None, Convention::SnakeCase)
+                .unwrap()
+                .as_os_str()
+        );
+        assert_eq!(
+            r"C:\Some Folder\Other Folder\some_file.txt",
+            convert_basename(path, None, Convention::SnakeCase)
+                .unwrap()
+                .as_os_str()
+        );
+    }
 }
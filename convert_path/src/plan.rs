@@ -0,0 +1,452 @@
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs;
+use std::ops::AddAssign;
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+/// A single planned rename from `source` to `target`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Rename {
+    pub source: PathBuf,
+    pub target: PathBuf,
+}
+
+impl Rename {
+    /// Whether the rename would leave the path unchanged.
+    pub fn is_noop(&self) -> bool {
+        self.source == self.target
+    }
+}
+
+/// Describes several distinct sources which would all be renamed to the same target.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Collision {
+    pub target: PathBuf,
+    pub sources: Vec<PathBuf>,
+}
+
+/// What performing a plan would do to the filesystem, estimated from it as it is before any
+/// of the renames are performed.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct Impact {
+    /// The number of files, including symlinks, which would be renamed.
+    pub files: usize,
+
+    /// The number of directories which would be renamed.
+    pub dirs: usize,
+
+    /// The number of directories which would be created to hold a target.
+    pub dirs_created: usize,
+
+    /// The number of directories which every entry would be moved out of, which are removed
+    /// when cleaning up empty directories.
+    pub dirs_emptied: usize,
+
+    /// The number of renames whose target is on another device than their source, so they
+    /// must be copied and then removed.
+    pub cross_device: usize,
+
+    /// The number of bytes copied by the renames across devices.
+    pub bytes_copied: u64,
+}
+
+impl AddAssign for Impact {
+    fn add_assign(&mut self, other: Impact) {
+        self.files += other.files;
+        self.dirs += other.dirs;
+        self.dirs_created += other.dirs_created;
+        self.dirs_emptied += other.dirs_emptied;
+        self.cross_device += other.cross_device;
+        self.bytes_copied += other.bytes_copied;
+    }
+}
+
+/// An ordered batch of renames which can be inspected before any of them are performed.
+///
+/// # Examples
+/// ```
+/// # use std::path::PathBuf;
+/// # use convert_path::plan::RenamePlan;
+///
+/// # fn main() {
+/// let mut plan = RenamePlan::new();
+///
+/// plan.push(PathBuf::from("Some File"), PathBuf::from("some_file"));
+/// plan.push(PathBuf::from("Some-File"), PathBuf::from("some_file"));
+///
+/// let collisions = plan.collisions();
+///
+/// assert_eq!(1, collisions.len());
+/// assert_eq!(PathBuf::from("some_file"), collisions[0].target);
+/// # }
+/// ```
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct RenamePlan {
+    renames: Vec<Rename>,
+}
+
+impl RenamePlan {
+    pub fn new() -> RenamePlan {
+        RenamePlan::default()
+    }
+
+    /// Add a rename to the end of the plan.
+    pub fn push(&mut self, source: PathBuf, target: PathBuf) {
+        self.renames.push(Rename { source, target });
+    }
+
+    pub fn renames(&self) -> &[Rename] {
+        &self.renames
+    }
+
+    pub fn len(&self) -> usize {
+        self.renames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.renames.is_empty()
+    }
+
+    /// Only keep the renames for which `f` returns true, preserving their order.
+    pub fn retain<F: FnMut(&Rename) -> bool>(&mut self, f: F) {
+        self.renames.retain(f);
+    }
+
+    /// Find every target which more than one distinct source would be renamed to.
+    ///
+    /// Collisions are returned in the order their targets first appear in the
+    /// plan, and the sources of each collision are kept in plan order.
+    pub fn collisions(&self) -> Vec<Collision> {
+        self.find_collisions(false)
+    }
+
+    /// Find every target which more than one distinct source would be renamed to on a
+    /// filesystem which does not distinguish names by case, like FAT.
+    ///
+    /// The target of each collision is the first of its targets in the plan.
+    pub fn collisions_ignoring_case(&self) -> Vec<Collision> {
+        self.find_collisions(true)
+    }
+
+    /// Estimate what performing the plan would do, looking at the filesystem as it is now.
+    ///
+    /// Renames leaving their path unchanged, and those whose source does not exist, have no
+    /// impact.
+    pub fn impact(&self) -> Impact {
+        let mut impact = Impact::default();
+        let sources: HashSet<&Path> = self.renames.iter().map(|r| r.source.as_path()).collect();
+        let targets: HashSet<&Path> = self.renames.iter().map(|r| r.target.as_path()).collect();
+        let mut created: HashSet<&Path> = HashSet::new();
+        let mut moved_out: HashMap<PathBuf, HashSet<OsString>> = HashMap::new();
+
+        for rename in self.renames.iter().filter(|rename| !rename.is_noop()) {
+            let metadata = match fs::symlink_metadata(&rename.source) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+
+            if metadata.is_dir() {
+                impact.dirs += 1;
+            } else {
+                impact.files += 1;
+            }
+
+            // only the directories up to the closest which exists, or is a target itself, are
+            // created for it
+            for dir in rename.target.ancestors().skip(1).map(or_cwd) {
+                if targets.contains(dir) || fs::symlink_metadata(dir).is_ok() {
+                    break;
+                }
+
+                created.insert(dir);
+            }
+
+            let source_dir = parent_or_cwd(&rename.source);
+
+            // a directory renamed itself is moved rather than emptied
+            if source_dir != parent_or_cwd(&rename.target) && !sources.contains(source_dir) {
+                if let Some(name) = rename.source.file_name() {
+                    moved_out
+                        .entry(source_dir.to_path_buf())
+                        .or_default()
+                        .insert(name.to_os_string());
+                }
+            }
+
+            if is_cross_device(&rename.source, &rename.target) {
+                impact.cross_device += 1;
+                impact.bytes_copied += if metadata.is_dir() {
+                    dir_size(&rename.source)
+                } else {
+                    metadata.len()
+                };
+            }
+        }
+
+        impact.dirs_created = created.len();
+        impact.dirs_emptied = count_emptied(moved_out, &sources);
+
+        impact
+    }
+
+    fn find_collisions(&self, ignore_case: bool) -> Vec<Collision> {
+        let mut order: Vec<PathBuf> = vec![];
+        let mut by_target: HashMap<PathBuf, (&Path, Vec<&Path>)> = HashMap::new();
+
+        for rename in &self.renames {
+            let key = if ignore_case {
+                fold_case(&rename.target)
+            } else {
+                rename.target.clone()
+            };
+
+            let (_, sources) = by_target.entry(key).or_insert_with_key(|key| {
+                order.push(key.clone());
+                (rename.target.as_path(), vec![])
+            });
+
+            if !sources.contains(&rename.source.as_path()) {
+                sources.push(rename.source.as_path());
+            }
+        }
+
+        order
+            .into_iter()
+            .filter_map(|key| {
+                let (target, sources) = by_target.remove(&key)?;
+
+                if sources.len() > 1 {
+                    Some(Collision {
+                        target: target.to_path_buf(),
+                        sources: sources.into_iter().map(Path::to_path_buf).collect(),
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+/// The working directory for the empty path `dir` is the parent of a bare name, otherwise
+/// `dir` itself.
+fn or_cwd(dir: &Path) -> &Path {
+    if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    }
+}
+
+/// The directory holding `path`, which is the working directory for a bare name.
+fn parent_or_cwd(path: &Path) -> &Path {
+    or_cwd(path.parent().unwrap_or_else(|| Path::new("")))
+}
+
+/// Count the directories which every entry would be moved out of, given the names moved out
+/// of each directory, including those only holding directories which would be emptied.
+///
+/// The working directory is never counted, nor are directories which are renamed themselves.
+fn count_emptied(
+    mut moved_out: HashMap<PathBuf, HashSet<OsString>>,
+    sources: &HashSet<&Path>,
+) -> usize {
+    // the deepest directories are looked at first, so that emptying one counts as moving it
+    // out of its parent before the parent is looked at
+    let mut queue: BinaryHeap<(usize, PathBuf)> = moved_out
+        .keys()
+        .map(|dir| (dir.components().count(), dir.clone()))
+        .collect();
+    let mut emptied = 0;
+
+    while let Some((_, dir)) = queue.pop() {
+        let moved = match moved_out.remove(&dir) {
+            Some(moved) if dir != Path::new(".") => moved,
+            _ => continue,
+        };
+
+        let is_emptied = match fs::read_dir(&dir) {
+            Ok(entries) => entries
+                .map(|entry| entry.map(|entry| moved.contains(&entry.file_name())))
+                .all(|moved| moved.unwrap_or(false)),
+            Err(_) => false,
+        };
+
+        if !is_emptied {
+            continue;
+        }
+
+        emptied += 1;
+
+        let parent = parent_or_cwd(&dir);
+
+        if let Some(name) = dir.file_name().filter(|_| !sources.contains(parent)) {
+            let names = moved_out.entry(parent.to_path_buf()).or_insert_with(|| {
+                queue.push((parent.components().count(), parent.to_path_buf()));
+                HashSet::new()
+            });
+
+            names.insert(name.to_os_string());
+        }
+    }
+
+    emptied
+}
+
+/// Whether `target` would be on another device than `source`, judged by the closest of its
+/// ancestors which exists.
+#[cfg(unix)]
+fn is_cross_device(source: &Path, target: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    let device = |path: &Path| fs::symlink_metadata(path).map(|metadata| metadata.dev());
+
+    let target_device = target
+        .ancestors()
+        .skip(1)
+        .map(or_cwd)
+        .find_map(|dir| device(dir).ok());
+
+    match (device(source), target_device) {
+        (Ok(source), Some(target)) => source != target,
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_cross_device(_: &Path, _: &Path) -> bool {
+    false
+}
+
+/// The total size of the files under `dir`.
+fn dir_size(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| !entry.file_type().is_dir())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// The path naming the same entry as `path` on a filesystem which does not distinguish names
+/// by case.
+pub fn fold_case(path: &Path) -> PathBuf {
+    PathBuf::from(path.to_string_lossy().to_lowercase())
+}
+
+impl<'a> IntoIterator for &'a RenamePlan {
+    type Item = &'a Rename;
+    type IntoIter = std::slice::Iter<'a, Rename>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.renames.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::plan::{Collision, Impact, RenamePlan};
+
+    #[test]
+    fn test_collisions_none() {
+        let mut plan = RenamePlan::new();
+
+        plan.push(PathBuf::from("Some File"), PathBuf::from("some_file"));
+        plan.push(PathBuf::from("Other File"), PathBuf::from("other_file"));
+
+        assert!(plan.collisions().is_empty());
+    }
+
+    #[test]
+    fn test_collisions_two_sources_one_target() {
+        let mut plan = RenamePlan::new();
+
+        plan.push(PathBuf::from("Some File"), PathBuf::from("some_file"));
+        plan.push(PathBuf::from("Other File"), PathBuf::from("other_file"));
+        plan.push(PathBuf::from("Some-File"), PathBuf::from("some_file"));
+
+        let expected = vec![Collision {
+            target: PathBuf::from("some_file"),
+            sources: vec![PathBuf::from("Some File"), PathBuf::from("Some-File")],
+        }];
+
+        assert_eq!(expected, plan.collisions());
+    }
+
+    #[test]
+    fn test_collisions_repeated_source() {
+        let mut plan = RenamePlan::new();
+
+        plan.push(PathBuf::from("Some File"), PathBuf::from("some_file"));
+        plan.push(PathBuf::from("Some File"), PathBuf::from("some_file"));
+
+        assert!(plan.collisions().is_empty());
+    }
+
+    #[test]
+    fn test_collisions_ignoring_case() {
+        let mut plan = RenamePlan::new();
+
+        plan.push(PathBuf::from("Some File"), PathBuf::from("Some_File"));
+        plan.push(PathBuf::from("Other File"), PathBuf::from("other_file"));
+        plan.push(PathBuf::from("some file"), PathBuf::from("some_file"));
+
+        let expected = vec![Collision {
+            target: PathBuf::from("Some_File"),
+            sources: vec![PathBuf::from("Some File"), PathBuf::from("some file")],
+        }];
+
+        assert!(plan.collisions().is_empty());
+        assert_eq!(expected, plan.collisions_ignoring_case());
+    }
+
+    #[test]
+    fn test_impact() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = |path: &str| dir.path().join(path);
+
+        fs::create_dir_all(path("Old Dir/Sub"))?;
+        fs::create_dir_all(path("Keep"))?;
+        fs::create_dir_all(path("Some Dir"))?;
+
+        for file in [
+            "Old Dir/Sub/a",
+            "Old Dir/b",
+            "Keep/c",
+            "Keep/d",
+            "Some Dir/e",
+        ] {
+            fs::write(path(file), "")?;
+        }
+
+        let mut plan = RenamePlan::new();
+
+        plan.push(path("Old Dir/Sub/a"), path("new/deep/a"));
+        plan.push(path("Old Dir/b"), path("new/b"));
+        plan.push(path("Keep/c"), path("new/c"));
+        plan.push(path("Keep/d"), path("Keep/d"));
+        plan.push(path("Some Dir"), path("some_dir"));
+        plan.push(path("Some Dir/e"), path("some_dir/E"));
+        plan.push(path("Missing"), path("missing"));
+
+        // 'Keep' still holds 'd', and 'Some Dir' is renamed rather than emptied
+        let expected = Impact {
+            files: 4,
+            dirs: 1,
+            dirs_created: 2,
+            dirs_emptied: 2,
+            cross_device: 0,
+            bytes_copied: 0,
+        };
+
+        assert_eq!(expected, plan.impact());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,467 @@
+//! A versioned file format for plans, so a plan can be reviewed, stored, and performed
+//! later, possibly by a newer version of the tool which made it.
+//!
+//! Plan files are TOML, starting with the version of the format and the tool which wrote
+//! them, followed by every rename along with the kind of its source and a hash of the
+//! state the source was in when the plan was made:
+//!
+//! ```toml
+//! version = 1
+//! tool = "ccpath 0.1.0"
+//!
+//! [[renames]]
+//! source = "Some File.txt"
+//! target = "some_file.txt"
+//! kind = "file"
+//! sha256 = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+//! ```
+//!
+//! The version is only raised for changes older readers can't read correctly, so any
+//! version of the tool can read every plan of a version up to its own. Fields added without
+//! raising it are optional and ignored by older readers, as are kinds they don't know.
+//!
+//! The same document may also be written as JSON, for tools which read JSON rather than
+//! TOML, with each rename on a line of its own so plans still diff well under version
+//! control:
+//!
+//! ```json
+//! {"version": 1, "tool": "ccpath 0.1.0", "renames": [
+//! {"source": "Some File.txt", "target": "some_file.txt", "kind": "file", "sha256": "e3b0..."}
+//! ]}
+//! ```
+
+use std::convert::TryFrom;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::plan::{Rename, RenamePlan};
+use crate::walk::EntryType;
+
+/// The version of the format written, and the newest which can be read.
+pub const PLAN_FILE_VERSION: u32 = 1;
+
+/// A rename along with the state its source was in when it was planned.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PlannedRename {
+    pub rename: Rename,
+
+    /// The kind of the source, or none if it did not exist or is of a kind not recorded.
+    pub kind: Option<EntryType>,
+
+    /// The hex sha256 of the content of a file source or of the target of a symlink source,
+    /// directories have none.
+    pub sha256: Option<String>,
+}
+
+impl PlannedRename {
+    /// Record the current state of the source of `rename`.
+    pub fn record(rename: Rename) -> io::Result<PlannedRename> {
+        let (kind, sha256) = state(&rename.source)?;
+
+        Ok(PlannedRename {
+            rename,
+            kind,
+            sha256,
+        })
+    }
+
+    /// Whether the source is still in the state it was planned in.
+    pub fn is_unchanged(&self) -> bool {
+        state(&self.rename.source).is_ok_and(|state| state == (self.kind, self.sha256.clone()))
+    }
+}
+
+/// The kind of the entry at `path` and the hash of its state.
+fn state(path: &Path) -> io::Result<(Option<EntryType>, Option<String>)> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok((None, None)),
+        Err(err) => return Err(err),
+    };
+
+    let mut hasher = Sha256::new();
+
+    let kind = if metadata.file_type().is_symlink() {
+        let target = fs::read_link(path)?;
+        hasher.update(target.to_string_lossy().as_bytes());
+
+        EntryType::Symlink
+    } else if metadata.is_dir() {
+        return Ok((Some(EntryType::Dir), None));
+    } else if metadata.is_file() {
+        io::copy(&mut File::open(path)?, &mut hasher)?;
+
+        EntryType::File
+    } else {
+        return Ok((None, None));
+    };
+
+    let hash = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+
+    Ok((Some(kind), Some(hash)))
+}
+
+/// A whole plan file, as read back.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PlanFile {
+    /// The version of the format the plan was written in.
+    pub version: u32,
+
+    /// The name and version of the tool which wrote the plan, like 'ccpath 0.1.0'.
+    pub tool: String,
+
+    pub renames: Vec<PlannedRename>,
+}
+
+impl PlanFile {
+    /// Read a plan file, failing if it is of a newer version than can be read.
+    pub fn load<R: Read>(mut input: R) -> io::Result<PlanFile> {
+        let mut content = String::new();
+        input.read_to_string(&mut content)?;
+
+        // the version is checked alone first, since a newer plan may not parse as this one
+        let version: Version = parse(&content)?;
+
+        if version.version > PLAN_FILE_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "the plan is of version {}, but only up to version {} can be read",
+                    version.version, PLAN_FILE_VERSION
+                ),
+            ));
+        }
+
+        let document: Document = parse(&content)?;
+
+        let renames = document
+            .renames
+            .into_iter()
+            .map(|entry| PlannedRename {
+                rename: Rename {
+                    source: PathBuf::from(entry.source),
+                    target: PathBuf::from(entry.target),
+                },
+                kind: entry
+                    .kind
+                    .and_then(|kind| EntryType::try_from(kind.as_str()).ok()),
+                sha256: entry.sha256,
+            })
+            .collect();
+
+        Ok(PlanFile {
+            version: document.version,
+            tool: document.tool,
+            renames,
+        })
+    }
+
+    /// Write the plan in the current version of the format.
+    pub fn save<W: Write>(&self, out: W) -> io::Result<()> {
+        let mut writer = PlanWriter::new(out, &self.tool)?;
+
+        for planned in &self.renames {
+            writer.write(planned)?;
+        }
+
+        writer.finish().map(|_| ())
+    }
+
+    /// Write the plan as JSON in the current version of the format.
+    pub fn save_json<W: Write>(&self, out: W) -> io::Result<()> {
+        let mut writer = PlanWriter::json(out, &self.tool)?;
+
+        for planned in &self.renames {
+            writer.write(planned)?;
+        }
+
+        writer.finish().map(|_| ())
+    }
+
+    /// The renames of the plan, without the state of their sources.
+    pub fn plan(&self) -> RenamePlan {
+        let mut plan = RenamePlan::new();
+
+        for planned in &self.renames {
+            plan.push(planned.rename.source.clone(), planned.rename.target.clone());
+        }
+
+        plan
+    }
+}
+
+/// Writes a plan file one rename at a time, so that plans too large to be held in memory
+/// can be written.
+///
+/// # Examples
+/// ```
+/// # use std::path::PathBuf;
+/// # use convert_path::plan::Rename;
+/// # use convert_path::plan_file::{PlanFile, PlanWriter, PlannedRename};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut written = vec![];
+/// let mut writer = PlanWriter::new(&mut written, "ccpath 0.1.0")?;
+///
+/// writer.write(&PlannedRename::record(Rename {
+///     source: PathBuf::from("Some File"),
+///     target: PathBuf::from("some_file"),
+/// })?)?;
+///
+/// writer.finish()?;
+///
+/// let plan = PlanFile::load(written.as_slice())?;
+///
+/// assert_eq!("ccpath 0.1.0", plan.tool);
+/// assert_eq!(PathBuf::from("some_file"), plan.renames[0].rename.target);
+/// # Ok(())
+/// # }
+/// ```
+pub struct PlanWriter<W: Write> {
+    out: W,
+    is_json: bool,
+    written: usize,
+}
+
+impl<W: Write> PlanWriter<W> {
+    /// Start a plan file written by `tool`.
+    pub fn new(mut out: W, tool: &str) -> io::Result<PlanWriter<W>> {
+        let header = Header {
+            version: PLAN_FILE_VERSION,
+            tool: String::from(tool),
+        };
+
+        write!(out, "{}", to_toml(&header)?)?;
+
+        Ok(PlanWriter {
+            out,
+            is_json: false,
+            written: 0,
+        })
+    }
+
+    /// Start a plan file written by `tool` as JSON.
+    pub fn json(mut out: W, tool: &str) -> io::Result<PlanWriter<W>> {
+        write!(
+            out,
+            "{{\"version\": {}, \"tool\": {}, \"renames\": [",
+            PLAN_FILE_VERSION,
+            to_json(&tool)?
+        )?;
+
+        Ok(PlanWriter {
+            out,
+            is_json: true,
+            written: 0,
+        })
+    }
+
+    /// Add a rename to the plan file, failing if either of its paths is not valid utf-8.
+    pub fn write(&mut self, planned: &PlannedRename) -> io::Result<()> {
+        let utf8 = |path: &Path| {
+            path.to_str().map(String::from).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("'{}' is not valid utf-8", path.display()),
+                )
+            })
+        };
+
+        let entry = Entry {
+            source: utf8(&planned.rename.source)?,
+            target: utf8(&planned.rename.target)?,
+            kind: planned.kind.map(|kind| String::from(kind.name())),
+            sha256: planned.sha256.clone(),
+        };
+
+        let separator = if self.written > 0 { "," } else { "" };
+        self.written += 1;
+
+        if self.is_json {
+            return write!(self.out, "{}\n{}", separator, to_json(&entry)?);
+        }
+
+        // each rename is its own array of tables holding just it, which toml joins into one
+        write!(
+            self.out,
+            "\n{}",
+            to_toml(&Entries {
+                renames: vec![entry]
+            })?
+        )
+    }
+
+    /// End the plan file, returning what it was written to.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.is_json {
+            writeln!(self.out, "\n]}}")?;
+        }
+
+        self.out.flush()?;
+
+        Ok(self.out)
+    }
+}
+
+/// Parse the plan file `content`, as JSON if it is an object and as TOML otherwise.
+fn parse<T: for<'de> Deserialize<'de>>(content: &str) -> io::Result<T> {
+    if content.trim_start().starts_with('{') {
+        return serde_json::from_str(content)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err));
+    }
+
+    toml::from_str(content)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.message().to_string()))
+}
+
+fn to_toml<T: Serialize>(value: &T) -> io::Result<String> {
+    toml::to_string(value).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn to_json<T: Serialize>(value: &T) -> io::Result<String> {
+    serde_json::to_string(value).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+#[derive(Serialize)]
+struct Header {
+    version: u32,
+    tool: String,
+}
+
+#[derive(Deserialize)]
+struct Document {
+    version: u32,
+    tool: String,
+
+    #[serde(default)]
+    renames: Vec<Entry>,
+}
+
+#[derive(Serialize)]
+struct Entries {
+    renames: Vec<Entry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    source: String,
+    target: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kind: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::plan::Rename;
+    use crate::plan_file::{PlanFile, PlannedRename, PLAN_FILE_VERSION};
+    use crate::walk::EntryType;
+
+    #[test]
+    fn test_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("Some File");
+        let sub_dir = dir.path().join("Some Dir");
+
+        fs::write(&file, "")?;
+        fs::create_dir(&sub_dir)?;
+
+        let rename = |source: &PathBuf, target: &str| Rename {
+            source: source.clone(),
+            target: dir.path().join(target),
+        };
+
+        let plan = PlanFile {
+            version: PLAN_FILE_VERSION,
+            tool: String::from("ccpath 0.1.0"),
+            renames: vec![
+                PlannedRename::record(rename(&file, "some_file"))?,
+                PlannedRename::record(rename(&sub_dir, "some_dir"))?,
+                PlannedRename::record(rename(&dir.path().join("Gone"), "gone"))?,
+            ],
+        };
+
+        assert_eq!(Some(EntryType::File), plan.renames[0].kind);
+        assert_eq!(
+            Some("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+            plan.renames[0].sha256.as_deref()
+        );
+        assert_eq!(Some(EntryType::Dir), plan.renames[1].kind);
+        assert_eq!(None, plan.renames[2].kind);
+
+        let mut saved = vec![];
+        plan.save(&mut saved)?;
+
+        assert_eq!(plan, PlanFile::load(saved.as_slice())?);
+
+        let mut saved = vec![];
+        plan.save_json(&mut saved)?;
+
+        assert_eq!(plan, PlanFile::load(saved.as_slice())?);
+        assert_eq!(5, String::from_utf8(saved)?.lines().count());
+        assert!(plan.renames.iter().all(PlannedRename::is_unchanged));
+
+        fs::write(&file, "changed")?;
+        assert!(!plan.renames[0].is_unchanged());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compatibility() -> Result<(), Box<dyn std::error::Error>> {
+        // fields and kinds added by newer versions of the same format are ignored
+        let plan = PlanFile::load(
+            r#"
+                version = 1
+                tool = "ccpath 9.0.0"
+                added = true
+
+                [[renames]]
+                source = "Some File"
+                target = "some_file"
+                kind = "socket"
+                added = 1
+            "#
+            .as_bytes(),
+        )?;
+
+        assert_eq!(1, plan.renames.len());
+        assert_eq!(None, plan.renames[0].kind);
+
+        let newer = PlanFile::load("version = 2\nrenames = \"elsewhere\"".as_bytes());
+        assert!(newer.unwrap_err().to_string().contains("version 2"));
+
+        assert!(PlanFile::load("tool = \"ccpath\"".as_bytes()).is_err());
+
+        let plan = PlanFile::load(
+            r#"{"version": 1, "tool": "ccpath 9.0.0", "added": true, "renames": [
+                {"source": "Some File", "target": "some_file", "kind": "socket"}
+            ]}"#
+            .as_bytes(),
+        )?;
+
+        assert_eq!(1, plan.renames.len());
+        assert_eq!(None, plan.renames[0].kind);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,233 @@
+use std::convert::TryFrom;
+
+use crate::stem;
+
+/// The platforms whose file naming restrictions can be enforced on converted names.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Platform {
+    /// Windows forbids several characters, names ending in a dot or space, and reserves
+    /// device names (ie 'CON', 'NUL', 'COM1') regardless of their extension.
+    Windows,
+
+    /// FAT32 and exFAT, like the SD cards of cameras and embedded devices, restrict names as
+    /// Windows does, but also limit them to 255 characters and never distinguish them by case.
+    Fat,
+}
+
+impl Platform {
+    /// Whether names differing only by case name the same path, which is always so on FAT
+    /// but can be changed per directory on Windows.
+    pub fn ignores_case(self) -> bool {
+        matches!(self, Platform::Fat)
+    }
+
+    /// The most characters a name may have, counted in UTF-16 code units.
+    fn max_len(self) -> Option<usize> {
+        match self {
+            Platform::Windows => None,
+            Platform::Fat => Some(FAT_MAX_LEN),
+        }
+    }
+}
+
+impl TryFrom<&str> for Platform {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "windows" => Ok(Platform::Windows),
+            "fat32" | "exfat" => Ok(Platform::Fat),
+            _ => Err(format!("Unsupported platform '{}'", s)),
+        }
+    }
+}
+
+const WINDOWS_RESERVED: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+const WINDOWS_FORBIDDEN: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+const FAT_MAX_LEN: usize = 255;
+
+fn is_forbidden(c: char, platform: Platform) -> bool {
+    match platform {
+        Platform::Windows | Platform::Fat => c.is_control() || WINDOWS_FORBIDDEN.contains(&c),
+    }
+}
+
+/// Whether the platform reserves `name` for a device.
+///
+/// # Examples
+/// ```
+/// # use convert_path::sanitize::{self, Platform};
+///
+/// # fn main() {
+/// assert!(sanitize::is_reserved("nul.txt", Platform::Windows));
+/// assert!(!sanitize::is_reserved("null.txt", Platform::Windows));
+/// # }
+/// ```
+pub fn is_reserved(name: &str, platform: Platform) -> bool {
+    match platform {
+        Platform::Windows | Platform::Fat => {
+            let stem = name.split('.').next().unwrap_or(name).trim_end();
+
+            WINDOWS_RESERVED
+                .iter()
+                .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+        }
+    }
+}
+
+/// Describe why `name` cannot be used on the given platform, if it can't.
+pub fn check(name: &str, platform: Platform) -> Result<(), String> {
+    let (name, _) = stem::split_stream(name);
+
+    if let Some(c) = name.chars().find(|c| is_forbidden(*c, platform)) {
+        return Err(format!("contains the forbidden character {:?}", c));
+    }
+
+    if name.ends_with('.') || name.ends_with(' ') {
+        return Err(String::from("ends with a dot or a space"));
+    }
+
+    if is_reserved(name, platform) {
+        return Err(String::from("is a reserved device name"));
+    }
+
+    if let Some(max_len) = platform.max_len() {
+        if name.encode_utf16().count() > max_len {
+            return Err(format!("is longer than {} characters", max_len));
+        }
+    }
+
+    Ok(())
+}
+
+/// Adjust `name` so that it can be used on the given platform.
+///
+/// Forbidden characters are replaced with underscores, trailing dots and spaces are
+/// removed, an underscore is appended to the stem of reserved device names, and the stem of
+/// names too long for the platform is truncated.
+///
+/// # Examples
+/// ```
+/// # use convert_path::sanitize::{self, Platform};
+///
+/// # fn main() {
+/// assert_eq!("con_.txt", sanitize::sanitize("con.txt", Platform::Windows));
+/// assert_eq!("what_", sanitize::sanitize("what?. ", Platform::Windows));
+/// # }
+/// ```
+pub fn sanitize(name: &str, platform: Platform) -> String {
+    let (name, stream) = stem::split_stream(name);
+    let replaced: String = name
+        .chars()
+        .map(|c| if is_forbidden(c, platform) { '_' } else { c })
+        .collect();
+
+    let mut sanitized = String::from(replaced.trim_end_matches(['.', ' ']));
+
+    if sanitized.is_empty() {
+        sanitized.push('_');
+    }
+
+    if is_reserved(&sanitized, platform) {
+        let stem_len = sanitized.find('.').unwrap_or(sanitized.len());
+        sanitized.insert(stem_len, '_');
+    }
+
+    if let Some(max_len) = platform.max_len() {
+        sanitized = truncate(&sanitized, max_len);
+    }
+
+    sanitized + stream
+}
+
+/// Truncate the stem of `name` so that it is at most `max_len` UTF-16 code units long along
+/// with its extension, which is kept unless it is too long itself.
+fn truncate(name: &str, max_len: usize) -> String {
+    let len = |s: &str| s.encode_utf16().count();
+
+    if len(name) <= max_len {
+        return String::from(name);
+    }
+
+    let ext = match name.rfind('.') {
+        Some(dot) if dot > 0 && len(&name[dot..]) < max_len => &name[dot..],
+        _ => "",
+    };
+
+    let mut truncated = String::new();
+
+    for c in name[..name.len() - ext.len()].chars() {
+        if len(&truncated) + c.len_utf16() + len(ext) > max_len {
+            break;
+        }
+
+        truncated.push(c);
+    }
+
+    let truncated = truncated.trim_end_matches(['.', ' ']);
+
+    format!("{}{}", truncated, ext)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sanitize::{check, is_reserved, sanitize, Platform};
+
+    #[test]
+    fn test_is_reserved_windows() {
+        assert!(is_reserved("CON", Platform::Windows));
+        assert!(is_reserved("con", Platform::Windows));
+        assert!(is_reserved("Com1.tar.gz", Platform::Windows));
+        assert!(is_reserved("lpt9 ", Platform::Windows));
+        assert!(!is_reserved("console", Platform::Windows));
+        assert!(!is_reserved("com10", Platform::Windows));
+    }
+
+    #[test]
+    fn test_check_windows() {
+        assert_eq!(Ok(()), check("some_file.txt", Platform::Windows));
+        assert!(check("some:file", Platform::Windows).is_err());
+        assert!(check("some_file.", Platform::Windows).is_err());
+        assert!(check("some_file ", Platform::Windows).is_err());
+        assert!(check("aux.txt", Platform::Windows).is_err());
+    }
+
+    #[test]
+    fn test_sanitize_windows() {
+        assert_eq!("some_file.txt", sanitize("some_file.txt", Platform::Windows));
+        assert_eq!("some_file", sanitize("some|file", Platform::Windows));
+        assert_eq!("some_file", sanitize("some_file. .", Platform::Windows));
+        assert_eq!("nul_", sanitize("nul", Platform::Windows));
+        assert_eq!("nul_.tar.gz", sanitize("nul.tar.gz", Platform::Windows));
+        assert_eq!("_", sanitize("...", Platform::Windows));
+    }
+
+    #[test]
+    fn test_fat() {
+        let long = "a".repeat(300);
+
+        assert!(check(&long, Platform::Fat).is_err());
+        assert!(check(&"日".repeat(255), Platform::Fat).is_ok());
+        assert!(check("some?file", Platform::Fat).is_err());
+        assert_eq!(Ok(()), check(&long, Platform::Windows));
+
+        assert_eq!("a".repeat(255), sanitize(&long, Platform::Fat));
+        assert_eq!(
+            format!("{}.txt", "a".repeat(251)),
+            sanitize(&format!("{}.txt", long), Platform::Fat)
+        );
+        assert_eq!(
+            format!("{}.txt", "😀".repeat(125)),
+            sanitize(&format!("{}.txt", "😀".repeat(200)), Platform::Fat)
+        );
+        assert_eq!("con_.txt", sanitize("con.txt", Platform::Fat));
+
+        assert!(Platform::Fat.ignores_case());
+        assert!(!Platform::Windows.ignores_case());
+    }
+}
@@ -0,0 +1,233 @@
+//! Splits names into the stem which is converted and the extension which is kept as is.
+//!
+//! Splitting at the last dot treats part of names like 'v1.2.3 Release Notes' or
+//! 'data.2024.backup' as their extension, so stricter ways of recognizing an extension can
+//! be chosen instead.
+
+/// The longest extension [`Extensions::Short`] recognizes.
+pub const SHORT_EXTENSION_LEN: usize = 5;
+
+/// Common extensions recognized by [`Extensions::Known`], along with any others given.
+pub const KNOWN_EXTENSIONS: &[&str] = &[
+    "7z", "aac", "avi", "bak", "bmp", "bz2", "c", "cfg", "conf", "cpp", "cs", "css", "csv", "doc",
+    "docx", "epub", "flac", "gif", "go", "gz", "h", "heic", "hpp", "htm", "html", "ico", "ini",
+    "iso", "java", "jpeg", "jpg", "js", "json", "jsx", "key", "kt", "log", "lua", "m4a", "md",
+    "mkv", "mov", "mp3", "mp4", "odp", "ods", "odt", "ogg", "pdf", "php", "pl", "png", "ppt",
+    "pptx", "py", "rar", "rb", "rs", "rtf", "sh", "sql", "svg", "swift", "tar", "tar.bz2",
+    "tar.gz", "tar.xz", "tex", "tgz", "tif", "tiff", "toml", "ts", "tsx", "txt", "wav", "webm",
+    "webp", "xls", "xlsx", "xml", "xz", "yaml", "yml", "zip", "zst",
+];
+
+/// How the extension of a name is recognized.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum Extensions {
+    /// Everything after the last dot, like [`std::path::Path::extension`].
+    #[default]
+    LastDot,
+
+    /// Everything after the last dot, but only if it is at most [`SHORT_EXTENSION_LEN`]
+    /// letters and digits including at least one letter, so that neither version numbers nor
+    /// words are mistaken for one.
+    Short,
+
+    /// Only the given extensions compared ignoring case, which may span several dots like
+    /// 'tar.gz'.
+    Known(Vec<String>),
+}
+
+impl Extensions {
+    /// Recognize [`KNOWN_EXTENSIONS`] along with `others`.
+    pub fn known<I: IntoIterator<Item = String>>(others: I) -> Extensions {
+        let mut known: Vec<String> = KNOWN_EXTENSIONS.iter().map(|ext| ext.to_string()).collect();
+        known.extend(others);
+
+        Extensions::Known(known)
+    }
+}
+
+/// Split `name` into its stem and extension, either of which is missing when `name` is
+/// '.' or '..', just like [`std::path::Path::file_stem`] and the like.
+///
+/// The leading dot of a hidden file never starts its extension.
+///
+/// # Examples
+/// ```
+/// # use convert_path::stem::{self, Extensions};
+///
+/// # fn main() {
+/// assert_eq!(
+///     (Some("v1.2"), Some("3 Release Notes")),
+///     stem::split("v1.2.3 Release Notes", &Extensions::LastDot)
+/// );
+/// assert_eq!(
+///     (Some("v1.2.3 Release Notes"), None),
+///     stem::split("v1.2.3 Release Notes", &Extensions::Short)
+/// );
+/// assert_eq!(
+///     (Some("Some Archive"), Some("TAR.GZ")),
+///     stem::split("Some Archive.TAR.GZ", &Extensions::known(vec![]))
+/// );
+/// # }
+/// ```
+pub fn split<'a>(name: &'a str, extensions: &Extensions) -> (Option<&'a str>, Option<&'a str>) {
+    if name.is_empty() || name == "." || name == ".." {
+        return (None, None);
+    }
+
+    let dot = match name.rfind('.') {
+        Some(dot) if dot > 0 => dot,
+        _ => return (Some(name), None),
+    };
+
+    match extensions {
+        Extensions::LastDot => (Some(&name[..dot]), Some(&name[dot + 1..])),
+        Extensions::Short if is_short(&name[dot + 1..]) => {
+            (Some(&name[..dot]), Some(&name[dot + 1..]))
+        }
+        Extensions::Short => (Some(name), None),
+        Extensions::Known(known) => {
+            // the longest extension wins, so 'tar.gz' is preferred over 'gz'
+            let ext_len = known
+                .iter()
+                .map(String::len)
+                .filter(|len| *len + 1 < name.len())
+                .filter(|len| {
+                    let start = name.len() - len;
+
+                    name.is_char_boundary(start - 1)
+                        && name[start - 1..].starts_with('.')
+                        && known
+                            .iter()
+                            .any(|ext| name[start..].eq_ignore_ascii_case(ext))
+                })
+                .max();
+
+            match ext_len {
+                Some(len) => {
+                    let start = name.len() - len;
+
+                    (Some(&name[..start - 1]), Some(&name[start..]))
+                }
+                None => (Some(name), None),
+            }
+        }
+    }
+}
+
+/// Whether the stem of `name` has no letters or digits, like '-----' or '___.txt', so there
+/// are no words to convert it by and its converted name may be empty or collide with others.
+pub fn is_wordless(name: &str, extensions: &Extensions) -> bool {
+    split(name, extensions)
+        .0
+        .is_some_and(|stem| !stem.chars().any(char::is_alphanumeric))
+}
+
+/// Split off the NTFS alternate data stream named by `name`, like ':summary' of
+/// 'notes.txt:summary', so that only the name of the file is converted.
+///
+/// Only on Windows does a ':' in a name start a stream, elsewhere it is part of the name and
+/// nothing is split off.
+pub fn split_stream(name: &str) -> (&str, &str) {
+    split_stream_if(name, cfg!(windows))
+}
+
+fn split_stream_if(name: &str, has_streams: bool) -> (&str, &str) {
+    match name.find(':') {
+        Some(colon) if has_streams && colon > 0 => name.split_at(colon),
+        _ => (name, ""),
+    }
+}
+
+fn is_short(ext: &str) -> bool {
+    ext.len() <= SHORT_EXTENSION_LEN
+        && ext.bytes().all(|b| b.is_ascii_alphanumeric())
+        && ext.bytes().any(|b| b.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::stem::{is_wordless, split, split_stream_if, Extensions};
+
+    #[test]
+    fn test_last_dot() {
+        let split = |name| split(name, &Extensions::LastDot);
+
+        assert_eq!((Some("Some File"), Some("txt")), split("Some File.txt"));
+        assert_eq!(
+            (Some("data.2024"), Some("backup")),
+            split("data.2024.backup")
+        );
+        assert_eq!((Some(".bashrc"), None), split(".bashrc"));
+        assert_eq!((Some("file"), Some("")), split("file."));
+        assert_eq!((None, None), split(".."));
+    }
+
+    #[test]
+    fn test_short() {
+        let split = |name| split(name, &Extensions::Short);
+
+        assert_eq!(
+            (Some("v1.2.3 Release Notes"), Some("txt")),
+            split("v1.2.3 Release Notes.txt")
+        );
+        assert_eq!(
+            (Some("v1.2.3 Release Notes"), None),
+            split("v1.2.3 Release Notes")
+        );
+        assert_eq!((Some("data.2024.backup"), None), split("data.2024.backup"));
+        assert_eq!((Some("Some Song"), Some("mp3")), split("Some Song.mp3"));
+        assert_eq!((Some("file."), None), split("file."));
+    }
+
+    #[test]
+    fn test_known() {
+        let extensions = Extensions::known(vec![String::from("backup")]);
+        let split = |name| split(name, &extensions);
+
+        assert_eq!(
+            (Some("data.2024"), Some("backup")),
+            split("data.2024.backup")
+        );
+        assert_eq!(
+            (Some("Some Archive"), Some("tar.gz")),
+            split("Some Archive.tar.gz")
+        );
+        assert_eq!((Some("Some Photo"), Some("JPG")), split("Some Photo.JPG"));
+        assert_eq!(
+            (Some("v1.2.3 Notes.final"), None),
+            split("v1.2.3 Notes.final")
+        );
+        assert_eq!((Some(".txt"), None), split(".txt"));
+        assert_eq!((Some("Café.ü"), Some("txt")), split("Café.ü.txt"));
+    }
+
+    #[test]
+    fn test_wordless() {
+        let is_wordless = |name| is_wordless(name, &Extensions::LastDot);
+
+        assert!(is_wordless("-----"));
+        assert!(is_wordless("+++"));
+        assert!(is_wordless("___.txt"));
+        assert!(!is_wordless("(1)"));
+        assert!(!is_wordless(".bashrc"));
+        assert!(!is_wordless("日本"));
+        assert!(!is_wordless(".."));
+    }
+
+    #[test]
+    fn test_split_stream() {
+        let split = |name| split_stream_if(name, true);
+
+        assert_eq!(
+            ("Some Notes.txt", ":Summary"),
+            split("Some Notes.txt:Summary")
+        );
+        assert_eq!(("Some File", "::$DATA"), split("Some File::$DATA"));
+        assert_eq!(("Some File", ""), split("Some File"));
+        assert_eq!((":Summary", ""), split(":Summary"));
+        assert_eq!(
+            ("Some Notes.txt:Summary", ""),
+            split_stream_if("Some Notes.txt:Summary", false)
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! Templates of names combining the converted stem with other tokens, like
+//! '{parent}_{stem}_{n:03}'.
+//!
+//! A token is a name in braces, optionally followed by the width its value is padded to with
+//! leading zeros, like '{n:03}'. Literal braces are written twice, like '{{'.
+
+/// A single piece of a template.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Part {
+    Literal(String),
+    Token { name: String, width: usize },
+}
+
+/// A parsed template, rendered by giving the value of each of its tokens.
+///
+/// # Examples
+/// ```
+/// # use convert_path::template::Template;
+///
+/// # fn main() -> Result<(), String> {
+/// let template = Template::parse("{parent}_{stem}_{n:03}")?;
+///
+/// let rendered = template.render(|token| match token {
+///     "parent" => Some(String::from("scans")),
+///     "stem" => Some(String::from("img")),
+///     "n" => Some(String::from("7")),
+///     _ => None,
+/// });
+///
+/// assert_eq!(Ok(String::from("scans_img_007")), rendered);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Parse `template`, failing if a brace is left unmatched, a token is malformed, or the
+    /// template would name something other than a single path component.
+    pub fn parse(template: &str) -> Result<Template, String> {
+        let mut parts = vec![];
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut token = String::new();
+
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => token.push(c),
+                            None => return Err(format!("unterminated token '{{{}'", token)),
+                        }
+                    }
+
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+
+                    parts.push(parse_token(&token)?);
+                }
+                '}' => return Err(String::from("unmatched '}', write '}}' for a literal one")),
+                '/' | '\\' => {
+                    return Err(String::from(
+                        "a template names a single component, so it can't contain a separator",
+                    ))
+                }
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(Template { parts })
+    }
+
+    /// The name of every token in the template, in order.
+    pub fn tokens(&self) -> impl Iterator<Item = &str> {
+        self.parts.iter().filter_map(|part| match part {
+            Part::Token { name, .. } => Some(name.as_str()),
+            Part::Literal(_) => None,
+        })
+    }
+
+    /// Render the template with the value `value` gives each token, failing with the name of
+    /// the first token it has none for.
+    pub fn render<F: FnMut(&str) -> Option<String>>(&self, mut value: F) -> Result<String, String> {
+        let mut rendered = String::new();
+
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => rendered.push_str(literal),
+                Part::Token { name, width } => {
+                    let value = value(name).ok_or_else(|| name.clone())?;
+
+                    for _ in value.chars().count()..*width {
+                        rendered.push('0');
+                    }
+
+                    rendered.push_str(&value);
+                }
+            }
+        }
+
+        Ok(rendered)
+    }
+}
+
+/// Parse the content of a token between its braces, like 'n:03'.
+fn parse_token(token: &str) -> Result<Part, String> {
+    let (name, width) = match token.split_once(':') {
+        Some((name, width)) => match width.parse::<usize>() {
+            Ok(parsed) if width.bytes().all(|b| b.is_ascii_digit()) => (name, parsed),
+            _ => {
+                return Err(format!(
+                    "invalid width '{}' of token '{{{}}}'",
+                    width, token
+                ))
+            }
+        },
+        None => (token, 0),
+    };
+
+    if name.is_empty() || !name.bytes().all(|b| b.is_ascii_lowercase() || b == b'_') {
+        return Err(format!("invalid token '{{{}}}'", token));
+    }
+
+    Ok(Part::Token {
+        name: String::from(name),
+        width,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::template::Template;
+
+    #[test]
+    fn test_template() -> Result<(), String> {
+        let value = |token: &str| match token {
+            "stem" => Some(String::from("some_file")),
+            "n" => Some(String::from("42")),
+            _ => None,
+        };
+
+        let render = |template: &str| Template::parse(template)?.render(value);
+
+        assert_eq!(Ok(String::from("some_file")), render("{stem}"));
+        assert_eq!(Ok(String::from("0042-some_file")), render("{n:04}-{stem}"));
+        assert_eq!(Ok(String::from("some_file_42")), render("{stem}_{n:1}"));
+        assert_eq!(Ok(String::from("{some_file}")), render("{{{stem}}}"));
+        assert_eq!(Err(String::from("date")), render("{date}_{stem}"));
+
+        let template = Template::parse("{parent}_{stem}_{n:03}")?;
+        assert_eq!(
+            vec!["parent", "stem", "n"],
+            template.tokens().collect::<Vec<&str>>()
+        );
+
+        for invalid in [
+            "{stem", "stem}", "{}", "{Stem}", "{n:x}", "{n:-1}", "a/{stem}",
+        ] {
+            assert!(Template::parse(invalid).is_err(), "{}", invalid);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+//! Pipelines of transforms applied to a name one after another, for cleanups which can't be
+//! done by converting into a single convention.
+
+use std::convert::TryFrom;
+use std::ffi::OsStr;
+use std::path::Path;
+
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::error::PathConvertError;
+use crate::stem;
+use crate::{convert_component, Convention};
+
+/// A single step of a pipeline.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Transform {
+    /// Convert the stem into a convention, like the functions converting whole paths.
+    Convention(Convention),
+
+    /// Strip accents from the stem, lowercase it, and join its runs of letters and digits
+    /// with '-', dropping everything else.
+    Slugify,
+
+    /// Lowercase the extension.
+    LowercaseExt,
+}
+
+impl Transform {
+    /// The name the transform is parsed from, like 'slugify' or 'snake'.
+    pub fn name(self) -> &'static str {
+        match self {
+            Transform::Convention(convention) => convention.name(),
+            Transform::Slugify => "slugify",
+            Transform::LowercaseExt => "lowercase-ext",
+        }
+    }
+
+    /// Apply the transform to a single name.
+    pub fn apply(self, name: &str) -> Result<String, PathConvertError> {
+        let path = Path::new(name);
+        let stem = path.file_stem().and_then(OsStr::to_str);
+        let ext = path.extension().and_then(OsStr::to_str);
+
+        match (self, stem) {
+            (Transform::Convention(convention), _) => {
+                convert_component(OsStr::new(name), None, convention)
+            }
+            (Transform::Slugify, Some(stem)) => {
+                // the leading dots of hidden files are kept so they stay hidden
+                let visible = stem.trim_start_matches('.');
+                let slug = slugify(visible);
+
+                // a stem of only punctuation is kept rather than leaving no name at all
+                if slug.is_empty() {
+                    Ok(String::from(name))
+                } else {
+                    let dots = &stem[..stem.len() - visible.len()];
+
+                    Ok(join(&format!("{}{}", dots, slug), ext))
+                }
+            }
+            (Transform::LowercaseExt, Some(stem)) => {
+                Ok(join(stem, ext.map(str::to_lowercase).as_deref()))
+            }
+            (_, None) => Ok(String::from(name)),
+        }
+    }
+}
+
+impl TryFrom<&str> for Transform {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "slugify" => Ok(Transform::Slugify),
+            "lowercase-ext" => Ok(Transform::LowercaseExt),
+            s => Convention::try_from(s)
+                .map(Transform::Convention)
+                .map_err(|_| format!("Unsupported transform '{}'", s)),
+        }
+    }
+}
+
+fn join(stem: &str, ext: Option<&str>) -> String {
+    match ext {
+        Some(ext) => format!("{}.{}", stem, ext),
+        None => String::from(stem),
+    }
+}
+
+fn slugify(stem: &str) -> String {
+    let mut slug = String::with_capacity(stem.len());
+    let mut is_separated = false;
+
+    for c in stem.nfd().filter(|c| !is_combining_mark(*c)) {
+        if c.is_alphanumeric() {
+            if is_separated && !slug.is_empty() {
+                slug.push('-');
+            }
+
+            is_separated = false;
+            slug.extend(c.to_lowercase());
+        } else {
+            is_separated = true;
+        }
+    }
+
+    // decomposition splits more than accents from their letters, ie hangul syllables
+    slug.nfc().collect()
+}
+
+/// Apply every transform of `pipeline` to `name` in order.
+///
+/// # Examples
+/// ```
+/// # use convert_path::transform::{self, Transform};
+/// # use convert_path::Convention;
+///
+/// # fn main() {
+/// let pipeline = vec![
+///     Transform::Slugify,
+///     Transform::Convention(Convention::SnakeCase),
+///     Transform::LowercaseExt,
+/// ];
+///
+/// assert_eq!(
+///     Ok(String::from("cafe_menu_2024.pdf")),
+///     transform::apply("Café Menu (2024).PDF", &pipeline)
+/// );
+/// # }
+/// ```
+pub fn apply(name: &str, pipeline: &[Transform]) -> Result<String, PathConvertError> {
+    let (name, stream) = stem::split_stream(name);
+    let mut name = String::from(name);
+
+    for transform in pipeline {
+        name = transform.apply(&name)?;
+    }
+
+    Ok(name + stream)
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryFrom;
+
+    use crate::transform::Transform;
+    use crate::Convention;
+
+    #[test]
+    fn test_slugify() {
+        let slugify = |name| Transform::Slugify.apply(name).unwrap();
+
+        assert_eq!("some-file.txt", slugify("Some  File!.txt"));
+        assert_eq!("creme-brulee", slugify("  Crème -- Brûlée  "));
+        assert_eq!("한국어-file", slugify("한국어 file"));
+        assert_eq!("!!!.txt", slugify("!!!.txt"));
+        assert_eq!(".some-rc", slugify(".Some RC"));
+    }
+
+    #[test]
+    fn test_lowercase_ext() {
+        let lowercase = |name| Transform::LowercaseExt.apply(name).unwrap();
+
+        assert_eq!("Some File.jpg", lowercase("Some File.JPG"));
+        assert_eq!("Archive.TAR.gz", lowercase("Archive.TAR.GZ"));
+        assert_eq!("README", lowercase("README"));
+    }
+
+    #[test]
+    fn test_try_from() {
+        assert_eq!(Ok(Transform::Slugify), Transform::try_from("slugify"));
+        assert_eq!(
+            Ok(Transform::Convention(Convention::KebabCase)),
+            Transform::try_from("kebab")
+        );
+        assert!(Transform::try_from("shout").is_err());
+    }
+}
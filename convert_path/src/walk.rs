@@ -0,0 +1,514 @@
+//! Walking a directory tree with the filters of 'ccpath --recursive', so that the entries an
+//! embedder converts are the same ones the command line would.
+
+use std::convert::TryFrom;
+use std::fs::{self, Metadata};
+use std::path::Path;
+
+use glob::{MatchOptions, Pattern};
+use walkdir::{DirEntry, WalkDir};
+
+/// The kind of entry a walk may be limited to.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum EntryType {
+    File,
+    Dir,
+
+    /// A symbolic link, whether or not it is followed.
+    Symlink,
+}
+
+impl EntryType {
+    /// The name the type is parsed from, like 'file' or 'dir'.
+    pub fn name(self) -> &'static str {
+        match self {
+            EntryType::File => "file",
+            EntryType::Dir => "dir",
+            EntryType::Symlink => "symlink",
+        }
+    }
+
+    fn matches(self, entry: &DirEntry) -> bool {
+        match self {
+            EntryType::File => entry.file_type().is_file(),
+            EntryType::Dir => entry.file_type().is_dir(),
+            EntryType::Symlink => entry.path_is_symlink(),
+        }
+    }
+}
+
+impl TryFrom<&str> for EntryType {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "file" => Ok(EntryType::File),
+            "dir" => Ok(EntryType::Dir),
+            "symlink" => Ok(EntryType::Symlink),
+            _ => Err(format!("Unsupported entry type '{}'", s)),
+        }
+    }
+}
+
+/// Files and directories operating systems create by themselves, like '.DS_Store' or
+/// 'Thumbs.db', which are skipped by default since renaming them confuses the tools which
+/// created them. Names starting with '._' are the resource forks macOS writes to volumes
+/// without support for them.
+pub const DEFAULT_IGNORES: &[&str] = &[
+    ".DS_Store",
+    ".AppleDouble",
+    ".LSOverride",
+    ".Spotlight-V100",
+    ".Trashes",
+    ".fseventsd",
+    ".TemporaryItems",
+    ".directory",
+    "Thumbs.db",
+    "ehthumbs.db",
+    "desktop.ini",
+    "$RECYCLE.BIN",
+    "System Volume Information",
+];
+
+/// Whether `name` is one of the [`DEFAULT_IGNORES`], compared ignoring case since the
+/// volumes they are found on often don't distinguish names by case.
+///
+/// # Examples
+/// ```
+/// # use convert_path::walk;
+///
+/// # fn main() {
+/// assert!(walk::is_default_ignored("Thumbs.db"));
+/// assert!(walk::is_default_ignored("._Some File.txt"));
+/// assert!(!walk::is_default_ignored("Some File.txt"));
+/// # }
+/// ```
+pub fn is_default_ignored(name: &str) -> bool {
+    name.starts_with("._")
+        || DEFAULT_IGNORES
+            .iter()
+            .any(|ignored| ignored.eq_ignore_ascii_case(name))
+}
+
+/// Which entries of a tree are walked, built up from the defaults of walking every entry
+/// but the [`DEFAULT_IGNORES`] at any depth without following symbolic links or leaving the
+/// filesystem of the walked directory.
+///
+/// Entries which are hidden, ignored by default, or excluded are skipped along with
+/// everything beneath them, while those which are only not included, of another type, or
+/// files of an excluded extension are skipped alone, so that the entries beneath them are
+/// still walked.
+///
+/// Globs containing a '/' are matched against the path of an entry relative to the walked
+/// directory, where '*' never matches a '/', while any other glob is only matched against
+/// the basename like in a '.gitignore'. The walked directory itself is never hidden or
+/// excluded.
+#[derive(Clone, Debug)]
+pub struct WalkOptions {
+    min_depth: usize,
+    max_depth: usize,
+    follow_links: bool,
+    one_file_system: bool,
+    hidden: bool,
+    default_ignores: bool,
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    exclude_ext: Vec<String>,
+    types: Vec<EntryType>,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        WalkOptions {
+            min_depth: 0,
+            max_depth: usize::MAX,
+            follow_links: false,
+            one_file_system: false,
+            hidden: true,
+            default_ignores: true,
+            include: vec![],
+            exclude: vec![],
+            exclude_ext: vec![],
+            types: vec![],
+        }
+    }
+}
+
+impl WalkOptions {
+    pub fn new() -> Self {
+        WalkOptions::default()
+    }
+
+    /// Skip entries less than `depth` below the walked directory, which is at depth 0.
+    pub fn min_depth(mut self, depth: usize) -> Self {
+        self.min_depth = depth;
+        self
+    }
+
+    /// Don't descend more than `depth` below the walked directory.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Descend into symbolic links to directories.
+    pub fn follow_links(mut self, follow: bool) -> Self {
+        self.follow_links = follow;
+        self
+    }
+
+    /// Skip the directories on another filesystem than the walked directory, like mounted
+    /// volumes or network shares, along with everything beneath them.
+    pub fn one_file_system(mut self, yes: bool) -> Self {
+        self.one_file_system = yes;
+        self
+    }
+
+    /// Walk hidden entries, those whose names start with a '.', which are walked unless
+    /// disabled.
+    pub fn hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Skip the [`DEFAULT_IGNORES`], which are skipped unless disabled.
+    pub fn default_ignores(mut self, skip: bool) -> Self {
+        self.default_ignores = skip;
+        self
+    }
+
+    /// Only yield entries matching `glob`, or any of the other included globs.
+    pub fn include(mut self, glob: Pattern) -> Self {
+        self.include.push(glob);
+        self
+    }
+
+    /// Skip entries matching `glob` along with everything beneath them.
+    ///
+    /// As in rsync's patterns, a glob ending with a '/' only matches directories, and one
+    /// starting with a '/' only matches relative to the walked directory.
+    pub fn exclude(mut self, glob: Pattern) -> Self {
+        self.exclude.push(glob);
+        self
+    }
+
+    /// Skip files whose names end with the extension `ext`, compared ignoring case, which may
+    /// span several dots like 'tar.gz'. Directories are walked whatever their names.
+    pub fn exclude_ext(mut self, ext: &str) -> Self {
+        self.exclude_ext
+            .push(ext.trim_start_matches('.').to_ascii_lowercase());
+        self
+    }
+
+    /// Only yield entries of type `entry_type`, or of any of the other given types.
+    pub fn entry_type(mut self, entry_type: EntryType) -> Self {
+        self.types.push(entry_type);
+        self
+    }
+
+    pub fn is_following_links(&self) -> bool {
+        self.follow_links
+    }
+
+    /// Walk `dir`, parents before their children.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::fs;
+    /// # use std::path::PathBuf;
+    /// # use convert_path::walk::{EntryType, WalkOptions};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = std::env::temp_dir().join("convert_path_walk_doctest");
+    /// # let _ = fs::remove_dir_all(&dir);
+    /// # fs::create_dir_all(dir.join(".git"))?;
+    /// # fs::write(dir.join(".git/HEAD"), "")?;
+    /// # fs::write(dir.join("Some File.txt"), "")?;
+    /// let walked: Vec<PathBuf> = WalkOptions::new()
+    ///     .hidden(false)
+    ///     .entry_type(EntryType::File)
+    ///     .walk(&dir)
+    ///     .filter_map(Result::ok)
+    ///     .map(|entry| entry.into_path())
+    ///     .collect();
+    ///
+    /// assert_eq!(vec![dir.join("Some File.txt")], walked);
+    /// # fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn walk<P: AsRef<Path>>(
+        &self,
+        dir: P,
+    ) -> impl Iterator<Item = walkdir::Result<DirEntry>> + '_ {
+        self.walk_pruning(dir, |_| false)
+    }
+
+    /// Walk `dir` like [`WalkOptions::walk`], also skipping every entry for which `prune`
+    /// returns true along with everything beneath it.
+    pub fn walk_pruning<'a, P, F>(
+        &'a self,
+        dir: P,
+        mut prune: F,
+    ) -> impl Iterator<Item = walkdir::Result<DirEntry>> + 'a
+    where
+        P: AsRef<Path>,
+        F: FnMut(&DirEntry) -> bool + 'a,
+    {
+        let root = dir.as_ref().to_path_buf();
+        let walked = root.clone();
+
+        let root_device = if self.one_file_system {
+            fs::metadata(&root).ok().as_ref().and_then(device)
+        } else {
+            None
+        };
+
+        // where devices can't be told apart, walkdir still keeps from descending into them
+        WalkDir::new(&root)
+            .min_depth(self.min_depth)
+            .max_depth(self.max_depth)
+            .follow_links(self.follow_links)
+            .same_file_system(self.one_file_system)
+            .into_iter()
+            .filter_entry(move |entry| {
+                !self.is_skipped(&walked, root_device, entry) && !prune(entry)
+            })
+            .filter(move |entry| match entry {
+                Ok(entry) => self.is_yielded(&root, entry),
+                Err(_) => true,
+            })
+    }
+
+    /// Whether `entry` and everything beneath it is skipped.
+    fn is_skipped(&self, root: &Path, root_device: Option<u64>, entry: &DirEntry) -> bool {
+        if entry.depth() == 0 {
+            return false;
+        }
+
+        // a directory on another filesystem is the mount point of that filesystem
+        if root_device.is_some()
+            && entry.file_type().is_dir()
+            && entry.metadata().ok().as_ref().and_then(device) != root_device
+        {
+            return true;
+        }
+
+        let name = entry.file_name().to_string_lossy();
+        let is_hidden = name.starts_with('.');
+
+        (is_hidden && !self.hidden)
+            || (self.default_ignores && is_default_ignored(&name))
+            || self.exclude.iter().any(|glob| matches(glob, root, entry))
+    }
+
+    /// Whether `entry`, which is not skipped, is yielded.
+    fn is_yielded(&self, root: &Path, entry: &DirEntry) -> bool {
+        let is_included =
+            self.include.is_empty() || self.include.iter().any(|glob| matches(glob, root, entry));
+
+        is_included
+            && !self.has_excluded_ext(entry)
+            && (self.types.is_empty() || self.types.iter().any(|t| t.matches(entry)))
+    }
+
+    fn has_excluded_ext(&self, entry: &DirEntry) -> bool {
+        if self.exclude_ext.is_empty() || entry.file_type().is_dir() {
+            return false;
+        }
+
+        let name = entry.file_name().to_string_lossy().to_lowercase();
+
+        self.exclude_ext.iter().any(|ext| {
+            name.len() > ext.len() + 1
+                && name.ends_with(ext.as_str())
+                && name[..name.len() - ext.len()].ends_with('.')
+        })
+    }
+}
+
+/// The device holding the entry of `metadata`, on platforms which tell.
+#[cfg(unix)]
+fn device(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some(metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn device(_: &Metadata) -> Option<u64> {
+    None
+}
+
+/// Whether `glob` matches `entry`, found by walking `root`.
+fn matches(glob: &Pattern, root: &Path, entry: &DirEntry) -> bool {
+    let options = MatchOptions {
+        require_literal_separator: true,
+        ..MatchOptions::new()
+    };
+
+    let path = entry.path();
+    let is_dir_only = glob.as_str().ends_with('/');
+
+    if is_dir_only && !entry.file_type().is_dir() {
+        return false;
+    }
+
+    let mut matched = if glob.as_str().trim_end_matches('/').contains('/') {
+        let relative = path.strip_prefix(root).unwrap_or(path);
+
+        if glob.as_str().starts_with('/') {
+            Path::new("/").join(relative).into_os_string()
+        } else {
+            relative.as_os_str().to_os_string()
+        }
+    } else {
+        match path.file_name() {
+            Some(name) => name.to_os_string(),
+            None => return false,
+        }
+    };
+
+    if is_dir_only {
+        matched.push("/");
+    }
+
+    glob.matches_path_with(Path::new(&matched), options)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use glob::Pattern;
+
+    use crate::walk::{EntryType, WalkOptions};
+
+    fn walked(options: &WalkOptions, dir: &std::path::Path) -> Vec<PathBuf> {
+        let mut walked: Vec<PathBuf> = options
+            .walk(dir)
+            .filter_map(Result::ok)
+            .map(|entry| entry.path().strip_prefix(dir).unwrap().to_path_buf())
+            .collect();
+
+        walked.sort();
+        walked
+    }
+
+    #[test]
+    fn test_walk() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let dir = dir.path();
+
+        fs::create_dir_all(dir.join("src/nested"))?;
+        fs::create_dir_all(dir.join(".git"))?;
+        fs::create_dir_all(dir.join("target"))?;
+
+        for path in [
+            "src/Some File.rs",
+            "src/nested/Other File.rs",
+            "src/README.md",
+            ".git/HEAD",
+            "target/Built File",
+        ] {
+            fs::write(dir.join(path), "")?;
+        }
+
+        let paths = |paths: &[&str]| -> Vec<PathBuf> { paths.iter().map(PathBuf::from).collect() };
+
+        let options = WalkOptions::new()
+            .hidden(false)
+            .exclude(Pattern::new("target")?)
+            .min_depth(1)
+            .max_depth(2);
+        assert_eq!(
+            paths(&["src", "src/README.md", "src/Some File.rs", "src/nested"]),
+            walked(&options, dir)
+        );
+
+        let options = WalkOptions::new()
+            .include(Pattern::new("*.rs")?)
+            .exclude(Pattern::new("src/nested")?);
+        assert_eq!(paths(&["src/Some File.rs"]), walked(&options, dir));
+
+        let options = WalkOptions::new()
+            .hidden(false)
+            .exclude(Pattern::new("target")?)
+            .entry_type(EntryType::Dir);
+        assert_eq!(paths(&["", "src", "src/nested"]), walked(&options, dir));
+
+        let options = WalkOptions::new()
+            .min_depth(1)
+            .exclude(Pattern::new(".git")?)
+            .exclude(Pattern::new("target")?)
+            .exclude_ext("RS")
+            .exclude_ext(".md");
+        assert_eq!(paths(&["src", "src/nested"]), walked(&options, dir));
+
+        let options = WalkOptions::new()
+            .min_depth(1)
+            .hidden(false)
+            .exclude(Pattern::new("/nested")?)
+            .exclude(Pattern::new("*.md/")?)
+            .exclude(Pattern::new("/target/")?);
+        assert_eq!(
+            paths(&[
+                "src",
+                "src/README.md",
+                "src/Some File.rs",
+                "src/nested",
+                "src/nested/Other File.rs"
+            ]),
+            walked(&options, dir)
+        );
+
+        let options = WalkOptions::new()
+            .min_depth(1)
+            .exclude(Pattern::new("/src")?)
+            .exclude(Pattern::new("HEAD/")?);
+        assert_eq!(
+            paths(&[".git", ".git/HEAD", "target", "target/Built File"]),
+            walked(&options, dir)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_default_ignores() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let dir = dir.path();
+
+        fs::create_dir_all(dir.join("$RECYCLE.BIN"))?;
+
+        for path in [
+            "Some File.txt",
+            ".DS_Store",
+            "THUMBS.DB",
+            "._Some File.txt",
+            "$RECYCLE.BIN/Deleted File",
+        ] {
+            fs::write(dir.join(path), "")?;
+        }
+
+        let options = WalkOptions::new().min_depth(1);
+        assert_eq!(vec![PathBuf::from("Some File.txt")], walked(&options, dir));
+
+        let options = WalkOptions::new().min_depth(1).default_ignores(false);
+        assert_eq!(6, walked(&options, dir).len());
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_one_file_system() {
+        let root = std::path::Path::new("/");
+        let options = WalkOptions::new().min_depth(1).max_depth(1);
+
+        // procfs is always mounted on a filesystem of its own
+        assert!(walked(&options, root).contains(&PathBuf::from("proc")));
+        assert!(!walked(&options.one_file_system(true), root).contains(&PathBuf::from("proc")));
+    }
+}
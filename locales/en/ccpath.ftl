@@ -0,0 +1,121 @@
+# The messages shown to the user, which every other locale translates. Messages missing
+# from another locale are shown in English instead.
+
+level-warning = Warning
+level-error = Error
+
+summary = { $renamed } renamed, { $conforming } already conforming, { $skipped } skipped, { $changed } changed during the run, { $failed } failed
+summary-special = { $special } special file(s) left as they are
+check-summary = { $conforming } already conforming, { $offending } not conforming
+impact = { $files } file(s) and { $dirs } directory(ies) to rename, { $created } directory(ies) to create, { $emptied } to empty, { $cross } move(s) across devices copying { $bytes } byte(s)
+notification-title = ccpath finished
+notification-title-failed = ccpath finished with failures
+
+no-such-path = no such file or directory '{ $path }'
+no-such-directory = no such directory '{ $path }'
+already-exists = '{ $path }' already exists
+
+root-filesystem = the filesystem root
+root-home = the home directory
+root-mount-point = a mount point
+dangerous-root-prompt = '{ $path }' is { $reason }, recursively rename everything under it?
+dangerous-root-refused = refusing to recurse into { $reason } '{ $path }' without '--force'
+run-prompt = { $renames } rename(s), { $skipped } skipped by collisions, proceed?
+run-refused = nothing was renamed
+rename-prompt = rename '{ $source }' to '{ $target }'?
+tree-locked = another run is renaming the tree under '{ $path }'
+
+prefix-mismatch = prefix '{ $prefix }' does not match '{ $path }'
+prefix-mismatch-converting = prefix '{ $prefix }' does not match '{ $path }', converting the full path
+
+collision = { $sources } would all be renamed to '{ $target }'
+escaping = renaming '{ $source }' to '{ $target }' would move it outside of '{ $root }'
+special-file = '{ $path }' is a { $kind }, give '--special-files skip' to leave it as it is or '--special-files rename' to rename it
+outside-root = '{ $path }' leads outside of the root '{ $root }'
+unwritable = { $count } path(s) cannot be renamed:
+unwritable-entry = '{ $source }': cannot modify '{ $dir }': { $reason }
+
+drift-vanished = '{ $path }' vanished since the plan was made, skipping
+drift-replaced = '{ $path }' was replaced since the plan was made, skipping
+trash-failed = could not move '{ $path }' to the trash: { $reason }
+max-errors = stopped after { $failed } rename(s) failed, leaving the rest of the plan undone
+
+open-log-failed = could not open log file '{ $path }': { $reason }
+audit-key-failed = could not read the audit key '{ $path }': { $reason }
+audit-key-empty = the audit key '{ $path }' is empty
+open-syslog-failed = could not open the system log: { $reason }
+read-failed = could not read '{ $path }': { $reason }
+spill-failed = could not spill the plan to disk: { $reason }
+read-spilled-failed = could not read the spilled plan: { $reason }
+diff-spilled = the plan is too large to diff, raise '--spill-after' to diff it anyway
+
+journal-unrecorded-run = '{ $path }' does not record the run it was written by
+replay-missing = { $count } recorded path(s) do not exist beneath '{ $root }':
+resolve-unmoved = '{ $path }' was not renamed by any of the recorded runs
+no-data-dir = neither XDG_DATA_HOME nor HOME is set
+undo-nothing = no run left to undo is recorded in '{ $path }'
+undo-missing = { $count } renamed path(s) recorded in '{ $path }' no longer exist:
+undo-mark-failed = could not mark '{ $path }' as undone: { $reason }
+plan-output = 'ccpath plan' always writes a JSON plan file, '--output' can't be given to it
+apply-changed = { $count } source(s) changed since '{ $path }' was planned:
+
+partial-skip = { $reason }, leaving '{ $path }' as it is
+partial-keep = { $reason }, keeping it as it is
+wordless = '{ $path }' has no letters or digits, so its converted name may be empty or collide with another
+symlink-loop = skipping symlink loop from '{ $path }' to '{ $ancestor }'
+special-file-left = leaving the { $kind } '{ $path }' as it is
+already-targeted = file '{ $path }' already targeted by another path
+file-exists = file '{ $path }' already exists
+trashed = moved '{ $path }' to the trash
+merge-kept = '{ $source }' was merged into '{ $target }', leaving { $count } path(s) whose names were taken
+merge-failed = could not merge '{ $source }' into '{ $target }': { $reason }
+retrying = retrying '{ $path }' ({ $attempt }/{ $attempts }): { $reason }
+removed-dir = removed directory '{ $path }'
+content-changed = the content of '{ $source }' changed while it was renamed to '{ $target }'
+xattr-lost = could not preserve the extended attribute '{ $attr }' of '{ $path }'
+stamp-failed = could not stamp '{ $path }', further failures are not reported: { $reason }
+references-rewrote = rewrote { $count } reference(s) in '{ $path }'
+references-would-rewrite = would rewrite { $count } reference(s) in '{ $path }'
+references-failed = could not fix references in '{ $path }': { $reason }
+
+explain-rule = '{ $path }' matches the rule '{ $rule }', so it is converted into { $convention }
+explain-split = '{ $word }' split at { $boundary }
+explain-transformed = '{ $path }' was transformed by { $transforms } to '{ $transformed }'
+explain-sanitized = '{ $path }' was adjusted for { $platform } to '{ $sanitized }'
+sampled = sampled { $picked } of { $seen } renames with seed { $seed }
+using-prefix = using '{ $prefix }' as the prefix
+no-common-prefix = the paths have no directory in common, converting them in full
+dry-run-hint = runs are dry by default, give '--execute' to perform the renames
+audit-dry-run = runs are dry by default, give '--execute' to perform and audit the renames
+stdin-twice = only one of '--rules' and '--files-from' can read stdin
+review-remote = the plan for '{ $url }' cannot be reviewed, only local paths can
+review-failed = could not review the plan: { $reason }
+lock-failed = could not lock the trees of the run, running unlocked: { $reason }
+uring-unavailable = io_uring is unavailable, using standard renames: { $reason }
+uring-unsupported = io_uring is not supported by this build, using standard renames
+uring-failed = io_uring failed, using standard renames: { $reason }
+
+write-converted-failed = could not write the converted paths: { $reason }
+write-offending-failed = could not write the offending paths: { $reason }
+write-script-failed = could not write the script: { $reason }
+write-diff-failed = could not write the diff: { $reason }
+notification-failed = could not show a notification: { $reason }
+audit-create-failed = could not create the audit report '{ $path }': { $reason }
+audit-write-failed = could not write the audit report: { $reason }
+state-load-failed = could not load the state of '{ $path }', examining everything: { $reason }
+state-save-failed = could not save the state for the next incremental run: { $reason }
+metrics-write-failed = could not write the metrics to '{ $path }': { $reason }
+stats-write-failed = could not write the stats to '{ $path }': { $reason }
+stats-phase = stats: { $phase } { $seconds }s, { $count } { $unit } ({ $rate }/s)
+stats-thread = stats:   thread { $thread } busy { $seconds }s ({ $utilization }%)
+stats-total = stats: total { $seconds }s
+
+init-exists = '{ $path }' already exists, give '--overwrite' to replace it
+doctor-summary = { $examined } paths examined, { $findings } issues found
+archive-unsupported = '{ $path }' is not a .zip, .tar, .tar.gz, or .tgz archive
+archive-failed = could not convert '{ $path }': { $reason }
+archive-escaping = renaming '{ $source }' to '{ $target }' would move it outside of the archive root
+remote-escaping = renaming '{ $source }' to '{ $target }' would move it outside of the remote root
+daemon-listening = listening on '{ $path }'
+daemon-listen-failed = could not listen on '{ $path }': { $reason }
+daemon-answer-failed = could not answer a request: { $reason }
@@ -0,0 +1,120 @@
+# Japanese translations of the messages in locales/en/ccpath.ftl.
+
+level-warning = 警告
+level-error = エラー
+
+summary = 名前変更 { $renamed } 件、規則に適合済み { $conforming } 件、スキップ { $skipped } 件、実行中に変更 { $changed } 件、失敗 { $failed } 件
+summary-special = 特殊ファイル { $special } 件はそのまま
+check-summary = 規則に適合済み { $conforming } 件、不適合 { $offending } 件
+impact = 名前変更するファイル { $files } 件とディレクトリ { $dirs } 件、作成するディレクトリ { $created } 件、空になるディレクトリ { $emptied } 件、デバイスをまたぐ移動 { $cross } 件 (コピー { $bytes } バイト)
+notification-title = ccpath が完了しました
+notification-title-failed = ccpath は失敗を伴って完了しました
+
+no-such-path = '{ $path }' というファイルまたはディレクトリはありません
+no-such-directory = '{ $path }' というディレクトリはありません
+already-exists = '{ $path }' はすでに存在します
+
+root-filesystem = ファイルシステムのルート
+root-home = ホームディレクトリ
+root-mount-point = マウントポイント
+dangerous-root-prompt = '{ $path }' は{ $reason }です。配下のすべてを再帰的に名前変更しますか?
+dangerous-root-refused = '--force' なしでは{ $reason } '{ $path }' の中を再帰的に処理しません
+run-prompt = { $renames } 件の名前変更、衝突により { $skipped } 件をスキップします。続行しますか?
+run-refused = 何も名前変更しませんでした
+rename-prompt = '{ $source }' を '{ $target }' に名前変更しますか?
+tree-locked = 別の実行が '{ $path }' 配下を名前変更しています
+
+prefix-mismatch = プレフィックス '{ $prefix }' が '{ $path }' と一致しません
+prefix-mismatch-converting = プレフィックス '{ $prefix }' が '{ $path }' と一致しないため、パス全体を変換します
+
+collision = { $sources } はすべて '{ $target }' に名前変更されてしまいます
+escaping = '{ $source }' を '{ $target }' に名前変更すると '{ $root }' の外に移動してしまいます
+special-file = '{ $path }' は { $kind } です。そのままにするには '--special-files skip'、名前変更するには '--special-files rename' を指定してください
+outside-root = '{ $path }' はルート '{ $root }' の外を指しています
+unwritable = { $count } 件のパスを名前変更できません:
+unwritable-entry = '{ $source }': '{ $dir }' を変更できません: { $reason }
+
+drift-vanished = '{ $path }' は計画の作成後に消えたため、スキップします
+drift-replaced = '{ $path }' は計画の作成後に置き換えられたため、スキップします
+trash-failed = '{ $path }' をゴミ箱に移動できませんでした: { $reason }
+max-errors = { $failed } 件の名前変更が失敗したため停止しました。計画の残りは実行されていません
+
+open-log-failed = ログファイル '{ $path }' を開けませんでした: { $reason }
+audit-key-failed = 監査キー '{ $path }' を読み込めませんでした: { $reason }
+audit-key-empty = 監査キー '{ $path }' が空です
+open-syslog-failed = システムログを開けませんでした: { $reason }
+read-failed = '{ $path }' を読み込めませんでした: { $reason }
+spill-failed = 計画をディスクに書き出せませんでした: { $reason }
+read-spilled-failed = 書き出した計画を読み込めませんでした: { $reason }
+diff-spilled = 計画が大きすぎて差分を表示できません。表示するには '--spill-after' を引き上げてください
+
+journal-unrecorded-run = '{ $path }' には書き込んだ実行が記録されていません
+replay-missing = 記録された { $count } 件のパスが '{ $root }' 配下に存在しません:
+resolve-unmoved = '{ $path }' は記録されたどの実行でも名前変更されていません
+no-data-dir = XDG_DATA_HOME も HOME も設定されていません
+undo-nothing = '{ $path }' に元に戻せる実行が記録されていません
+undo-missing = '{ $path }' に記録された名前変更後のパスのうち { $count } 件が存在しません:
+undo-mark-failed = '{ $path }' を元に戻し済みにできませんでした: { $reason }
+plan-output = 'ccpath plan' は常に JSON の計画ファイルを書き込むため、'--output' は指定できません
+apply-changed = '{ $path }' の計画後に { $count } 件の変更元が変更されました:
+
+partial-skip = { $reason } のため、'{ $path }' をそのままにします
+partial-keep = { $reason } のため、そのままにします
+wordless = '{ $path }' には文字も数字もないため、変換後の名前が空になるか他と衝突する可能性があります
+symlink-loop = '{ $path }' から '{ $ancestor }' へのシンボリックリンクのループをスキップします
+special-file-left = { $kind } '{ $path }' をそのままにします
+already-targeted = ファイル '{ $path }' は既に別のパスの変更先です
+file-exists = ファイル '{ $path }' は既に存在します
+trashed = '{ $path }' をゴミ箱に移動しました
+merge-kept = '{ $source }' を '{ $target }' に統合し、名前が使用済みの { $count } 件のパスを残しました
+merge-failed = '{ $source }' を '{ $target }' に統合できませんでした: { $reason }
+retrying = '{ $path }' を再試行しています ({ $attempt }/{ $attempts }): { $reason }
+removed-dir = ディレクトリ '{ $path }' を削除しました
+content-changed = '{ $source }' の内容が '{ $target }' への名前変更中に変更されました
+xattr-lost = '{ $path }' の拡張属性 '{ $attr }' を保持できませんでした
+stamp-failed = '{ $path }' に印を付けられませんでした。以降の失敗は報告されません: { $reason }
+references-rewrote = '{ $path }' の参照を { $count } 件書き換えました
+references-would-rewrite = '{ $path }' の参照を { $count } 件書き換えます
+references-failed = '{ $path }' の参照を修正できませんでした: { $reason }
+
+explain-rule = '{ $path }' はルール '{ $rule }' に一致するため、{ $convention } に変換されます
+explain-split = '{ $word }' は { $boundary } で分割されました
+explain-transformed = '{ $path }' は { $transforms } により '{ $transformed }' に変換されました
+explain-sanitized = '{ $path }' は { $platform } 向けに '{ $sanitized }' に調整されました
+sampled = シード { $seed } で { $seen } 件の名前変更から { $picked } 件を抽出しました
+using-prefix = '{ $prefix }' をプレフィックスとして使用します
+no-common-prefix = パスに共通のディレクトリがないため、パス全体を変換します
+dry-run-hint = 既定ではドライランです。名前変更を実行するには '--execute' を指定してください
+audit-dry-run = 既定ではドライランです。名前変更を実行して監査するには '--execute' を指定してください
+stdin-twice = 標準入力を読み込めるのは '--rules' と '--files-from' のどちらか一方だけです
+review-remote = '{ $url }' の計画は確認できません。確認できるのはローカルのパスだけです
+review-failed = 計画を確認できませんでした: { $reason }
+lock-failed = 実行対象のツリーをロックできなかったため、ロックせずに実行します: { $reason }
+uring-unavailable = io_uring が利用できないため、通常の名前変更を使用します: { $reason }
+uring-unsupported = このビルドは io_uring に対応していないため、通常の名前変更を使用します
+uring-failed = io_uring が失敗したため、通常の名前変更を使用します: { $reason }
+
+write-converted-failed = 変換後のパスを書き込めませんでした: { $reason }
+write-offending-failed = 規則に合わないパスを書き込めませんでした: { $reason }
+write-script-failed = スクリプトを書き込めませんでした: { $reason }
+write-diff-failed = 差分を書き込めませんでした: { $reason }
+notification-failed = 通知を表示できませんでした: { $reason }
+audit-create-failed = 監査レポート '{ $path }' を作成できませんでした: { $reason }
+audit-write-failed = 監査レポートを書き込めませんでした: { $reason }
+state-load-failed = '{ $path }' の状態を読み込めなかったため、すべてを調べます: { $reason }
+state-save-failed = 次回の増分実行のための状態を保存できませんでした: { $reason }
+metrics-write-failed = メトリクスを '{ $path }' に書き込めませんでした: { $reason }
+stats-write-failed = 統計を '{ $path }' に書き込めませんでした: { $reason }
+stats-phase = 統計: { $phase } { $seconds }秒、{ $count } { $unit } ({ $rate }/秒)
+stats-thread = 統計:   スレッド { $thread } の稼働 { $seconds }秒 ({ $utilization }%)
+stats-total = 統計: 合計 { $seconds }秒
+
+init-exists = '{ $path }' は既に存在します。置き換えるには '--overwrite' を指定してください
+doctor-summary = { $examined } 件のパスを調べ、{ $findings } 件の問題が見つかりました
+archive-unsupported = '{ $path }' は .zip、.tar、.tar.gz、.tgz のいずれのアーカイブでもありません
+archive-failed = '{ $path }' を変換できませんでした: { $reason }
+archive-escaping = '{ $source }' を '{ $target }' に名前変更するとアーカイブのルートの外に移動します
+remote-escaping = '{ $source }' を '{ $target }' に名前変更するとリモートのルートの外に移動します
+daemon-listening = '{ $path }' で待ち受けています
+daemon-listen-failed = '{ $path }' で待ち受けられませんでした: { $reason }
+daemon-answer-failed = 要求に応答できませんでした: { $reason }
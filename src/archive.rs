@@ -0,0 +1,303 @@
+//! Writes a copy of a zip or tar archive with every entry name converted, given as
+//! 'ccpath archive CONVENTION ARCHIVE', without extracting anything to disk.
+//!
+//! Zip entries are copied without being decompressed. Tar archives are read twice, once to
+//! plan the new names and once to copy the entries.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tempfile::NamedTempFile;
+use zip::{ZipArchive, ZipWriter};
+
+use convert_path::plan::RenamePlan;
+
+use crate::report::{self, Event};
+use crate::{adjust_target, resolve_collisions, Options, Outcome, Summary};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl Format {
+    const EXTENSIONS: [(&'static str, Format); 4] = [
+        (".zip", Format::Zip),
+        (".tar", Format::Tar),
+        (".tar.gz", Format::TarGz),
+        (".tgz", Format::TarGz),
+    ];
+
+    /// The format of the archive at `path` judging by its extension, along with the extension.
+    pub fn of(path: &Path) -> Option<(Format, &'static str)> {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+
+        Format::EXTENSIONS
+            .iter()
+            .filter(|(extension, _)| name.len() > extension.len() && name.ends_with(extension))
+            .max_by_key(|(extension, _)| extension.len())
+            .map(|(extension, format)| (*format, *extension))
+    }
+}
+
+/// Where the converted copy of `archive` is written unless an output is given, which is
+/// beside the original with '.converted' before the extension.
+pub fn default_output(archive: &Path) -> Option<PathBuf> {
+    let (_, extension) = Format::of(archive)?;
+    let name = archive.file_name()?.to_str()?;
+    let (stem, extension) = name.split_at(name.len() - extension.len());
+
+    Some(archive.with_file_name(format!("{}.converted{}", stem, extension)))
+}
+
+/// Write a copy of `archive` to `output` with every entry name converted.
+pub fn run(archive: &Path, output: &Path, opts: &Options) -> io::Result<Summary> {
+    let format = match Format::of(archive) {
+        Some((format, _)) => format,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "not a .zip, .tar, .tar.gz, or .tgz archive",
+            ))
+        }
+    };
+
+    let names = match format {
+        Format::Zip => zip_names(archive)?,
+        Format::Tar | Format::TarGz => tar_names(archive, format)?,
+    };
+
+    let (targets, summary) = plan(&names, opts);
+
+    if opts.is_dry_run {
+        return Ok(summary);
+    }
+
+    // the output only appears once it is complete, and never replaces an existing file
+    let dir = match output.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let temp = NamedTempFile::new_in(dir)?;
+
+    let temp = match format {
+        Format::Zip => copy_zip(archive, temp, &targets)?,
+        Format::Tar | Format::TarGz => copy_tar(archive, format, temp, &names, &targets)?,
+    };
+
+    temp.persist_noclobber(output).map_err(|err| err.error)?;
+
+    Ok(summary)
+}
+
+/// Convert every entry name, returning the new names in the same order.
+fn plan(names: &[PathBuf], opts: &Options) -> (Vec<PathBuf>, Summary) {
+    let mut plan = RenamePlan::new();
+    let mut summary = Summary::default();
+
+    for name in names {
+        let converted = convert_path::convert_full_with(
+            name,
+            opts.from,
+            opts.convention(name),
+            &opts.extensions,
+        )
+        .and_then(|target| adjust_target(name, target, name.components().count(), opts));
+
+        match converted {
+            // entries which would escape the archive root on extraction keep their name
+            Ok(target) if !crate::remote::is_plain(&target) => {
+                error!(
+                    "{}",
+                    tr!(
+                        "archive-escaping",
+                        source = name.display(),
+                        target = target.display()
+                    )
+                );
+                summary.record(Outcome::Failed);
+            }
+            Ok(target) => plan.push(name.clone(), target),
+            Err(err) => {
+                error!("{}", err);
+                summary.record(Outcome::Failed);
+            }
+        }
+    }
+
+    let skipped = resolve_collisions(plan.collisions(), opts);
+    let mut renamed: HashMap<&Path, &Path> = HashMap::new();
+
+    for rename in &plan {
+        let outcome = if skipped.contains(&rename.source) {
+            Outcome::Skipped
+        } else if rename.is_noop() {
+            Outcome::Conforming
+        } else {
+            renamed.insert(&rename.source, &rename.target);
+            Outcome::Renamed
+        };
+
+        if outcome == Outcome::Renamed && (opts.is_verbose || opts.is_dry_run) {
+            println!(
+                "'{}' -> '{}'",
+                rename.source.display(),
+                rename.target.display()
+            );
+        }
+
+        report::event(Event::Rename {
+            source: &rename.source,
+            target: &rename.target,
+            outcome,
+        });
+
+        summary.record(outcome);
+    }
+
+    let targets = names
+        .iter()
+        .map(|name| renamed.get(name.as_path()).map_or(name.as_path(), |t| t).to_path_buf())
+        .collect();
+
+    (targets, summary)
+}
+
+fn zip_names(archive: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(archive)?))?;
+
+    (0..archive.len())
+        .map(|i| Ok(PathBuf::from(archive.by_index_raw(i)?.name())))
+        .collect()
+}
+
+fn copy_zip(archive: &Path, temp: NamedTempFile, targets: &[PathBuf]) -> io::Result<NamedTempFile> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(archive)?))?;
+    let mut writer = ZipWriter::new(BufWriter::new(temp));
+
+    for (i, target) in targets.iter().enumerate() {
+        let entry = archive.by_index_raw(i)?;
+
+        // directories are told apart from files only by their trailing '/'
+        let mut name = target.to_string_lossy().into_owned();
+        if entry.is_dir() && !name.ends_with('/') {
+            name.push('/');
+        }
+
+        writer.raw_copy_file_rename(entry, name)?;
+    }
+
+    writer.set_raw_comment(archive.comment().into());
+
+    writer
+        .finish()?
+        .into_inner()
+        .map_err(|err| err.into_error())
+}
+
+fn open_tar(archive: &Path, format: Format) -> io::Result<tar::Archive<Box<dyn Read>>> {
+    let file = BufReader::new(File::open(archive)?);
+
+    let reader: Box<dyn Read> = match format {
+        Format::TarGz => Box::new(GzDecoder::new(file)),
+        _ => Box::new(file),
+    };
+
+    Ok(tar::Archive::new(reader))
+}
+
+fn tar_names(archive: &Path, format: Format) -> io::Result<Vec<PathBuf>> {
+    let mut archive = open_tar(archive, format)?;
+
+    archive
+        .entries()?
+        .map(|entry| Ok(entry?.path()?.into_owned()))
+        .collect()
+}
+
+fn copy_tar(
+    archive: &Path,
+    format: Format,
+    temp: NamedTempFile,
+    names: &[PathBuf],
+    targets: &[PathBuf],
+) -> io::Result<NamedTempFile> {
+    let mut archive = open_tar(archive, format)?;
+    let writer = BufWriter::new(temp.reopen()?);
+
+    match format {
+        Format::TarGz => {
+            let encoder = GzEncoder::new(writer, Compression::default());
+            write_tar(&mut archive, encoder, names, targets)?.finish()?.flush()?;
+        }
+        _ => write_tar(&mut archive, writer, names, targets)?.flush()?,
+    }
+
+    Ok(temp)
+}
+
+fn write_tar<R: Read, W: Write>(
+    archive: &mut tar::Archive<R>,
+    writer: W,
+    names: &[PathBuf],
+    targets: &[PathBuf],
+) -> io::Result<W> {
+    let mut builder = tar::Builder::new(writer);
+
+    // hard links name another entry of the archive, which may itself have been renamed
+    let renamed: HashMap<&Path, &Path> = names
+        .iter()
+        .map(PathBuf::as_path)
+        .zip(targets.iter().map(PathBuf::as_path))
+        .collect();
+
+    for (entry, target) in archive.entries()?.zip(targets) {
+        let mut entry = entry?;
+        let mut header = entry.header().clone();
+        let entry_type = header.entry_type();
+
+        if entry_type.is_hard_link() || entry_type.is_symlink() {
+            let link = entry.link_name()?.map(|link| link.into_owned()).unwrap_or_default();
+            let link = match renamed.get(link.as_path()) {
+                Some(renamed) if entry_type.is_hard_link() => renamed.to_path_buf(),
+                _ => link,
+            };
+
+            builder.append_link(&mut header, target, link)?;
+        } else {
+            builder.append_data(&mut header, target, &mut entry)?;
+        }
+    }
+
+    builder.into_inner()
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use crate::archive::{default_output, Format};
+
+    #[test]
+    fn test_format_of() {
+        assert_eq!(Some((Format::Zip, ".zip")), Format::of(Path::new("Backup.ZIP")));
+        assert_eq!(Some((Format::TarGz, ".tar.gz")), Format::of(Path::new("backup.tar.gz")));
+        assert_eq!(None, Format::of(Path::new("backup.rar")));
+        assert_eq!(None, Format::of(Path::new(".zip")));
+    }
+
+    #[test]
+    fn test_default_output() {
+        assert_eq!(
+            Some(PathBuf::from("dir/backup.converted.tar.gz")),
+            default_output(Path::new("dir/backup.tar.gz"))
+        );
+    }
+}
@@ -0,0 +1,326 @@
+//! Writes the audit report of '--audit FILE', recording for every path a run touches its old
+//! and new path, its size, and the checksum of its content before and after it was renamed.
+//!
+//! The report is JSON, written as the run goes so that it holds every rename performed even
+//! if the run is cut short:
+//!
+//! ```json
+//! {
+//!   "tool": "ccpath 0.1.0",
+//!   "user": "alice (uid 1000)",
+//!   "started": 1700000000.000,
+//!   "entries": [
+//!     {"source":"Some File","target":"some_file","kind":"file","size":0,"sha256_before":"e3b0…","sha256_after":"e3b0…","outcome":"renamed"}
+//!   ],
+//!   "finished": 1700000001.000,
+//!   "count": 1,
+//!   "seal": "9f86…"
+//! }
+//! ```
+//!
+//! Every entry is serialized on a line of its own. The seal is the HMAC-SHA256 of every byte
+//! of the report before the seal itself, header and count included, keyed with the content
+//! of the '--audit-key' file. Only those holding the key can recompute it, so any later change
+//! to the report is detected by checking the seal with the same key.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use rayon::prelude::*;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use convert_path::plan::{Rename, RenamePlan};
+
+use crate::Outcome;
+
+/// The kind, size, and checksum of the entry at a path.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct State {
+    kind: &'static str,
+
+    /// The size of a file, other kinds have none.
+    size: Option<u64>,
+
+    /// The hex sha256 of the content of a file, other kinds have none.
+    sha256: Option<String>,
+}
+
+impl State {
+    fn of(path: &Path) -> io::Result<State> {
+        let metadata = fs::symlink_metadata(path)?;
+        let file_type = metadata.file_type();
+
+        if file_type.is_symlink() {
+            return Ok(State::other("symlink"));
+        } else if file_type.is_dir() {
+            return Ok(State::other("dir"));
+        } else if !file_type.is_file() {
+            return Ok(State::other("other"));
+        }
+
+        let mut hasher = Sha256::new();
+        io::copy(&mut File::open(path)?, &mut hasher)?;
+
+        Ok(State {
+            kind: "file",
+            size: Some(metadata.len()),
+            sha256: Some(hex(&hasher.finalize())),
+        })
+    }
+
+    fn other(kind: &'static str) -> State {
+        State {
+            kind,
+            size: None,
+            sha256: None,
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
+
+/// `value` as compact JSON.
+fn json<T: Serialize + ?Sized>(value: &T) -> io::Result<String> {
+    Ok(serde_json::to_string(value)?)
+}
+
+/// An entry of the report, for a rename which was not a no-op.
+#[derive(Serialize)]
+struct Entry<'a> {
+    source: Cow<'a, str>,
+    target: Cow<'a, str>,
+    kind: Option<&'static str>,
+    size: Option<u64>,
+    sha256_before: Option<String>,
+    sha256_after: Option<String>,
+    outcome: &'static str,
+}
+
+/// Writes through to `out`, feeding every byte written into the seal.
+struct Sealed<W: Write> {
+    out: W,
+    mac: Hmac<Sha256>,
+}
+
+impl<W: Write> Write for Sealed<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.out.write(buf)?;
+        self.mac.update(&buf[..written]);
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+pub struct Audit {
+    out: Sealed<BufWriter<File>>,
+    count: usize,
+
+    /// The state of each source of the batch about to be performed.
+    before: HashMap<PathBuf, State>,
+}
+
+impl Audit {
+    /// Start the report at `path`, truncating it, to be sealed with `key`.
+    pub fn create(path: &Path, user: Option<&str>, key: &[u8]) -> io::Result<Audit> {
+        let mac = Hmac::<Sha256>::new_from_slice(key)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))?;
+        let mut out = Sealed {
+            out: BufWriter::new(File::create(path)?),
+            mac,
+        };
+
+        // the entries are written as the run goes, so only the values are serialized here
+        writeln!(out, "{{")?;
+        writeln!(
+            out,
+            "  \"tool\": {},",
+            json(concat!("ccpath ", env!("CARGO_PKG_VERSION")))?
+        )?;
+        writeln!(out, "  \"user\": {},", json(&user)?)?;
+        writeln!(out, "  \"started\": {:.3},", now())?;
+        write!(out, "  \"entries\": [")?;
+        out.flush()?;
+
+        Ok(Audit {
+            out,
+            count: 0,
+            before: HashMap::new(),
+        })
+    }
+
+    /// Record the state of every source of `plan` which is about to be renamed.
+    pub fn before(&mut self, plan: &RenamePlan) {
+        self.before = plan
+            .renames()
+            .par_iter()
+            .filter(|rename| !rename.is_noop())
+            .filter_map(|rename| {
+                State::of(&rename.source)
+                    .ok()
+                    .map(|state| (rename.source.clone(), state))
+            })
+            .collect();
+    }
+
+    /// Record the outcome of `rename`, checksumming its target again if it was renamed.
+    ///
+    /// Returns whether the content of a renamed file is unchanged.
+    pub fn record(&mut self, rename: &Rename, outcome: Outcome) -> io::Result<bool> {
+        if rename.is_noop() {
+            return Ok(true);
+        }
+
+        let before = self.before.remove(&rename.source);
+        let after = match outcome {
+            Outcome::Renamed => State::of(&rename.target).ok(),
+            _ => None,
+        };
+
+        let is_intact = match (&before, &after) {
+            (Some(before), Some(after)) => before == after,
+            _ => outcome != Outcome::Renamed,
+        };
+
+        let state = before.as_ref().or(after.as_ref());
+
+        let line = json(&Entry {
+            source: rename.source.to_string_lossy(),
+            target: rename.target.to_string_lossy(),
+            kind: state.map(|state| state.kind),
+            size: state.and_then(|state| state.size),
+            sha256_before: before.and_then(|state| state.sha256),
+            sha256_after: after.and_then(|state| state.sha256),
+            outcome: outcome.as_str(),
+        })?;
+
+        let comma = if self.count > 0 { "," } else { "" };
+        write!(self.out, "{}\n    {}", comma, line)?;
+        self.out.flush()?;
+
+        self.count += 1;
+
+        Ok(is_intact)
+    }
+
+    /// Finish the report with the number of entries and their seal.
+    pub fn finish(mut self) -> io::Result<()> {
+        let newline = if self.count > 0 { "\n  " } else { "" };
+
+        writeln!(self.out, "{}],", newline)?;
+        writeln!(self.out, "  \"finished\": {:.3},", now())?;
+        writeln!(self.out, "  \"count\": {},", self.count)?;
+
+        // the seal covers everything written before it
+        let Sealed { mut out, mac } = self.out;
+        let seal = hex(&mac.finalize().into_bytes());
+
+        writeln!(out, "  \"seal\": {}", json(&seal)?)?;
+        writeln!(out, "}}")?;
+
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use hmac::{Hmac, Mac};
+    use sha2::{Digest, Sha256};
+
+    use convert_path::plan::{Rename, RenamePlan};
+
+    use crate::audit::{hex, Audit};
+    use crate::Outcome;
+
+    #[test]
+    fn test_audit() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let source = dir.path().join("Some File");
+        let target = dir.path().join("some_file");
+        let report = dir.path().join("audit.json");
+
+        fs::write(&source, "content")?;
+
+        let mut plan = RenamePlan::new();
+        plan.push(source.clone(), target.clone());
+        plan.push(PathBuf::from("same"), PathBuf::from("same"));
+
+        let mut audit = Audit::create(&report, Some("alice (uid 1000)"), b"key")?;
+        audit.before(&plan);
+
+        fs::rename(&source, &target)?;
+
+        let rename = Rename {
+            source: source.clone(),
+            target: target.clone(),
+        };
+        assert!(audit.record(&rename, Outcome::Renamed)?);
+        assert!(audit.record(&plan.renames()[1], Outcome::Conforming)?);
+        audit.finish()?;
+
+        let written = fs::read_to_string(&report)?;
+        let sha = hex(&Sha256::digest(b"content"));
+
+        let parsed: serde_json::Value = serde_json::from_str(&written)?;
+        assert_eq!("alice (uid 1000)", parsed["user"]);
+        assert_eq!(1, parsed["count"]);
+        assert_eq!(
+            serde_json::json!([{
+                "source": source,
+                "target": target,
+                "kind": "file",
+                "size": 7,
+                "sha256_before": sha,
+                "sha256_after": sha,
+                "outcome": "renamed",
+            }]),
+            parsed["entries"]
+        );
+
+        // the seal covers everything before it, and only the key reproduces it
+        let sealed = &written[..written.find("  \"seal\": ").ok_or("no seal")?];
+        let seal = |key: &[u8]| -> Result<String, Box<dyn std::error::Error>> {
+            let mut mac = Hmac::<Sha256>::new_from_slice(key)?;
+            mac.update(sealed.as_bytes());
+
+            Ok(hex(&mac.finalize().into_bytes()))
+        };
+
+        assert_eq!(seal(b"key")?, parsed["seal"]);
+        assert_ne!(seal(b"other key")?, parsed["seal"]);
+
+        // a file whose content changed while it was renamed is caught
+        let mut audit = Audit::create(&report, None, b"key")?;
+        let mut plan = RenamePlan::new();
+        plan.push(target.clone(), source.clone());
+        audit.before(&plan);
+
+        fs::rename(&target, &source)?;
+        fs::write(&source, "changed")?;
+
+        assert!(!audit.record(&plan.renames()[0], Outcome::Renamed)?);
+
+        Ok(())
+    }
+}
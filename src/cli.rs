@@ -0,0 +1,928 @@
+//! The command line arguments, parsed into typed values before any work is done.
+
+use std::convert::TryFrom;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use clap::builder::{PossibleValue, PossibleValuesParser, Resettable, TypedValueParser};
+use clap::error::ErrorKind;
+use clap::{ArgGroup, Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use glob::Pattern;
+use regex::Regex;
+
+use convert_path::sanitize::Platform;
+use convert_path::template::Template;
+use convert_path::transform::Transform;
+use convert_path::walk::EntryType;
+use convert_path::Convention;
+
+use crate::manifest::path_from_bytes;
+use crate::report::LogFormat;
+use crate::script::Dialect;
+use crate::template;
+use crate::{
+    ExtensionPolicy, OutputFormat, PartialFailure, PrefixMismatch, SanitizeMode, SpecialFiles,
+    Wordless, DEFAULT_SPILL_AFTER,
+};
+
+/// The name converted into every convention to show what each of them looks like.
+pub const EXAMPLE_NAME: &str = "some example name";
+
+#[derive(Parser)]
+#[command(
+    author,
+    version,
+    about,
+    // 'ccpath snake archive' converts a path named 'archive' rather than running the subcommand
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// write a copy of a zip or tar archive with every entry name converted
+    Archive(ArchiveArgs),
+
+    /// review the plan as a tree, turning renames on or off and editing their targets, before performing it
+    Tui(Box<RunArgs>),
+
+    /// write the plan to stdout as a JSON plan file instead of renaming anything, for 'ccpath apply' to perform later
+    Plan(Box<RunArgs>),
+
+    /// perform the renames of a plan file written by 'ccpath plan' or '--output plan', failing if any source changed since it was planned
+    Apply(ApplyArgs),
+
+    /// perform the renames recorded in a '--log-file' log again beneath another directory
+    Replay(ReplayArgs),
+
+    /// print where paths renamed by the runs recorded in '--log-file' logs are now, following the renames of the directories above them too
+    Resolve(ResolveArgs),
+
+    /// revert the renames of the last run given '--record', or of the run recorded in a '--log-file' log
+    Undo(UndoArgs),
+
+    /// listen on a Unix socket for requests to convert, plan, or perform renames, keeping the config loaded between them
+    Daemon(DaemonArgs),
+
+    /// report names following no convention, directories mixing conventions, and names which won't survive Windows, cloud sync, or normalization, without renaming anything
+    Doctor(DoctorArgs),
+
+    /// print a name converted into every supported convention
+    Conventions(ConventionsArgs),
+
+    /// manage the '.ccpath.toml' config files holding the rules and defaults of runs
+    Config(ConfigArgs),
+}
+
+/// The arguments of a run over local paths or a backend.
+#[derive(Args)]
+#[command(group(ArgGroup::new("sanitizing").args(["sanitize", "target_fs"])))]
+pub struct RunArgs {
+    /// recurse into a directory, works in the same way as using '--prefix' and '--full-path' for all sub_paths
+    #[arg(short, long)]
+    pub recursive: bool,
+
+    /// follow symbolic links to directories when recursing
+    #[arg(short = 'L', long)]
+    pub follow_symlinks: bool,
+
+    /// don't descend into directories on another filesystem when recursing, like mounted backup volumes or network shares, nor rename the directories they are mounted on
+    #[arg(short = 'x', long, requires = "recursive")]
+    pub one_file_system: bool,
+
+    /// don't descend more than N directories below a path when recursing
+    #[arg(long, value_name = "N", requires = "recursive")]
+    pub max_depth: Option<usize>,
+
+    /// skip the entries less than N directories below a path when recursing, a path itself being at depth 0
+    #[arg(long, value_name = "N", requires = "recursive")]
+    pub min_depth: Option<usize>,
+
+    /// skip hidden entries, whose names start with a '.', and everything beneath them when recursing
+    #[arg(long, requires = "recursive")]
+    pub no_hidden: bool,
+
+    /// do not skip the files operating systems create by themselves, like '.DS_Store', 'Thumbs.db', or 'desktop.ini', when recursing
+    #[arg(long, requires = "recursive")]
+    pub no_default_ignores: bool,
+
+    /// when recursing, only convert the entries matching the given glob, may be given more than once
+    #[arg(long, value_name = "GLOB", value_parser = pattern, requires = "recursive")]
+    pub include: Vec<Pattern>,
+
+    /// skip the entries matching the given glob and everything beneath them when recursing, may be given more than once
+    #[arg(long, value_name = "GLOB", value_parser = pattern, requires = "recursive")]
+    pub exclude: Vec<Pattern>,
+
+    /// skip the entries matching the rsync-style patterns in the given file, one per line, may be given more than once
+    #[arg(long, value_name = "FILE", requires = "recursive")]
+    pub exclude_from: Vec<PathBuf>,
+
+    /// do not skip the entries matching the patterns in '~/.config/ccpath/ignore' when recursing
+    #[arg(long, requires = "recursive")]
+    pub no_ignore_file: bool,
+
+    /// when recursing, only convert the files git tracks and the directories holding them, skipping untracked and ignored entries
+    #[arg(long, requires = "recursive")]
+    pub git_tracked_only: bool,
+
+    /// when recursing, skip files with the given extensions, like 'iso,mkv,tmp', compared ignoring case
+    #[arg(long, value_name = "EXT", value_delimiter = ',', requires = "recursive")]
+    pub exclude_ext: Vec<String>,
+
+    /// when recursing, only convert entries of the given type, may be given more than once
+    #[arg(long = "type", value_name = "TYPE", value_parser = entry_type(), requires = "recursive")]
+    pub entry_type: Vec<EntryType>,
+
+    /// what to do with the sockets, FIFOs, and device files met when recursing: leave them as they are and count them in the summary, rename them like any other file, or abort the run
+    #[arg(long, value_name = "ACTION", value_enum, default_value_t = SpecialFiles::Skip, requires = "recursive")]
+    pub special_files: SpecialFiles,
+
+    /// allow recursing into the filesystem root, the home directory, or a mount point
+    #[arg(long)]
+    pub force: bool,
+
+    /// do not ask before performing the renames of a recursive run from a terminal
+    #[arg(short, long)]
+    pub yes: bool,
+
+    /// ask before performing each rename, answering y to perform it, n to skip it, a to perform it and every rename after it, or q to skip it and stop
+    #[arg(short, long, conflicts_with = "yes")]
+    pub interactive: bool,
+
+    /// only examine the entries of directories changed since the last recursive run over the same path
+    #[arg(long, requires = "recursive")]
+    pub incremental: bool,
+
+    /// descend into every directory, even those whose name and first few children already conform
+    #[arg(long)]
+    pub no_prune_conforming: bool,
+
+    /// write a script performing the planned renames to stdout instead of renaming anything
+    #[arg(
+        long,
+        value_name = "SHELL",
+        conflicts_with_all = ["fix_references", "remove_empty"]
+    )]
+    pub emit_script: Option<Dialect>,
+
+    /// write the plan to stdout in the given format instead of renaming anything
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        value_enum,
+        conflicts_with_all = ["emit_script", "fix_references", "remove_empty"]
+    )]
+    pub output: Option<OutputFormat>,
+
+    /// report how many paths already conform and how many do not instead of renaming anything, exiting with 10 if any do not
+    #[arg(
+        long,
+        conflicts_with_all = ["emit_script", "output", "fix_references", "remove_empty"]
+    )]
+    pub check: bool,
+
+    /// instead of renaming anything, write only the renames which would collide, overwrite an existing path, or differ from another path only in case to stdout, exiting with 3 if there are any
+    #[arg(
+        long,
+        conflicts_with_all = ["check", "emit_script", "output", "fix_references", "remove_empty"]
+    )]
+    pub check_collisions_only: bool,
+
+    /// instead of renaming anything, write a random sample of at most N of the renames to stdout
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = positive,
+        conflicts_with_all = ["check", "check_collisions_only", "emit_script", "output", "fix_references", "remove_empty"]
+    )]
+    pub sample: Option<usize>,
+
+    /// seed the random sample, so the same sample of the same plan is picked again
+    #[arg(long, value_name = "SEED", requires = "sample")]
+    pub seed: Option<u64>,
+
+    /// write every path which does not conform to stdout, one per line, before renaming them
+    #[arg(long)]
+    pub list_offenders: bool,
+
+    /// after renaming, rewrite references to the renamed paths in the text files matching the given glob, may be given more than once
+    #[arg(long, value_name = "GLOB")]
+    pub fix_references: Vec<String>,
+
+    /// also convert every path listed on its own line in the given file, or in stdin if '-'
+    #[arg(long, value_name = "FILE")]
+    pub files_from: Option<PathBuf>,
+
+    /// rename each old path to its new path as paired on each line of the given CSV, or TSV if it ends in '.tsv', rather than converting any, still checking for collisions and conflicts
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["paths", "files_from", "backend", "recursive", "filter", "template", "same_dir", "full_path", "flatten", "stamp", "sanitize", "target_fs"]
+    )]
+    pub map_file: Option<PathBuf>,
+
+    /// convert the keys of every object under an s3://bucket/prefix url, or the files under an sftp://[user@]host[:port]/path url, rather than local paths
+    #[arg(
+        long,
+        visible_alias = "remote",
+        value_name = "URL",
+        conflicts_with_all = ["paths", "files_from"]
+    )]
+    pub backend: Option<String>,
+
+    /// the number of threads used to convert paths, defaults to the number of cpus, independent renames are also performed in parallel when given
+    #[arg(short, long, value_name = "N", value_parser = positive)]
+    pub jobs: Option<usize>,
+
+    /// the number of planned renames held in memory before the plan is spilled to a temporary file
+    #[arg(long, value_name = "N", value_parser = positive, default_value_t = DEFAULT_SPILL_AFTER)]
+    pub spill_after: usize,
+
+    /// submit renames in batches through io_uring where supported, falling back to standard renames elsewhere
+    #[arg(long)]
+    pub io_uring: bool,
+
+    /// show a desktop notification with the summary when the run finishes
+    #[arg(long)]
+    pub notify: bool,
+
+    /// report how long walking, converting, and renaming took at the end of the run
+    #[arg(long)]
+    pub stats: bool,
+
+    /// write the outcome of every rename, the conventions of the converted names, how long the run took, and the most common reasons renames failed to the given file as JSON
+    #[arg(long, value_name = "PATH")]
+    pub stats_out: Option<PathBuf>,
+
+    /// add the outcomes of the run to the counters in the given file, in the text format of Prometheus for node_exporter's textfile collector, along with when it finished
+    #[arg(long, value_name = "PATH")]
+    pub metrics_file: Option<PathBuf>,
+
+    /// do not overwrite an existing file
+    #[arg(short, long)]
+    pub no_clobber: bool,
+
+    /// move existing files which would be overwritten to the trash rather than destroying them
+    #[arg(long, conflicts_with = "no_clobber")]
+    pub trash: bool,
+
+    /// merge the contents of a directory into the directory already at its new name, or which another directory is renamed to, rather than failing, moving entries whose names are taken in both as '--no-clobber' or '--trash' say
+    #[arg(long)]
+    pub merge_dirs: bool,
+
+    /// retry a rename failing with an error which may be transient on network filesystems, like a stale file handle or a busy file, up to the given number of times
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub retries: u32,
+
+    /// the delay in milliseconds before the first retry of a rename, doubled before each retry after it
+    #[arg(long, value_name = "MS", default_value_t = 100)]
+    pub retry_delay: u64,
+
+    /// perform at most N renames a second, so as not to overwhelm a fragile NAS or a rate-limited backend
+    #[arg(long, value_name = "N", value_parser = positive)]
+    pub throttle: Option<usize>,
+
+    /// copy at most the given number of bytes a second when a rename across filesystems falls back to copying, which may end in K, M, or G
+    #[arg(long, value_name = "BYTES", value_parser = bytes)]
+    pub throttle_bandwidth: Option<u64>,
+
+    /// stop starting renames once the given number of them have failed, letting those already started finish, and exit with 12
+    #[arg(long, value_name = "N", value_parser = positive)]
+    pub max_errors: Option<usize>,
+
+    /// show the operations that would be performed without doing them
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// perform the renames even though the config makes runs dry by default
+    #[arg(long, conflicts_with = "dry_run")]
+    pub execute: bool,
+
+    /// print a message for every converted path
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// show the paths of each rename made absolute
+    #[arg(long)]
+    pub absolute: bool,
+
+    /// show the paths of each rename relative to the given directory
+    #[arg(long, value_name = "DIR", conflicts_with = "absolute")]
+    pub relative_to: Option<PathBuf>,
+
+    /// interpret every path given, absolute or not, beneath the given directory, refusing any leading outside of it, and show the paths of each rename from it
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = ["absolute", "relative_to", "backend", "filter"]
+    )]
+    pub root: Option<PathBuf>,
+
+    /// read the rules choosing the convention of each path and the defaults of the run from the given file rather than the nearest '.ccpath.toml'
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// read the rules and defaults of the run as a JSON document of the same settings as a config file from the given file, or from stdin if '-', rather than from a config file
+    #[arg(long, value_name = "PATH", conflicts_with = "config")]
+    pub rules: Option<PathBuf>,
+
+    /// write diagnostics and events to stderr as messages, or as one json object per line with its level and fields for log pipelines to ingest
+    #[arg(long, value_name = "FORMAT", value_enum, default_value_t = LogFormat::Human)]
+    pub log: LogFormat,
+
+    /// write a report of every renamed path to the given file as JSON, with its size and the checksum of its content before and after it was renamed, sealed with '--audit-key'; renames are performed one at a time
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "audit_key",
+        conflicts_with_all = ["dry_run", "emit_script", "output", "check", "check_collisions_only", "sample", "backend"]
+    )]
+    pub audit: Option<PathBuf>,
+
+    /// seal the report of '--audit' with an HMAC-SHA256 keyed with the content of the given file
+    #[arg(long, value_name = "FILE", requires = "audit")]
+    pub audit_key: Option<PathBuf>,
+
+    /// write a complete log of the run to the given file
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// record the renames of the run so 'ccpath undo' can revert them; dry runs are not recorded
+    #[arg(long)]
+    pub record: bool,
+
+    /// record every performed rename, along with the user who performed it, in the system log
+    #[arg(long)]
+    pub syslog: bool,
+
+    /// only convert the basename of each given path
+    #[arg(short, long, conflicts_with = "full_path")]
+    pub basename: bool,
+
+    /// convert all components of the path
+    #[arg(short = 'F', long)]
+    pub full_path: bool,
+
+    /// with '--full-path', rename each converted component in place under its current parent rather than moving the path into newly created directories, deepest components first
+    #[arg(long, requires = "full_path")]
+    pub same_dir: bool,
+
+    /// move every converted file into the given directory, converting only its basename, while the directories holding them are left where they are
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["full_path", "backend"])]
+    pub flatten: Option<PathBuf>,
+
+    /// exclude a path prefix when converting a '--full-path' is specified, otherwise ignored
+    #[arg(short = 'P', long)]
+    pub prefix: Option<PathBuf>,
+
+    /// exclude the deepest directory containing every path given, rather than a prefix spelled out with '--prefix', when converting a '--full-path'
+    #[arg(long, requires = "full_path", conflicts_with = "prefix")]
+    pub auto_prefix: bool,
+
+    /// remove source directories left empty after a '--full-path' conversion, directories which were empty before the run are never removed
+    #[arg(long)]
+    pub remove_empty: bool,
+
+    /// after converting, transform each converted name with the given step, may be given more than once to build a pipeline applied in order, each step is 'slugify', 'lowercase-ext', or a convention
+    #[arg(long, value_name = "STEP", value_parser = transform)]
+    pub transform: Vec<Transform>,
+
+    /// only convert the components whose name matches the given regex, like ' ' for those containing spaces, leaving every other as it is
+    #[arg(long, value_name = "REGEX", value_parser = regex)]
+    pub component_pattern: Option<Regex>,
+
+    /// name each converted path after the given template rather than its converted stem, keeping its extension, where '{stem}' is the converted stem, '{n}' counts the converted paths from 1, '{date}' is the date it was last modified in UTC, and '{parent}' is the converted name of its parent, or when built with the 'exif' feature '{taken}' is the date a photo was taken and '{camera}' the converted model of the camera which took it, each optionally padded with zeros like '{n:03}'
+    #[arg(long, value_name = "TEMPLATE", value_parser = template::parse)]
+    pub template: Option<Template>,
+
+    /// ensure converted names can be used on the given platform
+    #[arg(long, value_name = "PLATFORM", value_parser = platform())]
+    pub sanitize: Option<Platform>,
+
+    /// ensure converted names can be used on the given filesystem, like the FAT32 or exFAT of SD cards, which also limits names to 255 characters and treats names differing only by case as colliding
+    #[arg(long, value_name = "FS", value_parser = filesystem(), conflicts_with = "sanitize")]
+    pub target_fs: Option<Platform>,
+
+    /// how the extension of a name, which is kept as is, is recognized
+    #[arg(long, value_name = "POLICY", value_enum, default_value_t = ExtensionPolicy::LastDot)]
+    pub extensions: ExtensionPolicy,
+
+    /// recognize the given extension along with the common ones, may be given more than once
+    #[arg(long, value_name = "EXT", requires = "extensions")]
+    pub known_extension: Vec<String>,
+
+    /// what to do with names with no letters or digits, like '-----': convert them anyway, convert them with a warning, or fail to convert them
+    #[arg(long, value_name = "ACTION", value_enum, default_value_t = Wordless::Convert)]
+    pub wordless: Wordless,
+
+    /// whether names which can't be used on the '--sanitize' platform or '--target-fs' filesystem are adjusted or rejected
+    #[arg(
+        long,
+        value_name = "MODE",
+        value_enum,
+        default_value_t = SanitizeMode::Adjust,
+        requires = "sanitizing"
+    )]
+    pub sanitize_mode: SanitizeMode,
+
+    /// do not preserve the given attributes when a path must be copied rather than renamed (ie across devices)
+    #[arg(
+        long,
+        value_name = "ATTRS",
+        value_delimiter = ',',
+        value_parser = ["mode", "ownership", "timestamps", "xattr", "all"]
+    )]
+    pub no_preserve: Vec<String>,
+
+    /// stamp converted paths with the 'user.ccpath.convention' extended attribute, and skip paths already stamped with the target convention under their current name
+    #[arg(long)]
+    pub stamp: bool,
+
+    /// warn about every extended attribute, like macOS Finder tags, which could not be preserved when a path was copied rather than renamed
+    #[arg(long)]
+    pub verify_xattr: bool,
+
+    /// what to do when '--prefix' does not match a path: convert it with a warning, abort the run, or silently convert it
+    #[arg(long, value_name = "ACTION", value_enum, default_value_t = PrefixMismatch::Warn)]
+    pub prefix_mismatch: PrefixMismatch,
+
+    /// what to do with a path converted with '--full-path' when only some of its components can be converted, like a directory whose name is not valid utf-8: fail to convert it, leave it as it is, or convert the others and keep those as they are
+    #[arg(
+        long,
+        value_name = "ACTION",
+        value_enum,
+        default_value_t = PartialFailure::Error,
+        requires = "full_path"
+    )]
+    pub partial_failure: PartialFailure,
+
+    /// print the words found in each converted name, why it was split between them, and which rules decided the result
+    #[arg(long)]
+    pub explain: bool,
+
+    /// set the current naming convention if it is known, this may improve the case conversion accuracy
+    #[arg(short, long, value_name = "CONVENTION", value_parser = ConventionParser)]
+    pub from: Option<Convention>,
+
+    /// set that target naming convention, or give it to '--into' to have it anywhere among the flags and paths
+    #[arg(
+        value_name = "CONVENTION",
+        required_unless_present = "map_file",
+        value_parser = ConventionParser
+    )]
+    pub into: Option<Convention>,
+
+    /// read paths from stdin, one per line, and print each converted rather than renaming anything, for use in pipelines
+    #[arg(
+        long,
+        conflicts_with_all = ["paths", "files_from", "backend", "recursive", "template", "same_dir", "stamp"]
+    )]
+    pub filter: bool,
+
+    /// with '--filter', read and print paths separated by NULs rather than newlines
+    #[arg(short = '0', long, requires = "filter")]
+    pub null: bool,
+
+    /// the paths to convert
+    #[arg(required_unless_present_any = ["files_from", "backend", "filter", "map_file"])]
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ArchiveArgs {
+    /// where to write the converted archive, defaults to the archive name with '.converted' before its extension
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    /// keep the original name of entries whose converted name is already taken
+    #[arg(short, long)]
+    pub no_clobber: bool,
+
+    /// show the entries that would be renamed without writing the archive
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// print a message for every converted entry
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// set the current naming convention if it is known
+    #[arg(short, long, value_name = "CONVENTION", value_parser = ConventionParser)]
+    pub from: Option<Convention>,
+
+    /// set that target naming convention
+    #[arg(value_name = "CONVENTION", value_parser = ConventionParser)]
+    pub into: Convention,
+
+    /// the .zip, .tar, .tar.gz, or .tgz archive to convert
+    pub archive: PathBuf,
+}
+
+#[derive(Args)]
+pub struct ReplayArgs {
+    /// the directory taking the place of the one the recorded run was made in
+    #[arg(long, value_name = "DIR")]
+    pub root: PathBuf,
+
+    /// do not overwrite an existing file
+    #[arg(short, long)]
+    pub no_clobber: bool,
+
+    /// show the renames that would be performed without doing them
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// print a message for every renamed path
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// the log written by '--log-file' during the recorded run
+    pub journal: PathBuf,
+}
+
+#[derive(Args)]
+pub struct ResolveArgs {
+    /// a log written by '--log-file', given once per run in the order the runs were made
+    #[arg(long = "journal", value_name = "FILE", required = true)]
+    pub journals: Vec<PathBuf>,
+
+    /// print where the paths were before the recorded runs instead
+    #[arg(long)]
+    pub reverse: bool,
+
+    /// the paths as they were before the recorded runs, or after them with '--reverse'
+    #[arg(required = true)]
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ApplyArgs {
+    /// do not overwrite an existing file
+    #[arg(short, long)]
+    pub no_clobber: bool,
+
+    /// show the renames that would be performed without doing them
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// print a message for every renamed path
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// the plan file to perform, or '-' to read it from stdin
+    pub plan: PathBuf,
+}
+
+#[derive(Args)]
+pub struct UndoArgs {
+    /// revert the run recorded in the given '--log-file' log rather than the last run given '--record'
+    #[arg(long, value_name = "FILE")]
+    pub journal: Option<PathBuf>,
+
+    /// show the renames that would be performed without doing them
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// print a message for every renamed path
+    #[arg(short, long)]
+    pub verbose: bool,
+}
+
+#[derive(Args)]
+pub struct DaemonArgs {
+    /// the Unix socket to listen on, replacing any left behind by a daemon which is no longer running
+    #[arg(long, value_name = "PATH")]
+    pub socket: PathBuf,
+
+    /// read the rules choosing the convention of each path from the given file rather than the nearest '.ccpath.toml'
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// add the outcomes of every request performed to the counters in the given file, as with a run, along with the requests being performed
+    #[arg(long, value_name = "PATH")]
+    pub metrics_file: Option<PathBuf>,
+}
+
+/// Replace every '@FILE' argument with the arguments listed in FILE, one per line, for when
+/// there are too many paths to fit on the command line. Blank lines are skipped, listed
+/// arguments are not expanded again, and nothing after '--' is expanded so a path starting
+/// with '@' can still be given.
+pub fn expand_response_files<I>(args: I) -> Result<Vec<OsString>, (PathBuf, io::Error)>
+where
+    I: IntoIterator<Item = OsString>,
+{
+    let mut expanded = vec![];
+    let mut args = args.into_iter();
+
+    // the program name is never expanded
+    expanded.extend(args.next());
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            expanded.push(arg);
+            expanded.extend(args);
+            break;
+        }
+
+        let path = match arg.to_str().and_then(|arg| arg.strip_prefix('@')) {
+            Some(path) if !path.is_empty() => PathBuf::from(path),
+            _ => {
+                expanded.push(arg);
+                continue;
+            }
+        };
+
+        let content = fs::read(&path).map_err(|err| (path, err))?;
+
+        expanded.extend(
+            content
+                .split(|b| *b == b'\n')
+                .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+                .filter(|line| !line.is_empty())
+                .map(|line| path_from_bytes(line).into_os_string()),
+        );
+    }
+
+    Ok(expanded)
+}
+
+#[derive(Args)]
+pub struct DoctorArgs {
+    /// report names longer than the given number of characters
+    #[arg(long, value_name = "N", value_parser = positive, default_value_t = 255)]
+    pub max_name_length: usize,
+
+    /// print how many names follow each convention, per directory and overall, instead of the issues
+    #[arg(long)]
+    pub histogram: bool,
+
+    /// print the histogram as json
+    #[arg(long, requires = "histogram")]
+    pub json: bool,
+
+    /// the directory to examine
+    pub dir: PathBuf,
+}
+
+#[derive(Args)]
+pub struct ConventionsArgs {
+    /// the name to convert
+    #[arg(default_value = EXAMPLE_NAME)]
+    pub name: String,
+}
+
+#[derive(Args)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// write a commented starter config to stdout, capturing the flags of a run given after the options of init as the defaults of every run, like 'ccpath config init --project --recursive --sanitize windows'
+    Init(InitArgs),
+}
+
+#[derive(Args)]
+pub struct InitArgs {
+    /// write the config to '.ccpath.toml' in the working directory rather than to stdout
+    #[arg(long)]
+    pub project: bool,
+
+    /// replace an existing '.ccpath.toml' when writing it with '--project'
+    #[arg(long, requires = "project")]
+    pub overwrite: bool,
+
+    /// the flags of a run to capture, along with its convention which is kept as an example rule
+    #[arg(
+        value_name = "FLAGS",
+        trailing_var_arg = true,
+        allow_hyphen_values = true
+    )]
+    pub flags: Vec<OsString>,
+}
+
+/// The long names of the flags which only make sense for a single run, so they are neither
+/// captured by 'ccpath config init' nor given defaults by a config.
+pub const PER_RUN_FLAGS: &[&str] = &[
+    "audit",
+    "backend",
+    "check",
+    "check-collisions-only",
+    "config",
+    "dry-run",
+    "emit-script",
+    "execute",
+    "files-from",
+    "list-offenders",
+    "log-file",
+    "output",
+    "prefix",
+    "relative-to",
+    "rules",
+    "sample",
+    "seed",
+    "stats-out",
+];
+
+/// The arguments of a run with none of them required, so that the flags of a run can be
+/// parsed without its convention or paths.
+pub fn run_flags() -> clap::Command {
+    RunArgs::augment_args(clap::Command::new("ccpath"))
+        .mut_arg("into", |arg| arg.required_unless_present(Resettable::Reset))
+        .mut_arg("paths", |arg| {
+            arg.required_unless_present(Resettable::Reset)
+        })
+}
+
+/// Parse the arguments of the whole command line, along with the matches they were parsed
+/// from so it can be told which were given.
+pub fn parse(args: &[OsString]) -> Result<(Cli, clap::ArgMatches), clap::Error> {
+    let mut cmd = Cli::command();
+
+    // the convention is given by '--into' rather than before the paths, so every positional
+    // argument is a path
+    if gives_into(args) {
+        cmd = cmd
+            .mut_arg("into", |arg| arg.long("into"))
+            .mut_subcommand("tui", |tui| tui.mut_arg("into", |arg| arg.long("into")))
+            .mut_subcommand("plan", |plan| plan.mut_arg("into", |arg| arg.long("into")));
+    }
+
+    let matches = cmd.try_get_matches_from_mut(args)?;
+    let cli = Cli::from_arg_matches(&matches).map_err(|err| err.with_cmd(&cmd))?;
+
+    Ok((cli, matches))
+}
+
+/// Whether `args` give the convention as '--into CONVENTION' or '--into=CONVENTION', before
+/// any '--' after which everything is a path.
+fn gives_into(args: &[OsString]) -> bool {
+    args.iter()
+        .skip(1)
+        .take_while(|arg| *arg != "--")
+        .filter_map(|arg| arg.to_str())
+        .any(|arg| arg == "--into" || arg.starts_with("--into="))
+}
+
+/// Parses a naming convention by name, listing every name along with an example in the help.
+#[derive(Clone)]
+struct ConventionParser;
+
+impl TypedValueParser for ConventionParser {
+    type Value = Convention;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        _: Option<&clap::Arg>,
+        value: &OsStr,
+    ) -> Result<Convention, clap::Error> {
+        let value = value
+            .to_str()
+            .ok_or_else(|| clap::Error::new(ErrorKind::InvalidUtf8).with_cmd(cmd))?;
+
+        Convention::try_from(value)
+            .map_err(|err| clap::Error::raw(ErrorKind::InvalidValue, err + "\n").with_cmd(cmd))
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        let examples = convert_path::examples(EXAMPLE_NAME).unwrap_or_default();
+
+        let values = examples.into_iter().map(|(convention, example)| {
+            let value = PossibleValue::new(convention.name());
+
+            match convention {
+                Convention::Keep => {
+                    value.help("names keep their case, only '--sanitize' changes them")
+                }
+                _ => value.help(example),
+            }
+        });
+
+        Some(Box::new(values))
+    }
+}
+
+fn platform() -> impl TypedValueParser<Value = Platform> {
+    PossibleValuesParser::new(["windows"]).map(|name| Platform::try_from(name.as_str()).unwrap())
+}
+
+fn filesystem() -> impl TypedValueParser<Value = Platform> {
+    PossibleValuesParser::new(["fat32", "exfat"])
+        .map(|name| Platform::try_from(name.as_str()).unwrap())
+}
+
+fn entry_type() -> impl TypedValueParser<Value = EntryType> {
+    PossibleValuesParser::new(["file", "dir", "symlink"])
+        .map(|name| EntryType::try_from(name.as_str()).unwrap())
+}
+
+fn regex(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|err| err.to_string())
+}
+
+/// Globs are matched against the basename, or the path relative to the walked directory
+/// if they contain a '/'.
+fn pattern(glob: &str) -> Result<Pattern, String> {
+    Pattern::new(glob).map_err(|err| err.to_string())
+}
+
+fn transform(step: &str) -> Result<Transform, String> {
+    Transform::try_from(step)
+}
+
+fn positive(n: &str) -> Result<usize, String> {
+    match n.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(String::from("must be a positive integer")),
+    }
+}
+
+/// A positive number of bytes, which may end in 'K', 'M', or 'G' to count in KiB, MiB, or GiB.
+fn bytes(size: &str) -> Result<u64, String> {
+    let (n, unit) = match size.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&size[..i], 1 << 10),
+        Some((i, 'M' | 'm')) => (&size[..i], 1 << 20),
+        Some((i, 'G' | 'g')) => (&size[..i], 1 << 30),
+        _ => (size, 1),
+    };
+
+    match n.parse::<u64>().map(|n| n.checked_mul(unit)) {
+        Ok(Some(bytes)) if bytes > 0 => Ok(bytes),
+        _ => Err(String::from("must be a positive number of bytes, like 512K or 10M")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::OsString;
+    use std::fs;
+
+    use clap::CommandFactory;
+
+    use crate::cli::{bytes, expand_response_files, run_flags, Cli};
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+        run_flags().debug_assert();
+    }
+
+    #[test]
+    fn test_bytes() {
+        assert_eq!(Ok(512), bytes("512"));
+        assert_eq!(Ok(512 * 1024), bytes("512K"));
+        assert_eq!(Ok(10 * 1024 * 1024), bytes("10m"));
+
+        for invalid in ["", "0", "G", "1.5M", "10T", "99999999999G"] {
+            assert!(bytes(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_expand_response_files() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let response = dir.path().join("paths.txt");
+        fs::write(&response, "Some File\r\n\n@nested\nOther File\n")?;
+
+        let args = [
+            "ccpath",
+            "snake",
+            &format!("@{}", response.display()),
+            "--",
+            "@literal",
+        ];
+        let expanded =
+            expand_response_files(args.iter().map(OsString::from)).map_err(|(_, err)| err)?;
+
+        assert_eq!(
+            vec![
+                "ccpath",
+                "snake",
+                "Some File",
+                "@nested",
+                "Other File",
+                "--",
+                "@literal"
+            ],
+            expanded
+        );
+
+        let missing = dir.path().join("missing.txt");
+        let args = ["ccpath", "snake", &format!("@{}", missing.display())];
+        assert!(expand_response_files(args.iter().map(OsString::from)).is_err());
+
+        Ok(())
+    }
+}
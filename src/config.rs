@@ -0,0 +1,555 @@
+//! Reads the settings of a run from a TOML config file, either the one given to '--config'
+//! or the nearest '.ccpath.toml' in the working directory or any of its ancestors.
+//!
+//! Defaults give the flags every run starts with, by their long names, unless they are given
+//! on the command line or conflict with one which is:
+//!
+//! ```toml
+//! [defaults]
+//! recursive = true
+//! sanitize = "windows"
+//! exclude = ["target", ".git"]
+//! ```
+//!
+//! Rules choose the convention each path is converted into by the first of their globs it
+//! matches, falling back to the convention given on the command line:
+//!
+//! ```toml
+//! [[rules]]
+//! glob = "docs/**"
+//! into = "kebab"
+//!
+//! [[rules]]
+//! glob = "*"
+//! into = "keep"
+//! ```
+//!
+//! Runs can also be made dry unless '--execute' is given, as a safer baseline than renaming
+//! right away:
+//!
+//! ```toml
+//! dry-run-by-default = true
+//! ```
+//!
+//! The same settings can be given to '--rules' as a JSON document instead, read from stdin
+//! when '-', so that they can be generated without writing a config file:
+//!
+//! ```json
+//! {"defaults": {"recursive": true}, "rules": [{"glob": "docs/**", "into": "kebab"}]}
+//! ```
+
+use std::convert::TryFrom;
+use std::env;
+use std::ffi::OsString;
+use std::fmt::Write;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use clap::parser::ValueSource;
+use clap::{Arg, ArgAction, ArgMatches};
+use glob::{MatchOptions, Pattern};
+use serde::Deserialize;
+use toml::{Table, Value};
+
+use convert_path::Convention;
+
+use crate::cli::{self, PER_RUN_FLAGS};
+use crate::display;
+
+/// The name of the config file looked for when '--config' is not given.
+pub const CONFIG_NAME: &str = ".ccpath.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default, rename = "dry-run-by-default")]
+    dry_run_by_default: bool,
+
+    #[serde(default)]
+    defaults: Table,
+
+    #[serde(default)]
+    rules: Vec<RuleConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleConfig {
+    glob: String,
+    into: String,
+}
+
+#[derive(Debug)]
+pub struct Rule {
+    pub glob: Pattern,
+    pub into: Convention,
+}
+
+/// The rules of a config file, matched against paths relative to the directory of the file.
+#[derive(Debug)]
+pub struct Rules {
+    root: PathBuf,
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    /// The first rule matching `path`.
+    ///
+    /// Globs containing a '/' are matched against the whole path, where '*' never matches a
+    /// '/', while any other glob is only matched against the basename like in a
+    /// '.gitignore'.
+    pub fn find(&self, path: &Path) -> Option<&Rule> {
+        let absolute = display::absolute(path);
+        let relative = absolute.strip_prefix(&self.root).unwrap_or(path);
+        let basename = relative.file_name().map(Path::new);
+
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::new()
+        };
+
+        self.rules.iter().find(|rule| {
+            if rule.glob.as_str().contains('/') {
+                rule.glob.matches_path_with(relative, options)
+            } else {
+                basename.is_some_and(|basename| rule.glob.matches_path_with(basename, options))
+            }
+        })
+    }
+}
+
+/// A config file, read into its rules and the defaults of every run.
+#[derive(Debug)]
+pub struct Config {
+    pub rules: Rules,
+
+    /// Whether runs are dry unless '--execute' is given.
+    pub is_dry_by_default: bool,
+
+    /// The value of each flag a run starts with, by its long name.
+    defaults: Table,
+}
+
+impl Config {
+    /// The flags giving a run parsed into `matches` the defaults of the config, skipping every
+    /// flag given on the command line or conflicting with one which was.
+    pub fn default_flags(&self, matches: &ArgMatches) -> Result<Vec<OsString>, String> {
+        let cmd = cli::run_flags();
+        let is_given = |arg: &Arg| {
+            matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+        };
+        let given: Vec<&Arg> = cmd.get_arguments().filter(|arg| is_given(arg)).collect();
+
+        let mut flags = vec![];
+
+        for (name, value) in &self.defaults {
+            let arg = match find_flag(&cmd, name) {
+                Some(arg) => arg,
+                None => return Err(format!("'{}' is not a flag which can have a default", name)),
+            };
+
+            // conflicts are only declared on one of the flags involved
+            let is_conflicting = given.iter().any(|other| {
+                cmd.get_arg_conflicts_with(arg).contains(other)
+                    || cmd.get_arg_conflicts_with(other).contains(&arg)
+            });
+
+            if is_given(arg) || is_conflicting {
+                continue;
+            }
+
+            let flag = OsString::from(format!("--{}", name));
+
+            match (value, arg.get_action().takes_values()) {
+                (Value::Boolean(true), false) => flags.push(flag),
+                (Value::Boolean(false), false) => {}
+                (_, false) => {
+                    return Err(format!("the default of '{}' must be true or false", name))
+                }
+                (Value::Array(values), true) => {
+                    for value in values {
+                        flags.push(flag.clone());
+                        flags.push(scalar(name, value)?);
+                    }
+                }
+                (value, true) => {
+                    flags.push(flag);
+                    flags.push(scalar(name, value)?);
+                }
+            }
+        }
+
+        Ok(flags)
+    }
+}
+
+/// The flag of a run named `name` which may be captured into or given a default by a config.
+fn find_flag<'a>(cmd: &'a clap::Command, name: &str) -> Option<&'a Arg> {
+    if PER_RUN_FLAGS.contains(&name) {
+        return None;
+    }
+
+    cmd.get_arguments().find(|arg| arg.get_long() == Some(name))
+}
+
+fn scalar(name: &str, value: &Value) -> Result<OsString, String> {
+    match value {
+        Value::String(value) => Ok(OsString::from(value)),
+        Value::Integer(value) => Ok(OsString::from(value.to_string())),
+        _ => Err(format!(
+            "the default of '{}' must be a string or an integer",
+            name
+        )),
+    }
+}
+
+/// The width comments of a starter config are wrapped at.
+const COMMENT_WIDTH: usize = 90;
+
+/// Write `text` as comment lines wrapped at [`COMMENT_WIDTH`].
+fn comment(config: &mut String, text: &str) {
+    let mut line = String::from("#");
+
+    for word in text.split_whitespace() {
+        if line.len() > 1 && line.len() + 1 + word.len() > COMMENT_WIDTH {
+            let _ = writeln!(config, "{}", line);
+            line = String::from("#");
+        }
+
+        line.push(' ');
+        line.push_str(word);
+    }
+
+    let _ = writeln!(config, "{}", line);
+}
+
+/// A commented starter config, capturing the flags of the run in `flags` as its defaults and
+/// its convention as an example rule.
+pub fn starter(flags: &[OsString]) -> Result<String, clap::Error> {
+    let mut cmd = cli::run_flags();
+    let matches = cmd.try_get_matches_from_mut(
+        std::iter::once(OsString::from("ccpath")).chain(flags.to_vec()),
+    )?;
+
+    let mut config = String::new();
+
+    comment(
+        &mut config,
+        "The config of ccpath, read by every run beneath the directory holding it as '.ccpath.toml', or by any run given its path with '--config'.",
+    );
+    config.push('\n');
+    comment(
+        &mut config,
+        "The flags every run starts with, by their long names, unless they are given on the command line or conflict with one which is.",
+    );
+    config.push_str("[defaults]\n");
+
+    let mut captured = 0;
+
+    for arg in cmd.get_arguments() {
+        let id = arg.get_id().as_str();
+
+        let name = match arg.get_long() {
+            Some(name) if find_flag(&cmd, name).is_some() => name,
+            _ => continue,
+        };
+
+        if matches.value_source(id) != Some(ValueSource::CommandLine) {
+            continue;
+        }
+
+        // numbers are written as such, since they are read back the same as their text
+        let raw: Vec<Value> = matches
+            .get_raw(id)
+            .into_iter()
+            .flatten()
+            .map(|value| {
+                let value = value.to_string_lossy();
+
+                match value.parse::<i64>() {
+                    Ok(n) if n.to_string() == value => Value::Integer(n),
+                    _ => Value::String(value.into_owned()),
+                }
+            })
+            .collect();
+
+        let value = match arg.get_action() {
+            ArgAction::Append => Value::Array(raw),
+            action if action.takes_values() => match raw.into_iter().next() {
+                Some(value) => value,
+                None => continue,
+            },
+            _ => Value::Boolean(true),
+        };
+
+        if captured > 0 {
+            config.push('\n');
+        }
+
+        if let Some(help) = arg.get_help() {
+            comment(&mut config, &help.to_string());
+        }
+
+        let _ = writeln!(config, "{} = {}", name, value);
+        captured += 1;
+    }
+
+    if captured == 0 {
+        config.push_str("# recursive = true\n# exclude = [\"target\", \".git\"]\n");
+    }
+
+    let into = matches
+        .get_one::<Convention>("into")
+        .unwrap_or(&Convention::SnakeCase);
+
+    config.push('\n');
+    comment(
+        &mut config,
+        "Make every run dry, showing the renames it would perform, unless it is given '--execute'.",
+    );
+    config.push_str("# dry-run-by-default = true\n");
+
+    config.push('\n');
+    comment(
+        &mut config,
+        "Rules choose the convention of each path by the first of their globs it matches, falling back to the convention given on the command line.",
+    );
+    let _ = write!(
+        config,
+        "#\n# [[rules]]\n# glob = \"*\"\n# into = \"{}\"\n",
+        into.name()
+    );
+
+    Ok(config)
+}
+
+/// The nearest config file in the working directory or its ancestors.
+fn find_config() -> Option<PathBuf> {
+    let cwd = env::current_dir().ok()?;
+
+    cwd.ancestors()
+        .map(|dir| dir.join(CONFIG_NAME))
+        .find(|path| path.is_file())
+}
+
+/// Parse the content of the config file at `path`.
+fn parse(path: &Path, content: &str) -> Result<Config, String> {
+    let config: ConfigFile = toml::from_str(content).map_err(|err| err.message().to_string())?;
+
+    let root = display::absolute(path)
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+
+    build(config, root)
+}
+
+/// Parse a JSON document of the same settings as a config file, whose rules are matched
+/// against paths relative to `root`.
+fn parse_json(root: PathBuf, content: &str) -> Result<Config, String> {
+    let config: ConfigFile = serde_json::from_str(content).map_err(|err| err.to_string())?;
+
+    build(config, root)
+}
+
+/// Check the settings of a config, whose rules are matched against paths relative to `root`.
+fn build(config: ConfigFile, root: PathBuf) -> Result<Config, String> {
+    let cmd = cli::run_flags();
+
+    if let Some(name) = config
+        .defaults
+        .keys()
+        .find(|name| find_flag(&cmd, name).is_none())
+    {
+        return Err(format!("'{}' is not a flag which can have a default", name));
+    }
+
+    let rules = config
+        .rules
+        .into_iter()
+        .map(|rule| {
+            let glob = Pattern::new(&rule.glob)
+                .map_err(|err| format!("invalid glob '{}': {}", rule.glob, err))?;
+            let into = Convention::try_from(rule.into.as_str())?;
+
+            Ok(Rule { glob, into })
+        })
+        .collect::<Result<Vec<Rule>, String>>()?;
+
+    Ok(Config {
+        rules: Rules { root, rules },
+        is_dry_by_default: config.dry_run_by_default,
+        defaults: config.defaults,
+    })
+}
+
+/// Load the config file at `path`, or the nearest one if not given.
+pub fn load(path: Option<&Path>) -> Result<Option<Config>, String> {
+    let path = match path.map(Path::to_path_buf).or_else(find_config) {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+
+    let content = fs::read_to_string(&path)
+        .map_err(|err| format!("could not read '{}': {}", path.display(), err))?;
+    let config = parse(&path, &content)
+        .map_err(|err| format!("invalid config '{}': {}", path.display(), err.trim_end()))?;
+
+    Ok(Some(config))
+}
+
+/// Load the settings given to '--rules' as JSON from the file at `path`, or from stdin if
+/// it is '-', matching their rules against paths relative to the working directory.
+pub fn load_json(path: &Path) -> Result<Config, String> {
+    let (name, content) = if path == Path::new("-") {
+        let mut content = String::new();
+
+        io::stdin()
+            .read_to_string(&mut content)
+            .map(|_| (String::from("stdin"), content))
+    } else {
+        fs::read_to_string(path).map(|content| (format!("'{}'", path.display()), content))
+    }
+    .map_err(|err| format!("could not read the rules: {}", err))?;
+
+    let root = env::current_dir().unwrap_or_default();
+
+    parse_json(root, &content).map_err(|err| format!("invalid rules in {}: {}", name, err))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use convert_path::Convention;
+
+    use std::ffi::OsString;
+
+    use crate::cli::run_flags;
+    use crate::config::{parse, parse_json, starter};
+
+    #[test]
+    fn test_rules() -> Result<(), Box<dyn std::error::Error>> {
+        let rules = &parse(
+            Path::new("/project/.ccpath.toml"),
+            r#"
+                [[rules]]
+                glob = "docs/**"
+                into = "kebab"
+
+                [[rules]]
+                glob = "*.py"
+                into = "snake"
+
+                [[rules]]
+                glob = "*"
+                into = "keep"
+            "#,
+        )?
+        .rules;
+
+        let into = |path: &str| rules.find(Path::new(path)).map(|rule| rule.into);
+
+        assert_eq!(
+            Some(Convention::KebabCase),
+            into("/project/docs/Some Guide.md")
+        );
+        assert_eq!(
+            Some(Convention::KebabCase),
+            into("/project/docs/nested/Some Guide.md")
+        );
+        assert_eq!(
+            Some(Convention::SnakeCase),
+            into("/project/src/docs/Some Module.py")
+        );
+        assert_eq!(Some(Convention::Keep), into("/project/Some File"));
+        assert_eq!(
+            Some(Convention::Keep),
+            into("/elsewhere/docs/Some Guide.md")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_defaults() -> Result<(), Box<dyn std::error::Error>> {
+        let flags: Vec<OsString> = [
+            "-r",
+            "--no-clobber",
+            "--exclude",
+            "target",
+            "--dry-run",
+            "kebab",
+        ]
+        .iter()
+        .map(OsString::from)
+        .collect();
+        let config = parse(Path::new(".ccpath.toml"), &starter(&flags)?)?;
+
+        let defaults = |given: &[&str]| -> Result<Vec<OsString>, String> {
+            let matches = run_flags().get_matches_from(["ccpath"].iter().chain(given));
+            config.default_flags(&matches)
+        };
+
+        // flags given on the command line or conflicting with one are left to it
+        assert_eq!(
+            vec!["--exclude", "target", "--no-clobber", "--recursive"],
+            defaults(&[])?
+        );
+        assert_eq!(
+            vec!["--exclude", "target", "--recursive"],
+            defaults(&["--verbose", "--no-clobber"])?
+        );
+        assert!(defaults(&["-r", "--trash", "--exclude", "build"])?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_json() -> Result<(), Box<dyn std::error::Error>> {
+        let config = parse_json(
+            PathBuf::from("/project"),
+            r#"{
+                "dry-run-by-default": true,
+                "defaults": {"recursive": true, "exclude": ["target"]},
+                "rules": [{"glob": "docs/**", "into": "kebab"}]
+            }"#,
+        )?;
+
+        assert!(config.is_dry_by_default);
+        assert_eq!(
+            Some(Convention::KebabCase),
+            config
+                .rules
+                .find(Path::new("/project/docs/Some Guide.md"))
+                .map(|rule| rule.into)
+        );
+
+        let matches = run_flags().get_matches_from(["ccpath"]);
+        assert_eq!(
+            vec!["--exclude", "target", "--recursive"],
+            config.default_flags(&matches)?
+        );
+
+        let root = || PathBuf::from("/project");
+        assert!(parse_json(root(), r#"{"rules": [{"glob": "*", "into": "shout"}]}"#).is_err());
+        assert!(parse_json(root(), r#"{"defaults": {"dry-run": true}}"#).is_err());
+        assert!(parse_json(root(), "[[rules]]").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        let path = Path::new(".ccpath.toml");
+
+        assert!(parse(path, "[[rules]]\nglob = \"*\"\ninto = \"shout\"").is_err());
+        assert!(parse(path, "[[rules]]\nglob = \"***\"\ninto = \"snake\"").is_err());
+        assert!(parse(path, "[[rule]]\nglob = \"*\"\ninto = \"snake\"").is_err());
+        assert!(parse(path, "[defaults]\nbogus = true").is_err());
+        assert!(parse(path, "[defaults]\ndry-run = true").is_err());
+        assert!(parse(path, "[defaults]\nexecute = true").is_err());
+        assert!(parse(path, "dry-run-by-default = 1").is_err());
+    }
+}
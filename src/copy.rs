@@ -0,0 +1,389 @@
+#[cfg(unix)]
+use std::ffi::OsString;
+use std::fs::{self, File, FileTimes};
+use std::io;
+use std::path::Path;
+
+use walkdir::WalkDir;
+
+use crate::hardlink::Copied;
+use crate::throttle::Throttle;
+
+/// Which pieces of metadata are carried over when a path must be copied rather than renamed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Preserve {
+    pub mode: bool,
+    pub ownership: bool,
+    pub timestamps: bool,
+    pub xattr: bool,
+
+    /// Check that every extended attribute arrived, warning about those which did not.
+    pub verify_xattr: bool,
+}
+
+impl Default for Preserve {
+    fn default() -> Self {
+        Preserve {
+            mode: true,
+            ownership: true,
+            timestamps: true,
+            xattr: true,
+            verify_xattr: false,
+        }
+    }
+}
+
+impl Preserve {
+    /// Disable preserving the named attribute, returning an error for unknown names.
+    pub fn disable(&mut self, attr: &str) -> Result<(), String> {
+        match attr {
+            "mode" => self.mode = false,
+            "ownership" => self.ownership = false,
+            "timestamps" => self.timestamps = false,
+            "xattr" => self.xattr = false,
+            "all" => {
+                *self = Preserve {
+                    mode: false,
+                    ownership: false,
+                    timestamps: false,
+                    xattr: false,
+                    verify_xattr: self.verify_xattr,
+                }
+            }
+            _ => return Err(format!("Unsupported attribute '{}'", attr)),
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether the error returned by a rename indicates that source and target live on different devices.
+pub fn is_cross_device(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::CrossesDevices
+}
+
+/// Move `source` to `target` by copying it, as fast as `throttle` allows, and then removing
+/// the original.
+///
+/// Directories are copied recursively, and the original is only removed once
+/// every entry has been copied successfully.
+pub fn move_by_copy(
+    source: &Path,
+    target: &Path,
+    preserve: &Preserve,
+    throttle: &Throttle,
+) -> io::Result<()> {
+    if fs::symlink_metadata(source)?.is_dir() {
+        // entries are visited parents first so their targets always exist, metadata on
+        // directories is applied afterwards so that copying children doesn't reset mtimes
+        let mut dirs = vec![];
+        let mut copied = Copied::default();
+
+        for entry in WalkDir::new(source) {
+            let entry = entry.map_err(io::Error::other)?;
+            let new_path = target.join(entry.path().strip_prefix(source).unwrap());
+
+            if entry.file_type().is_dir() {
+                fs::create_dir(&new_path)?;
+                dirs.push((entry.into_path(), new_path));
+            } else {
+                copied.link_or_copy(entry.path(), &new_path, || {
+                    copy_entry(entry.path(), &new_path, preserve, throttle)
+                })?;
+            }
+        }
+
+        for (old, new) in dirs.iter().rev() {
+            copy_metadata(old, new, preserve)?;
+        }
+
+        fs::remove_dir_all(source)
+    } else {
+        copy_entry(source, target, preserve, throttle)?;
+
+        fs::remove_file(source)
+    }
+}
+
+/// Copy a single non-directory entry, recreating symlinks rather than following them.
+fn copy_entry(
+    source: &Path,
+    target: &Path,
+    preserve: &Preserve,
+    throttle: &Throttle,
+) -> io::Result<()> {
+    if fs::symlink_metadata(source)?.file_type().is_symlink() {
+        let link = fs::read_link(source)?;
+
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(link, target)?;
+
+        #[cfg(windows)]
+        std::os::windows::fs::symlink_file(link, target)?;
+
+        // macOS keeps Finder tags on the links themselves
+        #[cfg(unix)]
+        if preserve.xattr {
+            report_lost_xattrs(target, &copy_xattrs(source, target), preserve);
+        }
+
+        Ok(())
+    } else {
+        // on Windows this copies the alternate data streams of the file along with its content
+        throttle.copy_file(source, target)?;
+
+        copy_metadata(source, target, preserve)
+    }
+}
+
+/// Apply the metadata of `source` to `target` as selected by `preserve`.
+///
+/// Ownership and extended attributes are preserved on a best-effort basis
+/// since unprivileged users usually cannot set either of them freely.
+fn copy_metadata(source: &Path, target: &Path, preserve: &Preserve) -> io::Result<()> {
+    let metadata = fs::metadata(source)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        if preserve.ownership {
+            let _ = std::os::unix::fs::chown(target, Some(metadata.uid()), Some(metadata.gid()));
+        }
+
+        if preserve.xattr {
+            report_lost_xattrs(target, &copy_xattrs(source, target), preserve);
+        }
+    }
+
+    // the times are set before the mode, which may leave the target read-only
+    if preserve.timestamps {
+        let times = FileTimes::new()
+            .set_accessed(metadata.accessed()?)
+            .set_modified(metadata.modified()?);
+
+        open_for_times(target, metadata.is_dir())?.set_times(times)?;
+    }
+
+    // fs::copy already carries permissions over for files, but not for directories
+    if preserve.mode {
+        fs::set_permissions(target, metadata.permissions())?;
+    }
+
+    Ok(())
+}
+
+/// Open `target` so that its times can be set, even once fs::copy made it read-only.
+#[cfg(unix)]
+fn open_for_times(target: &Path, _is_dir: bool) -> io::Result<File> {
+    // the owner of a file may set its times through any handle to it
+    File::open(target)
+}
+
+/// Open `target` so that its times can be set, even once fs::copy made it read-only.
+#[cfg(not(unix))]
+fn open_for_times(target: &Path, is_dir: bool) -> io::Result<File> {
+    // directories must be opened read-only, while regular files need write access here
+    if is_dir {
+        return File::open(target);
+    }
+
+    let permissions = fs::metadata(target)?.permissions();
+
+    if !permissions.readonly() {
+        return File::options().write(true).open(target);
+    }
+
+    let mut writable = permissions.clone();
+    writable.set_readonly(false);
+    fs::set_permissions(target, writable)?;
+
+    let file = File::options().write(true).open(target);
+    fs::set_permissions(target, permissions)?;
+
+    file
+}
+
+/// Copy every extended attribute of `source` to `target`, which includes Finder tags and
+/// quarantine flags on macOS, returning the names of those `target` did not end up with.
+#[cfg(unix)]
+fn copy_xattrs(source: &Path, target: &Path) -> Vec<OsString> {
+    let attrs = match xattr::list(source) {
+        Ok(attrs) => attrs,
+        Err(_) => return vec![],
+    };
+
+    let mut lost = vec![];
+
+    for attr in attrs {
+        let value = match xattr::get(source, &attr) {
+            Ok(Some(value)) => value,
+            _ => continue,
+        };
+
+        let is_copied = || matches!(xattr::get(target, &attr), Ok(Some(copied)) if copied == value);
+
+        // the copy may already have carried the attribute over, as it does on macOS, and
+        // otherwise it is only kept if it reads back unchanged
+        if !is_copied() {
+            let _ = xattr::set(target, &attr, &value);
+
+            if !is_copied() {
+                lost.push(attr);
+            }
+        }
+    }
+
+    lost
+}
+
+#[cfg(unix)]
+fn report_lost_xattrs(target: &Path, lost: &[OsString], preserve: &Preserve) {
+    if !preserve.verify_xattr {
+        return;
+    }
+
+    for attr in lost {
+        warning!(
+            "{}",
+            tr!(
+                "xattr-lost",
+                attr = attr.to_string_lossy().into_owned(),
+                path = target.display()
+            )
+        );
+
+        crate::report::event(crate::report::Event::XattrLost { path: target, attr });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::{self, File, FileTimes};
+    use std::time::{Duration, SystemTime};
+
+    use crate::copy::{move_by_copy, Preserve};
+    use crate::throttle::Throttle;
+
+    #[test]
+    fn test_move_by_copy_file_preserves_mtime() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let source = dir.path().join("Some File.txt");
+        let target = dir.path().join("some_file.txt");
+
+        let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        fs::write(&source, "content")?;
+        File::options()
+            .write(true)
+            .open(&source)?
+            .set_times(FileTimes::new().set_modified(mtime))?;
+
+        move_by_copy(&source, &target, &Preserve::default(), &Throttle::default())?;
+
+        assert!(!source.exists());
+        assert_eq!("content", fs::read_to_string(&target)?);
+        assert_eq!(mtime, fs::metadata(&target)?.modified()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_by_copy_no_preserve_timestamps() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let source = dir.path().join("Some File.txt");
+        let target = dir.path().join("some_file.txt");
+
+        let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        fs::write(&source, "content")?;
+        File::options()
+            .write(true)
+            .open(&source)?
+            .set_times(FileTimes::new().set_modified(mtime))?;
+
+        let mut preserve = Preserve::default();
+        preserve.disable("timestamps")?;
+
+        move_by_copy(&source, &target, &preserve, &Throttle::default())?;
+
+        assert_ne!(mtime, fs::metadata(&target)?.modified()?);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_move_by_copy_read_only_file() -> Result<(), Box<dyn std::error::Error>> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let source = dir.path().join("Some File.txt");
+        let target = dir.path().join("some_file.txt");
+
+        let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        fs::write(&source, "content")?;
+        File::options()
+            .write(true)
+            .open(&source)?
+            .set_times(FileTimes::new().set_modified(mtime))?;
+        fs::set_permissions(&source, fs::Permissions::from_mode(0o444))?;
+
+        move_by_copy(&source, &target, &Preserve::default(), &Throttle::default())?;
+
+        assert!(!source.exists());
+        assert_eq!("content", fs::read_to_string(&target)?);
+        assert_eq!(mtime, fs::metadata(&target)?.modified()?);
+        assert_eq!(0o444, fs::metadata(&target)?.permissions().mode() & 0o777);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_move_by_copy_dir_preserves_mode() -> Result<(), Box<dyn std::error::Error>> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let source = dir.path().join("Some Dir");
+        let target = dir.path().join("some_dir");
+
+        fs::create_dir(&source)?;
+        fs::write(source.join("script.sh"), "#!/bin/sh")?;
+        fs::set_permissions(source.join("script.sh"), fs::Permissions::from_mode(0o755))?;
+
+        move_by_copy(&source, &target, &Preserve::default(), &Throttle::default())?;
+
+        assert!(!source.exists());
+
+        let mode = fs::metadata(target.join("script.sh"))?.permissions().mode();
+        assert_eq!(0o755, mode & 0o777);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_move_by_copy_preserves_xattr() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let source = dir.path().join("Some File.txt");
+        let target = dir.path().join("some_file.txt");
+
+        fs::write(&source, "content")?;
+
+        // not every filesystem supports user attributes
+        if xattr::set(&source, "user.ccpath.tag", b"Red").is_err() {
+            return Ok(());
+        }
+
+        move_by_copy(&source, &target, &Preserve::default(), &Throttle::default())?;
+
+        assert_eq!(
+            Some(b"Red".to_vec()),
+            xattr::get(&target, "user.ccpath.tag")?
+        );
+
+        Ok(())
+    }
+}
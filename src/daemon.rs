@@ -0,0 +1,216 @@
+//! Serves conversions over a Unix socket for 'ccpath daemon', so that hosts where many
+//! short-lived callers like mail hooks or upload handlers each rename a file or two load the
+//! config once rather than on every call.
+//!
+//! Callers write one request per line as a JSON object and read one response per line:
+//!
+//! ```json
+//! {"command": "execute", "into": "snake", "paths": ["/srv/inbox/Some File.pdf"]}
+//! ```
+//!
+//! The command is either 'convert' to only name the target of each path, 'plan' to also check
+//! the renames for collisions, or 'execute' to perform them. Paths must be absolute since the
+//! daemon does not share the working directory of its callers. Every response carries 'ok',
+//! along with 'error' when it is false. Connections are served one at a time, so the renames
+//! of two callers never interleave.
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    /// Name the target of every path, without checking the renames against each other.
+    Convert,
+
+    /// Plan the renames, failing if any of them collide.
+    Plan,
+
+    /// Plan the renames and perform them.
+    Execute,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Request {
+    pub command: Action,
+
+    /// The name of the convention the paths are converted into.
+    pub into: String,
+
+    /// The name of the convention the paths are written in, if it is known.
+    #[serde(default)]
+    pub from: Option<String>,
+
+    pub paths: Vec<PathBuf>,
+
+    /// Skip renames onto existing files rather than overwriting them.
+    #[serde(default)]
+    pub no_clobber: bool,
+}
+
+/// Listen on the socket at `path`, answering every request with `handle`, until the daemon is
+/// killed.
+///
+/// A socket left behind by a daemon which is no longer running is replaced, while one another
+/// daemon still listens on, or anything which is not a socket, is not. Only the owner of the
+/// daemon may connect to it.
+pub fn serve<F>(path: &Path, mut handle: F) -> io::Result<()>
+where
+    F: FnMut(Request) -> Result<Value, String>,
+{
+    if let Ok(metadata) = fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "the path exists and is not a socket",
+            ));
+        }
+
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                "another daemon is listening on the socket",
+            ));
+        }
+
+        fs::remove_file(path)?;
+    }
+
+    let listener = bind(path)?;
+
+    for stream in listener.incoming() {
+        let result = stream.and_then(|stream| answer(stream, &mut handle));
+
+        if let Err(err) = result {
+            warning!("{}", tr!("daemon-answer-failed", reason = err.to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Bind the socket at `path` so that only its owner may connect to it, even before its
+/// permissions could be changed.
+fn bind(path: &Path) -> io::Result<UnixListener> {
+    // the umask is restored right away, before any other thread could create a file
+    let umask = unsafe { libc::umask(0o177) };
+    let listener = UnixListener::bind(path);
+    unsafe { libc::umask(umask) };
+
+    let listener = listener?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+
+    Ok(listener)
+}
+
+/// Answer every request written to `stream` until it is closed.
+fn answer<F>(stream: UnixStream, handle: &mut F) -> io::Result<()>
+where
+    F: FnMut(Request) -> Result<Value, String>,
+{
+    let mut writer = stream.try_clone()?;
+
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str(&line) {
+            Ok(request) => respond(handle(request)),
+            Err(err) => respond(Err(format!("invalid request: {}", err))),
+        };
+
+        writeln!(writer, "{}", response)?;
+    }
+
+    Ok(())
+}
+
+/// The response carrying the fields of `result`, or its error.
+fn respond(result: Result<Value, String>) -> Value {
+    match result {
+        Ok(Value::Object(mut fields)) => {
+            fields.insert(String::from("ok"), Value::Bool(true));
+            Value::Object(fields)
+        }
+        Ok(_) => json!({"ok": true}),
+        Err(err) => json!({"ok": false, "error": err}),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::UnixStream;
+    use std::thread;
+    use std::time::Duration;
+
+    use serde_json::{json, Value};
+
+    use crate::daemon::{serve, Action};
+
+    #[test]
+    fn test_serve() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let socket = dir.path().join("ccpath.sock");
+        let path = socket.clone();
+
+        thread::spawn(move || {
+            serve(&path, |request| match request.command {
+                Action::Convert => Ok(json!({"paths": request.paths})),
+                _ => Err(String::from("not supported")),
+            })
+        });
+
+        let stream = loop {
+            match UnixStream::connect(&socket) {
+                Ok(stream) => break stream,
+                Err(_) => thread::sleep(Duration::from_millis(10)),
+            }
+        };
+
+        let mut writer = stream.try_clone()?;
+        let mut lines = BufReader::new(stream).lines();
+        let mut request = |line: &str| -> Result<Value, Box<dyn std::error::Error>> {
+            writeln!(writer, "{}", line)?;
+            Ok(serde_json::from_str(&lines.next().unwrap()?)?)
+        };
+
+        assert_eq!(
+            json!({"ok": true, "paths": ["/Some File"]}),
+            request(r#"{"command": "convert", "into": "snake", "paths": ["/Some File"]}"#)?
+        );
+        assert_eq!(
+            json!({"ok": false, "error": "not supported"}),
+            request(r#"{"command": "plan", "into": "snake", "paths": []}"#)?
+        );
+        assert_eq!(
+            Value::Bool(false),
+            request(r#"{"command": "rename"}"#)?["ok"]
+        );
+
+        // a second daemon leaves the socket of the running one alone
+        assert!(serve(&socket, |_| Ok(Value::Null)).is_err());
+        assert_eq!(0o600, fs::metadata(&socket)?.permissions().mode() & 0o777);
+
+        // nor is anything which is not a socket replaced
+        let file = dir.path().join("notes.txt");
+        fs::write(&file, "notes")?;
+
+        assert!(serve(&file, |_| Ok(Value::Null)).is_err());
+        assert_eq!("notes", fs::read_to_string(&file)?);
+
+        Ok(())
+    }
+}
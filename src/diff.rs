@@ -0,0 +1,185 @@
+//! Writes the plan as a unified diff between the listing of every planned path as it is now
+//! and as it would be after the run, given '--output diff'.
+//!
+//! Both listings are sorted, so the lines they share are found by merging them rather than
+//! with a general diff algorithm.
+
+use std::io::{self, Write};
+
+/// The number of unchanged lines shown around each change.
+const CONTEXT: usize = 3;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Write a unified diff from `old` to `new`, both of which must be sorted and deduplicated.
+pub fn write<W: Write>(old: &[String], new: &[String], mut out: W) -> io::Result<()> {
+    let lines = merge(old, new);
+
+    if lines.iter().all(|line| matches!(line, Line::Same(_))) {
+        return Ok(());
+    }
+
+    writeln!(out, "--- current")?;
+    writeln!(out, "+++ planned")?;
+
+    // the position of each line in the old and new listings
+    let positions: Vec<(usize, usize)> = lines
+        .iter()
+        .scan((0, 0), |(o, n), line| {
+            let position = (*o, *n);
+
+            match line {
+                Line::Same(_) => (*o, *n) = (*o + 1, *n + 1),
+                Line::Removed(_) => *o += 1,
+                Line::Added(_) => *n += 1,
+            }
+
+            Some(position)
+        })
+        .collect();
+
+    for (start, end) in hunks(&lines) {
+        let hunk = &lines[start..end];
+        let (old_start, new_start) = positions[start];
+
+        let old_len = hunk.iter().filter(|l| !matches!(l, Line::Added(_))).count();
+        let new_len = hunk.iter().filter(|l| !matches!(l, Line::Removed(_))).count();
+
+        writeln!(
+            out,
+            "@@ -{} +{} @@",
+            range(old_start, old_len),
+            range(new_start, new_len)
+        )?;
+
+        for line in hunk {
+            match line {
+                Line::Same(text) => writeln!(out, " {}", text)?,
+                Line::Removed(text) => writeln!(out, "-{}", text)?,
+                Line::Added(text) => writeln!(out, "+{}", text)?,
+            }
+        }
+    }
+
+    out.flush()
+}
+
+/// Merge the sorted listings into a single sequence of lines, placing the removed lines of
+/// each change before the added ones.
+fn merge<'a>(old: &'a [String], new: &'a [String]) -> Vec<Line<'a>> {
+    let mut lines = vec![];
+    let mut added = vec![];
+    let (mut i, mut j) = (0, 0);
+
+    while i < old.len() || j < new.len() {
+        match (old.get(i), new.get(j)) {
+            (Some(a), Some(b)) if a == b => {
+                lines.append(&mut added);
+                lines.push(Line::Same(a));
+                i += 1;
+                j += 1;
+            }
+            (Some(a), Some(b)) if a < b => {
+                lines.push(Line::Removed(a));
+                i += 1;
+            }
+            (Some(a), None) => {
+                lines.push(Line::Removed(a));
+                i += 1;
+            }
+            (_, Some(b)) => {
+                added.push(Line::Added(b));
+                j += 1;
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+
+    lines.append(&mut added);
+    lines
+}
+
+/// The range of lines in each hunk, with changes closer than twice the context joined.
+fn hunks(lines: &[Line]) -> Vec<(usize, usize)> {
+    let mut hunks: Vec<(usize, usize)> = vec![];
+
+    for (i, _) in lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Line::Same(_)))
+    {
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + 1 + CONTEXT).min(lines.len());
+
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    hunks
+}
+
+/// A hunk range, whose start is the line before it when it is empty.
+fn range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::diff::write;
+
+    fn listing(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| String::from(*line)).collect()
+    }
+
+    #[test]
+    fn test_write() -> Result<(), Box<dyn std::error::Error>> {
+        let old = listing(&["A File", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k"]);
+        let new = listing(&["a_file", "b", "c", "d", "e", "f", "g", "h", "i", "j", "l"]);
+
+        let mut out = vec![];
+        write(&old, &new, &mut out)?;
+
+        assert_eq!(
+            "--- current\n\
+             +++ planned\n\
+             @@ -1,4 +1,4 @@\n\
+             -A File\n\
+             +a_file\n \
+             b\n \
+             c\n \
+             d\n\
+             @@ -8,4 +8,4 @@\n \
+             h\n \
+             i\n \
+             j\n\
+             -k\n\
+             +l\n",
+            String::from_utf8(out)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_unchanged() -> Result<(), Box<dyn std::error::Error>> {
+        let old = listing(&["a", "b"]);
+
+        let mut out = vec![];
+        write(&old, &old, &mut out)?;
+
+        assert!(out.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Remembers which directories are known to exist during a run, so that renaming many
+/// entries into the same parents only queries the filesystem once per parent.
+///
+/// A directory is only ever remembered along with all of its ancestors, so forgetting a
+/// directory which was moved away also forgets everything remembered beneath it.
+#[derive(Default)]
+pub struct DirCache {
+    existing: HashSet<PathBuf>,
+}
+
+impl DirCache {
+    /// Whether `dir` exists, only querying the filesystem until it is known to.
+    pub fn exists(&mut self, dir: &Path) -> bool {
+        if self.existing.contains(dir) {
+            return true;
+        }
+
+        let exists = dir.exists();
+
+        if exists {
+            self.insert(dir);
+        }
+
+        exists
+    }
+
+    /// Create `dir` and any of its missing ancestors unless it is already known to exist.
+    pub fn create_all(&mut self, dir: &Path) -> io::Result<()> {
+        if dir.as_os_str().is_empty() || self.exists(dir) {
+            return Ok(());
+        }
+
+        fs::create_dir_all(dir)?;
+        self.insert(dir);
+
+        Ok(())
+    }
+
+    /// Forget `source` and everything beneath it after it has been moved away.
+    pub fn moved(&mut self, source: &Path) {
+        if self.existing.remove(source) {
+            self.existing.retain(|dir| !dir.starts_with(source));
+        }
+    }
+
+    fn insert(&mut self, dir: &Path) {
+        for ancestor in dir.ancestors() {
+            if ancestor.as_os_str().is_empty() || !self.existing.insert(ancestor.to_path_buf()) {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::dircache::DirCache;
+
+    #[test]
+    fn test_moved_forgets_descendants() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let old = dir.path().join("Old Dir");
+        let new = dir.path().join("new_dir");
+
+        let mut cache = DirCache::default();
+
+        cache.create_all(&old.join("Child Dir"))?;
+        assert!(cache.exists(&old));
+
+        fs::rename(&old, &new)?;
+        cache.moved(&old);
+
+        assert!(!cache.exists(&old));
+        assert!(!cache.exists(&old.join("Child Dir")));
+        assert!(cache.exists(&new.join("Child Dir")));
+
+        Ok(())
+    }
+}
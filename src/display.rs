@@ -0,0 +1,142 @@
+//! Decides how paths are shown in the lines describing each rename, given '--absolute',
+//! '--relative-to', or '--root'. Only what is shown changes, every path is still renamed as
+//! given.
+
+use std::path::{self, Component, Path, PathBuf, MAIN_SEPARATOR_STR};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PathStyle {
+    /// Show paths as they were given or found.
+    AsGiven,
+
+    Absolute,
+
+    /// Show paths relative to a directory, which is absolute.
+    RelativeTo(PathBuf),
+
+    /// Show the paths beneath the '--root' directory as absolute paths from it, like
+    /// '/Some Dir' for '<root>/Some Dir'.
+    Rooted(PathBuf),
+}
+
+impl PathStyle {
+    /// Show paths relative to `dir`, which is made absolute against the working directory.
+    pub fn relative_to(dir: &Path) -> PathStyle {
+        PathStyle::RelativeTo(absolute(dir))
+    }
+
+    pub fn show(&self, path: &Path) -> PathBuf {
+        match self {
+            PathStyle::AsGiven => path.to_path_buf(),
+            PathStyle::Absolute => absolute(path),
+            PathStyle::RelativeTo(dir) => relative(&absolute(path), dir),
+            PathStyle::Rooted(root) => match path.strip_prefix(root) {
+                Ok(rooted) => Path::new(MAIN_SEPARATOR_STR).join(rooted),
+                Err(_) => path.to_path_buf(),
+            },
+        }
+    }
+}
+
+/// `path` made absolute and its '..' components resolved without following symlinks, or as
+/// it is when there is no working directory to resolve it against.
+pub fn absolute(path: &Path) -> PathBuf {
+    let absolute = match path::absolute(path) {
+        Ok(absolute) => absolute,
+        Err(_) => return path.to_path_buf(),
+    };
+
+    let mut resolved = PathBuf::new();
+
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            component => resolved.push(component),
+        }
+    }
+
+    resolved
+}
+
+/// The absolute `path` relative to the absolute `dir`, leaving `dir` through '..' if needed.
+fn relative(path: &Path, dir: &Path) -> PathBuf {
+    let mut path_components = path.components().peekable();
+    let mut dir_components = dir.components().peekable();
+
+    while let (Some(a), Some(b)) = (path_components.peek(), dir_components.peek()) {
+        if a != b {
+            break;
+        }
+
+        path_components.next();
+        dir_components.next();
+    }
+
+    // paths on different windows drives have nothing in common to be relative to
+    if path_components
+        .peek()
+        .is_some_and(|component| matches!(component, Component::Prefix(_) | Component::RootDir))
+    {
+        return path.to_path_buf();
+    }
+
+    let relative: PathBuf = dir_components
+        .map(|_| Component::ParentDir)
+        .chain(path_components)
+        .collect();
+
+    if relative.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        relative
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use crate::display::{relative, PathStyle};
+
+    #[test]
+    fn test_relative() {
+        let dir = Path::new("/some/deep/root");
+
+        assert_eq!(
+            PathBuf::from("a/Some File"),
+            relative(Path::new("/some/deep/root/a/Some File"), dir)
+        );
+        assert_eq!(
+            PathBuf::from("../../other/file"),
+            relative(Path::new("/some/other/file"), dir)
+        );
+        assert_eq!(PathBuf::from("."), relative(dir, dir));
+    }
+
+    #[test]
+    fn test_show() -> Result<(), Box<dyn std::error::Error>> {
+        let cwd = std::env::current_dir()?;
+
+        assert_eq!(
+            cwd.join("Some File"),
+            PathStyle::Absolute.show(Path::new("./Some File"))
+        );
+        assert_eq!(
+            PathBuf::from("../Some File"),
+            PathStyle::relative_to(Path::new("dir")).show(Path::new("Some File"))
+        );
+        assert_eq!(
+            PathBuf::from(cwd.file_name().unwrap()).join("Some File"),
+            PathStyle::relative_to(Path::new("..")).show(Path::new("Some File"))
+        );
+        assert_eq!(
+            Path::new("/").join("Some File"),
+            PathStyle::Rooted(PathBuf::from("root")).show(Path::new("root/Some File"))
+        );
+
+        Ok(())
+    }
+}
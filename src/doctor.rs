@@ -0,0 +1,451 @@
+//! Reports the naming health of a tree, given as 'ccpath doctor DIR', without renaming
+//! anything: names following no convention, directories mixing conventions, and names which
+//! won't survive being copied to Windows, synced to the cloud, or read on another system.
+
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use unicode_normalization::is_nfc;
+use walkdir::WalkDir;
+
+use convert_path::sanitize::{self, Platform};
+use convert_path::walk;
+use convert_path::Convention;
+
+use crate::stats::Entries;
+
+pub enum Finding {
+    /// The name is not written in any convention.
+    NoConvention(PathBuf),
+
+    /// The entries of the directory are written in conventions no single one of them shares,
+    /// along with how many are written in each.
+    MixedConventions(PathBuf, Vec<(Convention, usize)>),
+
+    /// The name can't be used as is elsewhere, for the given reason.
+    Unportable(PathBuf, String),
+
+    /// The name is longer than the limit, in characters.
+    Overlong(PathBuf, usize),
+
+    /// The name is not in NFC, so systems normalizing names may see a different one.
+    Unnormalized(PathBuf),
+
+    InvalidUtf8(PathBuf),
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Finding::NoConvention(path) => {
+                write!(f, "'{}' follows no naming convention", path.display())
+            }
+            Finding::MixedConventions(path, counts) => {
+                let counts: Vec<String> = counts
+                    .iter()
+                    .map(|(convention, count)| format!("{} ({})", convention.name(), count))
+                    .collect();
+
+                write!(
+                    f,
+                    "'{}' mixes the conventions {}",
+                    path.display(),
+                    counts.join(", ")
+                )
+            }
+            Finding::Unportable(path, reason) => write!(f, "'{}' {}", path.display(), reason),
+            Finding::Overlong(path, len) => write!(
+                f,
+                "'{}' is {} characters long, too long for some filesystems and sync services",
+                path.display(),
+                len
+            ),
+            Finding::Unnormalized(path) => write!(
+                f,
+                "'{}' is not in NFC, so macOS and cloud sync services may change it",
+                path.display()
+            ),
+            Finding::InvalidUtf8(path) => write!(
+                f,
+                "'{}' is not valid utf-8 and cannot be converted",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// How many names are written in each convention, in several at once, or in none.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Histogram {
+    /// The names written in exactly one convention, by convention in the order first found.
+    pub conventions: Vec<(Convention, usize)>,
+
+    pub ambiguous: usize,
+    pub none: usize,
+}
+
+impl Histogram {
+    fn record(&mut self, found: &[Convention]) {
+        match found {
+            [] => self.none += 1,
+            [convention] => match self.conventions.iter_mut().find(|(c, _)| c == convention) {
+                Some((_, count)) => *count += 1,
+                None => self.conventions.push((*convention, 1)),
+            },
+            _ => self.ambiguous += 1,
+        }
+    }
+
+    /// Every row counted at least once, named by its convention.
+    fn rows(&self) -> Vec<(&'static str, usize)> {
+        self.conventions
+            .iter()
+            .map(|(convention, count)| (convention.name(), *count))
+            .chain([("ambiguous", self.ambiguous), ("none", self.none)])
+            .filter(|(_, count)| *count > 0)
+            .collect()
+    }
+
+    fn json(&self) -> HistogramJson {
+        HistogramJson {
+            conventions: Entries(
+                self.conventions
+                    .iter()
+                    .map(|(convention, count)| (convention.name(), *count))
+                    .collect(),
+            ),
+            ambiguous: self.ambiguous,
+            none: self.none,
+        }
+    }
+}
+
+/// A histogram as written by '--histogram --json'.
+#[derive(Serialize)]
+struct HistogramJson {
+    conventions: Entries<&'static str, usize>,
+    ambiguous: usize,
+    none: usize,
+}
+
+#[derive(Serialize)]
+struct DirectoryJson {
+    path: String,
+
+    #[serde(flatten)]
+    histogram: HistogramJson,
+}
+
+#[derive(Serialize)]
+struct HistogramsJson {
+    directories: Vec<DirectoryJson>,
+    overall: HistogramJson,
+}
+
+pub struct Report {
+    /// The number of entries examined, not counting the root.
+    pub examined: usize,
+
+    pub findings: Vec<Finding>,
+
+    /// The histogram of the entries of each directory, in the order they were examined.
+    pub histograms: Vec<(PathBuf, Histogram)>,
+
+    /// The histogram of every entry.
+    pub overall: Histogram,
+}
+
+/// The width of the longest bar of a histogram.
+const BAR_WIDTH: usize = 40;
+
+impl Report {
+    /// Write the histogram of each directory and then the overall one as bars, all scaled
+    /// alike so they can be compared.
+    pub fn write_histograms<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let max = self
+            .overall
+            .rows()
+            .into_iter()
+            .map(|(_, count)| count)
+            .max();
+        let max = match max {
+            Some(max) => max,
+            None => return Ok(()),
+        };
+
+        let titled = self
+            .histograms
+            .iter()
+            .map(|(dir, histogram)| (format!("'{}'", dir.display()), histogram))
+            .chain([(String::from("overall"), &self.overall)]);
+
+        for (title, histogram) in titled {
+            writeln!(out, "{}", title)?;
+
+            for (name, count) in histogram.rows() {
+                // any row counted at all gets a bar, however small
+                let width = (count * BAR_WIDTH / max).max(1);
+
+                writeln!(out, "  {:<9} {:>6} {}", name, count, "#".repeat(width))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn write_histograms_json<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let directories = self
+            .histograms
+            .iter()
+            .map(|(dir, histogram)| DirectoryJson {
+                path: dir.to_string_lossy().into_owned(),
+                histogram: histogram.json(),
+            })
+            .collect();
+
+        let histograms = HistogramsJson {
+            directories,
+            overall: self.overall.json(),
+        };
+
+        serde_json::to_writer_pretty(&mut out, &histograms)?;
+        writeln!(out)
+    }
+}
+
+/// Why `name` can't be used as is on Windows or by cloud sync services, if it can't.
+fn unportable(name: &str) -> Option<String> {
+    if let Err(reason) = sanitize::check(name, Platform::Windows) {
+        return Some(format!("{} on windows", reason));
+    }
+
+    if name.starts_with(' ') {
+        return Some(String::from(
+            "starts with a space, which cloud sync services remove",
+        ));
+    }
+
+    None
+}
+
+/// Examine every entry under `dir`, reporting names longer than `max_name_length`
+/// characters among the other findings.
+pub fn examine(dir: &Path, max_name_length: usize) -> Report {
+    let mut report = Report {
+        examined: 0,
+        findings: vec![],
+        histograms: vec![],
+        overall: Histogram::default(),
+    };
+
+    let mut dirs: Vec<PathBuf> = vec![];
+    let mut conventions: HashMap<PathBuf, Vec<Vec<Convention>>> = HashMap::new();
+    let mut histograms: HashMap<PathBuf, Histogram> = HashMap::new();
+
+    // files the operating system creates by itself say nothing about how the tree is named
+    let walker = WalkDir::new(dir)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| !walk::is_default_ignored(&entry.file_name().to_string_lossy()));
+
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                warning!("{}", err);
+                continue;
+            }
+        };
+
+        report.examined += 1;
+
+        let path = entry.path();
+        let name = match entry.file_name().to_str() {
+            Some(name) => name,
+            None => {
+                report
+                    .findings
+                    .push(Finding::InvalidUtf8(path.to_path_buf()));
+                continue;
+            }
+        };
+
+        if let Some(reason) = unportable(name) {
+            report
+                .findings
+                .push(Finding::Unportable(path.to_path_buf(), reason));
+        }
+
+        let len = name.chars().count();
+        if len > max_name_length {
+            report
+                .findings
+                .push(Finding::Overlong(path.to_path_buf(), len));
+        }
+
+        if !is_nfc(name) {
+            report
+                .findings
+                .push(Finding::Unnormalized(path.to_path_buf()));
+        }
+
+        // hidden files are judged by the name after their leading dots
+        let found = convert_path::conforming_conventions(name.trim_start_matches('.'));
+        let parent = path.parent().unwrap_or(dir);
+
+        report.overall.record(&found);
+        histograms
+            .entry(parent.to_path_buf())
+            .or_insert_with(|| {
+                dirs.push(parent.to_path_buf());
+                Histogram::default()
+            })
+            .record(&found);
+
+        if found.is_empty() {
+            report
+                .findings
+                .push(Finding::NoConvention(path.to_path_buf()));
+            continue;
+        }
+
+        conventions
+            .entry(parent.to_path_buf())
+            .or_default()
+            .push(found);
+    }
+
+    for dir in dirs {
+        let histogram = histograms.remove(&dir).unwrap_or_default();
+        report.histograms.push((dir.clone(), histogram));
+
+        let names = match conventions.get(&dir) {
+            Some(names) => names,
+            None => continue,
+        };
+
+        let is_shared =
+            |convention: &Convention| names.iter().all(|found| found.contains(convention));
+
+        if names[0].iter().any(is_shared) {
+            continue;
+        }
+
+        // each name is counted once, towards the first convention it is detected as
+        let mut counts: Vec<(Convention, usize)> = vec![];
+
+        for found in names {
+            match counts
+                .iter_mut()
+                .find(|(convention, _)| *convention == found[0])
+            {
+                Some((_, count)) => *count += 1,
+                None => counts.push((found[0], 1)),
+            }
+        }
+
+        counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+        report.findings.push(Finding::MixedConventions(dir, counts));
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+
+    use convert_path::Convention;
+
+    use crate::doctor::{examine, Finding, Histogram};
+
+    /// The kind of a finding along with the name of the path it is about.
+    fn kind(finding: &Finding) -> (&'static str, String) {
+        let (kind, path): (&str, &Path) = match finding {
+            Finding::NoConvention(path) => ("no convention", path),
+            Finding::MixedConventions(path, _) => ("mixed", path),
+            Finding::Unportable(path, _) => ("unportable", path),
+            Finding::Overlong(path, _) => ("overlong", path),
+            Finding::Unnormalized(path) => ("unnormalized", path),
+            Finding::InvalidUtf8(path) => ("invalid utf-8", path),
+        };
+
+        (
+            kind,
+            path.file_name().unwrap().to_string_lossy().into_owned(),
+        )
+    }
+
+    #[test]
+    fn test_examine() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let mixed = dir.path().join("mixed");
+        let consistent = dir.path().join("consistent");
+
+        fs::create_dir(&mixed)?;
+        fs::create_dir(&consistent)?;
+
+        for name in ["some_file", "other_file", "third-file", "readme"] {
+            fs::write(mixed.join(name), "")?;
+        }
+
+        for name in [
+            ".hidden",
+            "readme",
+            "some_file",
+            "Some File_22",
+            "cafe\u{301}",
+            "what?",
+        ] {
+            fs::write(consistent.join(name), "")?;
+        }
+
+        let report = examine(dir.path(), 11);
+        let found: Vec<(&str, String)> = report.findings.iter().map(kind).collect();
+        let expected: Vec<(&str, String)> = vec![
+            ("overlong", "Some File_22"),
+            ("no convention", "Some File_22"),
+            ("unnormalized", "cafe\u{301}"),
+            ("no convention", "cafe\u{301}"),
+            ("unportable", "what?"),
+            ("no convention", "what?"),
+            ("mixed", "mixed"),
+        ]
+        .into_iter()
+        .map(|(kind, name)| (kind, String::from(name)))
+        .collect();
+
+        assert_eq!(12, report.examined);
+        assert_eq!(expected, found);
+
+        assert!(matches!(
+            report.findings.last(),
+            Some(Finding::MixedConventions(_, counts)) if *counts == vec![
+                (Convention::SnakeCase, 2),
+                (Convention::FlatCase, 1),
+                (Convention::KebabCase, 1),
+            ]
+        ));
+
+        assert_eq!(
+            Some(&(
+                mixed.clone(),
+                Histogram {
+                    conventions: vec![(Convention::SnakeCase, 2), (Convention::KebabCase, 1)],
+                    ambiguous: 1,
+                    none: 0,
+                }
+            )),
+            report.histograms.iter().find(|(dir, _)| *dir == mixed)
+        );
+        assert_eq!(3, report.overall.none);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+//! Lists the files git tracks beneath a directory for '--git-tracked-only', so that
+//! normalizing the names in a repository leaves build outputs, untracked artifacts and
+//! ignored directories alone.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::manifest::path_from_bytes;
+
+/// The files tracked beneath a directory, along with every directory holding one of them.
+#[derive(Debug)]
+pub struct Tracked {
+    paths: HashSet<PathBuf>,
+}
+
+impl Tracked {
+    /// List the files tracked beneath `dir`, failing unless it is inside a git work tree.
+    pub fn list(dir: &Path) -> Result<Tracked, String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["ls-files", "--cached", "-z"])
+            .output()
+            .map_err(|err| format!("could not run git: {}", err))?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "could not list the files git tracks in '{}': {}",
+                dir.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        let mut paths = HashSet::new();
+        paths.insert(dir.to_path_buf());
+
+        for file in output
+            .stdout
+            .split(|b| *b == 0)
+            .filter(|file| !file.is_empty())
+        {
+            let path = dir.join(path_from_bytes(file));
+
+            // every directory above a tracked file is walked to reach it
+            for ancestor in path.ancestors() {
+                if !paths.insert(ancestor.to_path_buf()) {
+                    break;
+                }
+            }
+        }
+
+        Ok(Tracked { paths })
+    }
+
+    /// Whether `path` is a tracked file or a directory holding one.
+    pub fn contains(&self, path: &Path) -> bool {
+        self.paths.contains(path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::process::Command;
+
+    use crate::git::Tracked;
+
+    #[test]
+    fn test_list() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path().join("Some Repo");
+
+        fs::create_dir_all(root.join("Some Dir"))?;
+        fs::create_dir_all(root.join("Build Output"))?;
+        fs::write(root.join("Some Dir/Some File.rs"), "")?;
+        fs::write(root.join("Build Output/Some Object.o"), "")?;
+
+        assert!(Tracked::list(&root).is_err());
+
+        let git = |args: &[&str]| Command::new("git").arg("-C").arg(&root).args(args).output();
+        git(&["init", "--quiet"])?;
+        git(&["add", "Some Dir/Some File.rs"])?;
+
+        let tracked = Tracked::list(&root)?;
+
+        assert!(tracked.contains(&root));
+        assert!(tracked.contains(&root.join("Some Dir")));
+        assert!(tracked.contains(&root.join("Some Dir/Some File.rs")));
+        assert!(!tracked.contains(&root.join("Build Output")));
+        assert!(!tracked.contains(&root.join("Build Output/Some Object.o")));
+
+        Ok(())
+    }
+}
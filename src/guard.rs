@@ -0,0 +1,312 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{self, Component, Path, PathBuf};
+use std::sync::OnceLock;
+
+/// The directory containing `path`, treating a bare file name as relative to the current directory.
+pub fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// The deepest directory containing every one of `paths` without being one of them, or none
+/// if they have no directory in common, like relative paths starting with different names.
+pub fn common_ancestor<P, I>(paths: I) -> Option<PathBuf>
+where
+    P: AsRef<Path>,
+    I: IntoIterator<Item = P>,
+{
+    let mut common: Option<PathBuf> = None;
+
+    for path in paths {
+        let parent = path.as_ref().parent()?;
+
+        common = Some(match common {
+            None => parent.to_path_buf(),
+            Some(common) => common
+                .components()
+                .zip(parent.components())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+
+    let common = common?;
+
+    if common.as_os_str().is_empty() {
+        None
+    } else {
+        Some(common)
+    }
+}
+
+/// The leading components of `path` before its first normal component (ie `/`, `..`, or `.`).
+pub fn anchor(path: &Path) -> PathBuf {
+    let anchor: PathBuf = path
+        .components()
+        .take_while(|component| !matches!(component, Component::Normal(_)))
+        .collect();
+
+    if anchor.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        anchor
+    }
+}
+
+/// Whether `target` would be placed strictly under `root` once `.` and `..` components and any
+/// symlinks in its existing parent directories are resolved.
+///
+/// The final component of `target` is never resolved since a rename replaces a symlink rather
+/// than writing through it.
+pub fn is_contained(target: &Path, root: &Path) -> bool {
+    // a trailing '.' component is silently dropped by Path::components
+    let raw = target.as_os_str().to_string_lossy();
+    let mut chars = raw.chars().rev();
+
+    if chars.next() == Some('.') && chars.next().is_none_or(path::is_separator) {
+        return false;
+    }
+
+    if !matches!(target.components().next_back(), Some(Component::Normal(_))) {
+        return false;
+    }
+
+    match (resolve(parent_dir(target)), resolve(root)) {
+        (Ok(parent), Ok(root)) => parent.starts_with(root),
+        _ => false,
+    }
+}
+
+/// `path` interpreted beneath `root` for '--root', whether it is absolute or relative, or none
+/// if a '..' component or a symlink would lead it outside of `root`.
+pub fn rooted(root: &Path, path: &Path) -> Option<PathBuf> {
+    let mut rooted = root.to_path_buf();
+    let mut depth = 0;
+
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+            Component::ParentDir if depth == 0 => return None,
+            Component::ParentDir => {
+                rooted.pop();
+                depth -= 1;
+            }
+            Component::Normal(name) => {
+                rooted.push(name);
+                depth += 1;
+            }
+        }
+    }
+
+    if depth > 0 && !is_contained(&rooted, root) {
+        return None;
+    }
+
+    Some(rooted)
+}
+
+/// Describe why recursively renaming everything under `dir` is likely a mistake, if it is.
+pub fn dangerous_root(dir: &Path) -> Option<String> {
+    let dir = fs::canonicalize(dir).ok()?;
+
+    if dir.parent().is_none() {
+        return Some(tr!("root-filesystem"));
+    }
+
+    let home = env::var_os("HOME").and_then(|home| fs::canonicalize(home).ok());
+
+    if home.is_some_and(|home| home == dir) {
+        return Some(tr!("root-home"));
+    }
+
+    if is_mount_point(&dir) {
+        return Some(tr!("root-mount-point"));
+    }
+
+    None
+}
+
+#[cfg(unix)]
+fn is_mount_point(dir: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (fs::metadata(dir), fs::metadata(parent_dir(dir))) {
+        (Ok(dir), Ok(parent)) => dir.dev() != parent.dev(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_mount_point(_dir: &Path) -> bool {
+    false
+}
+
+/// Lexically resolve `path` into an absolute path, canonicalizing any symlinks along the way.
+fn resolve(path: &Path) -> io::Result<PathBuf> {
+    static CURRENT_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+    let current_dir = CURRENT_DIR
+        .get_or_init(|| env::current_dir().and_then(fs::canonicalize).ok())
+        .clone();
+
+    // pushing the prefix of a Windows path like 'C:folder' would replace the current
+    // directory rather than resolve the path against it, so the platform makes it absolute
+    // and its root is canonicalized like the current directory is
+    let (mut resolved, path) = match path.components().next() {
+        Some(Component::Prefix(_)) => {
+            let absolute = path::absolute(path)?;
+            let root = anchor(&absolute);
+            let rest = absolute
+                .strip_prefix(&root)
+                .unwrap_or(&absolute)
+                .to_path_buf();
+
+            (fs::canonicalize(root)?, rest)
+        }
+        _ => match current_dir {
+            Some(current_dir) => (current_dir, path.to_path_buf()),
+            None => (fs::canonicalize(env::current_dir()?)?, path.to_path_buf()),
+        },
+    };
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            component => {
+                resolved.push(component);
+
+                if fs::symlink_metadata(&resolved).is_ok_and(|m| m.file_type().is_symlink()) {
+                    resolved = fs::canonicalize(&resolved)?;
+                }
+            }
+        }
+    }
+
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use crate::guard::{anchor, common_ancestor, is_contained, rooted};
+
+    #[test]
+    fn test_anchor() {
+        assert_eq!(Path::new("/"), anchor(Path::new("/Some Dir/Some File")));
+        assert_eq!(Path::new("../.."), anchor(Path::new("../../Some File")));
+        assert_eq!(Path::new("."), anchor(Path::new("Some Dir/Some File")));
+    }
+
+    #[test]
+    fn test_common_ancestor() {
+        let common = |paths: &[&str]| common_ancestor(paths.iter().map(Path::new));
+
+        assert_eq!(
+            Some(PathBuf::from("/tmp/upload")),
+            common(&["/tmp/upload/Some Dir/Some File", "/tmp/upload/Other File"])
+        );
+        assert_eq!(
+            Some(PathBuf::from("/tmp")),
+            common(&["/tmp/upload", "/tmp/upload/Some File"])
+        );
+        assert_eq!(
+            None,
+            common(&["Some Dir/Some File", "Other Dir/Other File"])
+        );
+        assert_eq!(None, common(&["Some File"]));
+        assert_eq!(None, common(&[]));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_rooted() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path().join("root");
+
+        fs::create_dir(&root)?;
+        std::os::unix::fs::symlink(dir.path(), root.join("escape"))?;
+
+        let rooted = |path: &str| rooted(&root, Path::new(path));
+
+        assert_eq!(
+            Some(root.join("Some Dir/Some File")),
+            rooted("/Some Dir/Some File")
+        );
+        assert_eq!(
+            Some(root.join("Some File")),
+            rooted("Some Dir/../Some File")
+        );
+        assert_eq!(Some(root.clone()), rooted("/"));
+        assert_eq!(None, rooted("../Some File"));
+        assert_eq!(None, rooted("/escape/Some File"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_contained_parent_dir() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path().join("root");
+
+        fs::create_dir(&root)?;
+
+        assert!(is_contained(&root.join("some_file"), &root));
+        assert!(is_contained(&root.join("new_dir/some_file"), &root));
+        assert!(!is_contained(&root.join("../some_file"), &root));
+        assert!(!is_contained(&root.join("some_dir/../../some_file"), &root));
+        assert!(!is_contained(&root.join(".."), &root));
+        assert!(!is_contained(&root.join("."), &root));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_is_contained_symlink() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path().join("root");
+        let outside = dir.path().join("outside");
+
+        fs::create_dir(&root)?;
+        fs::create_dir(&outside)?;
+        std::os::unix::fs::symlink(&outside, root.join("some_link"))?;
+
+        assert!(!is_contained(&root.join("some_link/some_file"), &root));
+
+        // the final component is replaced rather than followed
+        assert!(is_contained(&root.join("some_link"), &root));
+
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_is_contained_drive_relative() -> Result<(), Box<dyn std::error::Error>> {
+        let current_dir = std::env::current_dir()?;
+        let drive = current_dir.to_string_lossy()[..2].to_string();
+
+        // 'C:Some File' is relative to the current directory of drive 'C:'
+        let target = Path::new(&drive).join("Some File");
+        assert!(is_contained(&target, Path::new(".")));
+        assert!(is_contained(
+            Path::new(&format!(r"{}Some Dir/Other Dir\Some File", drive)),
+            &current_dir
+        ));
+        assert!(!is_contained(
+            Path::new(&format!(r"{}..\Some File", drive)),
+            &current_dir
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+//! Recognizes hard links to the same file, as in the hardlink farms of backup snapshots, so
+//! that each file is handled once however many names it has.
+//!
+//! A rename onto another link to the same file does nothing at all, and two links converting
+//! to the same name are not really a collision since only the name of one of them is needed.
+//! Only unix counts links, so elsewhere every file is taken to have a single name.
+
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::guard;
+use crate::snapshot::Identity;
+
+/// The number of names of the file `metadata` describes.
+#[cfg(unix)]
+fn link_count(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    metadata.nlink()
+}
+
+#[cfg(not(unix))]
+fn link_count(_: &Metadata) -> u64 {
+    1
+}
+
+/// The identity of the file `metadata` describes, if it is a file with more than one name.
+fn linked_identity(metadata: &Metadata) -> Option<Identity> {
+    if metadata.is_dir() || link_count(metadata) < 2 {
+        return None;
+    }
+
+    Some(Identity::from(metadata))
+}
+
+/// Whether every one of `paths` is a link to the same file.
+pub fn are_links<P: AsRef<Path>>(paths: &[P]) -> bool {
+    let mut identities = paths.iter().map(|path| {
+        fs::symlink_metadata(path)
+            .ok()
+            .as_ref()
+            .and_then(linked_identity)
+    });
+
+    match identities.next() {
+        Some(Some(first)) => identities.all(|identity| identity == Some(first)),
+        _ => false,
+    }
+}
+
+/// Whether `target` is another link to the file at `source`, rather than a different file or
+/// `source` itself named with a different case on a volume ignoring case.
+pub fn is_other_link(source: &Path, target: &Path) -> bool {
+    let target_identity = match fs::symlink_metadata(target) {
+        Ok(metadata) => linked_identity(&metadata),
+        Err(_) => return false,
+    };
+
+    let source_identity = fs::symlink_metadata(source)
+        .ok()
+        .as_ref()
+        .and_then(linked_identity);
+
+    if target_identity.is_none() || target_identity != source_identity {
+        return false;
+    }
+
+    let dir = guard::parent_dir(target);
+
+    if Identity::of(guard::parent_dir(source)) != Identity::of(dir) {
+        return true;
+    }
+
+    // within a single directory only another link is listed under exactly the target's name
+    let name = target.file_name();
+
+    source.file_name() != name
+        && fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .any(|entry| Some(entry.file_name().as_os_str()) == name)
+}
+
+/// The files with several names met while copying a tree, so that each is copied once and
+/// linked to from there for every other name.
+#[derive(Debug, Default)]
+pub struct Copied {
+    copies: HashMap<Identity, PathBuf>,
+}
+
+impl Copied {
+    /// Link `target` to the copy already made of the file at `source` if there is one,
+    /// otherwise make it with `copy`, remembering it if the file has several names.
+    pub fn link_or_copy<F>(&mut self, source: &Path, target: &Path, copy: F) -> io::Result<()>
+    where
+        F: FnOnce() -> io::Result<()>,
+    {
+        let identity = linked_identity(&fs::symlink_metadata(source)?);
+
+        if let Some(copied) = identity.and_then(|identity| self.copies.get(&identity)) {
+            return fs::hard_link(copied, target);
+        }
+
+        copy()?;
+
+        if let Some(identity) = identity {
+            self.copies.insert(identity, target.to_path_buf());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use std::fs;
+
+    use crate::hardlink::{are_links, is_other_link, Copied};
+
+    #[test]
+    fn test_links() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("Some File");
+        let link = dir.path().join("some-file");
+        let other = dir.path().join("Other File");
+
+        fs::write(&file, "")?;
+        fs::write(&other, "")?;
+        fs::hard_link(&file, &link)?;
+
+        assert!(are_links(&[&file, &link]));
+        assert!(!are_links(&[&file, &other]));
+        assert!(!are_links(&[&other]));
+
+        assert!(is_other_link(&file, &link));
+        assert!(!is_other_link(&file, &other));
+        assert!(!is_other_link(&file, &dir.path().join("some_file")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_link_or_copy() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("Some File");
+        let link = dir.path().join("Some Link");
+
+        fs::write(&file, "content")?;
+        fs::hard_link(&file, &link)?;
+
+        let mut copied = Copied::default();
+        let copy = |source, target| move || fs::copy(source, target).map(|_| ());
+
+        let first = dir.path().join("first");
+        let second = dir.path().join("second");
+        copied.link_or_copy(&file, &first, copy(&file, &first))?;
+        copied.link_or_copy(&link, &second, copy(&link, &second))?;
+
+        // the second name is linked to the first copy rather than copied again
+        assert!(are_links(&[&first, &second]));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+//! Translates the messages shown to the user into the language selected by the locale
+//! environment variables, checked in the order LC_ALL, LC_MESSAGES, then LANG like any
+//! other program. Messages are written in Fluent, one file per language under 'locales/',
+//! and anything not translated into the selected language is shown in English.
+//!
+//! Every message shown to the user has an id in 'locales/en/ccpath.ftl', so new ones are
+//! added there and to every other locale rather than written in English with `error!`,
+//! `warning!`, or `info!` directly. Only the output meant for other tools, like plans, scripts,
+//! and reports, is never translated.
+
+use std::env;
+use std::path::Display;
+use std::sync::OnceLock;
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use unic_langid::LanguageIdentifier;
+
+/// Every supported language along with its messages, English first since it is the
+/// fallback.
+const LOCALES: [(&str, &str); 2] = [
+    ("en", include_str!("../locales/en/ccpath.ftl")),
+    ("ja", include_str!("../locales/ja/ccpath.ftl")),
+];
+
+/// The bundle of the selected language followed by the English one.
+static BUNDLES: OnceLock<Vec<FluentBundle<FluentResource>>> = OnceLock::new();
+
+/// A value which can be given to a message.
+pub trait Arg {
+    fn into_value(self) -> FluentValue<'static>;
+}
+
+impl Arg for &str {
+    fn into_value(self) -> FluentValue<'static> {
+        FluentValue::from(String::from(self))
+    }
+}
+
+impl Arg for String {
+    fn into_value(self) -> FluentValue<'static> {
+        FluentValue::from(self)
+    }
+}
+
+impl Arg for Display<'_> {
+    fn into_value(self) -> FluentValue<'static> {
+        FluentValue::from(self.to_string())
+    }
+}
+
+impl Arg for usize {
+    fn into_value(self) -> FluentValue<'static> {
+        FluentValue::from(self)
+    }
+}
+
+impl Arg for u32 {
+    fn into_value(self) -> FluentValue<'static> {
+        FluentValue::from(self)
+    }
+}
+
+impl Arg for u64 {
+    fn into_value(self) -> FluentValue<'static> {
+        FluentValue::from(self)
+    }
+}
+
+/// The message with the given id in the language of the user, like
+/// `tr!("no-such-path", path = path.display())`.
+macro_rules! tr {
+    ($id:literal $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::i18n::message(
+            $id,
+            vec![$((stringify!($name), $crate::i18n::Arg::into_value($value))),*],
+        )
+    };
+}
+
+/// The language selected by the locale environment variables, if any.
+fn requested_language() -> Option<LanguageIdentifier> {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())?;
+
+    parse_locale(&locale)
+}
+
+/// Parse a POSIX locale like 'ja_JP.UTF-8' into the language it selects.
+fn parse_locale(locale: &str) -> Option<LanguageIdentifier> {
+    let tag = locale.split(['.', '@']).next()?.replace('_', "-");
+
+    // 'C' and 'POSIX' select no language at all
+    match tag.as_str() {
+        "C" | "POSIX" => None,
+        tag => tag.parse().ok(),
+    }
+}
+
+fn bundle(language: &str, source: &str) -> FluentBundle<FluentResource> {
+    let id: LanguageIdentifier = language.parse().unwrap();
+    let mut bundle = FluentBundle::new_concurrent(vec![id]);
+
+    // messages are only ever shown in a terminal, where the isolation marks would show up
+    bundle.set_use_isolating(false);
+
+    let resource = FluentResource::try_new(String::from(source))
+        .unwrap_or_else(|(resource, _)| resource);
+    let _ = bundle.add_resource(resource);
+
+    bundle
+}
+
+fn bundles() -> &'static [FluentBundle<FluentResource>] {
+    BUNDLES.get_or_init(|| {
+        let requested = requested_language();
+        let selected = LOCALES.iter().skip(1).find(|(language, _)| {
+            requested
+                .as_ref()
+                .is_some_and(|requested| requested.language.as_str() == *language)
+        });
+
+        selected
+            .into_iter()
+            .chain(LOCALES.iter().take(1))
+            .map(|(language, source)| bundle(language, source))
+            .collect()
+    })
+}
+
+/// Format the message `id` with `args`, falling back to English and then to the id itself.
+pub fn message(id: &str, args: Vec<(&str, FluentValue<'static>)>) -> String {
+    let mut fluent_args = FluentArgs::new();
+
+    for (name, value) in args {
+        fluent_args.set(name, value);
+    }
+
+    for bundle in bundles() {
+        if let Some(pattern) = bundle.get_message(id).and_then(|message| message.value()) {
+            let mut errors = vec![];
+
+            return bundle
+                .format_pattern(pattern, Some(&fluent_args), &mut errors)
+                .into_owned();
+        }
+    }
+
+    String::from(id)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use crate::i18n::{bundle, parse_locale, LOCALES};
+
+    /// The id of every message in `source`, each of which starts a line.
+    fn ids(source: &str) -> HashSet<&str> {
+        source
+            .lines()
+            .filter(|line| line.starts_with(|c: char| c.is_ascii_lowercase()))
+            .filter_map(|line| line.split(" =").next())
+            .collect()
+    }
+
+    #[test]
+    fn test_every_message_is_translated() {
+        let english = ids(LOCALES[0].1);
+
+        for (language, source) in LOCALES.iter().skip(1) {
+            assert_eq!(english, ids(source), "{}", language);
+        }
+    }
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!(Some("ja-JP"), parse_locale("ja_JP.UTF-8").map(|id| id.to_string()).as_deref());
+        assert_eq!(Some("de-DE"), parse_locale("de_DE@euro").map(|id| id.to_string()).as_deref());
+        assert_eq!(None, parse_locale("C.UTF-8"));
+    }
+
+    #[test]
+    fn test_bundle() {
+        let bundle = bundle(LOCALES[1].0, LOCALES[1].1);
+        let pattern = bundle.get_message("level-error").unwrap().value().unwrap();
+
+        assert_eq!("エラー", bundle.format_pattern(pattern, None, &mut vec![]));
+    }
+}
@@ -0,0 +1,104 @@
+//! Reads the exclude patterns of '--exclude-from' files and of the ignore file every
+//! recursive run honors, '$XDG_CONFIG_HOME/ccpath/ignore' or '~/.config/ccpath/ignore', so that
+//! paths like 'node_modules/' or '.git/' need not be excluded by hand on every run.
+//!
+//! Patterns are written as for rsync's '--exclude-from', one per line: blank lines and lines
+//! starting with '#' or ';' are skipped, and a pattern may be prefixed with '- ' to mark it as
+//! an exclude. A pattern ending with a '/' only matches directories, one starting with a '/'
+//! only matches relative to the walked directory, and any other holding a '/' matches the
+//! path relative to it rather than the name. Include rules, prefixed with '+ ', are not
+//! supported since '--include' only selects what is converted rather than what is walked.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use glob::Pattern;
+
+/// The ignore file read by every recursive run, if there is a home directory to find it in.
+pub fn global_file() -> Option<PathBuf> {
+    let config = match (env::var_os("XDG_CONFIG_HOME"), env::var_os("HOME")) {
+        (Some(config), _) if !config.is_empty() => PathBuf::from(config),
+        (_, Some(home)) => PathBuf::from(home).join(".config"),
+        _ => return None,
+    };
+
+    Some(config.join("ccpath").join("ignore"))
+}
+
+/// Read the patterns of the ignore file at `path`, or none if `is_optional` and there is no
+/// file there.
+pub fn load(path: &Path, is_optional: bool) -> Result<Vec<Pattern>, String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if is_optional && err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(format!("could not read '{}': {}", path.display(), err)),
+    };
+
+    parse(&text).map_err(|err| format!("invalid patterns in '{}': {}", path.display(), err))
+}
+
+/// Parse every pattern of `text`, failing on the first invalid one.
+fn parse(text: &str) -> Result<Vec<Pattern>, String> {
+    let mut patterns = vec![];
+
+    for (n, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line)) {
+        let line = line.trim_end_matches('\r');
+
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if line.starts_with("+ ") {
+            return Err(format!("line {}: include rules are not supported", n));
+        }
+
+        let glob = line.strip_prefix("- ").unwrap_or(line);
+        let pattern = Pattern::new(glob).map_err(|err| format!("line {}: {}", n, err))?;
+
+        patterns.push(pattern);
+    }
+
+    Ok(patterns)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::ignore::{load, parse};
+
+    #[test]
+    fn test_parse() {
+        let globs = |text| -> Result<Vec<String>, String> {
+            parse(text).map(|patterns| patterns.iter().map(|p| p.to_string()).collect())
+        };
+
+        assert_eq!(
+            Ok(vec![
+                String::from("node_modules/"),
+                String::from("/build"),
+                String::from("*.tmp"),
+            ]),
+            globs("# caches\nnode_modules/\r\n\n; output\n- /build\n*.tmp\n")
+        );
+
+        assert!(globs("+ *.rs\n").is_err());
+        assert!(globs("[a\n").is_err());
+    }
+
+    #[test]
+    fn test_load() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("ignore");
+
+        assert_eq!(Ok(vec![]), load(&path, true));
+        assert!(load(&path, false).is_err());
+
+        fs::write(&path, "target/\n")?;
+        assert_eq!(1, load(&path, false)?.len());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,203 @@
+//! Remembers the modification time of every directory walked by a recursive run, so the
+//! next run with '--incremental' only examines entries of directories changed since.
+//!
+//! Directories are still listed to find their subdirectories, but the entries of an
+//! unchanged directory are neither converted nor renamed.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+
+/// What a directory looked like at the end of the previous run.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Stamp {
+    ino: u64,
+    mtime: u128,
+}
+
+impl Stamp {
+    fn of(dir: &Path) -> Option<Stamp> {
+        let metadata = fs::symlink_metadata(dir).ok()?;
+
+        #[cfg(unix)]
+        let ino = std::os::unix::fs::MetadataExt::ino(&metadata);
+
+        #[cfg(not(unix))]
+        let ino = 0;
+
+        let mtime = metadata
+            .modified()
+            .ok()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()?
+            .as_nanos();
+
+        Some(Stamp { ino, mtime })
+    }
+}
+
+/// The state of a single recursively walked root.
+pub struct State {
+    root: PathBuf,
+    key: String,
+    previous: HashMap<PathBuf, Stamp>,
+    visited: Vec<PathBuf>,
+}
+
+impl State {
+    /// Load the state left by the last run over `root` with the same `key`, which should
+    /// describe every option affecting what names are produced.
+    pub fn load(root: &Path, key: &str) -> io::Result<State> {
+        let previous = match File::open(state_file(root, key)?) {
+            Ok(f) => read_stamps(BufReader::new(f))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err),
+        };
+
+        Ok(State {
+            root: root.to_path_buf(),
+            key: String::from(key),
+            previous,
+            visited: vec![],
+        })
+    }
+
+    /// Whether `dir` has not changed since the end of the last run, recording it to be
+    /// stamped at the end of this one.
+    pub fn visit(&mut self, dir: &Path) -> bool {
+        self.visited.push(dir.to_path_buf());
+
+        let previous = dir
+            .strip_prefix(&self.root)
+            .ok()
+            .and_then(|relative| self.previous.get(relative));
+
+        previous.is_some_and(|previous| Stamp::of(dir).as_ref() == Some(previous))
+    }
+
+    /// Stamp every visited directory where it ended up after the given directory renames.
+    ///
+    /// The state is saved for wherever the root itself ended up, since that is the path
+    /// the next run will be given.
+    pub fn save(self, renamed: &HashMap<PathBuf, PathBuf>) -> io::Result<()> {
+        let root = final_path(&self.root, renamed);
+        let file = state_file(&root, &self.key)?;
+
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut writer = BufWriter::new(File::create(&file)?);
+
+        for dir in &self.visited {
+            let dir = final_path(dir, renamed);
+
+            let (relative, stamp) = match (dir.strip_prefix(&root), Stamp::of(&dir)) {
+                (Ok(relative), Some(stamp)) => (relative, stamp),
+                _ => continue,
+            };
+
+            // paths go last so that they may contain anything but a newline
+            if let Some(relative) = relative.to_str().filter(|r| !r.contains('\n')) {
+                writeln!(writer, "{} {} {}", stamp.ino, stamp.mtime, relative)?;
+            }
+        }
+
+        writer.flush()
+    }
+}
+
+/// Where `path` is after renaming each of its ancestors according to `renamed`, which maps
+/// the original path of every renamed directory to its new path.
+pub fn final_path(path: &Path, renamed: &HashMap<PathBuf, PathBuf>) -> PathBuf {
+    let mut original = PathBuf::new();
+    let mut current = PathBuf::new();
+
+    for component in path.components() {
+        original.push(component);
+
+        match (component, renamed.get(&original).and_then(|target| target.file_name())) {
+            (Component::Normal(_), Some(name)) => current.push(name),
+            _ => current.push(component),
+        }
+    }
+
+    current
+}
+
+fn read_stamps<R: BufRead>(reader: R) -> io::Result<HashMap<PathBuf, Stamp>> {
+    let mut stamps = HashMap::new();
+
+    for line in reader.lines() {
+        let line = line?;
+        let mut fields = line.splitn(3, ' ');
+
+        let stamp = match (fields.next(), fields.next(), fields.next()) {
+            (Some(ino), Some(mtime), Some(path)) => match (ino.parse(), mtime.parse()) {
+                (Ok(ino), Ok(mtime)) => (PathBuf::from(path), Stamp { ino, mtime }),
+                _ => continue,
+            },
+            _ => continue,
+        };
+
+        stamps.insert(stamp.0, stamp.1);
+    }
+
+    Ok(stamps)
+}
+
+/// The file holding the state of runs over `root` with the same `key`.
+fn state_file(root: &Path, key: &str) -> io::Result<PathBuf> {
+    let canonical = fs::canonicalize(root)?;
+    let hash = fnv1a(format!("{}\0{}", canonical.display(), key).as_bytes());
+
+    Ok(state_dir()?.join(format!("{:016x}", hash)))
+}
+
+fn state_dir() -> io::Result<PathBuf> {
+    let cache = match (env::var_os("XDG_CACHE_HOME"), env::var_os("HOME")) {
+        (Some(cache), _) if !cache.is_empty() => PathBuf::from(cache),
+        (_, Some(home)) => PathBuf::from(home).join(".cache"),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "neither XDG_CACHE_HOME nor HOME is set",
+            ))
+        }
+    };
+
+    Ok(cache.join("ccpath"))
+}
+
+/// A hash which, unlike the standard library's, is stable between builds.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+
+    use crate::incremental::final_path;
+
+    #[test]
+    fn test_final_path() {
+        let renamed: HashMap<PathBuf, PathBuf> = vec![
+            (PathBuf::from("root/A Dir/B Dir"), PathBuf::from("root/A Dir/b_dir")),
+            (PathBuf::from("root/A Dir"), PathBuf::from("root/a_dir")),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            Path::new("root/a_dir/b_dir/C Dir"),
+            final_path(Path::new("root/A Dir/B Dir/C Dir"), &renamed)
+        );
+        assert_eq!(Path::new("root/Other Dir"), final_path(Path::new("root/Other Dir"), &renamed));
+    }
+}
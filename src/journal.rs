@@ -0,0 +1,481 @@
+//! Reads back the renames recorded in a '--log-file' log, so 'ccpath replay' can perform the
+//! same renames again beneath another root, 'ccpath resolve' can tell where a path went, and
+//! 'ccpath undo' can revert them.
+//!
+//! Every run records the directory it was made in, which is what the recorded paths are
+//! rebased from: relative paths are already relative to it, and absolute ones must be
+//! beneath it.
+//!
+//! Runs given '--record' write a journal of their renames, in the format of the log, to
+//! '$XDG_DATA_HOME/ccpath/journal' or '~/.local/share/ccpath/journal', named by when they
+//! started, which is where 'ccpath undo' finds the last of them. Once undone, a journal is
+//! marked as such so the next 'ccpath undo' reverts the run before it.
+
+use std::convert::TryFrom;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use convert_path::plan::{Rename, RenamePlan};
+use convert_path::Convention;
+
+use crate::manifest::path_from_bytes;
+
+pub struct Journal {
+    /// The directory the recorded run was made in.
+    pub cwd: Option<PathBuf>,
+
+    /// The convention the recorded run converted into.
+    pub into: Option<Convention>,
+
+    /// Every rename which was performed, in the order it was performed.
+    pub renames: Vec<Rename>,
+}
+
+impl Journal {
+    pub fn read(path: &Path) -> io::Result<Journal> {
+        let content = fs::read_to_string(path)?;
+
+        let mut journal = Journal {
+            cwd: None,
+            into: None,
+            renames: vec![],
+        };
+
+        for (n, line) in content.lines().enumerate() {
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {} is not a valid log entry", n + 1),
+                )
+            };
+
+            let fields = fields(line).ok_or_else(invalid)?;
+            let field = |key: &str| {
+                fields
+                    .iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, value)| value.as_slice())
+            };
+
+            match field("event") {
+                Some(b"run") => {
+                    journal.cwd = field("cwd").map(path_from_bytes);
+                    journal.into = field("into")
+                        .and_then(|into| std::str::from_utf8(into).ok())
+                        .and_then(|into| Convention::try_from(into).ok());
+                }
+                Some(b"rename") if field("outcome") == Some(b"renamed") => {
+                    let (source, target) = match (field("source"), field("target")) {
+                        (Some(source), Some(target)) => (source, target),
+                        _ => return Err(invalid()),
+                    };
+
+                    journal.renames.push(Rename {
+                        source: path_from_bytes(source),
+                        target: path_from_bytes(target),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        Ok(journal)
+    }
+
+    /// The recorded renames with every path moved from the recorded directory to `root`.
+    pub fn rebase(&self, root: &Path) -> Result<RenamePlan, String> {
+        let rebase = |path: &Path| -> Result<PathBuf, String> {
+            if path.is_relative() {
+                return Ok(root.join(path));
+            }
+
+            match self.cwd.as_deref().map(|cwd| path.strip_prefix(cwd)) {
+                Some(Ok(relative)) => Ok(root.join(relative)),
+                _ => Err(format!(
+                    "'{}' is not beneath the directory the run was recorded in",
+                    path.display()
+                )),
+            }
+        };
+
+        let mut plan = RenamePlan::new();
+
+        for rename in &self.renames {
+            plan.push(rebase(&rename.source)?, rebase(&rename.target)?);
+        }
+
+        Ok(plan)
+    }
+
+    /// The recorded renames with every relative path made absolute against the recorded
+    /// directory.
+    pub fn absolute_renames(&self) -> Result<Vec<Rename>, String> {
+        let absolute = |path: &Path| -> Result<PathBuf, String> {
+            match &self.cwd {
+                _ if path.is_absolute() => Ok(path.to_path_buf()),
+                Some(cwd) => Ok(cwd.join(path)),
+                None => Err(format!(
+                    "the directory '{}' is relative to was not recorded",
+                    path.display()
+                )),
+            }
+        };
+
+        self.renames
+            .iter()
+            .map(|rename| {
+                Ok(Rename {
+                    source: absolute(&rename.source)?,
+                    target: absolute(&rename.target)?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Follow `path` through `renames`, in the order they were performed, to where it is after
+/// them, or with `reverse` back to where it was before them, or none if none of them moved
+/// it or a directory above it.
+pub fn resolve(renames: &[Rename], path: &Path, reverse: bool) -> Option<PathBuf> {
+    let mut resolved = path.to_path_buf();
+    let mut is_moved = false;
+
+    let renames: Box<dyn Iterator<Item = (&Path, &Path)>> = if reverse {
+        Box::new(
+            renames
+                .iter()
+                .rev()
+                .map(|rename| (rename.target.as_path(), rename.source.as_path())),
+        )
+    } else {
+        Box::new(
+            renames
+                .iter()
+                .map(|rename| (rename.source.as_path(), rename.target.as_path())),
+        )
+    };
+
+    for (from, to) in renames {
+        // joining an empty path would leave a trailing separator
+        resolved = match resolved.strip_prefix(from) {
+            Ok(rest) if rest.as_os_str().is_empty() => to.to_path_buf(),
+            Ok(rest) => to.join(rest),
+            Err(_) => continue,
+        };
+
+        is_moved = true;
+    }
+
+    is_moved.then_some(resolved)
+}
+
+/// The renames reverting `renames`, in the order they must be performed, each along with
+/// where its source is before any of them are.
+///
+/// A rename is recorded with the paths it had when it was performed, so its target may since
+/// have been moved by the rename of a directory above it, which is only reverted before it.
+pub fn undo(renames: &[Rename]) -> Vec<(Rename, PathBuf)> {
+    renames
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(i, rename)| {
+            let current = resolve(&renames[i + 1..], &rename.target, false)
+                .unwrap_or_else(|| rename.target.clone());
+
+            let reverted = Rename {
+                source: rename.target.clone(),
+                target: rename.source.clone(),
+            };
+
+            (reverted, current)
+        })
+        .collect()
+}
+
+/// The directory the logs of runs given '--record' are written to, if there is a home
+/// directory to find it in.
+pub fn recorded_dir() -> Option<PathBuf> {
+    let data = match (env::var_os("XDG_DATA_HOME"), env::var_os("HOME")) {
+        (Some(data), _) if !data.is_empty() => PathBuf::from(data),
+        (_, Some(home)) => PathBuf::from(home).join(".local").join("share"),
+        _ => return None,
+    };
+
+    Some(data.join("ccpath").join("journal"))
+}
+
+/// The path in `dir` to record a run starting at `now` to, which sorts after that of every
+/// run started before it.
+pub fn record_path(dir: &Path, now: SystemTime) -> PathBuf {
+    let nanos = now
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+
+    dir.join(format!("{:020}.log", nanos))
+}
+
+/// The log of the last run recorded in `dir` which has not been undone yet, if any.
+pub fn last_recorded(dir: &Path) -> io::Result<Option<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+
+    let mut last = None;
+
+    for entry in entries {
+        let path = entry?.path();
+
+        if path.extension().is_some_and(|ext| ext == "log") && last.as_ref() < Some(&path) {
+            last = Some(path);
+        }
+    }
+
+    Ok(last)
+}
+
+/// Mark the recorded log at `path` as undone, so it is no longer the last recorded run.
+pub fn mark_undone(path: &Path) -> io::Result<()> {
+    fs::rename(path, path.with_extension("undone"))
+}
+
+/// Split a log line into its fields, undoing the quoting of quoted values.
+fn fields(line: &str) -> Option<Vec<(&str, Vec<u8>)>> {
+    let mut fields = vec![];
+    let mut rest = line.trim_start();
+
+    while !rest.is_empty() {
+        let eq = rest.find('=')?;
+        let key = &rest[..eq];
+        let value = &rest[eq + 1..];
+
+        let (value, len) = if value.starts_with('"') {
+            unquote(value)?
+        } else {
+            let len = value.find(' ').unwrap_or(value.len());
+
+            (value.as_bytes()[..len].to_vec(), len)
+        };
+
+        fields.push((key, value));
+        rest = rest[eq + 1 + len..].trim_start_matches(' ');
+    }
+
+    Some(fields)
+}
+
+/// Undo the quoting of a value written with `{:?}`, which starts `quoted`, returning its
+/// bytes and the length of the quoted value.
+fn unquote(quoted: &str) -> Option<(Vec<u8>, usize)> {
+    let mut bytes = vec![];
+    let mut chars = quoted.char_indices().skip(1);
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((bytes, i + 1)),
+            '\\' => {
+                let escaped = match chars.next()?.1 {
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    '0' => '\0',
+                    // bytes which are not utf-8 are written as '\xNN'
+                    'x' => {
+                        let hex: String = (0..2)
+                            .filter_map(|_| chars.next())
+                            .map(|(_, c)| c)
+                            .collect();
+                        bytes.push(u8::from_str_radix(&hex, 16).ok()?);
+                        continue;
+                    }
+                    'u' => {
+                        let hex: String = chars
+                            .by_ref()
+                            .map(|(_, c)| c)
+                            .skip_while(|c| *c == '{')
+                            .take_while(|c| *c != '}')
+                            .collect();
+                        char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?
+                    }
+                    c => c,
+                };
+
+                bytes.extend_from_slice(escaped.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+            c => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use convert_path::plan::Rename;
+    use convert_path::Convention;
+
+    use crate::journal::{fields, last_recorded, mark_undone, record_path, resolve, undo, Journal};
+
+    #[test]
+    fn test_fields() {
+        let line = r#"time=1.500 event=rename source="Some \"Dir\"/\u{301}\xff" outcome=renamed"#;
+
+        assert_eq!(
+            Some(vec![
+                ("time", b"1.500".to_vec()),
+                ("event", b"rename".to_vec()),
+                ("source", b"Some \"Dir\"/\xcc\x81\xff".to_vec()),
+                ("outcome", b"renamed".to_vec()),
+            ]),
+            fields(line)
+        );
+        assert_eq!(None, fields("time=1.500 msg=\"unterminated"));
+    }
+
+    #[test]
+    fn test_read_and_rebase() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let log = dir.path().join("ccpath.log");
+
+        fs::write(
+            &log,
+            "time=1.000 event=run cwd=\"/staging\" into=snake\n\
+             time=1.001 level=warning msg=\"event=rename outcome=renamed\"\n\
+             time=1.002 event=rename source=\"Some Dir/A File\" target=\"Some Dir/a_file\" outcome=renamed\n\
+             time=1.003 event=rename source=\"b_file\" target=\"b_file\" outcome=conforming\n\
+             time=1.004 event=rename source=\"/staging/Some Dir\" target=\"/staging/some_dir\" outcome=renamed\n",
+        )?;
+
+        let journal = Journal::read(&log)?;
+        assert_eq!(Some(Convention::SnakeCase), journal.into);
+
+        let targets: Vec<&Path> = journal
+            .renames
+            .iter()
+            .map(|rename| rename.target.as_path())
+            .collect();
+        assert_eq!(
+            vec![Path::new("Some Dir/a_file"), Path::new("/staging/some_dir")],
+            targets
+        );
+
+        let plan = journal.rebase(Path::new("/production"))?;
+        let sources: Vec<PathBuf> = plan
+            .renames()
+            .iter()
+            .map(|rename| rename.source.clone())
+            .collect();
+        assert_eq!(
+            vec![
+                PathBuf::from("/production/Some Dir/A File"),
+                PathBuf::from("/production/Some Dir")
+            ],
+            sources
+        );
+
+        let outside = Journal {
+            cwd: Some(PathBuf::from("/elsewhere")),
+            ..journal
+        };
+        assert!(outside.rebase(Path::new("/production")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let log = dir.path().join("ccpath.log");
+
+        fs::write(
+            &log,
+            "time=1.000 event=run cwd=\"/staging\" into=snake\n\
+             time=1.001 event=rename source=\"Some Dir/A File\" target=\"Some Dir/a_file\" outcome=renamed\n\
+             time=1.002 event=rename source=\"/staging/Some Dir\" target=\"/staging/some_dir\" outcome=renamed\n",
+        )?;
+
+        let renames = Journal::read(&log)?.absolute_renames()?;
+        let resolve = |path: &str, reverse| resolve(&renames, Path::new(path), reverse);
+
+        // a path is followed through its own rename and those of the directories above it
+        assert_eq!(
+            Some(PathBuf::from("/staging/some_dir/a_file")),
+            resolve("/staging/Some Dir/A File", false)
+        );
+        assert_eq!(
+            Some(PathBuf::from("/staging/some_dir/Other File")),
+            resolve("/staging/Some Dir/Other File", false)
+        );
+        assert_eq!(
+            Some(PathBuf::from("/staging/Some Dir/A File")),
+            resolve("/staging/some_dir/a_file", true)
+        );
+        assert_eq!(None, resolve("/staging/Some Dir 2", false));
+
+        let unrecorded = Journal {
+            cwd: None,
+            ..Journal::read(&log)?
+        };
+        assert!(unrecorded.absolute_renames().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo() {
+        let rename = |source: &str, target: &str| Rename {
+            source: PathBuf::from(source),
+            target: PathBuf::from(target),
+        };
+
+        let renames = [
+            rename("/staging/Some Dir/A File", "/staging/Some Dir/a_file"),
+            rename("/staging/Some Dir", "/staging/some_dir"),
+        ];
+
+        // the directory is reverted first, after which its file is back where it was renamed
+        assert_eq!(
+            vec![
+                (
+                    rename("/staging/some_dir", "/staging/Some Dir"),
+                    PathBuf::from("/staging/some_dir")
+                ),
+                (
+                    rename("/staging/Some Dir/a_file", "/staging/Some Dir/A File"),
+                    PathBuf::from("/staging/some_dir/a_file")
+                ),
+            ],
+            undo(&renames)
+        );
+    }
+
+    #[test]
+    fn test_last_recorded() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+
+        assert_eq!(None, last_recorded(&dir.path().join("journal"))?);
+
+        let first = record_path(dir.path(), at(9));
+        let second = record_path(dir.path(), at(10));
+        fs::write(&first, "")?;
+        fs::write(&second, "")?;
+
+        assert_eq!(Some(second.clone()), last_recorded(dir.path())?);
+
+        mark_undone(&second)?;
+        assert_eq!(Some(first), last_recorded(dir.path())?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,297 @@
+//! Finds the link destinations in Markdown and HTML documents, so that '--fix-references'
+//! only rewrites genuine references in them rather than every matching piece of text.
+//!
+//! This is not a complete parser, but it understands inline links and images, reference
+//! definitions, code spans and fenced code blocks, and the 'href' and 'src' attributes of
+//! HTML tags, which covers the links of most static sites.
+
+use std::ops::Range;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Kind {
+    Markdown,
+    Html,
+}
+
+impl Kind {
+    /// The kind of document at `path` judging by its extension.
+    pub fn of(path: &Path) -> Option<Kind> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+
+        match extension.as_str() {
+            "md" | "markdown" | "mdown" | "mkd" => Some(Kind::Markdown),
+            "html" | "htm" | "xhtml" => Some(Kind::Html),
+            _ => None,
+        }
+    }
+}
+
+/// The byte range of every link destination in `text`, in order.
+pub fn destinations(text: &str, kind: Kind) -> Vec<Range<usize>> {
+    let mut found = vec![];
+
+    match kind {
+        Kind::Html => attributes(text, 0, &mut found),
+        Kind::Markdown => {
+            let mut fence: Option<&str> = None;
+            let mut offset = 0;
+
+            for line in text.split_inclusive('\n') {
+                let start = offset;
+                offset += line.len();
+
+                let trimmed = line.trim_start();
+
+                if let Some(marker) = fence {
+                    if trimmed.starts_with(marker) {
+                        fence = None;
+                    }
+
+                    continue;
+                }
+
+                if trimmed.starts_with("```") {
+                    fence = Some("```");
+                } else if trimmed.starts_with("~~~") {
+                    fence = Some("~~~");
+                } else if let Some(dest) = definition(line) {
+                    found.push(start + dest.start..start + dest.end);
+                } else {
+                    inline(line, start, &mut found);
+                }
+            }
+        }
+    }
+
+    found
+}
+
+/// The destination of a reference definition like '[id]: path "title"'.
+fn definition(line: &str) -> Option<Range<usize>> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+
+    if indent > 3 || !line[indent..].starts_with('[') {
+        return None;
+    }
+
+    let close = indent + line[indent..].find("]:")?;
+
+    destination(line, close + 2)
+}
+
+/// Collect the destinations of the inline links and images of `line`, along with any HTML
+/// attributes outside of code spans.
+fn inline(line: &str, offset: usize, found: &mut Vec<Range<usize>>) {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    let mut plain = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'`' => {
+                let ticks = bytes[i..].iter().take_while(|b| **b == b'`').count();
+                let fence = &line[i..i + ticks];
+
+                attributes(&line[plain..i], offset + plain, found);
+
+                // an unclosed code span is just literal backticks
+                i = match line[i + ticks..].find(fence) {
+                    Some(close) => i + ticks + close + ticks,
+                    None => i + ticks,
+                };
+                plain = i;
+            }
+            b']' if bytes.get(i + 1) == Some(&b'(') => {
+                if let Some(dest) = destination(line, i + 2) {
+                    i = dest.end;
+                    found.push(offset + dest.start..offset + dest.end);
+                } else {
+                    i += 2;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+
+    attributes(&line[plain..], offset + plain, found);
+}
+
+/// The destination starting at `start`, either wrapped in angle brackets or ending at the
+/// first space or unbalanced parenthesis.
+fn destination(line: &str, start: usize) -> Option<Range<usize>> {
+    let start = start + (line[start..].len() - line[start..].trim_start().len());
+    let rest = &line[start..];
+
+    if let Some(inner) = rest.strip_prefix('<') {
+        let end = inner.find(['>', '\n'])?;
+
+        return Some(start + 1..start + 1 + end);
+    }
+
+    let mut depth = 0;
+
+    for (i, c) in rest.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return non_empty(start..start + i),
+            ')' => depth -= 1,
+            // only a title may follow the destination, otherwise this is not a link at all
+            c if c.is_whitespace() => {
+                let after = rest[i..].trim_start();
+
+                return if after.is_empty() || after.starts_with(&[')', '"', '\'', '('][..]) {
+                    non_empty(start..start + i)
+                } else {
+                    None
+                };
+            }
+            _ => {}
+        }
+    }
+
+    non_empty(start..line.len())
+}
+
+fn non_empty(range: Range<usize>) -> Option<Range<usize>> {
+    if range.is_empty() {
+        None
+    } else {
+        Some(range)
+    }
+}
+
+/// Collect the values of every 'href' and 'src' attribute in `text`.
+fn attributes(text: &str, offset: usize, found: &mut Vec<Range<usize>>) {
+    let lower = text.to_ascii_lowercase();
+
+    for name in ["href", "src"].iter() {
+        for (i, _) in lower.match_indices(name) {
+            let is_attribute = lower[..i]
+                .chars()
+                .next_back()
+                .is_some_and(char::is_whitespace);
+
+            if !is_attribute {
+                continue;
+            }
+
+            let after = &text[i + name.len()..];
+            let value = match after.trim_start().strip_prefix('=') {
+                Some(value) => value.trim_start(),
+                None => continue,
+            };
+            let start = text.len() - value.len();
+
+            let range = match value.chars().next() {
+                Some(quote) if quote == '"' || quote == '\'' => match value[1..].find(quote) {
+                    Some(end) => start + 1..start + 1 + end,
+                    None => continue,
+                },
+                Some(_) => {
+                    let end = value
+                        .find(|c: char| c.is_whitespace() || c == '>')
+                        .unwrap_or(value.len());
+
+                    start..start + end
+                }
+                None => continue,
+            };
+
+            if !range.is_empty() {
+                found.push(offset + range.start..offset + range.end);
+            }
+        }
+    }
+
+    found.sort_by_key(|range| range.start);
+}
+
+/// Decode the '%XX' escapes of a link destination.
+pub fn percent_decode(encoded: &str) -> Option<String> {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+
+        match escaped {
+            Some(b) => {
+                decoded.push(b);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+/// Escape everything but unreserved characters and '/' in a link destination.
+pub fn percent_encode(decoded: &str) -> String {
+    let mut encoded = String::with_capacity(decoded.len());
+
+    for b in decoded.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(b as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod test {
+    use crate::links::{destinations, percent_decode, Kind};
+
+    fn found(text: &str, kind: Kind) -> Vec<&str> {
+        destinations(text, kind)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect()
+    }
+
+    #[test]
+    fn test_markdown_destinations() {
+        let text = "[a](Some%20File.md) [x](Not A Link.md) ![b](<Some Image.png> \"title\") `[c](Code.md)`\n\
+                    ```\n[d](Fenced.md)\n```\n\
+                    [e]: Defined.md\n\
+                    <img src=\"Inline.png\"> Some File.md\n";
+
+        assert_eq!(
+            vec![
+                "Some%20File.md",
+                "Some Image.png",
+                "Defined.md",
+                "Inline.png"
+            ],
+            found(text, Kind::Markdown)
+        );
+    }
+
+    #[test]
+    fn test_html_destinations() {
+        let text = "<a class=\"x\" href='Some Page.html'>Some Page.html</a><img SRC=image.png>";
+
+        assert_eq!(vec!["Some Page.html", "image.png"], found(text, Kind::Html));
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(
+            Some(String::from("Some File.md")),
+            percent_decode("Some%20File.md")
+        );
+        assert_eq!(Some(String::from("100%")), percent_decode("100%"));
+    }
+}
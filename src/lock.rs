@@ -0,0 +1,141 @@
+//! Keeps two runs from renaming the same tree at once, like a cron job overlapping a run
+//! started by hand, by holding advisory locks keyed by the trees a run renames until it ends.
+//!
+//! A run holds an exclusive lock for each directory it recurses into, and a shared one for
+//! every directory above those and above the paths it renames without recursing. Runs over
+//! nested trees therefore exclude each other, while runs over unrelated trees or single
+//! paths in the same directory don't.
+//!
+//! The lock files live in '$XDG_RUNTIME_DIR/ccpath', or a directory of the system's temporary
+//! directory if it is not set, rather than in the trees themselves where they would be renamed.
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::incremental::fnv1a;
+
+/// Why the trees of a run could not be locked.
+#[derive(Debug)]
+pub enum LockError {
+    /// Another run holds a lock on the tree at the path.
+    Held(PathBuf),
+
+    Io(io::Error),
+}
+
+impl From<io::Error> for LockError {
+    fn from(err: io::Error) -> Self {
+        LockError::Io(err)
+    }
+}
+
+/// The locks held by a run, released when dropped.
+#[derive(Debug)]
+pub struct TreeLocks {
+    _files: Vec<File>,
+}
+
+impl TreeLocks {
+    /// Lock the trees under `roots` exclusively and the directories above them and `dirs`,
+    /// the directories of the paths renamed without recursing, shared.
+    pub fn acquire(roots: &[&Path], dirs: &[&Path]) -> Result<TreeLocks, LockError> {
+        let roots: BTreeSet<PathBuf> = roots
+            .iter()
+            .map(fs::canonicalize)
+            .collect::<io::Result<_>>()?;
+
+        let mut shared = BTreeSet::new();
+
+        for dir in dirs {
+            shared.extend(fs::canonicalize(dir)?.ancestors().map(Path::to_path_buf));
+        }
+
+        for root in &roots {
+            shared.extend(root.ancestors().skip(1).map(Path::to_path_buf));
+        }
+
+        let lock_dir = lock_dir();
+        fs::create_dir_all(&lock_dir)?;
+
+        let mut files = vec![];
+
+        for (dir, is_exclusive) in roots
+            .iter()
+            .map(|root| (root, true))
+            .chain(shared.difference(&roots).map(|dir| (dir, false)))
+        {
+            let file = OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(lock_file(&lock_dir, dir))?;
+
+            let locked = if is_exclusive {
+                file.try_lock()
+            } else {
+                file.try_lock_shared()
+            };
+
+            match locked {
+                Ok(()) => files.push(file),
+                Err(TryLockError::WouldBlock) => return Err(LockError::Held(dir.clone())),
+                Err(TryLockError::Error(err)) => return Err(LockError::Io(err)),
+            }
+        }
+
+        Ok(TreeLocks { _files: files })
+    }
+}
+
+fn lock_dir() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime) if !runtime.is_empty() => PathBuf::from(runtime).join("ccpath"),
+        _ => env::temp_dir().join("ccpath-locks"),
+    }
+}
+
+/// The file locked for the tree under `dir`, keyed by its canonical path.
+fn lock_file(lock_dir: &Path, dir: &Path) -> PathBuf {
+    let hash = fnv1a(dir.to_string_lossy().as_bytes());
+
+    lock_dir.join(format!("{:016x}.lock", hash))
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::lock::{LockError, TreeLocks};
+
+    #[test]
+    fn test_acquire() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path().join("Some Dir");
+        let nested = root.join("Nested Dir");
+        let other = dir.path().join("Other Dir");
+
+        fs::create_dir_all(&nested)?;
+        fs::create_dir_all(&other)?;
+
+        let locks = TreeLocks::acquire(&[&root], &[]).unwrap();
+
+        // the same tree and those nested in it are held
+        let held = |result| matches!(result, Err(LockError::Held(_)));
+        assert!(held(TreeLocks::acquire(&[&root], &[])));
+        assert!(held(TreeLocks::acquire(&[&nested], &[])));
+        assert!(held(TreeLocks::acquire(&[], &[&nested])));
+
+        // while other trees and the paths above it are not
+        let other_locks = TreeLocks::acquire(&[&other], &[dir.path()]).unwrap();
+        assert!(held(TreeLocks::acquire(&[dir.path()], &[])));
+
+        drop(locks);
+        drop(other_locks);
+        TreeLocks::acquire(&[dir.path()], &[]).unwrap();
+
+        Ok(())
+    }
+}
@@ -1,247 +1,3961 @@
 #[macro_use]
-extern crate clap;
+mod i18n;
+#[macro_use]
+mod report;
+
+mod archive;
+mod audit;
+mod cli;
+mod config;
+mod copy;
+#[cfg(unix)]
+mod daemon;
+mod diff;
+mod dircache;
+mod display;
+mod doctor;
+mod git;
+mod guard;
+mod hardlink;
+mod ignore;
+mod incremental;
+mod journal;
+mod links;
+mod lock;
+mod manifest;
+mod mapping;
+mod marker;
+#[cfg(feature = "exif")]
+mod media;
+mod merge;
+mod metrics;
+mod precheck;
+mod references;
+mod remote;
+mod retry;
+mod risk;
+#[cfg(feature = "s3")]
+mod s3;
+mod sample;
+mod script;
+#[cfg(feature = "sftp")]
+mod sftp;
+mod snapshot;
+mod spill;
+mod stats;
+mod template;
+mod throttle;
+mod tui;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod uring;
+mod volume;
 
-use std::convert::TryFrom;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::fmt::{self, Display, Formatter};
 use std::fs;
-use std::path::Path;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{self, Component, Path, PathBuf};
 use std::process::exit;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
 
-use clap::{Arg, ArgGroup, ArgMatches};
-use walkdir::WalkDir;
+use rayon::prelude::*;
+use regex::Regex;
 
 use convert_path::{self, Convention};
 use convert_path::error::PathConvertError;
+use convert_path::explain;
+use convert_path::plan::{Collision, Impact, Rename, RenamePlan};
+use convert_path::plan_file::{PlanFile, PlanWriter, PlannedRename};
+use convert_path::sanitize::{self, Platform};
+use convert_path::stem::{self, Extensions};
+use convert_path::template::Template;
+use convert_path::transform::{self, Transform};
+use convert_path::walk::WalkOptions;
 
-fn get_matches<'a>() -> ArgMatches<'a> {
-    app_from_crate!()
-        .arg(
-            Arg::with_name("recursive")
-                .help("recurse into a directory, works in the same way as using '--prefix' and '--full-path' for all sub_paths")
-                .short("r")
-                .long("recursive"),
-        )
-        .arg(
-            Arg::with_name("no-clobber")
-                .help("do not overwrite an existing file")
-                .short("n")
-                .long("no-clobber"),
-        )
-        .arg(
-            Arg::with_name("dry-run")
-                .help("show the operations that would be performed without doing them")
-                .long("dry-run"),
-        )
-        .arg(
-            Arg::with_name("verbose")
-                .help("print a message for every converted path")
-                .short("v")
-                .long("verbose"),
-        )
-        .arg(
-            Arg::with_name("basename")
-                .help("only convert the basename of each given path")
-                .short("b")
-                .long("basename"),
-        )
-        .arg(
-            Arg::with_name("full-path")
-                .help("convert all components of the path")
-                .short("F")
-                .long("full-path"),
-        )
-        .arg(
-            Arg::with_name("prefix")
-                .help("exclude a path prefix when converting a '--full-path' is specified, otherwise ignored'")
-                .short("P")
-                .long("prefix")
-                .takes_value(true)
-        )
-        .arg(
-            Arg::with_name("from")
-                .help("set the current naming convention if it is known, this may improve teh case conversion accuracy")
-                .short("f")
-                .long("from")
-                .value_name("CONVENTION")
-                .takes_value(true)
-        )
-        .arg(
-            Arg::with_name("into")
-                .help("set that target naming convention")
-                .value_name("CONVENTION")
-                .required(true)
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("paths")
-                .help("the paths to convert")
-                .multiple(true)
-                .required(true)
-                .takes_value(true),
-        )
-        .group(ArgGroup::with_name("mode").args(&["basename", "full-path"]))
-        .after_help("ccpath supports several naming conventions:\n  \
-                    title  Title Case\n  \
-                    flat   flatcase\n  \
-                    FLAT   UPPERFLATCASE\n  \
-                    camel  camelCase\n  \
-                    CAMEL  CamelCase\n  \
-                    snake  snake_case\n  \
-                    SNAKE  SNAKE_CASE\n  \
-                    kebab  kebab-case\n"
-        )
-        .get_matches()
+use crate::audit::Audit;
+use crate::cli::{
+    ApplyArgs, ArchiveArgs, Cli, Command, ConfigArgs, ConfigCommand, ConventionsArgs, DaemonArgs,
+    DoctorArgs, InitArgs, ReplayArgs, ResolveArgs, UndoArgs,
+};
+use crate::config::Rules;
+use crate::copy::Preserve;
+use crate::dircache::DirCache;
+use crate::display::PathStyle;
+use crate::git::Tracked;
+use crate::incremental::State;
+use crate::journal::Journal;
+use crate::lock::{LockError, TreeLocks};
+use crate::manifest::Manifest;
+use crate::merge::Conflict;
+use crate::report::Event;
+use crate::retry::Retry;
+use crate::script::{Dialect, Script};
+use crate::snapshot::{Drift, Identity, Snapshot};
+use crate::spill::{Batches, SpilledPlan};
+use crate::stats::Phase;
+use crate::throttle::Throttle;
+
+/// How names which cannot be used on the sanitize platform are handled.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+enum SanitizeMode {
+    Adjust,
+    Reject,
 }
 
-fn convert_single(
-    path: &Path,
+/// How the extension of a name, which is kept as is, is recognized.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+enum ExtensionPolicy {
+    /// Everything after the last dot.
+    LastDot,
+
+    /// Everything after the last dot, if it is at most 5 letters and digits with a letter.
+    Short,
+
+    /// Only common extensions like 'txt' or 'tar.gz', along with every '--known-extension'.
+    Known,
+}
+
+/// What to do when '--prefix' is not actually a prefix of a path converted with '--full-path'.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+enum PrefixMismatch {
+    Warn,
+    Error,
+    Convert,
+}
+
+/// What to do with a path converted with '--full-path' when some of its components cannot be
+/// converted, like a directory whose name is not valid utf-8.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+enum PartialFailure {
+    /// Fail to convert the path.
+    Error,
+
+    /// Leave the whole path as it is, with a warning.
+    Skip,
+
+    /// Convert every other component, keeping those which cannot be converted as they are.
+    Keep,
+}
+
+/// What to do with names which have no letters or digits, and so no words to convert by.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+enum Wordless {
+    Convert,
+    Warn,
+    Error,
+}
+
+/// What to do with the sockets, FIFOs, and device files met when recursing.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+enum SpecialFiles {
+    /// Leave them as they are, counting them in the summary.
+    Skip,
+
+    /// Rename them like any other file.
+    Rename,
+
+    /// Abort the run before anything is renamed.
+    Error,
+}
+
+/// How the plan is written instead of being performed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+enum OutputFormat {
+    /// A unified diff of the listing of every planned path, refused once the plan is spilled.
+    Diff,
+
+    /// A versioned plan file recording the state of every source, readable by later versions.
+    Plan,
+
+    /// The same plan file written as JSON, as 'ccpath plan' does.
+    Json,
+}
+
+/// The options which control how each path is converted.
+struct Options<'a> {
     from: Option<Convention>,
     to: Convention,
     is_full_path: bool,
-    prefix: Option<&Path>,
+    is_same_dir: bool,
+    flatten: Option<&'a Path>,
+    prefix: Option<&'a Path>,
+    prefix_mismatch: PrefixMismatch,
+    partial_failure: PartialFailure,
     is_verbose: bool,
     is_dry_run: bool,
     no_clobber: bool,
+    use_trash: bool,
+    merge_dirs: bool,
+    use_stamp: bool,
+    is_remove_empty: bool,
+    walk: WalkOptions,
+    prune_conforming: bool,
+    sanitize: Option<Platform>,
+    sanitize_mode: SanitizeMode,
+    transforms: &'a [Transform],
+    component_pattern: Option<&'a Regex>,
+    template: Option<&'a Template>,
+    extensions: Extensions,
+    wordless: Wordless,
+    special_files: SpecialFiles,
+    preserve: Preserve,
+    retry: Retry,
+    throttle: Throttle,
+    errors: ErrorBudget,
+    prompt: Prompt,
+    explain: bool,
+    path_style: PathStyle,
+    rules: Option<&'a Rules>,
+}
+
+impl Options<'_> {
+    /// Convert into `to` with every other option left at its default, for the subcommands
+    /// which perform renames to override only the options they take.
+    fn defaults(to: Convention) -> Options<'static> {
+        Options {
+            from: None,
+            to,
+            is_full_path: false,
+            is_same_dir: false,
+            flatten: None,
+            prefix: None,
+            prefix_mismatch: PrefixMismatch::Convert,
+            partial_failure: PartialFailure::Error,
+            is_verbose: false,
+            is_dry_run: false,
+            no_clobber: false,
+            use_trash: false,
+            merge_dirs: false,
+            use_stamp: false,
+            is_remove_empty: false,
+            walk: WalkOptions::default(),
+            prune_conforming: false,
+            sanitize: None,
+            sanitize_mode: SanitizeMode::Adjust,
+            transforms: &[],
+            component_pattern: None,
+            template: None,
+            extensions: Extensions::LastDot,
+            wordless: Wordless::Convert,
+            special_files: SpecialFiles::Skip,
+            preserve: Preserve::default(),
+            retry: Retry::default(),
+            throttle: Throttle::default(),
+            errors: ErrorBudget::default(),
+            prompt: Prompt::default(),
+            explain: false,
+            path_style: PathStyle::AsGiven,
+            rules: None,
+        }
+    }
+
+    /// The convention `path` is converted into, chosen by the first config rule matching it.
+    fn convention(&self, path: &Path) -> Convention {
+        self.rules
+            .and_then(|rules| rules.find(path))
+            .map_or(self.to, |rule| rule.into)
+    }
+}
+
+/// The number of planned renames held in memory unless '--spill-after' is given.
+const DEFAULT_SPILL_AFTER: usize = 100_000;
+
+/// Accumulates the renames for a run, setting aside any whose target would escape its root.
+///
+/// Once more than `spill_after` renames are planned they are spilled to disk so that
+/// arbitrarily large trees can be planned in bounded memory.
+struct Planner {
+    plan: RenamePlan,
+    spilled: Option<SpilledPlan>,
+    spill_after: usize,
+    escaping: Vec<(Rename, PathBuf)>,
+
+    /// Renames of single components held back until planning is finished, so that they can
+    /// be performed deepest first while every source is still where it was planned.
+    in_place: Vec<(Rename, PathBuf)>,
+    in_place_sources: HashSet<PathBuf>,
+
+    /// The number of targets named after the '--template' so far.
+    templated: usize,
+
+    /// The number of special files left as they are while recursing.
+    special: usize,
+}
+
+impl Planner {
+    fn new(spill_after: usize) -> Planner {
+        Planner {
+            plan: RenamePlan::new(),
+            spilled: None,
+            spill_after,
+            escaping: vec![],
+            in_place: vec![],
+            in_place_sources: HashSet::new(),
+            templated: 0,
+            special: 0,
+        }
+    }
+
+    /// Add the rename of the last component of `source` to `target` once planning is
+    /// finished, unless its source is already planned.
+    fn push_in_place(&mut self, source: PathBuf, target: PathBuf, root: &Path) {
+        if self.in_place_sources.insert(source.clone()) {
+            self.in_place
+                .push((Rename { source, target }, root.to_path_buf()));
+        }
+    }
+
+    fn push(&mut self, source: PathBuf, target: PathBuf, root: &Path) {
+        let is_contained = is_contained(&source, &target, root);
+
+        self.insert(source, target, root, is_contained);
+    }
+
+    /// Add a rename whose containment has already been checked with [`is_contained`].
+    fn insert(&mut self, source: PathBuf, target: PathBuf, root: &Path, is_contained: bool) {
+        if is_contained {
+            self.plan.push(source, target);
+        } else {
+            self.escaping.push((Rename { source, target }, root.to_path_buf()));
+        }
+
+        if self.plan.len() >= self.spill_after {
+            if let Err(err) = self.spill() {
+                error!("{}", tr!("spill-failed", reason = err.to_string()));
+                exit(9);
+            }
+        }
+    }
+
+    fn spill(&mut self) -> io::Result<()> {
+        let spilled = match &mut self.spilled {
+            Some(spilled) => spilled,
+            None => self.spilled.insert(SpilledPlan::new(self.spill_after)?),
+        };
+
+        spilled.append(&self.plan)?;
+        self.plan = RenamePlan::new();
+
+        Ok(())
+    }
+
+    /// Finish planning, taking the snapshot of any renames still held in memory.
+    fn into_batches(mut self) -> io::Result<Batches> {
+        let mut in_place = std::mem::take(&mut self.in_place);
+        in_place.sort_by_key(|(rename, _)| std::cmp::Reverse(rename.source.components().count()));
+
+        for (rename, root) in in_place {
+            self.push(rename.source, rename.target, &root);
+        }
+
+        if self.spilled.is_some() {
+            self.spill()?;
+        }
+
+        match self.spilled {
+            Some(spilled) => Ok(Batches::Spilled(spilled)),
+            None => {
+                let snapshot = Snapshot::take(&self.plan);
+
+                Ok(Batches::InMemory(self.plan, snapshot))
+            }
+        }
+    }
+}
+
+fn is_contained(source: &Path, target: &Path, root: &Path) -> bool {
+    source == target || guard::is_contained(target, root)
+}
+
+/// Add the conversion of a single path to the plan.
+fn convert_single(
+    path: &Path,
+    opts: &Options,
+    planner: &mut Planner,
 ) -> Result<(), PathConvertError> {
+    let to = opts.convention(path);
+
+    if opts.use_stamp && marker::is_stamped(path, to) {
+        return Ok(());
+    }
+
+    let (new_path, root) = match convert_target(path, opts)? {
+        Some(target) => target,
+        None => return Ok(()),
+    };
+    let new_path = template_target(path, new_path, opts, planner)?;
+
+    if opts.explain {
+        explain(path, opts);
+    }
+
+    if opts.is_same_dir {
+        push_in_place(path, &new_path, &root, planner);
+    } else {
+        planner.push(path.to_path_buf(), new_path, &root);
+    }
+
+    Ok(())
+}
+
+/// The target `path` is converted to before any '--template' is applied, along with the root
+/// it must stay within, without looking at the filesystem, or none if it is left as it is.
+fn convert_target(
+    path: &Path,
+    opts: &Options,
+) -> Result<Option<(PathBuf, PathBuf)>, PathConvertError> {
+    let to = opts.convention(path);
+
     // todo: take this as closure rather than method
     //       store method or closure reference outside loop or run separate loops
-    let new_path = if is_full_path {
-        if prefix.is_some() {
-            convert_path::convert_full_except_prefix(path, prefix.unwrap(), from, to)
-        } else {
-            convert_path::convert_full(path, from, to)
+    let target = if opts.is_full_path {
+        match opts.prefix {
+            Some(prefix) if path.starts_with(prefix) => {
+                let kept = prefix.components().count();
+                check_words(path, path.components().count() - kept, opts)?;
+
+                let new_path = match convert_full(path, Some(prefix), to, opts)? {
+                    Some(new_path) => new_path,
+                    None => return Ok(None),
+                };
+                let converted = new_path.components().count() - prefix.components().count();
+
+                let new_path = adjust_target(path, new_path, converted, opts)?;
+
+                (new_path, prefix.to_path_buf())
+            }
+            prefix => {
+                if let Some(prefix) = prefix {
+                    match opts.prefix_mismatch {
+                        PrefixMismatch::Warn => warning!(
+                            "{}",
+                            tr!(
+                                "prefix-mismatch-converting",
+                                prefix = prefix.display(),
+                                path = path.display()
+                            )
+                        ),
+                        PrefixMismatch::Error => {
+                            error!(
+                                "{}",
+                                tr!(
+                                    "prefix-mismatch",
+                                    prefix = prefix.display(),
+                                    path = path.display()
+                                )
+                            );
+                            exit(8);
+                        }
+                        PrefixMismatch::Convert => {}
+                    }
+                }
+
+                check_words(path, path.components().count(), opts)?;
+
+                let new_path = match convert_full(path, None, to, opts)? {
+                    Some(new_path) => new_path,
+                    None => return Ok(None),
+                };
+                let converted = new_path.components().count();
+
+                let new_path = adjust_target(path, new_path, converted, opts)?;
+
+                (new_path, guard::anchor(path))
+            }
         }
     } else {
-        convert_path::convert_basename(path, from, to)
-    }?;
+        check_words(path, 1, opts)?;
 
-    if !is_dry_run {
-        if new_path.exists() {
-            if no_clobber {
-                if is_verbose {
-                    println!("file '{}' already exists", new_path.display());
-                }
+        let new_path = convert_path::convert_basename_with(path, opts.from, to, &opts.extensions)?;
+        let new_path = adjust_target(path, new_path, 1, opts)?;
+
+        match opts.flatten {
+            Some(dir) => (flatten_target(new_path, dir), dir.to_path_buf()),
+            None => (new_path, guard::parent_dir(path).to_path_buf()),
+        }
+    };
+
+    Ok(Some(target))
+}
+
+/// Convert every component of `path` after `prefix`, handling those which cannot be converted
+/// as '--partial-failure' says, or none if the path is left as it is.
+fn convert_full(
+    path: &Path,
+    prefix: Option<&Path>,
+    to: Convention,
+    opts: &Options,
+) -> Result<Option<PathBuf>, PathConvertError> {
+    let (kept, rest) = match prefix {
+        Some(prefix) => (prefix, path.strip_prefix(prefix).unwrap_or(path)),
+        None => (Path::new(""), path),
+    };
+
+    let (converted, mut errors) =
+        convert_path::convert_full_keeping_invalid_with(rest, opts.from, to, &opts.extensions);
+
+    if errors.is_empty() {
+        return Ok(Some(kept.join(converted)));
+    }
 
-                return Ok(());
+    match opts.partial_failure {
+        PartialFailure::Error => Err(errors.remove(0)),
+        PartialFailure::Skip => {
+            warning!(
+                "{}",
+                tr!(
+                    "partial-skip",
+                    reason = errors[0].to_string(),
+                    path = path.display()
+                )
+            );
+            Ok(None)
+        }
+        PartialFailure::Keep => {
+            for err in errors {
+                warning!("{}", tr!("partial-keep", reason = err.to_string()));
             }
+
+            Ok(Some(kept.join(converted)))
         }
+    }
+}
+
+/// Print every path read from stdin converted, separated like they were read by newlines or
+/// with `null` by NULs, without touching the filesystem.
+///
+/// Paths which cannot be converted are reported and left out, exiting with 1 once every
+/// path is read.
+fn filter(opts: &Options, null: bool) {
+    let separator = if null { b'\0' } else { b'\n' };
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let mut failed = false;
+
+    for entry in io::stdin().lock().split(separator) {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                error!(
+                    "{}",
+                    tr!("read-failed", path = "stdin", reason = err.to_string())
+                );
+                exit(1);
+            }
+        };
+
+        let entry = match entry.strip_suffix(b"\r") {
+            Some(entry) if !null => entry,
+            _ => entry.as_slice(),
+        };
+
+        let path = manifest::path_from_bytes(entry);
 
-        let parent = new_path.parent();
-        if parent.is_some() && !parent.unwrap().exists() {
-            if let Err(err) = fs::create_dir_all(parent.unwrap()) {
-                eprintln!("Error: {}", err);
-                exit(4);
+        // a path which is left as it is is printed as it was read
+        let target = match convert_target(&path, opts) {
+            Ok(Some((target, _))) => target,
+            Ok(None) => path,
+            Err(err) => {
+                error!("{}", err);
+                failed = true;
+                continue;
             }
+        };
+
+        let written = out
+            .write_all(target.as_os_str().as_encoded_bytes())
+            .and_then(|_| out.write_all(&[separator]));
+
+        if let Err(err) = written {
+            error!(
+                "{}",
+                tr!("write-converted-failed", reason = err.to_string())
+            );
+            exit(1);
         }
+    }
+
+    if let Err(err) = out.flush() {
+        error!(
+            "{}",
+            tr!("write-converted-failed", reason = err.to_string())
+        );
+        exit(1);
+    }
+
+    if failed {
+        exit(1);
+    }
+}
 
-        if let Err(err) = fs::rename(path, new_path.to_path_buf()) {
-            eprintln!("Error: {}", err);
+/// Plan renaming each component of `path` which differs from `new_path` in place under its
+/// current parent, rather than moving `path` into directories created for it.
+fn push_in_place(path: &Path, new_path: &Path, root: &Path, planner: &mut Planner) {
+    let old_components: Vec<Component> = path.components().collect();
+    let new_components: Vec<Component> = new_path.components().collect();
+
+    // the whole path is still renamed as one when converting changed how many components
+    // it has, since there is no single component to rename in place
+    if old_components.len() != new_components.len() {
+        planner.push(path.to_path_buf(), new_path.to_path_buf(), root);
+        return;
+    }
+
+    let last = new_components.len() - 1;
+    let mut parent = PathBuf::new();
+
+    for (i, (old, new)) in old_components.iter().zip(&new_components).enumerate() {
+        let source = parent.join(old);
+
+        if old != new || i == last {
+            planner.push_in_place(source.clone(), parent.join(new), root);
         }
+
+        parent = source;
     }
+}
 
-    if is_verbose || is_dry_run {
-        println!(
+/// Print how each converted component of `path` is split into words and converted.
+fn explain(path: &Path, opts: &Options) {
+    let converted = match opts.prefix {
+        _ if !opts.is_full_path => 1,
+        Some(prefix) if path.starts_with(prefix) => {
+            path.components().count() - prefix.components().count()
+        }
+        _ => path.components().count(),
+    };
+
+    let skip = path.components().count().saturating_sub(converted);
+
+    if let Some(rule) = opts.rules.and_then(|rules| rules.find(path)) {
+        info!(
+            "{}",
+            tr!(
+                "explain-rule",
+                path = path.display(),
+                rule = rule.glob.to_string(),
+                convention = rule.into.name()
+            )
+        );
+    }
+
+    let to = opts.convention(path);
+
+    for component in path.components().skip(skip) {
+        let name = match component {
+            Component::Normal(name) => name,
+            _ => continue,
+        };
+
+        // names which cannot be converted are reported once the path is converted
+        let explanation = match explain::explain_component(name, opts.from, to, &opts.extensions) {
+            Ok(explanation) => explanation,
+            Err(_) => continue,
+        };
+
+        info!(
             "'{}' -> '{}'",
-            path.to_str().unwrap(),
-            new_path.to_str().unwrap()
+            name.to_string_lossy(),
+            explanation.converted
         );
+
+        for word in &explanation.words {
+            match word.boundary {
+                Some(boundary) => info!(
+                    "  {}",
+                    tr!(
+                        "explain-split",
+                        word = word.text.to_string(),
+                        boundary = boundary.to_string()
+                    )
+                ),
+                None => info!("  '{}'", word.text),
+            }
+        }
+
+        for rule in &explanation.rules {
+            info!("  {}", rule);
+        }
+
+        let mut converted = PathBuf::from(&explanation.converted);
+
+        if !opts.transforms.is_empty() {
+            match transform_target(converted.clone(), 1, opts) {
+                Ok(transformed) if transformed != converted => {
+                    let pipeline: Vec<&str> = opts
+                        .transforms
+                        .iter()
+                        .map(|transform| transform.name())
+                        .collect();
+
+                    info!(
+                        "  {}",
+                        tr!(
+                            "explain-transformed",
+                            path = converted.display(),
+                            transforms = pipeline.join(", "),
+                            transformed = transformed.display()
+                        )
+                    );
+
+                    converted = transformed;
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    info!("  {}", err);
+                    continue;
+                }
+            }
+        }
+
+        if let Some(platform) = opts.sanitize {
+            match sanitize_target(converted.clone(), 1, opts) {
+                Ok(sanitized) if sanitized != converted => info!(
+                    "  {}",
+                    tr!(
+                        "explain-sanitized",
+                        path = converted.display(),
+                        platform = format!("{:?}", platform),
+                        sanitized = sanitized.display()
+                    )
+                ),
+                Ok(_) => {}
+                Err(err) => info!("  {}", err),
+            }
+        }
+    }
+}
+
+/// Apply the '--wordless' policy to the last `converted` components of `path`.
+fn check_words(path: &Path, converted: usize, opts: &Options) -> Result<(), PathConvertError> {
+    if opts.wordless == Wordless::Convert {
+        return Ok(());
+    }
+
+    let skipped = path.components().count().saturating_sub(converted);
+    let mut checked = PathBuf::new();
+
+    for (i, component) in path.components().enumerate() {
+        checked.push(component);
+
+        let name = match component {
+            Component::Normal(name) if i >= skipped => name.to_string_lossy(),
+            _ => continue,
+        };
+
+        if stem::is_wordless(&name, &opts.extensions) {
+            match opts.wordless {
+                Wordless::Error => return Err(PathConvertError::Wordless(checked)),
+                _ => warning!("{}", tr!("wordless", path = checked.display())),
+            }
+        }
     }
 
     Ok(())
 }
 
-fn convert_recursive(
-    dir: &Path,
-    from: Option<Convention>,
-    to: Convention,
-    is_verbose: bool,
-    is_dry_run: bool,
-    no_clobber: bool,
-) -> Result<(), PathConvertError> {
-    for i in WalkDir::new(dir).contents_first(true) {
-        if let Ok(entry) = i {
-            let path = entry.path();
+/// Apply the '--transform' pipeline and then the sanitize policy to the last `converted`
+/// components of `target`, converted from `path`, before putting back those
+/// '--component-pattern' leaves as they are.
+fn adjust_target(
+    path: &Path,
+    target: PathBuf,
+    converted: usize,
+    opts: &Options,
+) -> Result<PathBuf, PathConvertError> {
+    let target = transform_target(target, converted, opts)?;
+    let target = sanitize_target(target, converted, opts)?;
 
-            convert_single(
-                path, from, to, false, None, is_verbose, is_dry_run, no_clobber,
-            )?;
+    Ok(keep_unmatched(path, target, opts))
+}
+
+/// Put back every component of `path` whose name does not match '--component-pattern' in
+/// place of its conversion in `target`.
+fn keep_unmatched(path: &Path, target: PathBuf, opts: &Options) -> PathBuf {
+    let pattern = match opts.component_pattern {
+        Some(pattern) => pattern,
+        None => return target,
+    };
+
+    // every component is converted into exactly one, so they pair up in order
+    path.components()
+        .zip(target.components())
+        .map(|(original, converted)| match original {
+            Component::Normal(name) if !name.to_str().is_some_and(|n| pattern.is_match(n)) => {
+                original
+            }
+            _ => converted,
+        })
+        .collect()
+}
+
+/// Apply the sanitize policy to the last `converted` components of `target`.
+fn sanitize_target(
+    target: PathBuf,
+    converted: usize,
+    opts: &Options,
+) -> Result<PathBuf, PathConvertError> {
+    let platform = match opts.sanitize {
+        Some(platform) => platform,
+        None => return Ok(target),
+    };
+
+    let skip = target.components().count().saturating_sub(converted);
+    let mut sanitized = PathBuf::new();
+
+    for (i, component) in target.components().enumerate() {
+        match component {
+            // only components kept as they are by '--partial-failure' are not valid utf-8
+            Component::Normal(name) if i >= skip && name.to_str().is_some() => {
+                let name = name.to_str().unwrap();
+
+                if opts.sanitize_mode == SanitizeMode::Reject {
+                    if let Err(reason) = sanitize::check(name, platform) {
+                        return Err(PathConvertError::InvalidName(sanitized.join(name), reason));
+                    }
+
+                    sanitized.push(name);
+                } else {
+                    sanitized.push(sanitize::sanitize(name, platform));
+                }
+            }
+            component => sanitized.push(component),
         }
     }
 
-    Ok(())
+    Ok(sanitized)
 }
 
-fn main() {
-    let matches = get_matches();
+/// Apply the '--transform' pipeline to the last `converted` components of `target`.
+fn transform_target(
+    target: PathBuf,
+    converted: usize,
+    opts: &Options,
+) -> Result<PathBuf, PathConvertError> {
+    if opts.transforms.is_empty() {
+        return Ok(target);
+    }
 
-    let is_verbose = matches.is_present("verbose");
-    let is_dry_run = matches.is_present("dry-run");
-    let no_clobber = matches.is_present("no-clobber");
-    let is_recursive = matches.is_present("recursive");
+    let skip = target.components().count().saturating_sub(converted);
+    let mut transformed = PathBuf::new();
 
-    let from_convention = if matches.is_present("from") {
-        match Convention::try_from(matches.value_of("from").unwrap()) {
-            Ok(c) => Some(c),
-            Err(err) => {
-                eprintln!("Error: {}", err);
-                exit(1);
+    for (i, component) in target.components().enumerate() {
+        match component {
+            // only components kept as they are by '--partial-failure' are not valid utf-8
+            Component::Normal(name) if i >= skip && name.to_str().is_some() => {
+                transformed.push(transform::apply(name.to_str().unwrap(), opts.transforms)?)
             }
+            component => transformed.push(component),
         }
-    } else {
-        None
+    }
+
+    Ok(transformed)
+}
+
+/// Name the converted `target` of `path` after the '--template', keeping its extension.
+fn template_target(
+    path: &Path,
+    target: PathBuf,
+    opts: &Options,
+    planner: &mut Planner,
+) -> Result<PathBuf, PathConvertError> {
+    let template = match opts.template {
+        Some(template) => template,
+        None => return Ok(target),
     };
 
-    let to_convention = match Convention::try_from(matches.value_of("into").unwrap()) {
-        Ok(c) => c,
-        Err(err) => {
-            eprintln!("Error: {}", err);
-            exit(1);
-        }
+    // converted names are always valid utf-8
+    let name = match target.file_name().and_then(|name| name.to_str()) {
+        Some(name) => name,
+        None => return Ok(target),
     };
 
-    // ensure that all specified paths exist
-    let paths: Vec<&Path> = matches
-        .values_of("paths")
-        .unwrap()
-        .map(|v| {
-            let path = Path::new(v);
+    let (stem, ext) = stem::split(name, &opts.extensions);
+    planner.templated += 1;
+
+    let convert = |name: &str| {
+        convert_path::convert_basename_with(
+            Path::new(name),
+            opts.from,
+            opts.convention(path),
+            &opts.extensions,
+        )
+        .ok()
+        .and_then(|converted| converted.to_str().map(String::from))
+    };
+
+    // the metadata of a media file is only read once the template needs it
+    #[cfg(feature = "exif")]
+    let mut metadata = None;
 
-            if !path.exists() {
-                eprintln!("Error: no such file or directory '{}'", path.display());
-                exit(2);
+    let rendered = template
+        .render(|token| match token {
+            "stem" => stem.map(String::from),
+            "n" => Some(planner.templated.to_string()),
+            "date" => fs::symlink_metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .map(template::date),
+            "parent" => {
+                let parent = fs::canonicalize(guard::parent_dir(path)).ok()?;
+
+                convert(parent.file_name()?.to_str()?)
             }
+            #[cfg(feature = "exif")]
+            "taken" => metadata
+                .get_or_insert_with(|| media::Metadata::read(path))
+                .taken
+                .clone(),
+            #[cfg(feature = "exif")]
+            "camera" => {
+                let metadata = metadata.get_or_insert_with(|| media::Metadata::read(path));
 
-            path
+                convert(metadata.camera.as_deref()?)
+            }
+            _ => None,
         })
-        .collect();
+        .map_err(|token| {
+            PathConvertError::InvalidName(
+                target.clone(),
+                format!("has no value for the token '{{{}}}'", token),
+            )
+        })?;
 
-    let is_full_path = matches.is_present("full-path");
-    let prefix = if matches.is_present("prefix") {
-        Some(Path::new(matches.value_of("prefix").unwrap()))
-    } else {
-        None
+    let name = match ext {
+        Some(ext) => format!("{}.{}", rendered, ext),
+        None => rendered,
     };
 
-    for path in paths {
-        if path.is_dir() && is_recursive {
-            convert_recursive(
-                path,
-                from_convention,
-                to_convention,
-                is_verbose,
-                is_dry_run,
-                no_clobber,
-            );
-        } else {
-            convert_single(
-                path,
-                from_convention,
-                to_convention,
-                is_full_path,
-                prefix,
-                is_verbose,
-                is_dry_run,
-                no_clobber,
-            );
-        }
-    }
+    sanitize_target(target.with_file_name(name), 1, opts)
 }
+
+/// Add the basename conversion of every entry under `dir` to the plan, children before their parents.
+///
+/// When following symlinks, an entry reachable through several paths is only added
+/// the first time it is found and symlink loops are reported rather than descended into.
+/// Unless disabled, directories which look to already be conforming are not descended into.
+/// Given the state of the previous run, the entries of unchanged directories are skipped.
+/// Given the files git tracks, every other entry is skipped along with everything beneath it.
+/// Sockets, FIFOs, and device files are handled as '--special-files' says.
+/// Entries are walked sequentially but converted in parallel batches.
+fn convert_recursive(
+    dir: &Path,
+    opts: &Options,
+    planner: &mut Planner,
+    mut state: Option<&mut State>,
+    tracked: Option<&Tracked>,
+) -> Result<(), PathConvertError> {
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    let mut paths: Vec<PathBuf> = vec![];
+    let mut special = 0;
+
+    let mut visit = |path: PathBuf| -> Result<(), PathConvertError> {
+        // every entry reached is remembered when following symlinks, so only then does
+        // memory grow with the size of the tree
+        if opts.walk.is_following_links() && !seen.insert(real_entry(&path)) {
+            return Ok(());
+        }
+
+        if opts.use_stamp && marker::is_stamped(&path, opts.convention(&path)) {
+            return Ok(());
+        }
+
+        paths.push(path);
+
+        if paths.len() >= CONVERT_BATCH_LEN {
+            convert_batch(std::mem::take(&mut paths), opts, planner)?;
+        }
+
+        Ok(())
+    };
+
+    // walking parents first is the only way to skip descending into a directory, so
+    // directories are deferred until everything beneath them has been visited instead
+    let mut walker = opts.walk.walk_pruning(dir, |entry| {
+        tracked.is_some_and(|tracked| !tracked.contains(entry.path()))
+            || (opts.prune_conforming && is_conforming_dir(entry, opts))
+    });
+
+    // each deferred directory is kept with whether it should be skipped, and whether its
+    // own entries should be
+    let mut deferred: Vec<(PathBuf, usize, bool, bool)> = vec![];
+
+    loop {
+        let started = stats::start();
+        let entry = walker.next();
+        stats::record(Phase::Walk, started, entry.is_some() as u64);
+
+        let entry = match entry {
+            None => break,
+            Some(entry) => entry,
+        };
+
+        let (path, depth, is_dir, kind) = match entry {
+            Ok(entry) => (
+                entry.path().to_path_buf(),
+                entry.depth(),
+                entry.file_type().is_dir(),
+                special_kind(entry.file_type()),
+            ),
+            Err(err) => match (err.path(), err.loop_ancestor()) {
+                // the link itself is still renamed, it is just not descended into
+                (Some(path), Some(ancestor)) => {
+                    warning!(
+                        "{}",
+                        tr!(
+                            "symlink-loop",
+                            path = path.display(),
+                            ancestor = ancestor.display()
+                        )
+                    );
+
+                    (path.to_path_buf(), err.depth(), false, None)
+                }
+                _ => continue,
+            },
+        };
+
+        if let Some(kind) = kind.filter(|_| opts.special_files != SpecialFiles::Rename) {
+            if opts.special_files == SpecialFiles::Error {
+                error!(
+                    "{}",
+                    tr!("special-file", path = path.display(), kind = kind)
+                );
+                exit(1);
+            }
+
+            if opts.is_verbose {
+                info!(
+                    "{}",
+                    tr!("special-file-left", kind = kind, path = path.display())
+                );
+            }
+
+            special += 1;
+            continue;
+        }
+
+        while deferred.last().is_some_and(|(_, d, ..)| *d >= depth) {
+            let (dir, _, skip, _) = deferred.pop().unwrap();
+
+            // only the files of a flattened tree are moved, its directories stay behind
+            if !skip && opts.flatten.is_none() {
+                visit(dir)?;
+            }
+        }
+
+        let skip = deferred.last().is_some_and(|(.., unchanged)| *unchanged);
+
+        if is_dir {
+            let unchanged = state.as_mut().is_some_and(|state| state.visit(&path));
+            deferred.push((path, depth, skip, unchanged));
+        } else if !skip {
+            visit(path)?;
+        }
+    }
+
+    while let Some((dir, _, skip, _)) = deferred.pop() {
+        if !skip && opts.flatten.is_none() {
+            visit(dir)?;
+        }
+    }
+
+    planner.special += special;
+
+    convert_batch(paths, opts, planner)
+}
+
+/// The number of children sampled when deciding whether a directory is already conforming.
+const PRUNE_SAMPLE_LEN: usize = 16;
+
+/// Whether `entry` is a directory whose name and first few children already conform, which
+/// is taken to mean that nothing beneath it needs to be renamed.
+fn is_conforming_dir(entry: &walkdir::DirEntry, opts: &Options) -> bool {
+    if !entry.file_type().is_dir() || !is_conforming(entry.path(), opts) {
+        return false;
+    }
+
+    match fs::read_dir(entry.path()) {
+        Ok(children) => children
+            .take(PRUNE_SAMPLE_LEN)
+            .all(|child| child.is_ok_and(|child| is_conforming(&child.path(), opts))),
+        Err(_) => false,
+    }
+}
+
+/// Whether the basename of `path` would be left unchanged.
+fn is_conforming(path: &Path, opts: &Options) -> bool {
+    convert_path::convert_basename_with(path, opts.from, opts.convention(path), &opts.extensions)
+        .and_then(|new_path| adjust_target(path, new_path, 1, opts))
+        .is_ok_and(|new_path| new_path == path)
+}
+
+/// The number of walked entries converted in parallel at once.
+const CONVERT_BATCH_LEN: usize = 4096;
+
+/// Convert the basenames of `paths` in parallel, adding them to the plan in order.
+fn convert_batch(
+    paths: Vec<PathBuf>,
+    opts: &Options,
+    planner: &mut Planner,
+) -> Result<(), PathConvertError> {
+    let started = stats::start();
+
+    let converted: Vec<Result<(PathBuf, bool), PathConvertError>> = paths
+        .par_iter()
+        .map(|path| {
+            let started = stats::start();
+
+            check_words(path, 1, opts)?;
+
+            let new_path = convert_path::convert_basename_with(
+                path,
+                opts.from,
+                opts.convention(path),
+                &opts.extensions,
+            )?;
+            let new_path = adjust_target(path, new_path, 1, opts)?;
+            let (new_path, root) = match opts.flatten {
+                Some(dir) => (flatten_target(new_path, dir), dir),
+                None => (new_path, guard::parent_dir(path)),
+            };
+            let is_contained = is_contained(path, &new_path, root);
+
+            stats::record_busy(started);
+
+            Ok((new_path, is_contained))
+        })
+        .collect();
+
+    stats::record(Phase::Convert, started, paths.len() as u64);
+
+    for (path, result) in paths.into_iter().zip(converted) {
+        let (new_path, is_contained) = result?;
+
+        // a path lacking a value the template needs, like a photo without the date it was
+        // taken, is left alone rather than ending the walk
+        let new_path = match template_target(&path, new_path, opts, planner) {
+            Ok(new_path) => new_path,
+            Err(err) => {
+                error!("{}", err);
+                continue;
+            }
+        };
+        let root = opts
+            .flatten
+            .unwrap_or_else(|| guard::parent_dir(&path))
+            .to_path_buf();
+
+        if opts.explain {
+            explain(&path, opts);
+        }
+
+        planner.insert(path, new_path, &root, is_contained);
+    }
+
+    Ok(())
+}
+
+/// Move the converted `new_path` into `dir`, keeping only its basename.
+fn flatten_target(new_path: PathBuf, dir: &Path) -> PathBuf {
+    match new_path.file_name() {
+        Some(name) => dir.join(name),
+        None => new_path,
+    }
+}
+
+/// Resolve the directory containing `path` without resolving `path` itself, so that
+/// every path leading to the same directory entry resolves to the same value.
+fn real_entry(path: &Path) -> PathBuf {
+    match (fs::canonicalize(guard::parent_dir(path)), path.file_name()) {
+        (Ok(parent), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Report collisions and exit, or with '--no-clobber' return the sources to skip so that
+/// only the first source of each collision is renamed.
+fn resolve_collisions(collisions: Vec<Collision>, opts: &Options) -> HashSet<PathBuf> {
+    // directories converting to the same name are merged into it one after another, while
+    // only one of the links to a file converting to the same name is needed
+    let collisions: Vec<Collision> = collisions
+        .into_iter()
+        .filter(|collision| !opts.merge_dirs || !collision.sources.iter().all(|s| is_dir(s)))
+        .filter(|collision| !hardlink::are_links(&collision.sources))
+        .collect();
+
+    if collisions.is_empty() {
+        return HashSet::new();
+    }
+
+    if !opts.no_clobber {
+        for collision in collisions {
+            let sources: Vec<String> = collision
+                .sources
+                .iter()
+                .map(|source| format!("'{}'", source.display()))
+                .collect();
+
+            error!(
+                "{}",
+                tr!(
+                    "collision",
+                    sources = sources.join(", "),
+                    target = collision.target.display()
+                )
+            );
+        }
+
+        exit(3);
+    }
+
+    let mut skipped: HashSet<PathBuf> = HashSet::new();
+
+    for collision in collisions {
+        for source in collision.sources.into_iter().skip(1) {
+            if opts.is_verbose {
+                info!(
+                    "{}",
+                    tr!("already-targeted", path = collision.target.display())
+                );
+            }
+
+            report::event(Event::Rename {
+                source: &source,
+                target: &collision.target,
+                outcome: Outcome::Skipped,
+            });
+
+            skipped.insert(source);
+        }
+    }
+
+    skipped
+}
+
+/// The result of performing a single planned rename.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Outcome {
+    /// The path was renamed, or would have been during a dry run.
+    Renamed,
+
+    /// The path already conforms to the target convention.
+    Conforming,
+
+    /// The path was not renamed to avoid clobbering another file.
+    Skipped,
+
+    /// The path vanished or was replaced by another entry after the plan was made.
+    Changed,
+
+    /// The rename was attempted but failed.
+    Failed,
+}
+
+impl Outcome {
+    fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Renamed => "renamed",
+            Outcome::Conforming => "conforming",
+            Outcome::Skipped => "skipped",
+            Outcome::Changed => "changed",
+            Outcome::Failed => "failed",
+        }
+    }
+}
+
+/// Counts the outcomes of every rename in a run.
+#[derive(Default)]
+struct Summary {
+    renamed: usize,
+    conforming: usize,
+    skipped: usize,
+    changed: usize,
+    failed: usize,
+
+    /// The special files left as they are while recursing, which were never planned.
+    special: usize,
+}
+
+impl Summary {
+    fn record(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Renamed => self.renamed += 1,
+            Outcome::Conforming => self.conforming += 1,
+            Outcome::Skipped => self.skipped += 1,
+            Outcome::Changed => self.changed += 1,
+            Outcome::Failed => self.failed += 1,
+        }
+    }
+
+    /// The count of each outcome, by the name it is reported under.
+    fn outcomes(&self) -> [(&'static str, usize); 6] {
+        [
+            ("renamed", self.renamed),
+            ("conforming", self.conforming),
+            ("skipped", self.skipped),
+            ("changed", self.changed),
+            ("failed", self.failed),
+            ("special", self.special),
+        ]
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            tr!(
+                "summary",
+                renamed = self.renamed,
+                conforming = self.conforming,
+                skipped = self.skipped,
+                changed = self.changed,
+                failed = self.failed
+            )
+        )?;
+
+        if self.special > 0 {
+            write!(f, " ({})", tr!("summary-special", special = self.special))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Stops a run once '--max-errors' renames have failed, letting those already started finish.
+#[derive(Debug, Default)]
+struct ErrorBudget {
+    max: Option<usize>,
+    failed: AtomicUsize,
+}
+
+impl ErrorBudget {
+    fn new(max: Option<usize>) -> ErrorBudget {
+        ErrorBudget {
+            max,
+            failed: AtomicUsize::new(0),
+        }
+    }
+
+    fn record(&self, outcome: Outcome) {
+        if outcome == Outcome::Failed {
+            self.failed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Whether no more renames should be started.
+    fn is_exhausted(&self) -> bool {
+        self.max
+            .is_some_and(|max| self.failed.load(Ordering::Relaxed) >= max)
+    }
+}
+
+/// What the user of an '--interactive' run has answered for the renames still to come.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+enum Answers {
+    /// Every rename is asked about.
+    #[default]
+    Each,
+
+    /// Every remaining rename is performed without asking.
+    All,
+
+    /// No remaining rename is performed.
+    Quit,
+}
+
+/// Asks before each rename of an '--interactive' run, as 'mv -i' does.
+#[derive(Debug, Default)]
+struct Prompt {
+    is_asking: bool,
+    answers: Mutex<Answers>,
+}
+
+impl Prompt {
+    fn new(is_asking: bool) -> Prompt {
+        Prompt {
+            is_asking,
+            answers: Mutex::new(Answers::Each),
+        }
+    }
+
+    /// Whether to perform `rename`, asking unless the rest were already accepted or refused.
+    fn allows(&self, rename: &Rename) -> bool {
+        if !self.is_asking {
+            return true;
+        }
+
+        let mut answers = self.answers.lock().unwrap();
+
+        loop {
+            match *answers {
+                Answers::All => return true,
+                Answers::Quit => return false,
+                Answers::Each => {}
+            }
+
+            let prompt = tr!(
+                "rename-prompt",
+                source = rename.source.display(),
+                target = rename.target.display()
+            );
+            eprint!("{} [y/n/a/q] ", prompt);
+
+            let mut answer = String::new();
+
+            // nothing more can be answered once stdin is closed
+            match io::stdin().read_line(&mut answer) {
+                Ok(0) | Err(_) => *answers = Answers::Quit,
+                Ok(_) => match answer.trim() {
+                    "y" | "Y" | "yes" => return true,
+                    "n" | "N" | "no" | "" => return false,
+                    "a" | "A" | "all" => *answers = Answers::All,
+                    "q" | "Q" | "quit" => *answers = Answers::Quit,
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    /// Whether no more renames should be started.
+    fn is_quit(&self) -> bool {
+        self.is_asking && *self.answers.lock().unwrap() == Answers::Quit
+    }
+}
+
+/// Perform a single planned rename.
+fn rename_single(
+    rename: &Rename,
+    opts: &Options,
+    snapshot: &Snapshot,
+    dirs: &mut DirCache,
+) -> Outcome {
+    if !rename.is_noop() && !opts.prompt.allows(rename) {
+        return Outcome::Skipped;
+    }
+
+    if let Some(outcome) = prepare_rename(rename, opts, snapshot, dirs) {
+        return outcome;
+    }
+
+    let result = if opts.is_dry_run {
+        Ok(())
+    } else {
+        opts.throttle.wait();
+        fs::rename(&rename.source, &rename.target)
+    };
+
+    finish_rename(rename, result, opts, dirs)
+}
+
+/// Decide the outcome of a rename which should not be performed, otherwise create any
+/// missing parent directories of its target.
+fn prepare_rename(
+    rename: &Rename,
+    opts: &Options,
+    snapshot: &Snapshot,
+    dirs: &mut DirCache,
+) -> Option<Outcome> {
+    let Rename { source, target } = rename;
+
+    if rename.is_noop() {
+        if opts.use_stamp && !opts.is_dry_run {
+            marker::stamp(source, opts.convention(source));
+        }
+
+        return Some(Outcome::Conforming);
+    }
+
+    if opts.is_dry_run {
+        return None;
+    }
+
+    if let Err(drift) = snapshot.verify(source) {
+        match drift {
+            Drift::Vanished => warning!("{}", tr!("drift-vanished", path = source.display())),
+            Drift::Replaced => warning!("{}", tr!("drift-replaced", path = source.display())),
+        }
+
+        return Some(Outcome::Changed);
+    }
+
+    if opts.merge_dirs && is_dir(source) && is_taken(source, target) && is_dir(target) {
+        return Some(merge_dir(rename, opts, dirs));
+    }
+
+    // a rename onto another link to the same file would silently leave both names
+    if hardlink::is_other_link(source, target) {
+        return Some(unlink_duplicate(rename, opts, dirs));
+    }
+
+    if opts.no_clobber && is_taken(source, target) {
+        if opts.is_verbose {
+            info!("{}", tr!("file-exists", path = target.display()));
+        }
+
+        return Some(Outcome::Skipped);
+    }
+
+    if opts.use_trash && is_taken(source, target) {
+        if let Err(err) = trash::delete(target) {
+            error!(
+                "{}",
+                tr!(
+                    "trash-failed",
+                    path = target.display(),
+                    reason = err.to_string()
+                )
+            );
+            stats::record_failure("could not move the target to the trash");
+
+            return Some(Outcome::Failed);
+        }
+
+        if opts.is_verbose {
+            info!("{}", tr!("trashed", path = target.display()));
+        }
+    }
+
+    if let Some(parent) = target.parent() {
+        if let Err(err) = dirs.create_all(parent) {
+            error!("{}", err);
+            exit(4);
+        }
+    }
+
+    None
+}
+
+/// Whether an entry other than `source` is at `target`, which may name `source` itself when
+/// they differ only in case on a case-insensitive filesystem, so that changing only the
+/// case of a name is not mistaken for clobbering it.
+fn is_taken(source: &Path, target: &Path) -> bool {
+    Identity::of(target).is_some_and(|target| Some(target) != Identity::of(source))
+}
+
+/// Remove the source of `rename` whose target already is another link to the same file, which
+/// is all that renaming it would do, unless the target is not to be overwritten.
+fn unlink_duplicate(rename: &Rename, opts: &Options, dirs: &mut DirCache) -> Outcome {
+    let Rename { source, target } = rename;
+
+    if opts.no_clobber {
+        if opts.is_verbose {
+            info!("{}", tr!("file-exists", path = target.display()));
+        }
+
+        return Outcome::Skipped;
+    }
+
+    match fs::remove_file(source) {
+        Ok(()) => finish_rename(rename, Ok(()), opts, dirs),
+        Err(err) => {
+            error!("{}", err);
+            stats::record_failure(&err.kind().to_string());
+
+            Outcome::Failed
+        }
+    }
+}
+
+/// What kind of special file an entry of `file_type` is, like a socket or a FIFO, if it is one.
+#[cfg(unix)]
+fn special_kind(file_type: fs::FileType) -> Option<&'static str> {
+    use std::os::unix::fs::FileTypeExt;
+
+    if file_type.is_socket() {
+        Some("socket")
+    } else if file_type.is_fifo() {
+        Some("FIFO")
+    } else if file_type.is_block_device() {
+        Some("block device")
+    } else if file_type.is_char_device() {
+        Some("character device")
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn special_kind(_: fs::FileType) -> Option<&'static str> {
+    None
+}
+
+/// Whether `path` is a directory rather than a file or a link to one.
+fn is_dir(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_dir())
+}
+
+/// Merge the directory at the source of `rename` into the one already at its target, treating
+/// the entries whose names are taken in both like any other existing file.
+fn merge_dir(rename: &Rename, opts: &Options, dirs: &mut DirCache) -> Outcome {
+    let Rename { source, target } = rename;
+
+    let conflict = if opts.no_clobber {
+        Conflict::Keep
+    } else if opts.use_trash {
+        Conflict::Trash
+    } else {
+        Conflict::Overwrite
+    };
+
+    match merge::merge(source, target, conflict) {
+        Ok(kept) if kept.is_empty() => finish_rename(rename, Ok(()), opts, dirs),
+        Ok(kept) => {
+            if opts.is_verbose {
+                for path in &kept {
+                    info!("{}", tr!("file-exists", path = path.display()));
+                }
+            }
+
+            warning!(
+                "{}",
+                tr!(
+                    "merge-kept",
+                    source = source.display(),
+                    target = target.display(),
+                    count = kept.len()
+                )
+            );
+
+            Outcome::Skipped
+        }
+        Err(err) => {
+            error!(
+                "{}",
+                tr!(
+                    "merge-failed",
+                    source = source.display(),
+                    target = target.display(),
+                    reason = err.to_string()
+                )
+            );
+            stats::record_failure(&err.kind().to_string());
+
+            Outcome::Failed
+        }
+    }
+}
+
+/// Whether any of `paths`, or the directory holding it if it is not a directory itself, is
+/// on a volume which does not distinguish names by case, probing each directory once.
+fn is_any_case_insensitive(paths: &[PathBuf]) -> bool {
+    let mut probed = HashSet::new();
+
+    paths
+        .iter()
+        .map(|path| {
+            if path.is_dir() {
+                path.as_path()
+            } else {
+                guard::parent_dir(path)
+            }
+        })
+        .filter(|dir| probed.insert(dir.to_path_buf()))
+        .any(|dir| volume::is_case_insensitive(dir) == Some(true))
+}
+
+/// Decide the outcome of a rename given the result of attempting it, falling back to
+/// copying across filesystems.
+fn finish_rename(
+    rename: &Rename,
+    result: io::Result<()>,
+    opts: &Options,
+    dirs: &mut DirCache,
+) -> Outcome {
+    let Rename { source, target } = rename;
+
+    let result = opts.retry.after(
+        result,
+        || fs::rename(source, target),
+        |err, attempt| {
+            if opts.is_verbose {
+                info!(
+                    "{}",
+                    tr!(
+                        "retrying",
+                        path = source.display(),
+                        attempt = attempt,
+                        attempts = opts.retry.attempts,
+                        reason = err.to_string()
+                    )
+                );
+            }
+        },
+    );
+
+    let result = match result {
+        Err(err) if copy::is_cross_device(&err) => {
+            copy::move_by_copy(source, target, &opts.preserve, &opts.throttle)
+        }
+        result => result,
+    };
+
+    if let Err(err) = result {
+        error!("{}", err);
+        stats::record_failure(&err.kind().to_string());
+
+        return Outcome::Failed;
+    }
+
+    if !opts.is_dry_run {
+        dirs.moved(source);
+
+        if opts.use_stamp {
+            marker::stamp(target, opts.convention(source));
+        }
+    }
+
+    if opts.is_verbose || opts.is_dry_run {
+        println!(
+            "'{}' -> '{}'",
+            opts.path_style.show(source).display(),
+            opts.path_style.show(target).display()
+        );
+    }
+
+    Outcome::Renamed
+}
+
+/// How planned renames are performed.
+enum Executor {
+    /// One rename syscall at a time.
+    Standard,
+
+    /// Independent renames performed in parallel on the thread pool.
+    Parallel,
+
+    /// Independent renames submitted in batches through io_uring.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    Uring(Box<uring::Ring>),
+}
+
+impl Executor {
+    /// Use io_uring when requested and supported, otherwise fall back to performing renames
+    /// in parallel when requested, or one at a time.
+    fn new(use_uring: bool, is_parallel: bool, is_verbose: bool) -> Executor {
+        let fallback = if is_parallel {
+            Executor::Parallel
+        } else {
+            Executor::Standard
+        };
+
+        if !use_uring {
+            return fallback;
+        }
+
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        match uring::Ring::new() {
+            Ok(ring) => return Executor::Uring(Box::new(ring)),
+            Err(err) => {
+                if is_verbose {
+                    info!("{}", tr!("uring-unavailable", reason = err.to_string()));
+                }
+            }
+        }
+
+        #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+        if is_verbose {
+            info!("{}", tr!("uring-unsupported"));
+        }
+
+        fallback
+    }
+
+    /// Perform every rename in `plan`, calling `report` with each outcome in plan order, until
+    /// the error budget of `opts` is exhausted.
+    fn execute<F: FnMut(&Rename, Outcome)>(
+        &mut self,
+        plan: &RenamePlan,
+        opts: &Options,
+        snapshot: &Snapshot,
+        dirs: &mut DirCache,
+        mut report: F,
+    ) {
+        match self {
+            Executor::Standard => {
+                for rename in plan {
+                    if opts.errors.is_exhausted() || opts.prompt.is_quit() {
+                        break;
+                    }
+
+                    let outcome = rename_single(rename, opts, snapshot, dirs);
+                    opts.errors.record(outcome);
+                    report(rename, outcome);
+                }
+            }
+            Executor::Parallel => rename_leveled(plan, opts, snapshot, dirs, report, |renames| {
+                renames
+                    .par_iter()
+                    .map(|rename| {
+                        opts.throttle.wait();
+                        fs::rename(&rename.source, &rename.target)
+                    })
+                    .collect()
+            }),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Executor::Uring(ring) => {
+                rename_leveled(plan, opts, snapshot, dirs, report, |renames| {
+                    ring.rename_all(renames).unwrap_or_else(|err| {
+                        warning!("{}", tr!("uring-failed", reason = err.to_string()));
+
+                        renames
+                            .iter()
+                            .map(|rename| fs::rename(&rename.source, &rename.target))
+                            .collect()
+                    })
+                })
+            }
+        }
+    }
+}
+
+/// Assign each rename the earliest level it can be performed in, so that the renames of a
+/// level are independent of each other and follow every rename they depend on.
+///
+/// A rename depends on each earlier rename whose source or target is the same as, an
+/// ancestor of, or a descendant of its own source or target. This keeps children renamed
+/// before their parents, and a name freed before it is reused.
+fn dependency_levels(renames: &[Rename]) -> Vec<usize> {
+    // the highest level of any rename to or from a path, and of any rename beneath it
+    let mut at: HashMap<&Path, usize> = HashMap::new();
+    let mut beneath: HashMap<&Path, usize> = HashMap::new();
+
+    renames
+        .iter()
+        .map(|rename| {
+            let paths = [rename.source.as_path(), rename.target.as_path()];
+
+            let level = paths
+                .iter()
+                .flat_map(|path| {
+                    let ancestors = path.ancestors().skip(1).filter_map(|a| at.get(a));
+
+                    at.get(path).into_iter().chain(beneath.get(path)).chain(ancestors)
+                })
+                .map(|level| level + 1)
+                .max()
+                .unwrap_or(0);
+
+            for path in paths {
+                let entry = at.entry(path).or_insert(level);
+                *entry = level.max(*entry);
+
+                for ancestor in path.ancestors().skip(1) {
+                    let entry = beneath.entry(ancestor).or_insert(level);
+                    *entry = level.max(*entry);
+                }
+            }
+
+            level
+        })
+        .collect()
+}
+
+/// Perform the renames in `plan` one dependency level at a time, handing every rename of a
+/// level to `rename_all` at once.
+fn rename_leveled<F, R>(
+    plan: &RenamePlan,
+    opts: &Options,
+    snapshot: &Snapshot,
+    dirs: &mut DirCache,
+    mut report: F,
+    mut rename_all: R,
+) where
+    F: FnMut(&Rename, Outcome),
+    R: FnMut(&[&Rename]) -> Vec<io::Result<()>>,
+{
+    let renames = plan.renames();
+    let mut outcomes: Vec<Option<Outcome>> = vec![None; renames.len()];
+    let mut levels: Vec<Vec<usize>> = vec![];
+
+    for (i, level) in dependency_levels(renames).into_iter().enumerate() {
+        if levels.len() <= level {
+            levels.resize(level + 1, vec![]);
+        }
+
+        levels[level].push(i);
+    }
+
+    for level in levels {
+        // every rename of a level is in flight at once, so the budget is only checked between
+        if opts.errors.is_exhausted() {
+            break;
+        }
+
+        let mut pending = vec![];
+
+        for &i in &level {
+            match prepare_rename(&renames[i], opts, snapshot, dirs) {
+                Some(outcome) => outcomes[i] = Some(outcome),
+                None => pending.push(i),
+            }
+        }
+
+        let batch: Vec<&Rename> = pending.iter().map(|i| &renames[*i]).collect();
+
+        let results = if opts.is_dry_run || batch.is_empty() {
+            batch.iter().map(|_| Ok(())).collect()
+        } else {
+            rename_all(&batch)
+        };
+
+        for (i, result) in pending.into_iter().zip(results) {
+            outcomes[i] = Some(finish_rename(&renames[i], result, opts, dirs));
+        }
+
+        for i in level {
+            opts.errors.record(outcomes[i].unwrap());
+        }
+    }
+
+    for (rename, outcome) in renames.iter().zip(outcomes) {
+        if let Some(outcome) = outcome {
+            report(rename, outcome);
+        }
+    }
+}
+
+/// Collect the directories which may have been emptied by moving `old` to `new`.
+///
+/// This includes every ancestor of `old` up to, but not including, the prefix or
+/// the first directory which also contains `new`.
+fn emptied_candidates(old: &Path, new: &Path, prefix: Option<&Path>) -> Vec<PathBuf> {
+    old.ancestors()
+        .skip(1)
+        .take_while(|dir| {
+            !dir.as_os_str().is_empty()
+                && !new.starts_with(dir)
+                && prefix.is_none_or(|prefix| !prefix.starts_with(dir))
+        })
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+/// Remove any of the given directories which are now empty, deepest first.
+fn remove_empty(mut dirs: Vec<PathBuf>, is_verbose: bool) {
+    dirs.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+    dirs.dedup();
+
+    for dir in dirs {
+        let is_empty = match fs::read_dir(&dir) {
+            Ok(mut entries) => entries.next().is_none(),
+            Err(_) => false,
+        };
+
+        if !is_empty {
+            continue;
+        }
+
+        match fs::remove_dir(&dir) {
+            Ok(_) => {
+                report::event(Event::RemoveDir { path: &dir });
+
+                if is_verbose {
+                    info!("{}", tr!("removed-dir", path = dir.display()));
+                }
+            }
+            Err(err) => error!("{}", err),
+        }
+    }
+}
+
+/// Normalize a path given on the command line so that a trailing slash or leading `./`
+/// doesn't leak into the converted path.
+fn normalize_input<P: AsRef<Path>>(path: P) -> PathBuf {
+    let normalized: PathBuf = path
+        .as_ref()
+        .components()
+        .filter(|component| *component != Component::CurDir)
+        .collect();
+
+    if normalized.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        normalized
+    }
+}
+
+/// `path` as given by the user, interpreted beneath `root` when one is given, exiting if it
+/// leads outside of it.
+fn input_path(path: &Path, root: Option<&Path>) -> PathBuf {
+    let root = match root {
+        Some(root) => root,
+        None => return normalize_input(path),
+    };
+
+    match guard::rooted(root, path) {
+        Some(rooted) => rooted,
+        None => {
+            error!(
+                "{}",
+                tr!(
+                    "outside-root",
+                    path = path.display(),
+                    root = root.display()
+                )
+            );
+            exit(6);
+        }
+    }
+}
+
+/// Exit unless `path` exists and, when `guard_roots` is set, the user agrees to recursively
+/// renaming everything under it if that is likely a mistake.
+fn check_input(path: &Path, guard_roots: bool) {
+    if !path.exists() {
+        error!("{}", tr!("no-such-path", path = path.display()));
+        exit(2);
+    }
+
+    if !guard_roots || !path.is_dir() {
+        return;
+    }
+
+    if let Some(reason) = guard::dangerous_root(path) {
+        let prompt = tr!(
+            "dangerous-root-prompt",
+            path = path.display(),
+            reason = reason.as_str()
+        );
+
+        if !confirm(&prompt) {
+            error!(
+                "{}",
+                tr!(
+                    "dangerous-root-refused",
+                    reason = reason,
+                    path = path.display()
+                )
+            );
+            exit(7);
+        }
+    }
+}
+
+/// Lock the trees recursed into and the directories of the paths renamed without recursing,
+/// exiting with 11 if another run holds any of them.
+///
+/// Failing to lock them otherwise, like when the directory of the locks belongs to another
+/// user, is only warned about rather than stopping the run.
+fn lock_trees(paths: &[PathBuf], is_recursive: bool) -> Option<TreeLocks> {
+    let (roots, dirs): (Vec<&Path>, Vec<&Path>) = paths
+        .iter()
+        .map(PathBuf::as_path)
+        .partition(|path| is_recursive && path.is_dir());
+    let dirs: Vec<&Path> = dirs.into_iter().map(guard::parent_dir).collect();
+
+    match TreeLocks::acquire(&roots, &dirs) {
+        Ok(locks) => Some(locks),
+        Err(LockError::Held(dir)) => {
+            error!("{}", tr!("tree-locked", path = dir.display()));
+            exit(11);
+        }
+        Err(LockError::Io(err)) => {
+            warning!("{}", tr!("lock-failed", reason = err.to_string()));
+            None
+        }
+    }
+}
+
+/// Ask the user a yes or no question, assuming no when stdin is not a terminal.
+fn confirm(prompt: &str) -> bool {
+    if !io::stdin().is_terminal() {
+        return false;
+    }
+
+    eprint!("{} [y/N] ", prompt);
+
+    let mut answer = String::new();
+
+    match io::stdin().read_line(&mut answer) {
+        Ok(_) => matches!(answer.trim(), "y" | "Y" | "yes"),
+        Err(_) => false,
+    }
+}
+
+fn conventions_main(args: ConventionsArgs) {
+    let examples = match convert_path::examples(&args.name) {
+        Ok(examples) => examples,
+        Err(err) => {
+            error!("{}", err);
+            exit(1);
+        }
+    };
+
+    for (convention, example) in examples {
+        println!("{:<5}  {}", convention.name(), example);
+    }
+}
+
+fn config_main(args: ConfigArgs) {
+    match args.command {
+        ConfigCommand::Init(args) => config_init(args),
+    }
+}
+
+fn config_init(args: InitArgs) {
+    let starter = match config::starter(&args.flags) {
+        Ok(starter) => starter,
+        Err(err) => {
+            let _ = err.print();
+            exit(1);
+        }
+    };
+
+    if !args.project {
+        print!("{}", starter);
+        return;
+    }
+
+    let path = Path::new(config::CONFIG_NAME);
+
+    if !args.overwrite && fs::symlink_metadata(path).is_ok() {
+        error!("{}", tr!("init-exists", path = path.display()));
+        exit(1);
+    }
+
+    if let Err(err) = fs::write(path, starter) {
+        error!("could not write '{}': {}", path.display(), err);
+        exit(1);
+    }
+
+    info!("wrote the starter config to '{}'", path.display());
+}
+
+/// Parse the command line, exiting if it is invalid.
+fn parse_cli(args: &[OsString]) -> (Cli, clap::ArgMatches) {
+    match cli::parse(args) {
+        Ok(parsed) => parsed,
+        // help and the version are printed to stdout and exit successfully
+        Err(err) if !err.use_stderr() => err.exit(),
+        Err(err) => {
+            let _ = err.print();
+            exit(1);
+        }
+    }
+}
+
+fn doctor_main(args: DoctorArgs) {
+    if !args.dir.is_dir() {
+        error!("{}", tr!("no-such-directory", path = args.dir.display()));
+        exit(2);
+    }
+
+    let report = doctor::examine(&args.dir, args.max_name_length);
+
+    if args.histogram {
+        let out = io::BufWriter::new(io::stdout().lock());
+        let written = if args.json {
+            report.write_histograms_json(out)
+        } else {
+            report.write_histograms(out)
+        };
+
+        if let Err(err) = written {
+            error!("{}", err);
+            exit(1);
+        }
+
+        return;
+    }
+
+    for finding in &report.findings {
+        println!("{}", finding);
+    }
+
+    info!(
+        "{}",
+        tr!(
+            "doctor-summary",
+            examined = report.examined,
+            findings = report.findings.len()
+        )
+    );
+}
+
+fn archive_main(args: ArchiveArgs, notify: bool) {
+    let archive = args.archive.as_path();
+    let is_verbose = args.verbose;
+    let is_dry_run = args.dry_run;
+
+    if !archive.exists() {
+        error!("{}", tr!("no-such-path", path = archive.display()));
+        exit(2);
+    }
+
+    let output = match args.output {
+        Some(output) => output,
+        None => match archive::default_output(archive) {
+            Some(output) => output,
+            None => {
+                error!("{}", tr!("archive-unsupported", path = archive.display()));
+                exit(1);
+            }
+        },
+    };
+
+    if !is_dry_run && output.exists() {
+        error!("{}", tr!("already-exists", path = output.display()));
+        exit(1);
+    }
+
+    // every entry is named relative to the archive root, so the whole name is converted
+    let opts = Options {
+        from: args.from,
+        is_full_path: true,
+        is_verbose,
+        is_dry_run,
+        no_clobber: args.no_clobber,
+        ..Options::defaults(args.into)
+    };
+
+    match archive::run(archive, &output, &opts) {
+        Ok(summary) => finish(&summary, is_verbose, is_dry_run, notify),
+        Err(err) => {
+            error!(
+                "{}",
+                tr!(
+                    "archive-failed",
+                    path = archive.display(),
+                    reason = err.to_string()
+                )
+            );
+            exit(1);
+        }
+    }
+}
+
+fn replay_main(args: ReplayArgs, notify: bool) {
+    let is_verbose = args.verbose;
+    let is_dry_run = args.dry_run;
+
+    let journal = match Journal::read(&args.journal) {
+        Ok(journal) => journal,
+        Err(err) => {
+            error!(
+                "{}",
+                tr!(
+                    "read-failed",
+                    path = args.journal.display(),
+                    reason = err.to_string()
+                )
+            );
+            exit(1);
+        }
+    };
+
+    let to = match journal.into {
+        Some(to) => to,
+        None => {
+            error!(
+                "{}",
+                tr!("journal-unrecorded-run", path = args.journal.display())
+            );
+            exit(1);
+        }
+    };
+
+    if !args.root.is_dir() {
+        error!("{}", tr!("no-such-directory", path = args.root.display()));
+        exit(2);
+    }
+
+    let plan = match journal.rebase(&args.root) {
+        Ok(plan) => plan,
+        Err(err) => {
+            error!("{}", err);
+            exit(1);
+        }
+    };
+
+    // every source existed when the recorded run was planned, so each must exist now
+    let missing: Vec<&Path> = plan
+        .renames()
+        .iter()
+        .map(|rename| rename.source.as_path())
+        .filter(|source| fs::symlink_metadata(source).is_err())
+        .collect();
+
+    if !missing.is_empty() {
+        error!(
+            "{}",
+            tr!(
+                "replay-missing",
+                count = missing.len(),
+                root = args.root.display()
+            )
+        );
+
+        for source in missing {
+            info!("  '{}'", source.display());
+        }
+
+        exit(2);
+    }
+
+    let snapshot = Snapshot::take(&plan);
+    let batches = Batches::InMemory(plan, snapshot);
+
+    if !is_dry_run {
+        check_writable(&batches);
+    }
+
+    let opts = Options {
+        is_verbose,
+        is_dry_run,
+        no_clobber: args.no_clobber,
+        ..Options::defaults(to)
+    };
+
+    let mut summary = Summary::default();
+    let mut dirs = DirCache::default();
+
+    let _ = batches.for_each(|plan, snapshot| {
+        Executor::Standard.execute(plan, &opts, snapshot, &mut dirs, |_, outcome| {
+            summary.record(outcome)
+        });
+    });
+
+    finish(&summary, is_verbose, is_dry_run, notify);
+}
+
+fn apply_main(args: ApplyArgs, notify: bool) {
+    let is_verbose = args.verbose;
+    let is_dry_run = args.dry_run;
+
+    let loaded = if args.plan == Path::new("-") {
+        PlanFile::load(io::stdin().lock())
+    } else {
+        fs::File::open(&args.plan).and_then(PlanFile::load)
+    };
+
+    let plan_file = match loaded {
+        Ok(plan_file) => plan_file,
+        Err(err) => {
+            error!(
+                "{}",
+                tr!(
+                    "read-failed",
+                    path = args.plan.display(),
+                    reason = err.to_string()
+                )
+            );
+            exit(1);
+        }
+    };
+
+    // the plan was checked against the tree as it was, so it is only performed on that tree
+    let changed: Vec<&Path> = plan_file
+        .renames
+        .iter()
+        .filter(|planned| !planned.is_unchanged())
+        .map(|planned| planned.rename.source.as_path())
+        .collect();
+
+    if !changed.is_empty() {
+        error!(
+            "{}",
+            tr!(
+                "apply-changed",
+                count = changed.len(),
+                path = args.plan.display()
+            )
+        );
+
+        for source in changed {
+            info!("  '{}'", source.display());
+        }
+
+        exit(1);
+    }
+
+    let plan = plan_file.plan();
+    let snapshot = Snapshot::take(&plan);
+    let batches = Batches::InMemory(plan, snapshot);
+
+    if !is_dry_run {
+        check_writable(&batches);
+    }
+
+    let opts = Options {
+        is_verbose,
+        is_dry_run,
+        no_clobber: args.no_clobber,
+        ..Options::defaults(Convention::Keep)
+    };
+
+    let mut summary = Summary::default();
+    let mut dirs = DirCache::default();
+
+    let _ = batches.for_each(|plan, snapshot| {
+        Executor::Standard.execute(plan, &opts, snapshot, &mut dirs, |_, outcome| {
+            summary.record(outcome)
+        });
+    });
+
+    finish(&summary, is_verbose, is_dry_run, notify);
+}
+
+fn resolve_main(args: ResolveArgs) {
+    let mut renames = vec![];
+
+    for path in &args.journals {
+        let journal = match Journal::read(path) {
+            Ok(journal) => journal,
+            Err(err) => {
+                error!(
+                    "{}",
+                    tr!(
+                        "read-failed",
+                        path = path.display(),
+                        reason = err.to_string()
+                    )
+                );
+                exit(1);
+            }
+        };
+
+        match journal.absolute_renames() {
+            Ok(absolute) => renames.extend(absolute),
+            Err(err) => {
+                error!("{}: {}", path.display(), err);
+                exit(1);
+            }
+        }
+    }
+
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let mut stdout = io::stdout().lock();
+
+    for path in &args.paths {
+        let absolute = path::absolute(path).unwrap_or_else(|_| path.clone());
+
+        let resolved = match journal::resolve(&renames, &absolute, args.reverse) {
+            // a relative path is answered relative to the current directory when it can be
+            Some(resolved) if path.is_relative() => match resolved.strip_prefix(&cwd) {
+                Ok(relative) if !relative.as_os_str().is_empty() => relative.to_path_buf(),
+                _ => resolved,
+            },
+            Some(resolved) => resolved,
+            None => {
+                warning!("{}", tr!("resolve-unmoved", path = path.display()));
+                path.clone()
+            }
+        };
+
+        if writeln!(stdout, "{}", resolved.display()).is_err() {
+            exit(1);
+        }
+    }
+}
+
+fn undo_main(args: UndoArgs, notify: bool) {
+    let is_verbose = args.verbose;
+    let is_dry_run = args.dry_run;
+
+    let (path, is_recorded) = match args.journal {
+        Some(path) => (path, false),
+        None => {
+            let dir = match journal::recorded_dir() {
+                Some(dir) => dir,
+                None => {
+                    error!("{}", tr!("no-data-dir"));
+                    exit(1);
+                }
+            };
+
+            match journal::last_recorded(&dir) {
+                Ok(Some(path)) => (path, true),
+                Ok(None) => {
+                    error!("{}", tr!("undo-nothing", path = dir.display()));
+                    exit(1);
+                }
+                Err(err) => {
+                    error!(
+                        "{}",
+                        tr!(
+                            "read-failed",
+                            path = dir.display(),
+                            reason = err.to_string()
+                        )
+                    );
+                    exit(1);
+                }
+            }
+        }
+    };
+
+    let journal = match Journal::read(&path) {
+        Ok(journal) => journal,
+        Err(err) => {
+            error!(
+                "{}",
+                tr!(
+                    "read-failed",
+                    path = path.display(),
+                    reason = err.to_string()
+                )
+            );
+            exit(1);
+        }
+    };
+
+    let to = match journal.into {
+        Some(to) => to,
+        None => {
+            error!("{}", tr!("journal-unrecorded-run", path = path.display()));
+            exit(1);
+        }
+    };
+
+    let renames = match journal.absolute_renames() {
+        Ok(renames) => renames,
+        Err(err) => {
+            error!("{}: {}", path.display(), err);
+            exit(1);
+        }
+    };
+
+    let mut plan = RenamePlan::new();
+    let mut snapshot = Snapshot::default();
+    let mut missing = vec![];
+
+    for (rename, current) in journal::undo(&renames) {
+        match Identity::of(&current) {
+            Some(identity) => snapshot.insert(rename.source.clone(), identity),
+            None => missing.push(current),
+        }
+
+        plan.push(rename.source, rename.target);
+    }
+
+    // every target of the recorded run must still be there to be renamed back
+    if !missing.is_empty() {
+        error!(
+            "{}",
+            tr!("undo-missing", count = missing.len(), path = path.display())
+        );
+
+        for current in missing {
+            info!("  '{}'", current.display());
+        }
+
+        exit(2);
+    }
+
+    // the directories are not probed for being writable up front, since most of them only
+    // exist once the renames of the directories above them are reverted
+    let batches = Batches::InMemory(plan, snapshot);
+
+    let opts = Options {
+        is_verbose,
+        is_dry_run,
+        // whatever took an original name since the recorded run is kept rather than overwritten
+        no_clobber: true,
+        ..Options::defaults(to)
+    };
+
+    let mut summary = Summary::default();
+    let mut dirs = DirCache::default();
+
+    let _ = batches.for_each(|plan, snapshot| {
+        Executor::Standard.execute(plan, &opts, snapshot, &mut dirs, |_, outcome| {
+            summary.record(outcome)
+        });
+    });
+
+    let is_undone = summary.skipped == 0 && summary.changed == 0 && summary.failed == 0;
+
+    if is_recorded && is_undone && !is_dry_run {
+        if let Err(err) = journal::mark_undone(&path) {
+            warning!(
+                "{}",
+                tr!(
+                    "undo-mark-failed",
+                    path = path.display(),
+                    reason = err.to_string()
+                )
+            );
+        }
+    }
+
+    finish(&summary, is_verbose, is_dry_run, notify);
+}
+
+#[cfg(unix)]
+fn daemon_main(args: DaemonArgs) {
+    // the config is only read once, so it stays loaded for every request
+    let rules = match config::load(args.config.as_deref()) {
+        Ok(config) => config.map(|config| config.rules),
+        Err(err) => {
+            error!("{}", err);
+            exit(1);
+        }
+    };
+
+    if let Some(path) = args.metrics_file {
+        metrics::enable(path);
+    }
+
+    info!("{}", tr!("daemon-listening", path = args.socket.display()));
+
+    if let Err(err) = daemon::serve(&args.socket, |request| {
+        answer_request(request, rules.as_ref())
+    }) {
+        error!(
+            "{}",
+            tr!(
+                "daemon-listen-failed",
+                path = args.socket.display(),
+                reason = err.to_string()
+            )
+        );
+        exit(1);
+    }
+}
+
+#[cfg(not(unix))]
+fn daemon_main(_: DaemonArgs) {
+    error!("the daemon is only supported where there are Unix sockets");
+    exit(1);
+}
+
+/// Convert, plan, or perform the renames of a request to the daemon, with the same checks as a
+/// run renaming the same paths.
+#[cfg(unix)]
+fn answer_request(
+    request: daemon::Request,
+    rules: Option<&Rules>,
+) -> Result<serde_json::Value, String> {
+    use serde_json::json;
+    use std::convert::TryFrom;
+
+    let to = Convention::try_from(request.into.as_str())?;
+    let from = request
+        .from
+        .as_deref()
+        .map(Convention::try_from)
+        .transpose()?;
+
+    for path in &request.paths {
+        if path.is_relative() {
+            return Err(format!("'{}' is not an absolute path", path.display()));
+        }
+
+        if fs::symlink_metadata(path).is_err() {
+            return Err(tr!("no-such-path", path = path.display()));
+        }
+    }
+
+    let opts = Options {
+        from,
+        is_dry_run: request.command != daemon::Action::Execute,
+        no_clobber: request.no_clobber,
+        rules,
+        ..Options::defaults(to)
+    };
+
+    if request.command == daemon::Action::Convert {
+        let mut targets = vec![];
+
+        for path in &request.paths {
+            match convert_target(path, &opts).map_err(|err| err.to_string())? {
+                Some((target, _)) => targets.push(target),
+                None => targets.push(path.clone()),
+            }
+        }
+
+        return Ok(json!({ "paths": targets }));
+    }
+
+    let mut planner = Planner::new(DEFAULT_SPILL_AFTER);
+
+    for path in &request.paths {
+        convert_single(path, &opts, &mut planner).map_err(|err| err.to_string())?;
+    }
+
+    if let Some((rename, root)) = planner.escaping.first() {
+        return Err(tr!(
+            "escaping",
+            source = rename.source.display(),
+            target = rename.target.display(),
+            root = root.display()
+        ));
+    }
+
+    let batches = planner.into_batches().map_err(|err| err.to_string())?;
+    let collisions = batches
+        .collisions(is_any_case_insensitive(&request.paths))
+        .map_err(|err| err.to_string())?;
+
+    if let Some(collision) = collisions.first() {
+        let sources: Vec<String> = collision
+            .sources
+            .iter()
+            .map(|source| format!("'{}'", source.display()))
+            .collect();
+
+        return Err(tr!(
+            "collision",
+            sources = sources.join(", "),
+            target = collision.target.display()
+        ));
+    }
+
+    let is_executing = request.command == daemon::Action::Execute;
+
+    if is_executing {
+        let mut queued = 0;
+
+        let result = batches.for_each(|plan, _| {
+            queued += plan
+                .renames()
+                .iter()
+                .filter(|rename| !rename.is_noop())
+                .count();
+        });
+
+        result.map_err(|err| err.to_string())?;
+
+        metrics::report_queue(metrics::Queue {
+            in_flight: 1,
+            queued,
+        });
+    }
+
+    let mut renames = vec![];
+    let mut dirs = DirCache::default();
+    let mut summary = Summary::default();
+
+    let result = batches.for_each(|plan, snapshot| {
+        if !is_executing {
+            for rename in plan {
+                renames.push(json!({"source": rename.source, "target": rename.target}));
+            }
+
+            return;
+        }
+
+        Executor::Standard.execute(plan, &opts, snapshot, &mut dirs, |rename, outcome| {
+            summary.record(outcome);
+            renames.push(json!({
+                "source": rename.source,
+                "target": rename.target,
+                "outcome": outcome.as_str(),
+            }));
+        });
+    });
+
+    if is_executing {
+        // the request is done even if reading its plan failed part way through
+        metrics::report(&summary.outcomes(), Some(metrics::Queue::default()));
+    }
+
+    result.map_err(|err| err.to_string())?;
+
+    Ok(json!({ "renames": renames }))
+}
+
+/// Exit if any planned rename is bound to fail because a directory it modifies is not
+/// writable, listing every such rename.
+fn check_writable(batches: &Batches) {
+    let mut unwritable = vec![];
+
+    let result = batches.for_each(|plan, _| {
+        for entry in precheck::unwritable(plan) {
+            unwritable.push((entry.rename.source.clone(), entry.dir, entry.reason));
+        }
+    });
+
+    if let Err(err) = result {
+        error!("{}", tr!("read-spilled-failed", reason = err.to_string()));
+        exit(9);
+    }
+
+    if !unwritable.is_empty() {
+        error!("{}", tr!("unwritable", count = unwritable.len()));
+
+        for (source, dir, reason) in unwritable {
+            info!(
+                "  {}",
+                tr!(
+                    "unwritable-entry",
+                    source = source.display(),
+                    dir = dir.display(),
+                    reason = reason
+                )
+            );
+        }
+
+        exit(5);
+    }
+}
+
+/// Write every way the plan could clobber a path to stdout instead of performing it, exiting
+/// with 3 if there are any.
+fn check_collisions(batches: &Batches) {
+    let risks = match risk::find(batches) {
+        Ok(risks) => risks,
+        Err(err) => {
+            error!("{}", tr!("read-spilled-failed", reason = err.to_string()));
+            exit(9);
+        }
+    };
+
+    for risk in &risks {
+        println!("{}", risk);
+    }
+
+    if !risks.is_empty() {
+        exit(3);
+    }
+}
+
+/// Write a random sample of at most `n` of the planned renames to stdout, picked with
+/// `seed` if given.
+fn show_sample(batches: &Batches, n: usize, seed: Option<u64>, opts: &Options) {
+    // the seed is reported so a sample worth another look can be picked again
+    let seed = seed.unwrap_or_else(|| fastrand::u64(..));
+
+    let (picked, seen) = match sample::sample(batches, n, &mut fastrand::Rng::with_seed(seed)) {
+        Ok(sample) => sample,
+        Err(err) => {
+            error!("{}", tr!("read-spilled-failed", reason = err.to_string()));
+            exit(9);
+        }
+    };
+
+    for rename in &picked {
+        println!(
+            "'{}' -> '{}'",
+            opts.path_style.show(&rename.source).display(),
+            opts.path_style.show(&rename.target).display()
+        );
+    }
+
+    info!(
+        "{}",
+        tr!("sampled", picked = picked.len(), seen = seen, seed = seed)
+    );
+}
+
+/// Count the planned paths which already conform and those which do not, writing the
+/// latter to stdout when `list` is set.
+fn classify(batches: &Batches, list: bool) -> (usize, usize) {
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let mut written = Ok(());
+    let (mut conforming, mut offending) = (0, 0);
+
+    let result = batches.for_each(|plan, _| {
+        for rename in plan {
+            if rename.is_noop() {
+                conforming += 1;
+                continue;
+            }
+
+            offending += 1;
+
+            if list && written.is_ok() {
+                written = writeln!(out, "{}", rename.source.display());
+            }
+        }
+    });
+
+    if let Err(err) = result {
+        error!("{}", tr!("read-spilled-failed", reason = err.to_string()));
+        exit(9);
+    }
+
+    if let Err(err) = written.and_then(|_| out.flush()) {
+        error!(
+            "{}",
+            tr!("write-offending-failed", reason = err.to_string())
+        );
+        exit(1);
+    }
+
+    (conforming, offending)
+}
+
+/// The number of renames of the plan which change a path.
+fn count_renames(batches: &Batches) -> usize {
+    let mut renames = 0;
+
+    let result = batches.for_each(|plan, _| {
+        renames += plan.renames().iter().filter(|rename| !rename.is_noop()).count();
+    });
+
+    if let Err(err) = result {
+        error!("{}", tr!("read-spilled-failed", reason = err.to_string()));
+        exit(9);
+    }
+
+    renames
+}
+
+/// Write the plan to stdout as a script rather than performing it.
+fn emit_script(dialect: Dialect, batches: &Batches, opts: &Options) {
+    let out = io::BufWriter::new(io::stdout().lock());
+
+    let mut script = match Script::new(dialect, out, opts.no_clobber) {
+        Ok(script) => script,
+        Err(err) => {
+            error!("{}", tr!("write-script-failed", reason = err.to_string()));
+            exit(1);
+        }
+    };
+
+    let mut written = Ok(());
+
+    let result = batches.for_each(|plan, _| {
+        for rename in plan {
+            if written.is_ok() {
+                written = script.rename(rename);
+            }
+        }
+    });
+
+    if let Err(err) = result {
+        error!("{}", tr!("read-spilled-failed", reason = err.to_string()));
+        exit(9);
+    }
+
+    if let Err(err) = written.and_then(|_| script.finish().map(|_| ())) {
+        error!("{}", tr!("write-script-failed", reason = err.to_string()));
+        exit(1);
+    }
+}
+
+/// Write the renames of the plan to stdout as a plan file, along with the current state of
+/// each source.
+fn output_plan(batches: &Batches, is_json: bool) {
+    let out = io::BufWriter::new(io::stdout().lock());
+    let tool = concat!("ccpath ", env!("CARGO_PKG_VERSION"));
+
+    let writer = if is_json {
+        PlanWriter::json(out, tool)
+    } else {
+        PlanWriter::new(out, tool)
+    };
+
+    let mut writer = match writer {
+        Ok(writer) => writer,
+        Err(err) => {
+            error!("{}", err);
+            exit(1);
+        }
+    };
+
+    let mut written = Ok(());
+
+    let result = batches.for_each(|plan, _| {
+        for rename in plan.renames().iter().filter(|rename| !rename.is_noop()) {
+            if written.is_err() {
+                return;
+            }
+
+            written = PlannedRename::record(rename.clone())
+                .and_then(|planned| writer.write(&planned))
+                .map_err(|err| format!("'{}': {}", rename.source.display(), err));
+        }
+    });
+
+    if let Err(err) = result {
+        error!("{}", tr!("read-spilled-failed", reason = err.to_string()));
+        exit(9);
+    }
+
+    if let Err(err) = written.and_then(|_| writer.finish().map_err(|err| err.to_string())) {
+        error!("{}", err);
+        exit(1);
+    }
+}
+
+/// Write the listing of every planned path before and after the plan as a unified diff.
+fn output_diff(batches: &Batches) {
+    // both listings are sorted in memory, which a spilled plan is too large for
+    if let Batches::Spilled(_) = batches {
+        error!("{}", tr!("diff-spilled"));
+        exit(9);
+    }
+
+    let mut renames = vec![];
+
+    let result = batches.for_each(|plan, _| renames.extend(plan.renames().iter().cloned()));
+
+    if let Err(err) = result {
+        error!("{}", tr!("read-spilled-failed", reason = err.to_string()));
+        exit(9);
+    }
+
+    let renamed: HashMap<PathBuf, PathBuf> = renames
+        .iter()
+        .filter(|rename| !rename.is_noop())
+        .map(|rename| (rename.source.clone(), rename.target.clone()))
+        .collect();
+
+    // directories are listed with a trailing separator
+    let listed = |path: &Path, is_dir: bool| {
+        let mut listed = path.to_string_lossy().into_owned();
+
+        if is_dir && !listed.ends_with(path::MAIN_SEPARATOR) {
+            listed.push(path::MAIN_SEPARATOR);
+        }
+
+        listed
+    };
+
+    let mut current = vec![];
+    let mut planned = vec![];
+
+    for rename in &renames {
+        let is_dir = fs::symlink_metadata(&rename.source).is_ok_and(|m| m.is_dir());
+
+        // the target is only renamed itself, its ancestors may have been renamed as well
+        let target = match (rename.target.parent(), rename.target.file_name()) {
+            (Some(parent), Some(name)) => incremental::final_path(parent, &renamed).join(name),
+            _ => rename.target.clone(),
+        };
+
+        current.push(listed(&rename.source, is_dir));
+        planned.push(listed(&target, is_dir));
+    }
+
+    current.sort();
+    current.dedup();
+    planned.sort();
+    planned.dedup();
+
+    if let Err(err) = diff::write(&current, &planned, io::BufWriter::new(io::stdout().lock())) {
+        error!("{}", tr!("write-diff-failed", reason = err.to_string()));
+        exit(1);
+    }
+}
+
+/// Report the summary of a finished run.
+fn finish(summary: &Summary, is_verbose: bool, is_dry_run: bool, notify: bool) {
+    report::event(Event::Summary(summary));
+
+    if is_verbose || is_dry_run {
+        info!("{}", summary);
+    }
+
+    let outcomes = summary.outcomes();
+
+    stats::report(&outcomes);
+
+    if !is_dry_run {
+        metrics::report(&outcomes, None);
+    }
+
+    if notify {
+        show_notification(summary);
+    }
+}
+
+fn show_notification(summary: &Summary) {
+    let title = if summary.failed > 0 {
+        tr!("notification-title-failed")
+    } else {
+        tr!("notification-title")
+    };
+
+    let result = notify_rust::Notification::new()
+        .appname("ccpath")
+        .summary(&title)
+        .body(&summary.to_string())
+        .show();
+
+    if let Err(err) = result {
+        warning!("{}", tr!("notification-failed", reason = err.to_string()));
+    }
+}
+
+fn main() {
+    let argv = match cli::expand_response_files(std::env::args_os()) {
+        Ok(argv) => argv,
+        Err((path, err)) => {
+            error!(
+                "{}",
+                tr!("read-failed", path = path.display(), reason = err.to_string())
+            );
+            exit(1);
+        }
+    };
+
+    let (cli, matches) = parse_cli(&argv);
+
+    let (mut args, is_interactive) = match cli.command {
+        Some(Command::Archive(args)) => {
+            archive_main(args, cli.run.notify);
+            return;
+        }
+        Some(Command::Replay(args)) => {
+            replay_main(args, cli.run.notify);
+            return;
+        }
+        Some(Command::Apply(args)) => {
+            apply_main(args, cli.run.notify);
+            return;
+        }
+        Some(Command::Resolve(args)) => {
+            resolve_main(args);
+            return;
+        }
+        Some(Command::Undo(args)) => {
+            undo_main(args, cli.run.notify);
+            return;
+        }
+        Some(Command::Daemon(args)) => {
+            daemon_main(args);
+            return;
+        }
+        Some(Command::Doctor(args)) => {
+            doctor_main(args);
+            return;
+        }
+        Some(Command::Conventions(args)) => {
+            conventions_main(args);
+            return;
+        }
+        Some(Command::Config(args)) => {
+            config_main(args);
+            return;
+        }
+        Some(Command::Tui(args)) => (*args, true),
+        Some(Command::Plan(args)) => (*args, false),
+        None => (cli.run, false),
+    };
+
+    let is_planning = matches.subcommand_name() == Some("plan");
+
+    report::init(args.log);
+
+    let is_stdin = |path: &Option<PathBuf>| path.as_deref() == Some(Path::new("-"));
+
+    if is_stdin(&args.rules) && is_stdin(&args.files_from) {
+        error!("{}", tr!("stdin-twice"));
+        exit(1);
+    }
+
+    let config = match &args.rules {
+        Some(path) => config::load_json(path).map(Some),
+        None => config::load(args.config.as_deref()),
+    };
+
+    let config = match config {
+        Ok(config) => config,
+        Err(err) => {
+            error!("{}", err);
+            exit(1);
+        }
+    };
+
+    let defaults = match &config {
+        Some(config) => {
+            let matches = match matches.subcommand() {
+                Some(("tui" | "plan", matches)) => matches,
+                _ => &matches,
+            };
+
+            config.default_flags(matches).unwrap_or_else(|err| {
+                error!("{}", err);
+                exit(1);
+            })
+        }
+        None => vec![],
+    };
+
+    // the defaults are given right before the flags of the command line, after any subcommand
+    if !defaults.is_empty() {
+        let at = if is_interactive || is_planning { 2 } else { 1 };
+        let mut with_defaults = argv[..at].to_vec();
+        with_defaults.extend(defaults);
+        with_defaults.extend_from_slice(&argv[at..]);
+
+        let (cli, _) = parse_cli(&with_defaults);
+
+        args = match cli.command {
+            Some(Command::Tui(args)) | Some(Command::Plan(args)) => *args,
+            _ => cli.run,
+        };
+    }
+
+    if is_planning {
+        if args.output.is_some() {
+            error!("{}", tr!("plan-output"));
+            exit(1);
+        }
+
+        args.output = Some(OutputFormat::Json);
+    }
+
+    if let Some(log_file) = &args.log_file {
+        if let Err(err) = report::open_log(log_file) {
+            error!(
+                "{}",
+                tr!(
+                    "open-log-failed",
+                    path = log_file.display(),
+                    reason = err.to_string()
+                )
+            );
+            exit(1);
+        }
+    }
+
+    if args.syslog {
+        if let Err(err) = report::open_syslog() {
+            error!("{}", tr!("open-syslog-failed", reason = err.to_string()));
+            exit(1);
+        }
+    }
+
+    let notify = args.notify;
+
+    let is_verbose = args.verbose;
+    let no_clobber = args.no_clobber;
+    let is_recursive = args.recursive;
+
+    let is_dry_by_default = config
+        .as_ref()
+        .is_some_and(|config| config.is_dry_by_default);
+    let is_dry_run = args.dry_run || (is_dry_by_default && !args.execute);
+
+    if is_dry_run && args.audit.is_some() {
+        error!("{}", tr!("audit-dry-run"));
+        exit(1);
+    }
+
+    // a dry run renames nothing, so there would be nothing to undo
+    if args.record && !is_dry_run {
+        let dir = match journal::recorded_dir() {
+            Some(dir) => dir,
+            None => {
+                error!("{}", tr!("no-data-dir"));
+                exit(1);
+            }
+        };
+
+        let path = journal::record_path(&dir, SystemTime::now());
+        let result = fs::create_dir_all(&dir).and_then(|_| report::open_journal(&path));
+
+        if let Err(err) = result {
+            error!(
+                "{}",
+                tr!(
+                    "open-log-failed",
+                    path = path.display(),
+                    reason = err.to_string()
+                )
+            );
+            exit(1);
+        }
+    }
+
+    if let Some(jobs) = args.jobs {
+        // the global pool is only ever built here, before any parallel work starts
+        let _ = rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global();
+    }
+
+    if args.stats || args.stats_out.is_some() {
+        stats::enable(args.stats, args.stats_out.clone());
+    }
+
+    if let Some(path) = &args.metrics_file {
+        metrics::enable(path.clone());
+    }
+
+    let rules = config.map(|config| config.rules);
+
+    let guard_roots = is_recursive && !is_dry_run && !args.force;
+
+    let root = args.root.as_ref().map(normalize_input);
+
+    if let Some(root) = &root {
+        if !root.is_dir() {
+            error!("{}", tr!("no-such-directory", path = root.display()));
+            exit(2);
+        }
+    }
+
+    // ensure that all specified paths exist
+    let paths: Vec<PathBuf> = args
+        .paths
+        .iter()
+        .map(|path| input_path(path, root.as_deref()))
+        .collect();
+
+    for path in &paths {
+        check_input(path, guard_roots);
+    }
+
+    let manifest = args.files_from.as_deref().map(|file| {
+        match Manifest::open(file) {
+            Ok(manifest) => manifest,
+            Err(err) => {
+                error!(
+                    "{}",
+                    tr!("read-failed", path = file.display(), reason = err.to_string())
+                );
+                exit(1);
+            }
+        }
+    });
+
+    let mapping = args.map_file.as_deref().map(|file| {
+        let text = match fs::read_to_string(file) {
+            Ok(text) => text,
+            Err(err) => {
+                error!(
+                    "{}",
+                    tr!(
+                        "read-failed",
+                        path = file.display(),
+                        reason = err.to_string()
+                    )
+                );
+                exit(1);
+            }
+        };
+
+        match mapping::parse(&text, mapping::separator(file)) {
+            Ok(renames) => renames,
+            Err(err) => {
+                error!("{}: {}", file.display(), err);
+                exit(1);
+            }
+        }
+    });
+
+    let mut preserve = Preserve {
+        verify_xattr: args.verify_xattr,
+        ..Preserve::default()
+    };
+
+    for attr in &args.no_preserve {
+        if let Err(err) = preserve.disable(attr) {
+            error!("{}", err);
+            exit(1);
+        }
+    }
+
+    let prefix = if args.auto_prefix {
+        let manifest_paths = manifest
+            .iter()
+            .flat_map(Manifest::paths)
+            .map(|path| input_path(&path, root.as_deref()));
+
+        let prefix = guard::common_ancestor(paths.iter().cloned().chain(manifest_paths));
+
+        match &prefix {
+            Some(prefix) if is_verbose => {
+                info!("{}", tr!("using-prefix", prefix = prefix.display()))
+            }
+            Some(_) => {}
+            None => warning!("{}", tr!("no-common-prefix")),
+        }
+
+        prefix
+    } else {
+        args.prefix
+            .as_ref()
+            .map(|prefix| input_path(prefix, root.as_deref()))
+    };
+
+    // the root itself is never converted, as if it were the prefix of every path
+    let prefix = prefix.or_else(|| root.clone());
+    let flatten = args
+        .flatten
+        .as_ref()
+        .map(|flatten| input_path(flatten, root.as_deref()));
+
+    let mut walk = WalkOptions::new()
+        .follow_links(args.follow_symlinks)
+        .one_file_system(args.one_file_system)
+        .hidden(!args.no_hidden)
+        .default_ignores(!args.no_default_ignores);
+
+    if let Some(depth) = args.max_depth {
+        walk = walk.max_depth(depth);
+    }
+
+    if let Some(depth) = args.min_depth {
+        walk = walk.min_depth(depth);
+    }
+
+    for glob in &args.include {
+        walk = walk.include(glob.clone());
+    }
+
+    for glob in &args.exclude {
+        walk = walk.exclude(glob.clone());
+    }
+
+    let global_ignore = match ignore::global_file() {
+        Some(file) if args.recursive && !args.no_ignore_file => Some((file, true)),
+        _ => None,
+    };
+
+    for (file, is_optional) in global_ignore
+        .into_iter()
+        .chain(args.exclude_from.iter().map(|file| (file.clone(), false)))
+    {
+        match ignore::load(&file, is_optional) {
+            Ok(globs) => walk = globs.into_iter().fold(walk, WalkOptions::exclude),
+            Err(err) => {
+                error!("{}", err);
+                exit(1);
+            }
+        }
+    }
+
+    for ext in &args.exclude_ext {
+        walk = walk.exclude_ext(ext);
+    }
+
+    for entry_type in &args.entry_type {
+        walk = walk.entry_type(*entry_type);
+    }
+
+    let extensions = match args.extensions {
+        ExtensionPolicy::LastDot => Extensions::LastDot,
+        ExtensionPolicy::Short => Extensions::Short,
+        ExtensionPolicy::Known => Extensions::known(args.known_extension.clone()),
+    };
+
+    let path_style = match &args.relative_to {
+        Some(dir) => PathStyle::relative_to(dir),
+        None if args.absolute => PathStyle::Absolute,
+        None => match &root {
+            Some(root) => PathStyle::Rooted(root.clone()),
+            None => PathStyle::AsGiven,
+        },
+    };
+
+    let opts = Options {
+        from: args.from,
+        // only the archive subcommand and '--map-file', which converts nothing, lift the
+        // requirement for a convention
+        to: args.into.unwrap_or(Convention::Keep),
+        is_full_path: args.full_path,
+        is_same_dir: args.same_dir,
+        flatten: flatten.as_deref(),
+        prefix: prefix.as_deref(),
+        prefix_mismatch: args.prefix_mismatch,
+        partial_failure: args.partial_failure,
+        is_verbose,
+        is_dry_run,
+        no_clobber,
+        use_trash: args.trash,
+        merge_dirs: args.merge_dirs,
+        use_stamp: args.stamp,
+        is_remove_empty: args.remove_empty,
+        walk,
+        // a conforming directory may still hold files to move when flattening, or to name
+        // after a template
+        prune_conforming: !args.no_prune_conforming
+            && args.flatten.is_none()
+            && args.template.is_none(),
+        sanitize: args.sanitize.or(args.target_fs),
+        sanitize_mode: args.sanitize_mode,
+        transforms: &args.transform,
+        component_pattern: args.component_pattern.as_ref(),
+        template: args.template.as_ref(),
+        extensions,
+        wordless: args.wordless,
+        special_files: args.special_files,
+        preserve,
+        retry: Retry {
+            attempts: args.retries,
+            delay: Duration::from_millis(args.retry_delay),
+        },
+        throttle: Throttle::new(args.throttle, args.throttle_bandwidth),
+        errors: ErrorBudget::new(args.max_errors),
+        prompt: Prompt::new(args.interactive && !is_dry_run),
+        explain: args.explain,
+        path_style,
+        rules: rules.as_ref(),
+    };
+
+    if args.filter {
+        filter(&opts, args.null);
+        return;
+    }
+
+    // recorded so that 'ccpath replay' can rebase the renames in the log elsewhere
+    let cwd = std::env::current_dir().ok();
+
+    report::event(Event::Run {
+        cwd: cwd.as_deref(),
+        into: opts.to.name(),
+    });
+
+    if let Some(url) = &args.backend {
+        if is_interactive {
+            error!("{}", tr!("review-remote", url = url.as_str()));
+            exit(1);
+        }
+
+        let mut remote = match remote::open(url) {
+            Ok(remote) => remote,
+            Err(err) => {
+                error!("{}", err);
+                exit(1);
+            }
+        };
+
+        match remote::run(remote.as_mut(), &opts) {
+            Ok(summary) => finish(&summary, is_verbose, is_dry_run, notify),
+            Err(err) => {
+                error!("{}: {}", url, err);
+                exit(1);
+            }
+        }
+
+        return;
+    }
+
+    // the paths given, or those mapped, which is never both
+    let sources: Vec<PathBuf> = match &mapping {
+        Some(renames) => renames
+            .iter()
+            .map(|rename| input_path(&rename.source, root.as_deref()))
+            .collect(),
+        None => paths.clone(),
+    };
+
+    // held until the run ends, so that no other run renames the same trees meanwhile
+    let _locks = if is_dry_run {
+        None
+    } else {
+        lock_trees(&sources, is_recursive)
+    };
+
+    let mut planner = Planner::new(args.spill_after);
+    let is_incremental = args.incremental;
+    let is_git_tracked_only = args.git_tracked_only;
+    let mut states: Vec<State> = vec![];
+
+    // manifest entries are only checked as they are reached so it is never held in memory
+    let manifest_paths = manifest.iter().flat_map(Manifest::paths).map(|path| {
+        let path = input_path(&path, root.as_deref());
+        check_input(&path, guard_roots);
+
+        path
+    });
+
+    // mapped renames are explicit, so they are planned as is wherever their targets are
+    for Rename { source, target } in mapping.into_iter().flatten() {
+        let source = input_path(&source, root.as_deref());
+        check_input(&source, false);
+
+        let target = input_path(&target, root.as_deref());
+        let parent = guard::parent_dir(&source).to_path_buf();
+        planner.insert(source, target, &parent, true);
+    }
+
+    // the paths which could be converted are still renamed, but the run fails
+    let mut is_unconverted = false;
+
+    for path in paths.iter().cloned().chain(manifest_paths) {
+        let path = &path;
+
+        let result = if path.is_dir() && is_recursive {
+            let mut state = None;
+
+            if is_incremental {
+                let key = format!(
+                    "{:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?}",
+                    opts.from,
+                    opts.to,
+                    opts.flatten,
+                    opts.template,
+                    opts.sanitize,
+                    opts.transforms,
+                    opts.component_pattern,
+                    opts.extensions,
+                    opts.rules,
+                    opts.walk,
+                    is_git_tracked_only
+                );
+
+                match State::load(path, &key) {
+                    Ok(loaded) => state = Some(loaded),
+                    Err(err) => warning!(
+                        "{}",
+                        tr!(
+                            "state-load-failed",
+                            path = path.display(),
+                            reason = err.to_string()
+                        )
+                    ),
+                }
+            }
+
+            let tracked = if is_git_tracked_only {
+                match Tracked::list(path) {
+                    Ok(tracked) => Some(tracked),
+                    Err(err) => {
+                        error!("{}", err);
+                        exit(1);
+                    }
+                }
+            } else {
+                None
+            };
+
+            let result =
+                convert_recursive(path, &opts, &mut planner, state.as_mut(), tracked.as_ref());
+            states.extend(state);
+
+            result
+        } else {
+            let started = stats::start();
+            let result = convert_single(path, &opts, &mut planner);
+            stats::record(Phase::Convert, started, 1);
+
+            result
+        };
+
+        if let Err(err) = result {
+            error!("{}", err);
+            is_unconverted = true;
+        }
+    }
+
+    if !planner.escaping.is_empty() {
+        for (rename, root) in planner.escaping {
+            error!(
+                "{}",
+                tr!(
+                    "escaping",
+                    source = rename.source.display(),
+                    target = rename.target.display(),
+                    root = root.display()
+                )
+            );
+        }
+
+        exit(6);
+    }
+
+    let special = planner.special;
+
+    let mut batches = match planner.into_batches() {
+        Ok(batches) => batches,
+        Err(err) => {
+            error!("{}", tr!("spill-failed", reason = err.to_string()));
+            exit(9);
+        }
+    };
+
+    report::event(Event::Plan {
+        renames: batches.planned(),
+    });
+
+    if args.check_collisions_only {
+        check_collisions(&batches);
+        return;
+    }
+
+    // a filesystem which ignores case makes names differing only by case collide as well,
+    // whether it is the one targeted or the one a path is on
+    let ignore_case =
+        opts.sanitize.is_some_and(Platform::ignores_case) || is_any_case_insensitive(&sources);
+
+    let collisions = match batches.collisions(ignore_case) {
+        Ok(collisions) => collisions,
+        Err(err) => {
+            error!("{}", tr!("read-spilled-failed", reason = err.to_string()));
+            exit(9);
+        }
+    };
+
+    let skipped = resolve_collisions(collisions, &opts);
+
+    let mut summary = Summary {
+        skipped: skipped.len(),
+        special,
+        ..Summary::default()
+    };
+
+    batches.exclude(skipped);
+
+    if args.check || args.list_offenders {
+        let (conforming, offending) = classify(&batches, args.list_offenders);
+
+        if args.check {
+            info!(
+                "{}",
+                tr!("check-summary", conforming = conforming, offending = offending)
+            );
+
+            if offending > 0 {
+                exit(10);
+            }
+
+            return;
+        }
+    }
+
+    if let Some(n) = args.sample {
+        show_sample(&batches, n, args.seed, &opts);
+        return;
+    }
+
+    if let Some(dialect) = args.emit_script {
+        emit_script(dialect, &batches, &opts);
+        return;
+    }
+
+    if args.output == Some(OutputFormat::Diff) {
+        output_diff(&batches);
+        return;
+    }
+
+    if args.output == Some(OutputFormat::Plan) {
+        output_plan(&batches, false);
+        return;
+    }
+
+    if args.output == Some(OutputFormat::Json) {
+        output_plan(&batches, true);
+        return;
+    }
+
+    if is_interactive {
+        batches = match tui::review(batches) {
+            Ok(Some(reviewed)) => reviewed,
+            Ok(None) => return,
+            Err(err) => {
+                error!("{}", tr!("review-failed", reason = err.to_string()));
+                exit(1);
+            }
+        };
+    }
+
+    if !is_dry_run {
+        check_writable(&batches);
+
+        // the renames of a recursive run are many and easily unexpected, so whoever started it
+        // from a terminal is asked to confirm their impact first
+        let is_recursing = is_recursive && paths.iter().any(|path| path.is_dir());
+
+        if is_recursing
+            && !args.yes
+            && !args.interactive
+            && !is_interactive
+            && io::stdin().is_terminal()
+        {
+            let renames = count_renames(&batches);
+            let prompt = tr!("run-prompt", renames = renames, skipped = summary.skipped);
+
+            if renames > 0 && !confirm(&prompt) {
+                info!("{}", tr!("run-refused"));
+                return;
+            }
+        }
+    } else if !args.dry_run {
+        info!("{}", tr!("dry-run-hint"));
+    }
+
+    // globs name files as they are before the run, since they may be renamed themselves
+    let references = (!args.fix_references.is_empty()).then(|| {
+        match references::expand(args.fix_references.iter().map(String::as_str)) {
+            Ok(files) => files,
+            Err(err) => {
+                error!("{}", err);
+                exit(1);
+            }
+        }
+    });
+
+    let mut emptied = vec![];
+
+    #[cfg(unix)]
+    let user = Some(report::current_user());
+    #[cfg(not(unix))]
+    let user: Option<String> = None;
+
+    let mut audit = args
+        .audit
+        .as_deref()
+        .zip(args.audit_key.as_deref())
+        .map(|(path, key_path)| {
+            let key = match fs::read(key_path) {
+                Ok(key) if !key.is_empty() => key,
+                Ok(_) => {
+                    error!("{}", tr!("audit-key-empty", path = key_path.display()));
+                    exit(1);
+                }
+                Err(err) => {
+                    error!(
+                        "{}",
+                        tr!(
+                            "audit-key-failed",
+                            path = key_path.display(),
+                            reason = err.to_string()
+                        )
+                    );
+                    exit(1);
+                }
+            };
+
+            match Audit::create(path, user.as_deref(), &key) {
+                Ok(audit) => audit,
+                Err(err) => {
+                    error!(
+                        "{}",
+                        tr!(
+                            "audit-create-failed",
+                            path = path.display(),
+                            reason = err.to_string()
+                        )
+                    );
+                    exit(1);
+                }
+            }
+        });
+
+    // an audited file is checksummed right after it is renamed, before anything else moves it,
+    // and an interactive run asks about one rename at a time
+    let is_sequential = is_dry_run || audit.is_some() || opts.prompt.is_asking;
+
+    // a batch submitted through io_uring can't be paced
+    let mut executor = Executor::new(
+        args.io_uring && !is_sequential && !opts.throttle.is_pacing(),
+        args.jobs.is_some() && !is_sequential,
+        is_verbose,
+    );
+
+    let mut dirs = DirCache::default();
+    let mut renamed_dirs: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let mut renamed: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let mut impact = Impact::default();
+
+    let result = batches.for_each(|plan, snapshot| {
+        let started = stats::start();
+
+        if is_dry_run {
+            impact += plan.impact();
+        }
+
+        if let Some(audit) = &mut audit {
+            audit.before(plan);
+        }
+
+        executor.execute(plan, &opts, snapshot, &mut dirs, |rename, outcome| {
+            report::event(Event::Rename {
+                source: &rename.source,
+                target: &rename.target,
+                outcome,
+            });
+
+            if let Some(audit) = &mut audit {
+                match audit.record(rename, outcome) {
+                    Ok(true) => {}
+                    Ok(false) => warning!(
+                        "{}",
+                        tr!(
+                            "content-changed",
+                            source = rename.source.display(),
+                            target = rename.target.display()
+                        )
+                    ),
+                    Err(err) => {
+                        error!("{}", tr!("audit-write-failed", reason = err.to_string()));
+                        exit(1);
+                    }
+                }
+            }
+
+            if outcome == Outcome::Renamed && !opts.is_dry_run {
+                let absolute = |path: &Path| path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+
+                report::audit(&absolute(&rename.source), &absolute(&rename.target));
+            }
+
+            if outcome == Outcome::Renamed && !opts.is_dry_run && opts.is_remove_empty {
+                emptied.extend(emptied_candidates(&rename.source, &rename.target, opts.prefix));
+            }
+
+            if outcome == Outcome::Renamed
+                && !opts.is_dry_run
+                && !states.is_empty()
+                && fs::symlink_metadata(&rename.target).is_ok_and(|m| m.is_dir())
+            {
+                renamed_dirs.insert(rename.source.clone(), rename.target.clone());
+            }
+
+            if outcome == Outcome::Renamed && references.is_some() {
+                renamed.insert(rename.source.clone(), rename.target.clone());
+            }
+
+            stats::record_source(&rename.source);
+            summary.record(outcome);
+        });
+
+        stats::record(Phase::Rename, started, plan.len() as u64);
+    });
+
+    if let Err(err) = result {
+        error!("{}", tr!("read-spilled-failed", reason = err.to_string()));
+        exit(9);
+    }
+
+    if let Some(Err(err)) = audit.map(Audit::finish) {
+        error!("{}", tr!("audit-write-failed", reason = err.to_string()));
+        exit(1);
+    }
+
+    if opts.is_remove_empty {
+        remove_empty(emptied, is_verbose);
+    }
+
+    if let Some(files) = references {
+        references::fix(&files, &renamed, &opts);
+    }
+
+    // a directory whose entries were not all renamed must be examined again next time
+    if !is_dry_run && summary.failed == 0 && summary.changed == 0 {
+        for state in states {
+            if let Err(err) = state.save(&renamed_dirs) {
+                warning!("{}", tr!("state-save-failed", reason = err.to_string()));
+            }
+        }
+    }
+
+    if is_dry_run {
+        info!(
+            "{}",
+            tr!(
+                "impact",
+                files = impact.files,
+                dirs = impact.dirs,
+                created = impact.dirs_created,
+                emptied = impact.dirs_emptied,
+                cross = impact.cross_device,
+                bytes = impact.bytes_copied
+            )
+        );
+    }
+
+    finish(&summary, is_verbose, is_dry_run, notify);
+
+    if opts.errors.is_exhausted() {
+        error!("{}", tr!("max-errors", failed = summary.failed));
+        exit(12);
+    }
+
+    if is_unconverted {
+        exit(1);
+    }
+}
+
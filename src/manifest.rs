@@ -0,0 +1,108 @@
+//! Reads the newline delimited paths given to '--files-from'.
+//!
+//! Manifests are memory mapped and parsed lazily so that even multi-gigabyte listings
+//! (ie exports from object stores) are never loaded into memory all at once.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use memmap2::Mmap;
+
+pub enum Manifest {
+    Mapped(Mmap),
+
+    /// Standard input and empty files cannot be mapped, so they are read instead.
+    Read(Vec<u8>),
+}
+
+impl Manifest {
+    /// Open the manifest at `path`, or read standard input when it is '-'.
+    pub fn open(path: &Path) -> io::Result<Manifest> {
+        if path == Path::new("-") {
+            let mut bytes = vec![];
+            io::stdin().read_to_end(&mut bytes)?;
+
+            return Ok(Manifest::Read(bytes));
+        }
+
+        let file = File::open(path)?;
+
+        if file.metadata()?.len() == 0 {
+            return Ok(Manifest::Read(vec![]));
+        }
+
+        // the mapping is only ever read, and a manifest modified while it is being read
+        // at worst yields garbled paths which then fail to be found
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        Ok(Manifest::Mapped(mmap))
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match self {
+            Manifest::Mapped(mmap) => mmap,
+            Manifest::Read(bytes) => bytes,
+        }
+    }
+
+    /// Every non-empty line of the manifest, parsed only as it is reached.
+    pub fn paths(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.bytes()
+            .split(|b| *b == b'\n')
+            .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+            .filter(|line| !line.is_empty())
+            .map(path_from_bytes)
+    }
+}
+
+#[cfg(unix)]
+pub fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    PathBuf::from(OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+pub fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::manifest::Manifest;
+
+    #[test]
+    fn test_paths() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("manifest");
+
+        fs::write(&file, "Some File\r\n\nSome Dir/Other File\n")?;
+
+        let manifest = Manifest::open(&file)?;
+        let paths: Vec<PathBuf> = manifest.paths().collect();
+
+        assert_eq!(
+            vec![PathBuf::from("Some File"), PathBuf::from("Some Dir/Other File")],
+            paths
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_paths_empty() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("manifest");
+
+        fs::write(&file, "")?;
+
+        assert_eq!(0, Manifest::open(&file)?.paths().count());
+
+        Ok(())
+    }
+}
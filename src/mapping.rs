@@ -0,0 +1,150 @@
+//! Reads the explicit 'old,new' pairs given to '--map-file', for renames decided by another
+//! system but performed with the same checks as converted ones.
+//!
+//! Files ending in '.tsv' separate the pair with a tab and are never quoted, any others are
+//! CSV whose fields may be quoted with '"' to hold commas, doubling any quote inside. A quoted
+//! field cannot span lines.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use convert_path::plan::Rename;
+
+/// The separator of the fields of the mapping at `path`, a tab for '.tsv' and a comma
+/// otherwise.
+pub fn separator(path: &Path) -> char {
+    match path.extension() {
+        Some(ext) if ext.eq_ignore_ascii_case("tsv") => '\t',
+        _ => ',',
+    }
+}
+
+/// Parse every non-empty line of `text` into the rename of its first field to its second,
+/// failing on a line without exactly two fields or mapping a path already mapped.
+pub fn parse(text: &str, separator: char) -> Result<Vec<Rename>, String> {
+    let mut renames = vec![];
+    let mut mapped: HashMap<PathBuf, usize> = HashMap::new();
+
+    for (n, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line)) {
+        if line.is_empty() {
+            continue;
+        }
+
+        let fields = if separator == '\t' {
+            line.split('\t').map(String::from).collect()
+        } else {
+            split_csv(line).map_err(|err| format!("line {}: {}", n, err))?
+        };
+
+        let (source, target) = match fields.as_slice() {
+            [source, target] if !source.is_empty() && !target.is_empty() => {
+                (PathBuf::from(source), PathBuf::from(target))
+            }
+            [_, _] => return Err(format!("line {}: a path is empty", n)),
+            _ => {
+                return Err(format!(
+                    "line {}: expected an old and new path, found {} field(s)",
+                    n,
+                    fields.len()
+                ))
+            }
+        };
+
+        if let Some(first) = mapped.insert(source.clone(), n) {
+            return Err(format!(
+                "line {}: '{}' is already mapped on line {}",
+                n,
+                source.display(),
+                first
+            ));
+        }
+
+        renames.push(Rename { source, target });
+    }
+
+    Ok(renames)
+}
+
+/// Split a line of CSV into its fields, unquoting any quoted ones.
+fn split_csv(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut is_quoted = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if is_quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if is_quoted => {
+                is_quoted = false;
+
+                if !matches!(chars.peek(), None | Some(',')) {
+                    return Err(String::from("a closing quote must end its field"));
+                }
+            }
+            '"' if field.is_empty() => is_quoted = true,
+            ',' if !is_quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+
+    if is_quoted {
+        return Err(String::from("unterminated quote"));
+    }
+
+    fields.push(field);
+
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use convert_path::plan::Rename;
+
+    use crate::mapping::{parse, separator};
+
+    fn rename(source: &str, target: &str) -> Rename {
+        Rename {
+            source: PathBuf::from(source),
+            target: PathBuf::from(target),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Ok(vec![
+                rename("Some File", "some_file"),
+                rename("A, B.txt", "a \"b\".txt"),
+            ]),
+            parse(
+                "Some File,some_file\n\n\"A, B.txt\",\"a \"\"b\"\".txt\"\r\n",
+                ','
+            )
+        );
+        assert_eq!(Ok(vec![rename("A, B", "a_b")]), parse("A, B\ta_b\n", '\t'));
+
+        for invalid in [
+            "Some File\n",
+            "a,b,c\n",
+            "a,\n",
+            "\"a,b\n",
+            "\"a\"b,c\n",
+            "a,b\na,c\n",
+        ] {
+            assert!(parse(invalid, ',').is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_separator() {
+        assert_eq!('\t', separator(Path::new("renames.TSV")));
+        assert_eq!(',', separator(Path::new("renames.csv")));
+        assert_eq!(',', separator(Path::new("renames")));
+    }
+}
@@ -0,0 +1,125 @@
+//! Stamps converted paths with the convention they were converted to given '--stamp', so
+//! that later runs skip them without converting their names again.
+//!
+//! The stamp is the 'user.ccpath.convention' extended attribute, which only describes the
+//! name of the path itself, so the entries beneath a stamped directory are still examined.
+//! It holds the convention along with the name it was stamped under, like
+//! 'snake:some_file.txt', since attributes follow a path when something else renames it and
+//! the stamp must not then vouch for the new name.
+
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use convert_path::Convention;
+
+pub const ATTR: &str = "user.ccpath.convention";
+
+/// Whether a failed stamp has been reported, since a filesystem without extended
+/// attributes would otherwise produce a warning for every path.
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+/// Whether `path` was stamped with `convention` by an earlier run, under its current name.
+pub fn is_stamped(path: &Path, convention: Convention) -> bool {
+    #[cfg(unix)]
+    return match (xattr::get(path, ATTR), value(path, convention)) {
+        (Ok(Some(stamped)), Some(value)) => stamped == value,
+        _ => false,
+    };
+
+    #[cfg(not(unix))]
+    {
+        let _ = (path, convention);
+        false
+    }
+}
+
+/// Stamp `path` with `convention`, warning about the first failure of the run.
+///
+/// Symlinks are never stamped, since few platforms allow attributes on the links themselves.
+pub fn stamp(path: &Path, convention: Convention) {
+    if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+        return;
+    }
+
+    // a path without a name of its own, like '..', is never stamped
+    #[cfg(unix)]
+    let result = match value(path, convention) {
+        Some(value) => xattr::set(path, ATTR, &value),
+        None => return,
+    };
+
+    #[cfg(not(unix))]
+    let result: std::io::Result<()> = Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "extended attributes are not supported on this platform",
+    ));
+
+    if let Err(err) = result {
+        if !WARNED.swap(true, Ordering::Relaxed) {
+            warning!(
+                "{}",
+                tr!(
+                    "stamp-failed",
+                    path = path.display(),
+                    reason = err.to_string()
+                )
+            );
+        }
+    }
+}
+
+/// The stamp of `path` converted into `convention`, if it has a name.
+#[cfg(unix)]
+fn value(path: &Path, convention: Convention) -> Option<Vec<u8>> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let name = path.file_name()?;
+
+    let mut value = convention.name().as_bytes().to_vec();
+    value.push(b':');
+    value.extend_from_slice(name.as_bytes());
+
+    Some(value)
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use std::fs;
+
+    use convert_path::Convention;
+
+    use crate::marker::{is_stamped, stamp};
+
+    #[test]
+    fn test_stamp() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("some_file.txt");
+
+        fs::write(&path, "content")?;
+
+        // not every filesystem supports user attributes
+        if xattr::set(&path, "user.ccpath.test", b"").is_err() {
+            return Ok(());
+        }
+
+        assert!(!is_stamped(&path, Convention::SnakeCase));
+
+        stamp(&path, Convention::SnakeCase);
+
+        assert!(is_stamped(&path, Convention::SnakeCase));
+        assert!(!is_stamped(&path, Convention::KebabCase));
+        assert_eq!(
+            Some(b"snake:some_file.txt".to_vec()),
+            xattr::get(&path, "user.ccpath.convention")?
+        );
+
+        // the stamp no longer holds once something else renames the path
+        let renamed = dir.path().join("Some File.txt");
+        fs::rename(&path, &renamed)?;
+
+        assert!(!is_stamped(&renamed, Convention::SnakeCase));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+//! Reads the metadata embedded in media files, like the date a photo was taken, for the
+//! tokens of '--template' built with the 'exif' feature.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use exif::{DateTime, In, Reader, Tag, Value};
+
+/// The metadata of a media file which may fill a template.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Metadata {
+    /// The date the photo was taken, like '2024-05-01'.
+    pub taken: Option<String>,
+
+    /// The model of the camera which took the photo, as it names itself.
+    pub camera: Option<String>,
+}
+
+impl Metadata {
+    /// Read the EXIF metadata of the file at `path`, which has none if it can't be read or
+    /// isn't a media file.
+    pub fn read(path: &Path) -> Metadata {
+        let exif = match File::open(path)
+            .map_err(exif::Error::Io)
+            .and_then(|file| Reader::new().read_from_container(&mut BufReader::new(file)))
+        {
+            Ok(exif) => exif,
+            Err(_) => return Metadata::default(),
+        };
+
+        let ascii = |tag: Tag| match exif.get_field(tag, In::PRIMARY).map(|field| &field.value) {
+            Some(Value::Ascii(values)) => values.first().cloned(),
+            _ => None,
+        };
+
+        let taken = ascii(Tag::DateTimeOriginal)
+            .or_else(|| ascii(Tag::DateTime))
+            .and_then(|value| DateTime::from_ascii(&value).ok())
+            .map(|taken| format!("{:04}-{:02}-{:02}", taken.year, taken.month, taken.day));
+
+        let camera = ascii(Tag::Model)
+            .map(|model| {
+                String::from_utf8_lossy(&model)
+                    .trim_end_matches('\0')
+                    .trim()
+                    .to_string()
+            })
+            .filter(|model| !model.is_empty());
+
+        Metadata { taken, camera }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use exif::experimental::Writer;
+    use exif::{Field, In, Tag, Value};
+
+    use crate::media::Metadata;
+
+    #[test]
+    fn test_read() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let photo = dir.path().join("IMG 001.jpg");
+
+        let model = Field {
+            tag: Tag::Model,
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![b"Some Camera".to_vec()]),
+        };
+        let taken = Field {
+            tag: Tag::DateTimeOriginal,
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![b"2021:01:15 10:30:00".to_vec()]),
+        };
+
+        let mut tiff = std::io::Cursor::new(vec![]);
+        let mut writer = Writer::new();
+        writer.push_field(&model);
+        writer.push_field(&taken);
+        writer.write(&mut tiff, false)?;
+        let tiff = tiff.into_inner();
+
+        // a jpeg holding nothing but the exif segment
+        let mut jpeg = vec![0xff, 0xd8, 0xff, 0xe1];
+        jpeg.extend_from_slice(&((tiff.len() + 8) as u16).to_be_bytes());
+        jpeg.extend_from_slice(b"Exif\0\0");
+        jpeg.extend_from_slice(&tiff);
+        jpeg.extend_from_slice(&[0xff, 0xd9]);
+        fs::write(&photo, jpeg)?;
+
+        assert_eq!(
+            Metadata {
+                taken: Some(String::from("2021-01-15")),
+                camera: Some(String::from("Some Camera")),
+            },
+            Metadata::read(&photo)
+        );
+
+        fs::write(&photo, "not a photo")?;
+        assert_eq!(Metadata::default(), Metadata::read(&photo));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+//! Merges a directory into the one already at its converted name for '--merge-dirs', like
+//! 'Photos' and 'photos' both converting to 'photos', rather than failing to rename it or
+//! clobbering one of the trees.
+//!
+//! Entries are moved into the directory at the target one by one, descending into every
+//! directory present in both, so only entries whose names are taken in both trees are
+//! subject to the policy for existing files.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// What happens to an entry whose name is already taken in the directory it is merged into.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Conflict {
+    /// Replace the existing entry, as a rename onto it would.
+    Overwrite,
+
+    /// Leave the entry where it is, along with the directories holding it.
+    Keep,
+
+    /// Move the existing entry to the trash first.
+    Trash,
+}
+
+/// The entries a merge could not move because their names were taken, empty if the source
+/// was merged entirely and removed.
+pub type Kept = Vec<PathBuf>;
+
+/// Move every entry beneath the directory `source` into the directory `target`, then remove
+/// whatever of `source` is left empty.
+pub fn merge(source: &Path, target: &Path, conflict: Conflict) -> io::Result<Kept> {
+    let mut kept = vec![];
+
+    merge_into(source, target, conflict, &mut kept)?;
+
+    Ok(kept)
+}
+
+fn merge_into(source: &Path, target: &Path, conflict: Conflict, kept: &mut Kept) -> io::Result<()> {
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let from = entry.path();
+        let to = target.join(entry.file_name());
+
+        match fs::symlink_metadata(&to) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+            Ok(existing) if existing.is_dir() && entry.file_type()?.is_dir() => {
+                merge_into(&from, &to, conflict, kept)?;
+                continue;
+            }
+            Ok(_) => match conflict {
+                Conflict::Overwrite => {}
+                Conflict::Keep => {
+                    kept.push(from);
+                    continue;
+                }
+                Conflict::Trash => trash::delete(&to).map_err(io::Error::other)?,
+            },
+        }
+
+        fs::rename(&from, &to)?;
+    }
+
+    if fs::read_dir(source)?.next().is_none() {
+        fs::remove_dir(source)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::merge::{merge, Conflict};
+
+    #[test]
+    fn test_merge() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let source = dir.path().join("Photos");
+        let target = dir.path().join("photos");
+
+        for root in [&source, &target] {
+            fs::create_dir_all(root.join("2024"))?;
+            fs::write(root.join("shared.jpg"), root.to_string_lossy().as_bytes())?;
+        }
+
+        fs::write(source.join("2024/beach.jpg"), "")?;
+        fs::write(target.join("2024/hike.jpg"), "")?;
+
+        // only the name taken in both trees is kept back
+        let kept = merge(&source, &target, Conflict::Keep)?;
+        assert_eq!(vec![source.join("shared.jpg")], kept);
+        assert!(target.join("2024/beach.jpg").exists());
+        assert!(target.join("2024/hike.jpg").exists());
+        assert!(!source.join("2024").exists());
+
+        let kept = merge(&source, &target, Conflict::Overwrite)?;
+        assert!(kept.is_empty());
+        assert!(!source.exists());
+        assert_eq!(
+            source.to_string_lossy(),
+            fs::read_to_string(target.join("shared.jpg"))?
+        );
+
+        Ok(())
+    }
+}
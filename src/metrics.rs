@@ -0,0 +1,238 @@
+//! Writes the outcomes of every run given '--metrics-file' in the text format of Prometheus,
+//! for the textfile collector of node_exporter to expose, so that monitoring can alert when
+//! a scheduled normalizer stops running or starts failing.
+//!
+//! Counters carry on from the values already in the file so they only ever grow across runs,
+//! as Prometheus expects of them, and the file is replaced at once so the collector never
+//! reads it half written. Dry runs are not counted.
+//!
+//! 'ccpath daemon' counts every request it performs as a run, and also exports its queue:
+//! the requests it is performing and the renames they have left, written when it starts
+//! performing a request and again once it is done, so a daemon stuck on a request shows.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static METRICS_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Write the metrics of every finished run to the file at `path`.
+pub fn enable(path: PathBuf) {
+    let _ = METRICS_FILE.set(path);
+}
+
+/// The requests the daemon is performing, and the renames they have left to perform.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Queue {
+    pub in_flight: usize,
+    pub queued: usize,
+}
+
+/// Add the count of each outcome of a finished run to the metrics file, if one is written,
+/// along with the queue of the daemon if it performed the run.
+pub fn report(outcomes: &[(&str, usize)], queue: Option<Queue>) {
+    update(Some(outcomes), queue);
+}
+
+/// Write the queue of the daemon to the metrics file, if one is written.
+pub fn report_queue(queue: Queue) {
+    update(None, Some(queue));
+}
+
+fn update(outcomes: Option<&[(&str, usize)]>, queue: Option<Queue>) {
+    let path = match METRICS_FILE.get() {
+        Some(path) => path,
+        None => return,
+    };
+
+    if let Err(err) = write(path, outcomes, queue, SystemTime::now()) {
+        warning!(
+            "{}",
+            tr!(
+                "metrics-write-failed",
+                path = path.display(),
+                reason = err.to_string()
+            )
+        );
+    }
+}
+
+fn write(
+    path: &Path,
+    outcomes: Option<&[(&str, usize)]>,
+    queue: Option<Queue>,
+    now: SystemTime,
+) -> io::Result<()> {
+    let mut samples = match fs::read_to_string(path) {
+        Ok(text) => parse(&text),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+        Err(err) => return Err(err),
+    };
+
+    if let Some(outcomes) = outcomes {
+        let timestamp = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        add_run(&mut samples, outcomes, timestamp);
+    }
+
+    if let Some(queue) = queue {
+        samples.insert(String::from(IN_FLIGHT), queue.in_flight as f64);
+        samples.insert(String::from(QUEUED), queue.queued as f64);
+    }
+
+    let text = render(&samples);
+
+    let mut partial = path.as_os_str().to_os_string();
+    partial.push(".partial");
+
+    fs::write(&partial, text)?;
+    fs::rename(&partial, path)
+}
+
+/// The value of every sample in the metrics `text`, keyed by its name and labels.
+fn parse(text: &str) -> HashMap<String, f64> {
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.rsplit_once(' '))
+        .filter_map(|(sample, value)| Some((String::from(sample), value.parse().ok()?)))
+        .collect()
+}
+
+const IN_FLIGHT: &str = "ccpath_daemon_requests_in_flight";
+const QUEUED: &str = "ccpath_daemon_queued_renames";
+
+/// Every metric written, along with its type and help, in the order they are written.
+const METRICS: [(&str, &str, &str); 6] = [
+    (
+        "ccpath_paths_total",
+        "counter",
+        "Paths handled by every run, by outcome.",
+    ),
+    ("ccpath_runs_total", "counter", "Runs finished."),
+    (
+        "ccpath_last_run_timestamp_seconds",
+        "gauge",
+        "When the last run finished.",
+    ),
+    (
+        "ccpath_last_run_failed",
+        "gauge",
+        "Renames which failed in the last run.",
+    ),
+    (IN_FLIGHT, "gauge", "Requests the daemon is performing."),
+    (
+        QUEUED,
+        "gauge",
+        "Renames the requests the daemon is performing have left.",
+    ),
+];
+
+/// Add the outcomes of a finished run to `samples`.
+fn add_run(samples: &mut HashMap<String, f64>, outcomes: &[(&str, usize)], timestamp: u64) {
+    let mut add = |sample: String, count: usize| {
+        *samples.entry(sample).or_insert(0.0) += count as f64;
+    };
+
+    for (outcome, count) in outcomes {
+        add(
+            format!("ccpath_paths_total{{outcome=\"{}\"}}", outcome),
+            *count,
+        );
+    }
+
+    add(String::from("ccpath_runs_total"), 1);
+
+    let failed: usize = outcomes
+        .iter()
+        .filter(|(outcome, _)| *outcome == "failed")
+        .map(|(_, count)| count)
+        .sum();
+
+    samples.insert(
+        String::from("ccpath_last_run_timestamp_seconds"),
+        timestamp as f64,
+    );
+    samples.insert(String::from("ccpath_last_run_failed"), failed as f64);
+}
+
+/// Write every sample of `samples` under the help and type of its metric.
+fn render(samples: &HashMap<String, f64>) -> String {
+    let mut text = String::new();
+
+    for (name, kind, help) in METRICS {
+        let mut lines: Vec<(&String, &f64)> = samples
+            .iter()
+            .filter(|(sample, _)| {
+                sample
+                    .strip_prefix(name)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('{'))
+            })
+            .collect();
+
+        if lines.is_empty() {
+            continue;
+        }
+
+        lines.sort_by(|a, b| a.0.cmp(b.0));
+
+        let _ = writeln!(text, "# HELP {} {}", name, help);
+        let _ = writeln!(text, "# TYPE {} {}", name, kind);
+
+        for (sample, value) in lines {
+            let _ = writeln!(text, "{} {}", sample, value);
+        }
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::metrics::{parse, write, Queue};
+
+    #[test]
+    fn test_write() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("ccpath.prom");
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+
+        write(&path, Some(&[("renamed", 3), ("failed", 1)]), None, at(100))?;
+        write(&path, Some(&[("renamed", 2), ("failed", 0)]), None, at(200))?;
+
+        // the queue of the daemon is written without counting a run
+        let queue = Queue {
+            in_flight: 1,
+            queued: 4,
+        };
+        write(&path, None, Some(queue), at(300))?;
+
+        let samples = parse(&fs::read_to_string(&path)?);
+
+        // counters add up over runs while gauges only describe the last one
+        assert_eq!(
+            Some(&5.0),
+            samples.get("ccpath_paths_total{outcome=\"renamed\"}")
+        );
+        assert_eq!(
+            Some(&1.0),
+            samples.get("ccpath_paths_total{outcome=\"failed\"}")
+        );
+        assert_eq!(Some(&2.0), samples.get("ccpath_runs_total"));
+        assert_eq!(
+            Some(&200.0),
+            samples.get("ccpath_last_run_timestamp_seconds")
+        );
+        assert_eq!(Some(&0.0), samples.get("ccpath_last_run_failed"));
+        assert_eq!(Some(&1.0), samples.get("ccpath_daemon_requests_in_flight"));
+        assert_eq!(Some(&4.0), samples.get("ccpath_daemon_queued_renames"));
+        assert!(!dir.path().join("ccpath.prom.partial").exists());
+
+        Ok(())
+    }
+}
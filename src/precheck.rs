@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use convert_path::plan::{Rename, RenamePlan};
+
+use crate::guard::parent_dir;
+
+/// A planned rename which cannot be performed because `dir` is not writable.
+pub struct Unwritable<'a> {
+    pub rename: &'a Rename,
+    pub dir: PathBuf,
+    pub reason: String,
+}
+
+/// Probe every directory the plan would modify, returning each rename which is bound to fail.
+///
+/// A rename modifies the directory containing its source and the directory which
+/// will contain its target. When the target directory does not exist yet, its
+/// nearest existing ancestor is probed instead since that is where it will be created.
+pub fn unwritable(plan: &RenamePlan) -> Vec<Unwritable<'_>> {
+    let mut probed: HashMap<PathBuf, Option<String>> = HashMap::new();
+    let mut unwritable = vec![];
+
+    for rename in plan {
+        if rename.is_noop() {
+            continue;
+        }
+
+        let dirs = [
+            parent_dir(&rename.source),
+            nearest_existing(parent_dir(&rename.target)),
+        ];
+
+        for dir in dirs {
+            let reason = probed
+                .entry(dir.to_path_buf())
+                .or_insert_with(|| probe(dir).err().map(|err| err.to_string()));
+
+            if let Some(reason) = reason {
+                unwritable.push(Unwritable {
+                    rename,
+                    dir: dir.to_path_buf(),
+                    reason: reason.clone(),
+                });
+
+                break;
+            }
+        }
+    }
+
+    unwritable
+}
+
+fn nearest_existing(dir: &Path) -> &Path {
+    dir.ancestors()
+        .find(|dir| dir.exists())
+        .unwrap_or_else(|| Path::new("."))
+}
+
+/// Check whether entries can be added to and removed from `dir`.
+#[cfg(unix)]
+fn probe(dir: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_dir = CString::new(dir.as_os_str().as_bytes())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+    // unlike inspecting the permission bits, access also accounts for read-only mounts
+    if unsafe { libc::access(c_dir.as_ptr(), libc::W_OK | libc::X_OK) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(unix))]
+fn probe(dir: &Path) -> io::Result<()> {
+    if std::fs::metadata(dir)?.permissions().readonly() {
+        Err(io::Error::from(io::ErrorKind::PermissionDenied))
+    } else {
+        Ok(())
+    }
+}
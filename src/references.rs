@@ -0,0 +1,370 @@
+//! Rewrites references to renamed paths in text files, given as '--fix-references GLOB', so
+//! that relative links and includes keep working after a run.
+//!
+//! In Markdown and HTML documents only the destinations of links are rewritten. Elsewhere
+//! references are found by searching for each renamed path as it would have been written
+//! from the directory of the text file, as well as its absolute path, and are only replaced
+//! where they are not part of a longer name.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{self, Component, Path, PathBuf};
+
+use crate::incremental::final_path;
+use crate::links::{self, Kind};
+use crate::Options;
+
+/// Every file matching the given globs, expanded before anything is renamed.
+pub fn expand<'a, I: IntoIterator<Item = &'a str>>(globs: I) -> Result<Vec<PathBuf>, String> {
+    let mut files = vec![];
+
+    for pattern in globs {
+        let paths =
+            glob::glob(pattern).map_err(|err| format!("invalid glob '{}': {}", pattern, err))?;
+
+        files.extend(paths.filter_map(Result::ok).filter(|path| path.is_file()));
+    }
+
+    files.sort();
+    files.dedup();
+
+    Ok(files)
+}
+
+/// Rewrite the references in each of `files` according to `renamed`, which maps the source
+/// of every rename performed during the run to its target.
+pub fn fix(files: &[PathBuf], renamed: &HashMap<PathBuf, PathBuf>, opts: &Options) {
+    let renamed: HashMap<PathBuf, PathBuf> = renamed
+        .iter()
+        .filter_map(|(source, target)| {
+            Some((path::absolute(source).ok()?, path::absolute(target).ok()?))
+        })
+        .collect();
+
+    for file in files {
+        let original = match path::absolute(file) {
+            Ok(original) => original,
+            Err(_) => continue,
+        };
+
+        let current = final_path(&original, &renamed);
+
+        match fix_file(&original, &current, &renamed, opts.is_dry_run) {
+            Ok(0) => {}
+            Ok(count) if opts.is_dry_run => info!(
+                "{}",
+                tr!(
+                    "references-would-rewrite",
+                    count = count,
+                    path = current.display()
+                )
+            ),
+            Ok(count) if opts.is_verbose => info!(
+                "{}",
+                tr!(
+                    "references-rewrote",
+                    count = count,
+                    path = current.display()
+                )
+            ),
+            Ok(_) => {}
+            Err(err) => warning!(
+                "{}",
+                tr!(
+                    "references-failed",
+                    path = current.display(),
+                    reason = err.to_string()
+                )
+            ),
+        }
+    }
+}
+
+/// Rewrite the references in the file originally at `original` and now at `current`,
+/// returning the number of references rewritten.
+fn fix_file(
+    original: &Path,
+    current: &Path,
+    renamed: &HashMap<PathBuf, PathBuf>,
+    is_dry_run: bool,
+) -> io::Result<usize> {
+    // during a dry run nothing has moved, so the file is still at its original path
+    let text = fs::read_to_string(if is_dry_run { original } else { current })?;
+
+    let (old_dir, new_dir) = match (original.parent(), current.parent()) {
+        (Some(old_dir), Some(new_dir)) => (old_dir, new_dir),
+        _ => return Ok(0),
+    };
+
+    let (text, count) = match Kind::of(current) {
+        Some(kind) => fix_links(&text, kind, old_dir, new_dir, renamed),
+        None => fix_text(text, old_dir, new_dir, renamed),
+    };
+
+    if count > 0 && !is_dry_run {
+        fs::write(current, text)?;
+    }
+
+    Ok(count)
+}
+
+/// Rewrite the destination of every link in a document of the given kind.
+fn fix_links(
+    text: &str,
+    kind: Kind,
+    old_dir: &Path,
+    new_dir: &Path,
+    renamed: &HashMap<PathBuf, PathBuf>,
+) -> (String, usize) {
+    let mut fixed = String::with_capacity(text.len());
+    let mut count = 0;
+    let mut rest = 0;
+
+    for range in links::destinations(text, kind) {
+        if let Some(new) = fix_link(&text[range.clone()], old_dir, new_dir, renamed) {
+            fixed.push_str(&text[rest..range.start]);
+            fixed.push_str(&new);
+            rest = range.end;
+            count += 1;
+        }
+    }
+
+    fixed.push_str(&text[rest..]);
+
+    (fixed, count)
+}
+
+/// The new destination of a link to a renamed path, keeping its query, fragment, and style.
+fn fix_link(
+    dest: &str,
+    old_dir: &Path,
+    new_dir: &Path,
+    renamed: &HashMap<PathBuf, PathBuf>,
+) -> Option<String> {
+    // links to other sites, to anchors, or from the root of the site are left alone
+    if dest.starts_with('#') || dest.starts_with('/') || has_scheme(dest) {
+        return None;
+    }
+
+    let (path, suffix) = dest.split_at(dest.find(&['?', '#'][..]).unwrap_or(dest.len()));
+    let decoded = links::percent_decode(path)?;
+
+    let original = normalize(&old_dir.join(&decoded));
+    let target = final_path(&original, renamed);
+
+    if target == original && old_dir == new_dir {
+        return None;
+    }
+
+    let mut new = String::from(relative(&target, new_dir).to_str()?);
+
+    if new.is_empty() || new == decoded.trim_end_matches('/') {
+        return None;
+    }
+
+    if decoded.ends_with('/') {
+        new.push('/');
+    }
+
+    if path.starts_with("./") && !new.starts_with("../") {
+        new.insert_str(0, "./");
+    }
+
+    if path.contains('%') || (new.contains(' ') && !path.contains(' ')) {
+        new = links::percent_encode(&new);
+    }
+
+    Some(new + suffix)
+}
+
+/// Whether `dest` starts with a url scheme, like 'https:' or 'mailto:'.
+fn has_scheme(dest: &str) -> bool {
+    match dest.find(':') {
+        // a single letter is more likely a windows drive
+        Some(i) if i > 1 => dest[..i]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.'),
+        _ => false,
+    }
+}
+
+/// Resolve the '.' and '..' components of `path` without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+/// Replace every reference to a renamed path found anywhere in `text`.
+fn fix_text(
+    mut text: String,
+    old_dir: &Path,
+    new_dir: &Path,
+    renamed: &HashMap<PathBuf, PathBuf>,
+) -> (String, usize) {
+    let mut replacements: Vec<(String, String)> = vec![];
+
+    for source in renamed.keys() {
+        let target = final_path(source, renamed);
+
+        let candidates = [
+            (relative(source, old_dir), relative(&target, new_dir)),
+            (source.clone(), target),
+        ];
+
+        for (old, new) in candidates.iter() {
+            if let (Some(old), Some(new)) = (old.to_str(), new.to_str()) {
+                if old != new && text.contains(old) {
+                    replacements.push((String::from(old), String::from(new)));
+                }
+            }
+        }
+    }
+
+    // longer references go first so that a renamed directory does not break references
+    // to the renamed entries beneath it
+    replacements.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+
+    let mut count = 0;
+
+    for (old, new) in &replacements {
+        let (replaced, n) = replace_references(&text, old, new);
+
+        text = replaced;
+        count += n;
+    }
+
+    (text, count)
+}
+
+/// Replace every occurrence of `old` in `text` which is not part of a longer name.
+fn replace_references(text: &str, old: &str, new: &str) -> (String, usize) {
+    let mut replaced = String::with_capacity(text.len());
+    let mut count = 0;
+    let mut rest = 0;
+
+    for (i, _) in text.match_indices(old) {
+        if i < rest || !is_start(&text[..i]) || !is_end(&text[i + old.len()..]) {
+            continue;
+        }
+
+        replaced.push_str(&text[rest..i]);
+        replaced.push_str(new);
+        rest = i + old.len();
+        count += 1;
+    }
+
+    replaced.push_str(&text[rest..]);
+
+    (replaced, count)
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Whether a reference may start after `before`, which is true unless the reference would
+/// continue a name or a path other than the current directory.
+fn is_start(before: &str) -> bool {
+    let mut chars = before.chars().rev();
+
+    match chars.next() {
+        None => true,
+        Some('/') => {
+            chars.next() == Some('.')
+                && chars
+                    .next()
+                    .is_none_or(|c| !is_name_char(c) && c != '.' && c != '/')
+        }
+        Some(c) => !is_name_char(c) && c != '.',
+    }
+}
+
+/// Whether a reference may end before `after`, which is true unless the reference would
+/// continue into a longer name or extension.
+fn is_end(after: &str) -> bool {
+    let mut chars = after.chars();
+
+    match chars.next() {
+        None => true,
+        Some('.') => chars.next().is_none_or(|c| !is_name_char(c)),
+        Some(c) => !is_name_char(c),
+    }
+}
+
+/// The path of `path` relative to `dir`, both of which are absolute.
+fn relative(path: &Path, dir: &Path) -> PathBuf {
+    let path: Vec<Component> = path.components().collect();
+    let dir: Vec<Component> = dir.components().collect();
+
+    let common = path
+        .iter()
+        .zip(dir.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut relative = PathBuf::new();
+
+    for _ in common..dir.len() {
+        relative.push("..");
+    }
+
+    for component in &path[common..] {
+        relative.push(component);
+    }
+
+    relative
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use crate::references::{relative, replace_references};
+
+    #[test]
+    fn test_relative() {
+        assert_eq!(
+            PathBuf::from("../Other Dir/Some File.md"),
+            relative(
+                Path::new("/root/Other Dir/Some File.md"),
+                Path::new("/root/docs")
+            )
+        );
+        assert_eq!(
+            PathBuf::from("Some File.md"),
+            relative(
+                Path::new("/root/docs/Some File.md"),
+                Path::new("/root/docs")
+            )
+        );
+    }
+
+    #[test]
+    fn test_replace_references() {
+        let text = "[a](Some File.md) [b](./Some File.md#top) [c](Awesome File.md) \
+                    [d](Some File.mdx) [e](other/Some File.md). Some File.md.";
+
+        assert_eq!(
+            (
+                String::from(
+                    "[a](some_file.md) [b](./some_file.md#top) [c](Awesome File.md) \
+                     [d](Some File.mdx) [e](other/Some File.md). some_file.md."
+                ),
+                3
+            ),
+            replace_references(text, "Some File.md", "some_file.md")
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Renaming entries which don't live on the local filesystem (ie objects in a bucket or files
+//! on a server), using the same conversion, collision, and dry run handling as local paths.
+
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use convert_path::plan::RenamePlan;
+
+use crate::report::{self, Event};
+use crate::{resolve_collisions, adjust_target, Options, Outcome, Summary};
+
+/// A tree of entries which can be listed and renamed.
+///
+/// Paths are always relative to the root of the remote.
+pub trait Remote {
+    /// Every entry under the root, children before their parents.
+    fn list(&mut self) -> io::Result<Vec<PathBuf>>;
+
+    fn exists(&mut self, path: &Path) -> io::Result<bool>;
+
+    fn rename(&mut self, source: &Path, target: &Path) -> io::Result<()>;
+}
+
+/// Open the remote described by `url`.
+pub fn open(url: &str) -> Result<Box<dyn Remote>, String> {
+    if url.starts_with("s3://") {
+        return open_s3(url);
+    }
+
+    if url.starts_with("sftp://") {
+        return open_sftp(url);
+    }
+
+    Err(format!(
+        "unsupported backend '{}', expected an s3:// or sftp:// url",
+        url
+    ))
+}
+
+#[cfg(feature = "s3")]
+fn open_s3(url: &str) -> Result<Box<dyn Remote>, String> {
+    let location = crate::s3::Location::parse(url)?;
+
+    match crate::s3::S3::connect(location) {
+        Ok(s3) => Ok(Box::new(s3)),
+        Err(err) => Err(format!("could not connect to '{}': {}", url, err)),
+    }
+}
+
+#[cfg(not(feature = "s3"))]
+fn open_s3(_url: &str) -> Result<Box<dyn Remote>, String> {
+    Err(String::from("ccpath was built without s3 support"))
+}
+
+#[cfg(feature = "sftp")]
+fn open_sftp(url: &str) -> Result<Box<dyn Remote>, String> {
+    let location = crate::sftp::Location::parse(url)?;
+
+    match crate::sftp::SftpRemote::connect(location) {
+        Ok(sftp) => Ok(Box::new(sftp)),
+        Err(err) => Err(format!("could not connect to '{}': {}", url, err)),
+    }
+}
+
+#[cfg(not(feature = "sftp"))]
+fn open_sftp(_url: &str) -> Result<Box<dyn Remote>, String> {
+    Err(String::from("ccpath was built without sftp support"))
+}
+
+/// Plan and perform the conversion of every entry in `remote`.
+pub fn run(remote: &mut dyn Remote, opts: &Options) -> io::Result<Summary> {
+    let mut plan = RenamePlan::new();
+
+    for path in remote.list()? {
+        let to = opts.convention(&path);
+        let converted = if opts.is_full_path {
+            convert_path::convert_full_with(&path, opts.from, to, &opts.extensions)
+                .and_then(|target| adjust_target(&path, target, path.components().count(), opts))
+        } else {
+            convert_path::convert_basename_with(&path, opts.from, to, &opts.extensions)
+                .and_then(|target| adjust_target(&path, target, 1, opts))
+        };
+
+        match converted {
+            // a converted name of '.' or '..' would move the entry somewhere else entirely
+            Ok(target) if !is_plain(&target) => error!(
+                "{}",
+                tr!(
+                    "remote-escaping",
+                    source = path.display(),
+                    target = target.display()
+                )
+            ),
+            Ok(target) => plan.push(path, target),
+            Err(err) => error!("{}", err),
+        }
+    }
+
+    let skipped = resolve_collisions(plan.collisions(), opts);
+    plan.retain(|rename| !skipped.contains(&rename.source));
+
+    let mut summary = Summary {
+        skipped: skipped.len(),
+        ..Summary::default()
+    };
+
+    for rename in &plan {
+        let outcome = if rename.is_noop() {
+            Outcome::Conforming
+        } else if opts.is_dry_run {
+            Outcome::Renamed
+        } else if opts.no_clobber && remote.exists(&rename.target)? {
+            if opts.is_verbose {
+                info!("{}", tr!("file-exists", path = rename.target.display()));
+            }
+
+            Outcome::Skipped
+        } else {
+            opts.throttle.wait();
+
+            match remote.rename(&rename.source, &rename.target) {
+                Ok(()) => {
+                    crate::report::audit(&rename.source, &rename.target);
+                    Outcome::Renamed
+                }
+                Err(err) => {
+                    error!("{}: {}", rename.source.display(), err);
+                    Outcome::Failed
+                }
+            }
+        };
+
+        if outcome == Outcome::Renamed && (opts.is_verbose || opts.is_dry_run) {
+            println!(
+                "'{}' -> '{}'",
+                rename.source.display(),
+                rename.target.display()
+            );
+        }
+
+        report::event(Event::Rename {
+            source: &rename.source,
+            target: &rename.target,
+            outcome,
+        });
+
+        summary.record(outcome);
+    }
+
+    Ok(summary)
+}
+
+/// Whether `path` is made up of only normal components.
+pub fn is_plain(path: &Path) -> bool {
+    let raw = path.to_string_lossy();
+
+    !raw.ends_with("/.")
+        && raw != "."
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use crate::remote::is_plain;
+
+    #[test]
+    fn test_is_plain() {
+        assert!(is_plain(Path::new("some_dir/some_file")));
+        assert!(!is_plain(Path::new("some_dir/..")));
+        assert!(!is_plain(Path::new("some_dir/.")));
+        assert!(!is_plain(Path::new("/some_file")));
+    }
+}
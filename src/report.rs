@@ -0,0 +1,378 @@
+//! Human diagnostics go to stderr so stdout only ever carries data, everything is also
+//! mirrored into the log file given by '--log-file'. Given '--syslog', every performed
+//! rename is also recorded in the system log as an audit trail.
+//!
+//! Given '--record', the run and its renames are also written to a journal of their own, in
+//! the format of the log file but without any diagnostics, for 'ccpath undo' to read back.
+//!
+//! Given '--log json', diagnostics and events are instead written to stderr through tracing
+//! as one json object per line, carrying their level and fields for log pipelines to filter.
+
+use std::ffi::OsStr;
+use std::fmt::{Arguments, Display};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{Outcome, Summary};
+
+static LOG_FILE: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// The journal recording the run and its renames for 'ccpath undo', given '--record'.
+static JOURNAL: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// Whether diagnostics are written as json events rather than as messages.
+static IS_JSON: AtomicBool = AtomicBool::new(false);
+
+/// The user performing the run, once the system log has been opened.
+static SYSLOG_USER: OnceLock<String> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    fn as_str(self) -> &'static str {
+        match self {
+            Level::Info => "info",
+            Level::Warning => "warning",
+            Level::Error => "error",
+        }
+    }
+}
+
+/// Emit a tracing event at a level only known at runtime.
+macro_rules! trace_at {
+    ($level:expr, $($fields:tt)*) => {
+        match $level {
+            $crate::report::Level::Info => tracing::info!($($fields)*),
+            $crate::report::Level::Warning => tracing::warn!($($fields)*),
+            $crate::report::Level::Error => tracing::error!($($fields)*),
+        }
+    };
+}
+
+/// How diagnostics and events are written to stderr.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Messages for people to read.
+    Human,
+
+    /// One json object per line for every diagnostic and event, with its level and fields.
+    Json,
+}
+
+/// Write every diagnostic and event to stderr in `format` from now on.
+pub fn init(format: LogFormat) {
+    if format != LogFormat::Json {
+        return;
+    }
+
+    let subscriber = tracing_subscriber::fmt()
+        .json()
+        .flatten_event(true)
+        .with_current_span(false)
+        .with_span_list(false)
+        .with_target(false)
+        .with_writer(io::stderr)
+        .finish();
+
+    // the subscriber is only ever set here, once at startup
+    if tracing::subscriber::set_global_default(subscriber).is_ok() {
+        IS_JSON.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Start mirroring every diagnostic and event into the file at `path`, truncating it.
+pub fn open_log(path: &Path) -> io::Result<()> {
+    let file = File::create(path)?;
+
+    // the log is only ever opened once at startup
+    let _ = LOG_FILE.set(Mutex::new(file));
+
+    Ok(())
+}
+
+/// Start recording the run and every rename into the journal at `path`, truncating it.
+pub fn open_journal(path: &Path) -> io::Result<()> {
+    let file = File::create(path)?;
+
+    // the journal is only ever opened once at startup
+    let _ = JOURNAL.set(Mutex::new(file));
+
+    Ok(())
+}
+
+/// Start recording every performed rename in the system log, which journald also reads.
+#[cfg(unix)]
+pub fn open_syslog() -> io::Result<()> {
+    // syslog keeps the ident pointer for the rest of the process, so it must be static
+    unsafe {
+        libc::openlog(
+            b"ccpath\0".as_ptr() as *const libc::c_char,
+            libc::LOG_PID,
+            libc::LOG_USER,
+        );
+    }
+
+    let _ = SYSLOG_USER.set(current_user());
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn open_syslog() -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "there is no system log on this platform",
+    ))
+}
+
+/// Record a performed rename in the system log, if it is open. The log adds the time.
+pub fn audit(source: &Path, target: &Path) {
+    #[cfg(unix)]
+    if let Some(user) = SYSLOG_USER.get() {
+        let message = format!("renamed {:?} to {:?} by {}", source, target, user);
+
+        // the message is passed as an argument so that it is never read as a format string
+        if let Ok(message) = std::ffi::CString::new(message.replace('\0', "")) {
+            unsafe {
+                libc::syslog(
+                    libc::LOG_NOTICE,
+                    b"%s\0".as_ptr() as *const libc::c_char,
+                    message.as_ptr(),
+                );
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = (source, target);
+}
+
+/// The name and id of the user running the process, like 'alice (uid 1000)'.
+#[cfg(unix)]
+pub fn current_user() -> String {
+    let uid = unsafe { libc::getuid() };
+
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+    let mut buf = vec![0 as libc::c_char; 4096];
+
+    let found = unsafe {
+        libc::getpwuid_r(uid, &mut passwd, buf.as_mut_ptr(), buf.len(), &mut result)
+    } == 0
+        && !result.is_null();
+
+    if found {
+        let name = unsafe { std::ffi::CStr::from_ptr(passwd.pw_name) };
+
+        format!("{} (uid {})", name.to_string_lossy(), uid)
+    } else {
+        format!("uid {}", uid)
+    }
+}
+
+/// Print a diagnostic to stderr and record it in the log file.
+pub fn emit(level: Level, args: Arguments) {
+    if IS_JSON.load(Ordering::Relaxed) {
+        trace_at!(level, message = %args);
+    } else {
+        match level {
+            Level::Info => eprintln!("{}", args),
+            Level::Warning => eprintln!("{}: {}", tr!("level-warning"), args),
+            Level::Error => eprintln!("{}: {}", tr!("level-error"), args),
+        }
+    }
+
+    write_line(
+        &LOG_FILE,
+        &[("level", &level.as_str()), ("msg", &format!("{:?}", args.to_string()))],
+    );
+}
+
+/// Something which happened during a run.
+pub enum Event<'a> {
+    /// A run converting into `into` started in `cwd`, if it could be found.
+    Run {
+        cwd: Option<&'a Path>,
+        into: &'a str,
+    },
+
+    /// The plan of a run was made, holding `renames` renames.
+    Plan {
+        renames: u64,
+    },
+
+    Rename {
+        source: &'a Path,
+        target: &'a Path,
+        outcome: Outcome,
+    },
+
+    RemoveDir {
+        path: &'a Path,
+    },
+
+    XattrLost {
+        path: &'a Path,
+        attr: &'a OsStr,
+    },
+
+    Summary(&'a Summary),
+}
+
+/// Record an event in the log file, and as a json event given '--log json'. The run and its
+/// renames are also recorded in the journal.
+///
+/// Renames which were skipped are recorded as warnings and those which failed as errors.
+pub fn event(event: Event) {
+    let quoted = |path: &Path| format!("{:?}", path);
+
+    match event {
+        Event::Run { cwd, into } => {
+            let lossy = cwd.map(Path::to_string_lossy);
+            trace_at!(Level::Info, event = "run", cwd = lossy.as_deref(), into);
+
+            match cwd {
+                Some(cwd) => journal_event("run", &[("cwd", &quoted(cwd)), ("into", &into)]),
+                None => journal_event("run", &[("into", &into)]),
+            }
+        }
+        Event::Plan { renames } => {
+            trace_at!(Level::Info, event = "plan", renames);
+            log_event("plan", &[("renames", &renames)]);
+        }
+        Event::Rename {
+            source,
+            target,
+            outcome,
+        } => {
+            let level = match outcome {
+                Outcome::Renamed | Outcome::Conforming => Level::Info,
+                Outcome::Skipped | Outcome::Changed => Level::Warning,
+                Outcome::Failed => Level::Error,
+            };
+
+            trace_at!(
+                level,
+                event = "rename",
+                source = %source.display(),
+                target = %target.display(),
+                outcome = outcome.as_str()
+            );
+            journal_event(
+                "rename",
+                &[
+                    ("source", &quoted(source)),
+                    ("target", &quoted(target)),
+                    ("outcome", &outcome.as_str()),
+                ],
+            );
+        }
+        Event::RemoveDir { path } => {
+            trace_at!(Level::Info, event = "remove_dir", path = %path.display());
+            log_event("remove_dir", &[("path", &quoted(path))]);
+        }
+        Event::XattrLost { path, attr } => {
+            trace_at!(
+                Level::Warning,
+                event = "xattr_lost",
+                path = %path.display(),
+                attr = %attr.to_string_lossy()
+            );
+            log_event(
+                "xattr_lost",
+                &[("path", &quoted(path)), ("attr", &format!("{:?}", attr))],
+            );
+        }
+        Event::Summary(summary) => {
+            trace_at!(
+                Level::Info,
+                event = "summary",
+                renamed = summary.renamed,
+                conforming = summary.conforming,
+                skipped = summary.skipped,
+                changed = summary.changed,
+                failed = summary.failed,
+                special = summary.special
+            );
+            log_event(
+                "summary",
+                &[
+                    ("renamed", &summary.renamed),
+                    ("conforming", &summary.conforming),
+                    ("skipped", &summary.skipped),
+                    ("changed", &summary.changed),
+                    ("failed", &summary.failed),
+                    ("special", &summary.special),
+                ],
+            );
+        }
+    }
+}
+
+/// Record an event with the given fields in the log file only.
+///
+/// String fields should be quoted by the caller (ie with `{:?}`) if they may contain spaces.
+fn log_event(name: &str, fields: &[(&str, &dyn Display)]) {
+    let mut all: Vec<(&str, &dyn Display)> = vec![("event", &name)];
+    all.extend_from_slice(fields);
+
+    write_line(&LOG_FILE, &all);
+}
+
+/// Record an event with the given fields in both the log file and the journal.
+fn journal_event(name: &str, fields: &[(&str, &dyn Display)]) {
+    let mut all: Vec<(&str, &dyn Display)> = vec![("event", &name)];
+    all.extend_from_slice(fields);
+
+    write_line(&LOG_FILE, &all);
+    write_line(&JOURNAL, &all);
+}
+
+fn write_line(file: &OnceLock<Mutex<File>>, fields: &[(&str, &dyn Display)]) {
+    let file = match file.get() {
+        Some(file) => file,
+        None => return,
+    };
+
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+
+    let mut line = format!("time={:.3}", time);
+
+    for (key, value) in fields {
+        line.push_str(&format!(" {}={}", key, value));
+    }
+
+    if let Ok(mut file) = file.lock() {
+        let _ = writeln!(file, "{}", line);
+    }
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::report::emit($crate::report::Level::Info, format_args!($($arg)*))
+    };
+}
+
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        $crate::report::emit($crate::report::Level::Warning, format_args!($($arg)*))
+    };
+}
+
+macro_rules! error {
+    ($($arg:tt)*) => {
+        $crate::report::emit($crate::report::Level::Error, format_args!($($arg)*))
+    };
+}
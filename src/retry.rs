@@ -0,0 +1,132 @@
+//! Retries renames which fail with errors network filesystems like NFS and SMB raise
+//! sporadically, so that a large run over a share doesn't need re-running to mop them up.
+
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+/// How often and how patiently a failed rename is retried.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Retry {
+    /// The number of times a rename is retried after its first attempt.
+    pub attempts: u32,
+
+    /// The delay before the first retry, doubled before each one after it.
+    pub delay: Duration,
+}
+
+impl Retry {
+    /// Retry `op`, whose first attempt gave `result`, until it succeeds, fails with an error
+    /// which is not transient, or has been retried as many times as allowed, calling
+    /// `retrying` with each error before retrying.
+    pub fn after<T, F, R>(
+        &self,
+        mut result: io::Result<T>,
+        mut op: F,
+        mut retrying: R,
+    ) -> io::Result<T>
+    where
+        F: FnMut() -> io::Result<T>,
+        R: FnMut(&io::Error, u32),
+    {
+        let mut delay = self.delay;
+
+        for attempt in 1..=self.attempts {
+            match &result {
+                Err(err) if is_transient(err) => retrying(err, attempt),
+                _ => break,
+            }
+
+            thread::sleep(delay);
+            delay = delay.saturating_mul(2);
+            result = op();
+        }
+
+        result
+    }
+}
+
+/// Whether `err` may go away by itself, like a busy or stale file handle on a network
+/// filesystem.
+pub fn is_transient(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::WouldBlock
+            | io::ErrorKind::Interrupted
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::ResourceBusy
+            | io::ErrorKind::StaleNetworkFileHandle
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use std::time::Duration;
+
+    use crate::retry::Retry;
+
+    #[test]
+    fn test_retry() {
+        let retry = Retry {
+            attempts: 3,
+            delay: Duration::from_millis(1),
+        };
+
+        // transient errors are retried until the operation succeeds
+        let mut calls = 1;
+        let mut retried = vec![];
+        let result = retry.after(
+            Err(io::Error::from(io::ErrorKind::WouldBlock)),
+            || {
+                calls += 1;
+
+                match calls {
+                    2 => Err(io::Error::from(io::ErrorKind::StaleNetworkFileHandle)),
+                    _ => Ok(calls),
+                }
+            },
+            |_, attempt| retried.push(attempt),
+        );
+
+        assert_eq!(3, result.unwrap());
+        assert_eq!(vec![1, 2], retried);
+
+        // up to the number of attempts allowed
+        let mut calls = 1;
+        let result: io::Result<()> = retry.after(
+            Err(io::Error::from(io::ErrorKind::ResourceBusy)),
+            || {
+                calls += 1;
+                Err(io::Error::from(io::ErrorKind::ResourceBusy))
+            },
+            |_, _| {},
+        );
+
+        assert_eq!(io::ErrorKind::ResourceBusy, result.unwrap_err().kind());
+        assert_eq!(4, calls);
+
+        // while other errors fail at once
+        let mut calls = 1;
+        let result: io::Result<()> = retry.after(
+            Err(io::Error::from(io::ErrorKind::PermissionDenied)),
+            || {
+                calls += 1;
+                Ok(())
+            },
+            |_, _| {},
+        );
+
+        assert!(result.is_err());
+        assert_eq!(1, calls);
+
+        // and nothing is retried when no attempts are allowed
+        let result = Retry::default().after(
+            Err(io::Error::from(io::ErrorKind::WouldBlock)),
+            || Ok(()),
+            |_, _| {},
+        );
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,169 @@
+//! Finds every way performing a plan could clobber a path, which '--check-collisions-only'
+//! reports in place of performing it.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::path::PathBuf;
+
+use convert_path::plan::{Collision, Rename};
+
+use crate::snapshot::Identity;
+use crate::spill::Batches;
+
+pub enum Risk {
+    /// Several sources would be renamed to the same target.
+    Collision(Collision),
+
+    /// A path which is not itself renamed away already exists at the target.
+    Exists(Rename),
+
+    /// Paths which would only differ in case, and so collide on case-insensitive filesystems.
+    CaseDuplicate(Vec<PathBuf>),
+}
+
+impl Display for Risk {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let quoted = |paths: &[PathBuf]| {
+            paths
+                .iter()
+                .map(|path| format!("'{}'", path.display()))
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+
+        match self {
+            Risk::Collision(collision) => write!(
+                f,
+                "{}",
+                tr!(
+                    "collision",
+                    sources = quoted(&collision.sources),
+                    target = collision.target.display()
+                )
+            ),
+            Risk::Exists(rename) => write!(
+                f,
+                "'{}' would be renamed to '{}', which already exists",
+                rename.source.display(),
+                rename.target.display()
+            ),
+            Risk::CaseDuplicate(paths) => write!(f, "{} would only differ in case", quoted(paths)),
+        }
+    }
+}
+
+/// Every risk of performing the plan, with those of each kind in plan order.
+pub fn find(batches: &Batches) -> io::Result<Vec<Risk>> {
+    let mut risks: Vec<Risk> = batches
+        .collisions(false)?
+        .into_iter()
+        .map(Risk::Collision)
+        .collect();
+
+    let mut sources = HashSet::new();
+    let mut renames = vec![];
+    let mut by_folded: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    let mut folded_order = vec![];
+
+    batches.for_each(|plan, _| {
+        for rename in plan {
+            let folded = rename.target.to_string_lossy().to_lowercase();
+            let targets = by_folded.entry(folded.clone()).or_insert_with(|| {
+                folded_order.push(folded);
+                vec![]
+            });
+
+            if !targets.contains(&rename.target) {
+                targets.push(rename.target.clone());
+            }
+
+            if !rename.is_noop() {
+                sources.insert(rename.source.clone());
+                renames.push(rename.clone());
+            }
+        }
+    })?;
+
+    let collided: HashSet<PathBuf> = risks
+        .iter()
+        .filter_map(|risk| match risk {
+            Risk::Collision(collision) => Some(collision.target.clone()),
+            _ => None,
+        })
+        .collect();
+
+    // a target which is renamed away beforehand, or which is the source itself reached
+    // through a case-insensitive name, is not at risk
+    risks.extend(
+        renames
+            .into_iter()
+            .filter(|rename| {
+                !sources.contains(&rename.target) && !collided.contains(&rename.target)
+            })
+            .filter(|rename| {
+                Identity::of(&rename.target)
+                    .is_some_and(|target| Some(target) != Identity::of(&rename.source))
+            })
+            .map(Risk::Exists),
+    );
+
+    risks.extend(
+        folded_order
+            .into_iter()
+            .filter_map(|folded| by_folded.remove(&folded))
+            .filter(|targets| targets.len() > 1)
+            .map(Risk::CaseDuplicate),
+    );
+
+    Ok(risks)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use convert_path::plan::RenamePlan;
+
+    use crate::risk::{find, Risk};
+    use crate::snapshot::Snapshot;
+    use crate::spill::Batches;
+
+    #[test]
+    fn test_find() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = |name: &str| dir.path().join(name);
+
+        for name in [
+            "Some File",
+            "Some-File",
+            "Other File",
+            "other_file",
+            "Third File",
+        ] {
+            fs::write(path(name), "")?;
+        }
+
+        let mut plan = RenamePlan::new();
+        plan.push(path("Some File"), path("some_file"));
+        plan.push(path("Some-File"), path("some_file"));
+        plan.push(path("Other File"), path("other_file"));
+        plan.push(path("Third File"), path("THIRD_FILE"));
+        plan.push(path("third_file"), path("third_file"));
+
+        let snapshot = Snapshot::take(&plan);
+        let risks = find(&Batches::InMemory(plan, snapshot))?;
+
+        assert_eq!(3, risks.len());
+        assert!(
+            matches!(&risks[0], Risk::Collision(collision) if collision.target == path("some_file"))
+        );
+        assert!(matches!(&risks[1], Risk::Exists(rename) if rename.target == path("other_file")));
+        assert!(matches!(
+            &risks[2],
+            Risk::CaseDuplicate(paths) if *paths == vec![path("THIRD_FILE"), path("third_file")]
+        ));
+
+        Ok(())
+    }
+}
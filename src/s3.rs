@@ -0,0 +1,204 @@
+//! Renames the keys of objects in an S3 compatible bucket, given as '--backend s3://bucket/prefix'.
+//!
+//! Credentials, the region, and the endpoint of S3 compatible stores are all taken from the
+//! standard AWS environment variables and configuration files. Since S3 has no rename, each
+//! object is copied to its new key before the old key is deleted, so objects over the 5GiB
+//! limit of a single copy cannot be renamed.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use aws_sdk_s3::error::DisplayErrorContext;
+use aws_sdk_s3::Client;
+use tokio::runtime::Runtime;
+
+use crate::remote::Remote;
+
+/// A bucket and the prefix under which keys are renamed.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Location {
+    bucket: String,
+
+    /// Either empty or ending in a '/'.
+    prefix: String,
+}
+
+impl Location {
+    pub fn parse(url: &str) -> Result<Location, String> {
+        let rest = url
+            .strip_prefix("s3://")
+            .ok_or_else(|| format!("'{}' is not an s3:// url", url))?;
+
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+
+        if bucket.is_empty() {
+            return Err(format!("'{}' does not name a bucket", url));
+        }
+
+        // a prefix is treated as a directory so that 'photos' does not also match 'photos2/'
+        let prefix = prefix.trim_matches('/');
+        let prefix = if prefix.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", prefix)
+        };
+
+        Ok(Location {
+            bucket: String::from(bucket),
+            prefix,
+        })
+    }
+
+    fn key(&self, path: &Path) -> String {
+        format!("{}{}", self.prefix, path.to_string_lossy())
+    }
+}
+
+pub struct S3 {
+    location: Location,
+    client: Client,
+    runtime: Runtime,
+}
+
+impl S3 {
+    pub fn connect(location: Location) -> io::Result<S3> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        let config = runtime.block_on(aws_config::load_defaults(
+            aws_config::BehaviorVersion::latest(),
+        ));
+
+        Ok(S3 {
+            location,
+            client: Client::new(&config),
+            runtime,
+        })
+    }
+}
+
+impl Remote for S3 {
+    fn list(&mut self) -> io::Result<Vec<PathBuf>> {
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.location.bucket)
+            .prefix(&self.location.prefix)
+            .into_paginator()
+            .send();
+
+        let mut keys = vec![];
+
+        while let Some(page) = self.runtime.block_on(pages.next()) {
+            let page = page.map_err(to_io)?;
+
+            let relative = page
+                .contents()
+                .iter()
+                .filter_map(|object| object.key())
+                .filter_map(|key| key.strip_prefix(self.location.prefix.as_str()))
+                // keys ending in '/' are placeholders for empty "directories"
+                .filter(|key| !key.is_empty() && !key.ends_with('/'))
+                .map(PathBuf::from);
+
+            keys.extend(relative);
+        }
+
+        Ok(keys)
+    }
+
+    fn exists(&mut self, path: &Path) -> io::Result<bool> {
+        let result = self.runtime.block_on(
+            self.client
+                .head_object()
+                .bucket(&self.location.bucket)
+                .key(self.location.key(path))
+                .send(),
+        );
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(err) if err.as_service_error().is_some_and(|err| err.is_not_found()) => Ok(false),
+            Err(err) => Err(to_io(err)),
+        }
+    }
+
+    fn rename(&mut self, source: &Path, target: &Path) -> io::Result<()> {
+        let source = self.location.key(source);
+        let copy_source = encode(&format!("{}/{}", self.location.bucket, source));
+
+        self.runtime
+            .block_on(
+                self.client
+                    .copy_object()
+                    .bucket(&self.location.bucket)
+                    .copy_source(copy_source)
+                    .key(self.location.key(target))
+                    .send(),
+            )
+            .map_err(to_io)?;
+
+        self.runtime
+            .block_on(
+                self.client
+                    .delete_object()
+                    .bucket(&self.location.bucket)
+                    .key(source)
+                    .send(),
+            )
+            .map_err(to_io)?;
+
+        Ok(())
+    }
+}
+
+fn to_io<E: std::error::Error>(err: E) -> io::Error {
+    io::Error::other(DisplayErrorContext(err).to_string())
+}
+
+/// Percent encode everything but unreserved characters and '/', as expected of a copy source.
+fn encode(source: &str) -> String {
+    let mut encoded = String::with_capacity(source.len());
+
+    for b in source.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(b as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod test {
+    use crate::s3::{encode, Location};
+
+    #[test]
+    fn test_location_parse() {
+        assert_eq!(
+            Ok(Location {
+                bucket: String::from("bucket"),
+                prefix: String::from("Some Dir/"),
+            }),
+            Location::parse("s3://bucket/Some Dir")
+        );
+        assert_eq!(
+            Ok(Location {
+                bucket: String::from("bucket"),
+                prefix: String::new(),
+            }),
+            Location::parse("s3://bucket/")
+        );
+        assert!(Location::parse("s3:///prefix").is_err());
+        assert!(Location::parse("sftp://host/path").is_err());
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!("bucket/Some%20File%2B1.txt", encode("bucket/Some File+1.txt"));
+    }
+}
@@ -0,0 +1,94 @@
+//! Picks a random sample of the renames of a plan, which '--sample' shows in place of
+//! performing them so the heuristics can be checked on a slice of a tree too large to read
+//! through whole.
+
+use std::io;
+
+use fastrand::Rng;
+
+use convert_path::plan::Rename;
+
+use crate::spill::Batches;
+
+/// Pick up to `n` of the renames which change a path, each equally likely, in plan order
+/// along with the number of renames they were picked from.
+///
+/// The plan is read once without being held in memory, so it may be as large as a spilled
+/// plan can be.
+pub fn sample(batches: &Batches, n: usize, rng: &mut Rng) -> io::Result<(Vec<Rename>, usize)> {
+    let mut picked: Vec<(usize, Rename)> = Vec::with_capacity(n);
+    let mut seen = 0;
+
+    batches.for_each(|plan, _| {
+        for rename in plan.renames().iter().filter(|rename| !rename.is_noop()) {
+            if picked.len() < n {
+                picked.push((seen, rename.clone()));
+            } else {
+                // the rename replaces a picked one with a chance of n in however many were seen
+                let i = rng.usize(..=seen);
+
+                if i < n {
+                    picked[i] = (seen, rename.clone());
+                }
+            }
+
+            seen += 1;
+        }
+    })?;
+
+    picked.sort_by_key(|(i, _)| *i);
+
+    Ok((picked.into_iter().map(|(_, rename)| rename).collect(), seen))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use fastrand::Rng;
+
+    use convert_path::plan::RenamePlan;
+
+    use crate::sample::sample;
+    use crate::snapshot::Snapshot;
+    use crate::spill::Batches;
+
+    #[test]
+    fn test_sample() -> Result<(), Box<dyn std::error::Error>> {
+        let mut plan = RenamePlan::new();
+
+        for i in 0..100 {
+            plan.push(
+                PathBuf::from(format!("File {}", i)),
+                PathBuf::from(format!("file_{}", i)),
+            );
+        }
+
+        plan.push(PathBuf::from("already_fine"), PathBuf::from("already_fine"));
+
+        let snapshot = Snapshot::take(&plan);
+        let batches = Batches::InMemory(plan, snapshot);
+
+        let (picked, seen) = sample(&batches, 10, &mut Rng::with_seed(7))?;
+        let sources: Vec<PathBuf> = picked.iter().map(|rename| rename.source.clone()).collect();
+
+        assert_eq!(100, seen);
+        assert_eq!(10, picked.len());
+
+        // picked in plan order, and the same again for the same seed
+        let mut sorted = picked.clone();
+        sorted.sort_by_key(|rename| rename.source.to_string_lossy()[5..].parse::<usize>().ok());
+        assert_eq!(sorted, picked);
+
+        let (again, _) = sample(&batches, 10, &mut Rng::with_seed(7))?;
+        assert_eq!(picked, again);
+
+        assert!(!sources.contains(&PathBuf::from("already_fine")));
+
+        let (all, seen) = sample(&batches, 1000, &mut Rng::with_seed(7))?;
+        assert_eq!(100, seen);
+        assert_eq!(100, all.len());
+
+        Ok(())
+    }
+}
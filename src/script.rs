@@ -0,0 +1,306 @@
+//! Writes the plan as a script given '--emit-script', so the renames can be reviewed,
+//! archived, or performed where ccpath isn't installed.
+//!
+//! Scripts are idempotent: each rename only happens while its source still exists, so a
+//! script which was interrupted can simply be run again.
+//!
+//! A rename onto an existing directory is skipped, since moving the source into it would not
+//! be the rename which was planned, and ccpath itself refuses to replace a directory which is
+//! not empty.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use convert_path::plan::Rename;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+pub enum Dialect {
+    /// Any POSIX shell.
+    Sh,
+
+    /// Windows PowerShell 5.1 or later.
+    #[value(name = "powershell")]
+    PowerShell,
+
+    /// A POSIX shell script of 'git mv' commands, which only renames tracked paths.
+    Git,
+}
+
+pub struct Script<W: Write> {
+    dialect: Dialect,
+    out: W,
+}
+
+impl<W: Write> Script<W> {
+    /// Start a script, defining the function every rename is performed with.
+    pub fn new(dialect: Dialect, mut out: W, no_clobber: bool) -> io::Result<Script<W>> {
+        match dialect {
+            Dialect::Sh => {
+                // a source which is a dangling symlink still needs to be renamed
+                let condition = if no_clobber {
+                    "{ [ -e \"$1\" ] || [ -L \"$1\" ]; } && [ ! -e \"$2\" ]"
+                } else {
+                    "[ -e \"$1\" ] || [ -L \"$1\" ]"
+                };
+
+                write!(
+                    out,
+                    "#!/bin/sh\n\
+                     # generated by ccpath, relative paths are relative to where it was run\n\
+                     set -eu\n\
+                     \n\
+                     rename() {{\n    \
+                         if {}; then\n        \
+                             if [ -d \"$2\" ]; then\n            \
+                                 echo \"skipping '$1', '$2' is a directory\" >&2\n            \
+                                 return\n        \
+                             fi\n\
+                     \n        \
+                             mkdir -p -- \"$(dirname -- \"$2\")\"\n        \
+                             mv -- \"$1\" \"$2\"\n    \
+                         fi\n\
+                     }}\n\
+                     \n",
+                    condition
+                )?;
+            }
+            Dialect::PowerShell => {
+                let condition = if no_clobber {
+                    "(Test-Path -LiteralPath $Source) -and \
+                     (($Source -ieq $Target) -or -not (Test-Path -LiteralPath $Target))"
+                } else {
+                    "Test-Path -LiteralPath $Source"
+                };
+
+                // without a byte order mark Windows PowerShell reads scripts as ANSI, which
+                // would garble every name outside of ASCII
+                out.write_all("\u{feff}".as_bytes())?;
+
+                write!(
+                    out,
+                    "# generated by ccpath, relative paths are relative to where it was run\r\n\
+                     $ErrorActionPreference = 'Stop'\r\n\
+                     \r\n\
+                     function Rename-Path([string] $Source, [string] $Target) {{\r\n    \
+                         if (-not ({})) {{ return }}\r\n\
+                     \r\n    \
+                         # a target differing only in case is the source itself\r\n    \
+                         if (($Source -ine $Target) -and (Test-Path -LiteralPath $Target -PathType Container)) {{\r\n        \
+                             Write-Warning \"skipping '$Source', '$Target' is a directory\"\r\n        \
+                             return\r\n    \
+                         }}\r\n\
+                     \r\n    \
+                         $parent = Split-Path -Parent $Target\r\n    \
+                         if ($parent -and -not (Test-Path -LiteralPath $parent)) {{\r\n        \
+                             New-Item -ItemType Directory -Path $parent -Force | Out-Null\r\n    \
+                         }}\r\n\
+                     \r\n    \
+                         $name = Split-Path -Leaf $Target\r\n    \
+                         if ((Split-Path -Parent $Source) -ne $parent) {{\r\n        \
+                             Move-Item -LiteralPath $Source -Destination $Target\r\n    \
+                         }} elseif ($Source -ieq $Target) {{\r\n        \
+                             # names differing only in case must go through another name\r\n        \
+                             $temporary = \"$Source.ccpath-$PID\"\r\n        \
+                             Rename-Item -LiteralPath $Source -NewName (Split-Path -Leaf $temporary)\r\n        \
+                             Rename-Item -LiteralPath $temporary -NewName $name\r\n    \
+                         }} else {{\r\n        \
+                             Rename-Item -LiteralPath $Source -NewName $name\r\n    \
+                         }}\r\n\
+                     }}\r\n\
+                     \r\n",
+                    condition
+                )?;
+            }
+            Dialect::Git => {
+                let condition = if no_clobber {
+                    "tracked \"$1\" && [ ! -e \"$2\" ]"
+                } else {
+                    "tracked \"$1\""
+                };
+
+                write!(
+                    out,
+                    "#!/bin/sh\n\
+                     # generated by ccpath, relative paths are relative to where it was run\n\
+                     set -eu\n\
+                     \n\
+                     tracked() {{\n    \
+                         [ -n \"$(git --literal-pathspecs ls-files -- \"$1\" | head -n 1)\" ]\n\
+                     }}\n\
+                     \n\
+                     rename() {{\n    \
+                         if {}; then\n        \
+                             if [ -d \"$2\" ]; then\n            \
+                                 echo \"skipping '$1', '$2' is a directory\" >&2\n            \
+                                 return\n        \
+                             fi\n\
+                     \n        \
+                             mkdir -p -- \"$(dirname -- \"$2\")\"\n        \
+                             git --literal-pathspecs mv -- \"$1\" \"$2\"\n    \
+                         elif [ -e \"$1\" ] && ! tracked \"$1\"; then\n        \
+                             echo \"skipping '$1', which is not tracked\" >&2\n    \
+                         fi\n\
+                     }}\n\
+                     \n\
+                     # case-insensitive checkouts consider names differing only in case to be the\n\
+                     # same, so they are renamed through a temporary name\n\
+                     rename_case() {{\n    \
+                         if tracked \"$1\"; then\n        \
+                             git --literal-pathspecs mv -- \"$1\" \"$1.ccpath-tmp\"\n        \
+                             git --literal-pathspecs mv -- \"$1.ccpath-tmp\" \"$2\"\n    \
+                         fi\n\
+                     }}\n\
+                     \n",
+                    condition
+                )?;
+            }
+        }
+
+        Ok(Script { dialect, out })
+    }
+
+    pub fn rename(&mut self, rename: &Rename) -> io::Result<()> {
+        if rename.is_noop() {
+            return Ok(());
+        }
+
+        match self.dialect {
+            Dialect::Sh | Dialect::Git => {
+                let function: &[u8] = if self.dialect == Dialect::Git && is_case_only(rename) {
+                    b"rename_case "
+                } else {
+                    b"rename "
+                };
+
+                self.out.write_all(function)?;
+                write_sh_quoted(&mut self.out, &rename.source)?;
+                self.out.write_all(b" ")?;
+                write_sh_quoted(&mut self.out, &rename.target)?;
+                self.out.write_all(b"\n")
+            }
+            Dialect::PowerShell => writeln!(
+                self.out,
+                "Rename-Path {} {}\r",
+                ps_quoted(&rename.source),
+                ps_quoted(&rename.target)
+            ),
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+
+        Ok(self.out)
+    }
+}
+
+/// Whether the source and target of `rename` differ only in case.
+fn is_case_only(rename: &Rename) -> bool {
+    rename.source.to_string_lossy().to_lowercase() == rename.target.to_string_lossy().to_lowercase()
+}
+
+/// Write `path` in single quotes, which preserve every byte but a single quote itself.
+fn write_sh_quoted<W: Write>(out: &mut W, path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str());
+
+    #[cfg(not(unix))]
+    let lossy = path.to_string_lossy();
+    #[cfg(not(unix))]
+    let bytes = lossy.as_bytes();
+
+    out.write_all(b"'")?;
+
+    for (i, part) in bytes.split(|b| *b == b'\'').enumerate() {
+        if i > 0 {
+            out.write_all(b"'\\''")?;
+        }
+
+        out.write_all(part)?;
+    }
+
+    out.write_all(b"'")
+}
+
+/// Quote `path` for PowerShell, which also treats the typographic single quotes as quotes.
+fn ps_quoted(path: &Path) -> String {
+    let mut quoted = String::from("'");
+
+    for c in path.to_string_lossy().chars() {
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{201b}') {
+            quoted.push(c);
+        }
+
+        quoted.push(c);
+    }
+
+    quoted.push('\'');
+    quoted
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use convert_path::plan::Rename;
+
+    use crate::script::{ps_quoted, write_sh_quoted, Dialect, Script};
+
+    #[test]
+    fn test_sh_quoted() -> Result<(), Box<dyn std::error::Error>> {
+        let mut out = vec![];
+        write_sh_quoted(&mut out, Path::new("it's a $file"))?;
+
+        assert_eq!("'it'\\''s a $file'", String::from_utf8(out)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ps_quoted() {
+        assert_eq!(
+            "'C:\\Users\\It''s \u{2019}\u{2019}$File'",
+            ps_quoted(Path::new("C:\\Users\\It's \u{2019}$File"))
+        );
+    }
+
+    #[test]
+    fn test_sh_script() -> Result<(), Box<dyn std::error::Error>> {
+        let mut script = Script::new(Dialect::Sh, vec![], false)?;
+
+        script.rename(&Rename {
+            source: PathBuf::from("Some File"),
+            target: PathBuf::from("some_file"),
+        })?;
+        script.rename(&Rename {
+            source: PathBuf::from("other_file"),
+            target: PathBuf::from("other_file"),
+        })?;
+
+        let script = String::from_utf8(script.finish()?)?;
+
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.ends_with("}\n\nrename 'Some File' 'some_file'\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_powershell_script() -> Result<(), Box<dyn std::error::Error>> {
+        let mut script = Script::new(Dialect::PowerShell, vec![], false)?;
+
+        script.rename(&Rename {
+            source: PathBuf::from("Some Dir\\Some File"),
+            target: PathBuf::from("Some Dir\\some_file"),
+        })?;
+
+        let script = String::from_utf8(script.finish()?)?;
+
+        assert!(script.starts_with("\u{feff}# generated by ccpath"));
+        assert!(script.contains("Test-Path -LiteralPath $Target -PathType Container"));
+        assert!(script
+            .ends_with("}\r\n\r\nRename-Path 'Some Dir\\Some File' 'Some Dir\\some_file'\r\n"));
+
+        Ok(())
+    }
+}
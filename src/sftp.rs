@@ -0,0 +1,233 @@
+//! Renames files on a server over SFTP, given as '--remote sftp://[user@]host[:port]/path'.
+//!
+//! The server must already be listed in '~/.ssh/known_hosts', and authentication is tried
+//! with the ssh agent before the default private keys in '~/.ssh'.
+
+use std::env;
+use std::io;
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+
+use ssh2::{CheckResult, KnownHostFileKind, Session, Sftp};
+
+use crate::remote::Remote;
+
+const DEFAULT_PORT: u16 = 22;
+
+/// The keys tried, in order, when the agent cannot authenticate.
+const DEFAULT_KEYS: [&str; 3] = ["id_ed25519", "id_ecdsa", "id_rsa"];
+
+/// A server and the directory on it under which files are renamed.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Location {
+    user: Option<String>,
+    host: String,
+    port: u16,
+    root: PathBuf,
+}
+
+impl Location {
+    pub fn parse(url: &str) -> Result<Location, String> {
+        let rest = url
+            .strip_prefix("sftp://")
+            .ok_or_else(|| format!("'{}' is not an sftp:// url", url))?;
+
+        let (authority, root) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, ""),
+        };
+
+        let (user, address) = match authority.rsplit_once('@') {
+            Some((user, address)) => (Some(String::from(user)), address),
+            None => (None, authority),
+        };
+
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) => match port.parse() {
+                Ok(port) => (host, port),
+                Err(_) => return Err(format!("'{}' has an invalid port", url)),
+            },
+            None => (address, DEFAULT_PORT),
+        };
+
+        if host.is_empty() {
+            return Err(format!("'{}' does not name a host", url));
+        }
+
+        Ok(Location {
+            user,
+            host: String::from(host),
+            port,
+            // without a path the login directory is converted
+            root: PathBuf::from(if root.is_empty() { "." } else { root }),
+        })
+    }
+}
+
+pub struct SftpRemote {
+    root: PathBuf,
+    sftp: Sftp,
+
+    /// Kept alive for as long as `sftp` is used.
+    _session: Session,
+}
+
+impl SftpRemote {
+    pub fn connect(location: Location) -> io::Result<SftpRemote> {
+        let mut session = Session::new()?;
+        session.set_tcp_stream(TcpStream::connect((location.host.as_str(), location.port))?);
+        session.handshake()?;
+
+        let home = env::var_os("HOME")
+            .map(PathBuf::from)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME is not set"))?;
+
+        check_host_key(&session, &location, &home)?;
+
+        let user = match &location.user {
+            Some(user) => user.clone(),
+            None => env::var("USER").map_err(|_| {
+                io::Error::new(io::ErrorKind::NotFound, "no user was given and USER is not set")
+            })?,
+        };
+
+        authenticate(&session, &user, &home);
+
+        if !session.authenticated() {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("could not authenticate as '{}'", user),
+            ));
+        }
+
+        Ok(SftpRemote {
+            root: location.root,
+            sftp: session.sftp()?,
+            _session: session,
+        })
+    }
+
+    fn walk(&self, dir: &Path, entries: &mut Vec<PathBuf>) -> io::Result<()> {
+        for (path, stat) in self.sftp.readdir(dir)? {
+            // entries are stat'd without following symlinks, so links are never descended
+            if stat.is_dir() {
+                self.walk(&path, entries)?;
+            }
+
+            if let Ok(relative) = path.strip_prefix(&self.root) {
+                entries.push(relative.to_path_buf());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn create_all(&self, dir: &Path) -> io::Result<()> {
+        if dir.as_os_str().is_empty() || self.sftp.lstat(dir).is_ok() {
+            return Ok(());
+        }
+
+        if let Some(parent) = dir.parent() {
+            self.create_all(parent)?;
+        }
+
+        Ok(self.sftp.mkdir(dir, 0o755)?)
+    }
+}
+
+impl Remote for SftpRemote {
+    fn list(&mut self) -> io::Result<Vec<PathBuf>> {
+        let mut entries = vec![];
+        self.walk(&self.root, &mut entries)?;
+
+        Ok(entries)
+    }
+
+    fn exists(&mut self, path: &Path) -> io::Result<bool> {
+        match self.sftp.lstat(&self.root.join(path)) {
+            Ok(_) => Ok(true),
+            Err(err) => match io::Error::from(err) {
+                err if err.kind() == io::ErrorKind::NotFound => Ok(false),
+                err => Err(err),
+            },
+        }
+    }
+
+    fn rename(&mut self, source: &Path, target: &Path) -> io::Result<()> {
+        let target = self.root.join(target);
+
+        if let Some(parent) = target.parent() {
+            self.create_all(parent)?;
+        }
+
+        Ok(self.sftp.rename(&self.root.join(source), &target, None)?)
+    }
+}
+
+fn check_host_key(session: &Session, location: &Location, home: &Path) -> io::Result<()> {
+    let mut known_hosts = session.known_hosts()?;
+    known_hosts.read_file(&home.join(".ssh/known_hosts"), KnownHostFileKind::OpenSSH)?;
+
+    let (key, _) = session
+        .host_key()
+        .ok_or_else(|| io::Error::other("the server did not send a host key"))?;
+
+    match known_hosts.check_port(&location.host, location.port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::NotFound => Err(io::Error::other(format!(
+            "'{}' is not a known host, connect with ssh once to add it",
+            location.host
+        ))),
+        CheckResult::Mismatch => Err(io::Error::other(format!(
+            "the host key of '{}' does not match the one in known_hosts",
+            location.host
+        ))),
+        CheckResult::Failure => Err(io::Error::other("could not check the host key")),
+    }
+}
+
+/// Try the agent and then each of the default keys until one is accepted.
+fn authenticate(session: &Session, user: &str, home: &Path) {
+    if session.userauth_agent(user).is_ok() {
+        return;
+    }
+
+    for key in DEFAULT_KEYS.iter() {
+        let key = home.join(".ssh").join(key);
+
+        if key.exists() && session.userauth_pubkey_file(user, None, &key, None).is_ok() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use crate::sftp::Location;
+
+    #[test]
+    fn test_location_parse() {
+        assert_eq!(
+            Ok(Location {
+                user: Some(String::from("user")),
+                host: String::from("host"),
+                port: 2222,
+                root: PathBuf::from("/srv/Some Dir"),
+            }),
+            Location::parse("sftp://user@host:2222/srv/Some Dir")
+        );
+        assert_eq!(
+            Ok(Location {
+                user: None,
+                host: String::from("host"),
+                port: 22,
+                root: PathBuf::from("."),
+            }),
+            Location::parse("sftp://host")
+        );
+        assert!(Location::parse("sftp://host:port/").is_err());
+        assert!(Location::parse("sftp:///srv").is_err());
+    }
+}
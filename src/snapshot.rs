@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+
+use convert_path::plan::RenamePlan;
+
+/// Identifies a filesystem entry independently of the path used to reach it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Identity {
+    #[cfg(unix)]
+    dev: u64,
+    #[cfg(unix)]
+    ino: u64,
+
+    #[cfg(not(unix))]
+    len: u64,
+    /// Nanoseconds since the epoch, or 0 when the platform cannot report it.
+    #[cfg(not(unix))]
+    modified: u64,
+}
+
+impl Identity {
+    /// The identity of the entry currently at `path` without following symlinks.
+    pub fn of(path: &Path) -> Option<Identity> {
+        fs::symlink_metadata(path)
+            .ok()
+            .map(|metadata| Identity::from(&metadata))
+    }
+
+    #[cfg(unix)]
+    pub fn to_bytes(self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[..8].copy_from_slice(&self.dev.to_le_bytes());
+        bytes[8..].copy_from_slice(&self.ino.to_le_bytes());
+
+        bytes
+    }
+
+    #[cfg(not(unix))]
+    pub fn to_bytes(self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[..8].copy_from_slice(&self.len.to_le_bytes());
+        bytes[8..].copy_from_slice(&self.modified.to_le_bytes());
+
+        bytes
+    }
+
+    #[cfg(unix)]
+    pub fn from_bytes(bytes: [u8; 16]) -> Identity {
+        let (dev, ino) = bytes.split_at(8);
+
+        Identity {
+            dev: u64::from_le_bytes(dev.try_into().unwrap()),
+            ino: u64::from_le_bytes(ino.try_into().unwrap()),
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn from_bytes(bytes: [u8; 16]) -> Identity {
+        let (len, modified) = bytes.split_at(8);
+
+        Identity {
+            len: u64::from_le_bytes(len.try_into().unwrap()),
+            modified: u64::from_le_bytes(modified.try_into().unwrap()),
+        }
+    }
+}
+
+impl From<&Metadata> for Identity {
+    #[cfg(unix)]
+    fn from(metadata: &Metadata) -> Self {
+        use std::os::unix::fs::MetadataExt;
+
+        Identity {
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn from(metadata: &Metadata) -> Self {
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+            .map_or(0, |modified| modified.as_nanos() as u64);
+
+        Identity {
+            len: metadata.len(),
+            modified,
+        }
+    }
+}
+
+/// How an entry differs from when its snapshot was taken.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Drift {
+    /// Nothing exists at the path anymore.
+    Vanished,
+
+    /// A different entry now exists at the path.
+    Replaced,
+}
+
+/// The identity of every source in a plan at the time it was made.
+#[derive(Default)]
+pub struct Snapshot {
+    entries: HashMap<PathBuf, Identity>,
+}
+
+impl Snapshot {
+    pub fn take(plan: &RenamePlan) -> Snapshot {
+        let entries = plan
+            .into_iter()
+            .filter_map(|rename| Some((rename.source.clone(), Identity::of(&rename.source)?)))
+            .collect();
+
+        Snapshot { entries }
+    }
+
+    /// Record the identity `path` had when it was planned.
+    pub fn insert(&mut self, path: PathBuf, identity: Identity) {
+        self.entries.insert(path, identity);
+    }
+
+    /// Check that `path` still refers to the same entry it did when the snapshot was taken.
+    pub fn verify(&self, path: &Path) -> Result<(), Drift> {
+        let current = match Identity::of(path) {
+            Some(identity) => identity,
+            None => return Err(Drift::Vanished),
+        };
+
+        match self.entries.get(path) {
+            Some(identity) if *identity != current => Err(Drift::Replaced),
+            Some(_) => Ok(()),
+            None => Err(Drift::Vanished),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use convert_path::plan::RenamePlan;
+
+    use crate::snapshot::{Drift, Snapshot};
+
+    #[cfg(unix)]
+    #[test]
+    fn test_verify() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+
+        let unchanged = dir.path().join("Unchanged File");
+        let vanished = dir.path().join("Vanished File");
+        let replaced = dir.path().join("Replaced File");
+
+        for path in [&unchanged, &vanished, &replaced] {
+            fs::write(path, "")?;
+        }
+
+        let mut plan = RenamePlan::new();
+
+        for path in [&unchanged, &vanished, &replaced] {
+            plan.push(path.clone(), PathBuf::from("target"));
+        }
+
+        let snapshot = Snapshot::take(&plan);
+
+        // write the replacement before removing the original so it cannot reuse the inode
+        fs::write(dir.path().join("replacement"), "")?;
+        fs::rename(dir.path().join("replacement"), &replaced)?;
+        fs::remove_file(&vanished)?;
+
+        assert_eq!(Ok(()), snapshot.verify(&unchanged));
+        assert_eq!(Err(Drift::Vanished), snapshot.verify(&vanished));
+        assert_eq!(Err(Drift::Replaced), snapshot.verify(&replaced));
+
+        Ok(())
+    }
+}
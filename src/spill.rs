@@ -0,0 +1,413 @@
+//! Plans too large to comfortably hold in memory are spilled to a temporary file and
+//! read back one batch at a time, so memory use is bounded by the batch size rather
+//! than by the size of the tree being renamed.
+
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use convert_path::plan::{self, Collision, Rename, RenamePlan};
+
+use crate::snapshot::{Identity, Snapshot};
+
+/// A plan either held entirely in memory or spilled to disk, which is always read in
+/// batches alongside the snapshot taken when each batch was planned.
+pub enum Batches {
+    InMemory(RenamePlan, Snapshot),
+    Spilled(SpilledPlan),
+}
+
+impl Batches {
+    /// Find every target which more than one distinct source would be renamed to, ignoring
+    /// the case of targets if `ignore_case`.
+    pub fn collisions(&self, ignore_case: bool) -> io::Result<Vec<Collision>> {
+        match self {
+            Batches::InMemory(plan, _) if ignore_case => Ok(plan.collisions_ignoring_case()),
+            Batches::InMemory(plan, _) => Ok(plan.collisions()),
+            Batches::Spilled(spilled) => spilled.collisions(ignore_case),
+        }
+    }
+
+    /// The number of renames planned, including those leaving a path unchanged.
+    pub fn planned(&self) -> u64 {
+        match self {
+            Batches::InMemory(plan, _) => plan.len() as u64,
+            Batches::Spilled(spilled) => spilled.len,
+        }
+    }
+
+    /// Drop every rename of the given sources from the plan.
+    pub fn exclude(&mut self, sources: HashSet<PathBuf>) {
+        match self {
+            Batches::InMemory(plan, _) => plan.retain(|rename| !sources.contains(&rename.source)),
+            Batches::Spilled(spilled) => spilled.excluded = sources,
+        }
+    }
+
+    /// Call `f` on each batch of the plan in order.
+    pub fn for_each<F: FnMut(&RenamePlan, &Snapshot)>(&self, mut f: F) -> io::Result<()> {
+        match self {
+            Batches::InMemory(plan, snapshot) => {
+                f(plan, snapshot);
+
+                Ok(())
+            }
+            Batches::Spilled(spilled) => spilled.for_each(f),
+        }
+    }
+}
+
+/// A plan written to an anonymous temporary file along with the identity of each source
+/// at the time it was planned.
+pub struct SpilledPlan {
+    file: File,
+    len: u64,
+    batch_len: usize,
+    excluded: HashSet<PathBuf>,
+}
+
+impl SpilledPlan {
+    /// Create an empty spilled plan which will be read back `batch_len` renames at a time.
+    pub fn new(batch_len: usize) -> io::Result<SpilledPlan> {
+        Ok(SpilledPlan {
+            file: tempfile::tempfile()?,
+            len: 0,
+            batch_len: batch_len.max(1),
+            excluded: HashSet::new(),
+        })
+    }
+
+    /// Append every rename in `plan`, recording the current identity of its source.
+    pub fn append(&mut self, plan: &RenamePlan) -> io::Result<()> {
+        self.file.seek(SeekFrom::End(0))?;
+
+        let mut writer = BufWriter::new(&self.file);
+
+        for rename in plan {
+            write_path(&mut writer, &rename.source)?;
+            write_path(&mut writer, &rename.target)?;
+
+            match Identity::of(&rename.source) {
+                Some(identity) => {
+                    writer.write_all(&[1])?;
+                    writer.write_all(&identity.to_bytes())?;
+                }
+                None => writer.write_all(&[0])?,
+            }
+        }
+
+        writer.flush()?;
+        self.len += plan.len() as u64;
+
+        Ok(())
+    }
+
+    fn reader(&self) -> io::Result<BufReader<&File>> {
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(0))?;
+
+        Ok(BufReader::new(file))
+    }
+
+    fn for_each<F: FnMut(&RenamePlan, &Snapshot)>(&self, mut f: F) -> io::Result<()> {
+        let mut reader = self.reader()?;
+        let mut remaining = self.len;
+
+        while remaining > 0 {
+            let mut plan = RenamePlan::new();
+            let mut snapshot = Snapshot::default();
+
+            while remaining > 0 && plan.len() < self.batch_len {
+                let (rename, identity) = read_record(&mut reader)?;
+                remaining -= 1;
+
+                if self.excluded.contains(&rename.source) {
+                    continue;
+                }
+
+                if let Some(identity) = identity {
+                    snapshot.insert(rename.source.clone(), identity);
+                }
+
+                plan.push(rename.source, rename.target);
+            }
+
+            f(&plan, &snapshot);
+        }
+
+        Ok(())
+    }
+
+    /// Find collisions with an external merge sort on the targets, so only a single batch
+    /// and the renames sharing one target are ever held in memory at once.
+    ///
+    /// Collisions are returned in the same order as [`RenamePlan::collisions`], and with
+    /// `ignore_case` the same as [`RenamePlan::collisions_ignoring_case`].
+    fn collisions(&self, ignore_case: bool) -> io::Result<Vec<Collision>> {
+        let mut reader = self.reader()?;
+        let mut runs = vec![];
+        let mut run: Vec<RunEntry> = vec![];
+
+        for seq in 0..self.len {
+            let (rename, _) = read_record(&mut reader)?;
+            let key = if ignore_case {
+                plan::fold_case(&rename.target)
+            } else {
+                rename.target.clone()
+            };
+
+            run.push((key, seq, rename.source, rename.target));
+
+            if run.len() >= self.batch_len {
+                runs.push(write_run(&mut run)?);
+            }
+        }
+
+        if !run.is_empty() {
+            runs.push(write_run(&mut run)?);
+        }
+
+        let mut readers: Vec<BufReader<File>> = runs.into_iter().map(BufReader::new).collect();
+        let mut heap = BinaryHeap::new();
+
+        for (i, reader) in readers.iter_mut().enumerate() {
+            if let Some(entry) = read_run_entry(reader)? {
+                heap.push(Reverse((entry, i)));
+            }
+        }
+
+        let mut collisions: Vec<(u64, Collision)> = vec![];
+        let mut group: Option<Group> = None;
+
+        while let Some(Reverse(((key, seq, source, target), i))) = heap.pop() {
+            if let Some(entry) = read_run_entry(&mut readers[i])? {
+                heap.push(Reverse((entry, i)));
+            }
+
+            // the first entry of each group comes first in the plan, so its target is kept
+            match &mut group {
+                Some((group_key, _, sources)) if *group_key == key => sources.push((seq, source)),
+                _ => {
+                    collisions.extend(group.take().and_then(collision));
+                    group = Some((key, target, vec![(seq, source)]));
+                }
+            }
+        }
+
+        collisions.extend(group.and_then(collision));
+        collisions.sort_by_key(|(seq, _)| *seq);
+
+        Ok(collisions.into_iter().map(|(_, collision)| collision).collect())
+    }
+}
+
+/// The renames sharing a key: the key, the target of the first of them, and where each of
+/// them appears in the plan along with its source.
+type Group = (PathBuf, PathBuf, Vec<(u64, PathBuf)>);
+
+/// Build the collision for every rename to `target`, keyed by where it first appears in the plan.
+fn collision((_, target, sources): Group) -> Option<(u64, Collision)> {
+    let first = sources.first()?.0;
+    let mut distinct: Vec<PathBuf> = vec![];
+
+    for (_, source) in sources {
+        if !distinct.contains(&source) {
+            distinct.push(source);
+        }
+    }
+
+    if distinct.len() > 1 {
+        Some((
+            first,
+            Collision {
+                target,
+                sources: distinct,
+            },
+        ))
+    } else {
+        None
+    }
+}
+
+/// A rename in a run of the merge sort: the key its target is compared by, where it appears
+/// in the plan, its source, and its target.
+type RunEntry = (PathBuf, u64, PathBuf, PathBuf);
+
+/// Sort `run` and write it to a new temporary file, leaving `run` empty.
+fn write_run(run: &mut Vec<RunEntry>) -> io::Result<File> {
+    run.sort();
+
+    let mut file = tempfile::tempfile()?;
+    let mut writer = BufWriter::new(&file);
+
+    for (key, seq, source, target) in run.drain(..) {
+        write_path(&mut writer, &key)?;
+        writer.write_all(&seq.to_le_bytes())?;
+        write_path(&mut writer, &source)?;
+        write_path(&mut writer, &target)?;
+    }
+
+    writer.flush()?;
+    drop(writer);
+
+    file.seek(SeekFrom::Start(0))?;
+
+    Ok(file)
+}
+
+fn read_run_entry<R: Read>(reader: &mut R) -> io::Result<Option<RunEntry>> {
+    let key = match read_path(reader) {
+        Ok(key) => key,
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    };
+
+    let mut seq = [0; 8];
+    reader.read_exact(&mut seq)?;
+
+    let source = read_path(reader)?;
+    let target = read_path(reader)?;
+
+    Ok(Some((key, u64::from_le_bytes(seq), source, target)))
+}
+
+fn read_record<R: Read>(reader: &mut R) -> io::Result<(Rename, Option<Identity>)> {
+    let source = read_path(reader)?;
+    let target = read_path(reader)?;
+
+    let mut flag = [0; 1];
+    reader.read_exact(&mut flag)?;
+
+    let identity = if flag[0] == 1 {
+        let mut bytes = [0; 16];
+        reader.read_exact(&mut bytes)?;
+
+        Some(Identity::from_bytes(bytes))
+    } else {
+        None
+    };
+
+    Ok((Rename { source, target }, identity))
+}
+
+fn write_path<W: Write>(writer: &mut W, path: &Path) -> io::Result<()> {
+    let bytes = path_bytes(path);
+
+    writer.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    writer.write_all(&bytes)
+}
+
+fn read_path<R: Read>(reader: &mut R) -> io::Result<PathBuf> {
+    let mut len = [0; 8];
+    reader.read_exact(&mut len)?;
+
+    let mut bytes = vec![0; u64::from_le_bytes(len) as usize];
+    reader.read_exact(&mut bytes)?;
+
+    Ok(path_from_bytes(bytes))
+}
+
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+
+    Cow::Borrowed(path.as_os_str().as_bytes())
+}
+
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> Cow<'_, [u8]> {
+    match path.to_string_lossy() {
+        Cow::Borrowed(s) => Cow::Borrowed(s.as_bytes()),
+        Cow::Owned(s) => Cow::Owned(s.into_bytes()),
+    }
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStringExt;
+
+    PathBuf::from(OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use convert_path::plan::RenamePlan;
+
+    use crate::spill::SpilledPlan;
+
+    fn plan(renames: &[(&str, &str)]) -> RenamePlan {
+        let mut plan = RenamePlan::new();
+
+        for (source, target) in renames {
+            plan.push(PathBuf::from(source), PathBuf::from(target));
+        }
+
+        plan
+    }
+
+    #[test]
+    fn test_collisions_match_in_memory() -> Result<(), Box<dyn std::error::Error>> {
+        let plan = plan(&[
+            ("Other File", "other_file"),
+            ("Some File", "some_file"),
+            ("Another File", "another_file"),
+            ("Some File", "some_file"),
+            ("Another-File", "another_file"),
+            ("Some-File", "some_file"),
+        ]);
+
+        let mut spilled = SpilledPlan::new(2)?;
+        spilled.append(&plan)?;
+
+        assert_eq!(plan.collisions(), spilled.collisions(false)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_collisions_ignoring_case_match_in_memory() -> Result<(), Box<dyn std::error::Error>> {
+        let plan = plan(&[
+            ("Some File", "Some_File"),
+            ("Other File", "other_file"),
+            ("some file", "some_file"),
+            ("SOME FILE", "SOME_FILE"),
+        ]);
+
+        let mut spilled = SpilledPlan::new(2)?;
+        spilled.append(&plan)?;
+
+        assert_eq!(1, plan.collisions_ignoring_case().len());
+        assert_eq!(plan.collisions_ignoring_case(), spilled.collisions(true)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_for_each_batches() -> Result<(), Box<dyn std::error::Error>> {
+        let first = plan(&[("A File", "a_file"), ("B File", "b_file")]);
+        let second = plan(&[("C File", "c_file")]);
+
+        let mut spilled = SpilledPlan::new(2)?;
+        spilled.append(&first)?;
+        spilled.append(&second)?;
+        spilled.excluded.insert(PathBuf::from("B File"));
+
+        let mut batches = vec![];
+        spilled.for_each(|plan, _| batches.push(plan.clone()))?;
+
+        assert_eq!(vec![plan(&[("A File", "a_file"), ("C File", "c_file")])], batches);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,323 @@
+//! Timing breakdowns reported at the end of a run given '--stats', so slow runs can be
+//! attributed to either the filesystem or conversion.
+//!
+//! Given '--stats-out', the breakdown is also written as JSON along with the outcome of
+//! every rename, the conventions the converted names were written in, and the most common
+//! reasons renames failed, so runs can be tracked over time without scraping the output.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use convert_path::detect_convention;
+use serde::{Serialize, Serializer};
+
+static STATS: OnceLock<Stats> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Phase {
+    /// Reading directory entries.
+    Walk,
+
+    /// Converting names and checking where they would be moved.
+    Convert,
+
+    /// Performing the planned renames.
+    Rename,
+}
+
+impl Phase {
+    const ALL: [Phase; 3] = [Phase::Walk, Phase::Convert, Phase::Rename];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Phase::Walk => "walk",
+            Phase::Convert => "convert",
+            Phase::Rename => "rename",
+        }
+    }
+
+    fn unit(self) -> &'static str {
+        match self {
+            Phase::Walk => "entries",
+            Phase::Convert => "paths",
+            Phase::Rename => "renames",
+        }
+    }
+}
+
+#[derive(Default)]
+struct Timer {
+    nanos: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Timer {
+    fn add(&self, elapsed: Duration, count: u64) {
+        self.nanos.fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+        self.count.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::Relaxed))
+    }
+}
+
+/// The number of failure reasons written to '--stats-out'.
+const TOP_FAILURES_LEN: usize = 5;
+
+struct Stats {
+    started: Instant,
+    phases: [Timer; 3],
+
+    /// Time each worker thread spent converting, indexed by its rayon thread index.
+    busy: Vec<AtomicU64>,
+
+    /// Whether the breakdown is printed at the end of the run.
+    print: bool,
+
+    /// Where the breakdown is written as JSON at the end of the run.
+    out: Option<PathBuf>,
+
+    /// The number of converted names written in each convention.
+    conventions: Mutex<HashMap<&'static str, u64>>,
+
+    /// The number of renames which failed for each reason.
+    failures: Mutex<HashMap<String, u64>>,
+}
+
+/// Start collecting stats, which must happen after the thread pool is configured.
+pub fn enable(print: bool, out: Option<PathBuf>) {
+    let _ = STATS.set(Stats {
+        started: Instant::now(),
+        phases: Default::default(),
+        busy: (0..rayon::current_num_threads())
+            .map(|_| AtomicU64::new(0))
+            .collect(),
+        print,
+        out,
+        conventions: Mutex::default(),
+        failures: Mutex::default(),
+    });
+}
+
+/// Start timing an operation, or do nothing when stats are not being collected.
+pub fn start() -> Option<Instant> {
+    STATS.get().map(|_| Instant::now())
+}
+
+/// Record `count` operations of `phase` which took place since `started`.
+pub fn record(phase: Phase, started: Option<Instant>, count: u64) {
+    if let (Some(stats), Some(started)) = (STATS.get(), started) {
+        stats.phases[phase as usize].add(started.elapsed(), count);
+    }
+}
+
+/// Record time the current worker thread spent busy since `started`.
+pub fn record_busy(started: Option<Instant>) {
+    let (stats, started) = match (STATS.get(), started) {
+        (Some(stats), Some(started)) => (stats, started),
+        _ => return,
+    };
+
+    if let Some(busy) = rayon::current_thread_index().and_then(|i| stats.busy.get(i)) {
+        busy.fetch_add(started.elapsed().as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Record the convention the name of `source` is written in.
+pub fn record_source(source: &Path) {
+    if let Some(stats) = STATS.get() {
+        let name = detect_convention(source).map_or("unknown", |convention| convention.name());
+
+        if let Ok(mut conventions) = stats.conventions.lock() {
+            *conventions.entry(name).or_default() += 1;
+        }
+    }
+}
+
+/// Record a rename which failed because of `reason`.
+pub fn record_failure(reason: &str) {
+    if let Some(stats) = STATS.get() {
+        if let Ok(mut failures) = stats.failures.lock() {
+            *failures.entry(String::from(reason)).or_default() += 1;
+        }
+    }
+}
+
+/// Print the breakdown of the run so far and write it along with the count of each outcome
+/// to '--stats-out', if stats are being collected.
+pub fn report(outcomes: &[(&str, usize)]) {
+    let stats = match STATS.get() {
+        Some(stats) => stats,
+        None => return,
+    };
+
+    if stats.print {
+        print(stats);
+    }
+
+    if let Some(out) = &stats.out {
+        let result = File::create(out)
+            .and_then(|file| write_json(stats, outcomes, BufWriter::new(file)));
+
+        if let Err(err) = result {
+            warning!(
+                "{}",
+                tr!(
+                    "stats-write-failed",
+                    path = out.display(),
+                    reason = err.to_string()
+                )
+            );
+        }
+    }
+}
+
+fn print(stats: &Stats) {
+    for phase in Phase::ALL {
+        let timer = &stats.phases[phase as usize];
+        let elapsed = timer.elapsed();
+        let count = timer.count.load(Ordering::Relaxed);
+
+        info!(
+            "{}",
+            tr!(
+                "stats-phase",
+                phase = phase.as_str(),
+                seconds = format!("{:.3}", elapsed.as_secs_f64()),
+                count = count,
+                unit = phase.unit(),
+                rate = format!("{:.0}", rate(count, elapsed))
+            )
+        );
+
+        if phase == Phase::Convert {
+            for (i, busy) in stats.busy.iter().enumerate() {
+                let busy = Duration::from_nanos(busy.load(Ordering::Relaxed));
+
+                info!(
+                    "{}",
+                    tr!(
+                        "stats-thread",
+                        thread = i,
+                        seconds = format!("{:.3}", busy.as_secs_f64()),
+                        utilization = format!("{:.0}", utilization(busy, elapsed))
+                    )
+                );
+            }
+        }
+    }
+
+    info!(
+        "{}",
+        tr!(
+            "stats-total",
+            seconds = format!("{:.3}", stats.started.elapsed().as_secs_f64())
+        )
+    );
+}
+
+/// The stats written to '--stats-out'.
+#[derive(Serialize)]
+struct Report<'a> {
+    outcomes: Entries<&'a str, usize>,
+    conventions: Entries<String, u64>,
+    duration_seconds: f64,
+    phases: Entries<&'static str, PhaseReport>,
+    top_failure_reasons: Vec<FailureReport>,
+}
+
+#[derive(Serialize)]
+struct PhaseReport {
+    seconds: f64,
+    count: u64,
+}
+
+#[derive(Serialize)]
+struct FailureReport {
+    reason: String,
+    count: u64,
+}
+
+/// Entries written as a JSON object in the order they are in, rather than sorted by key.
+pub struct Entries<K, V>(pub Vec<(K, V)>);
+
+impl<K: Serialize, V: Serialize> Serialize for Entries<K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(key, value)| (key, value)))
+    }
+}
+
+fn write_json<W: Write>(stats: &Stats, outcomes: &[(&str, usize)], mut out: W) -> io::Result<()> {
+    let phases = Phase::ALL
+        .iter()
+        .map(|phase| {
+            let timer = &stats.phases[*phase as usize];
+            let report = PhaseReport {
+                seconds: millis(timer.elapsed()),
+                count: timer.count.load(Ordering::Relaxed),
+            };
+
+            (phase.as_str(), report)
+        })
+        .collect();
+
+    let mut failures = by_count(&stats.failures);
+    failures.truncate(TOP_FAILURES_LEN);
+
+    let report = Report {
+        outcomes: Entries(outcomes.to_vec()),
+        conventions: Entries(by_count(&stats.conventions)),
+        duration_seconds: millis(stats.started.elapsed()),
+        phases: Entries(phases),
+        top_failure_reasons: failures
+            .into_iter()
+            .map(|(reason, count)| FailureReport { reason, count })
+            .collect(),
+    };
+
+    serde_json::to_writer_pretty(&mut out, &report)?;
+    writeln!(out)?;
+
+    out.flush()
+}
+
+/// `duration` in seconds, to the millisecond.
+fn millis(duration: Duration) -> f64 {
+    duration.as_millis() as f64 / 1000.0
+}
+
+/// The entries of `counts` from the most to the least common, ties ordered by name.
+fn by_count<K: AsRef<str>>(counts: &Mutex<HashMap<K, u64>>) -> Vec<(String, u64)> {
+    let mut sorted: Vec<(String, u64)> = match counts.lock() {
+        Ok(counts) => counts
+            .iter()
+            .map(|(key, count)| (String::from(key.as_ref()), *count))
+            .collect(),
+        Err(_) => vec![],
+    };
+
+    sorted.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    sorted
+}
+
+fn rate(count: u64, elapsed: Duration) -> f64 {
+    if elapsed.is_zero() {
+        0.0
+    } else {
+        count as f64 / elapsed.as_secs_f64()
+    }
+}
+
+fn utilization(busy: Duration, elapsed: Duration) -> f64 {
+    if elapsed.is_zero() {
+        0.0
+    } else {
+        (busy.as_secs_f64() / elapsed.as_secs_f64() * 100.0).min(100.0)
+    }
+}
@@ -0,0 +1,102 @@
+//! The tokens '--template' fills for each renamed path, besides the converted stem.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use convert_path::template::Template;
+
+/// Every token a template may contain.
+#[cfg(not(feature = "exif"))]
+pub const TOKENS: &[&str] = &["stem", "n", "date", "parent"];
+
+/// Every token a template may contain, including those filled from the metadata of media
+/// files.
+#[cfg(feature = "exif")]
+pub const TOKENS: &[&str] = &["stem", "n", "date", "parent", "taken", "camera"];
+
+/// The tokens filled from the metadata of media files, which are only known when built with
+/// the 'exif' feature.
+const MEDIA_TOKENS: &[&str] = &["taken", "camera"];
+
+/// Parse a template, failing if it contains a token which can't be filled.
+pub fn parse(s: &str) -> Result<Template, String> {
+    let template = Template::parse(s)?;
+
+    if let Some(token) = template.tokens().find(|token| !TOKENS.contains(token)) {
+        if MEDIA_TOKENS.contains(&token) {
+            return Err(format!(
+                "the token '{{{}}}' needs ccpath built with the 'exif' feature",
+                token
+            ));
+        }
+
+        return Err(format!(
+            "unknown token '{{{}}}', expected one of {}",
+            token,
+            TOKENS
+                .iter()
+                .map(|token| format!("'{{{}}}'", token))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ));
+    }
+
+    Ok(template)
+}
+
+/// The date of `time` in UTC, like '2024-05-01'.
+pub fn date(time: SystemTime) -> String {
+    let days = match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => (since.as_secs() / 86_400) as i64,
+        Err(before) => -(before.duration().as_secs().div_ceil(86_400) as i64),
+    };
+
+    let (year, month, day) = civil(days);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// The year, month, and day `days` after 1970-01-01 in the proleptic gregorian calendar.
+fn civil(days: i64) -> (i64, u32, u32) {
+    // shifted so that eras of 400 years start on the 1st of march, putting leap days last
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::template::{date, parse};
+
+    #[test]
+    fn test_date() {
+        let at = |secs: u64| date(UNIX_EPOCH + Duration::from_secs(secs));
+
+        assert_eq!("1970-01-01", at(0));
+        assert_eq!("2000-02-29", at(951_782_400));
+        assert_eq!("2024-12-31", at(1_735_689_599));
+        assert_eq!("1969-12-31", date(UNIX_EPOCH - Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(parse("{parent}_{stem}_{n:03}_{date}").is_ok());
+        assert!(parse("{stem}_{bogus}").unwrap_err().contains("'{bogus}'"));
+        assert_eq!(cfg!(feature = "exif"), parse("{taken}_{camera}").is_ok());
+    }
+}
@@ -0,0 +1,156 @@
+//! Paces renames for '--throttle', and the copies renames fall back to across devices for
+//! '--throttle-bandwidth', so that a migration doesn't overwhelm a fragile NAS appliance or a
+//! rate-limited remote backend.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The size of the chunks a paced copy reads and writes at once.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// How fast renames and copies may go, without any limit by default.
+#[derive(Debug, Default)]
+pub struct Throttle {
+    /// The least time between the starts of two renames.
+    interval: Option<Duration>,
+
+    /// The most bytes copied per second.
+    bandwidth: Option<u64>,
+
+    /// When the next rename may start.
+    next: Mutex<Option<Instant>>,
+}
+
+impl Throttle {
+    /// Allow at most `per_second` renames a second, and copying at most `bandwidth` bytes a
+    /// second.
+    pub fn new(per_second: Option<usize>, bandwidth: Option<u64>) -> Throttle {
+        Throttle {
+            interval: per_second.map(|n| Duration::from_secs_f64(1.0 / n as f64)),
+            bandwidth,
+            next: Mutex::new(None),
+        }
+    }
+
+    /// Whether renames are paced.
+    pub fn is_pacing(&self) -> bool {
+        self.interval.is_some()
+    }
+
+    /// Wait until another rename may start, waiting in turn with every other thread doing so.
+    pub fn wait(&self) {
+        let interval = match self.interval {
+            Some(interval) => interval,
+            None => return,
+        };
+
+        let mut next = self
+            .next
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = Instant::now();
+
+        let start = match *next {
+            Some(at) if at > now => {
+                thread::sleep(at - now);
+                at
+            }
+            _ => now,
+        };
+
+        *next = Some(start + interval);
+    }
+
+    /// Copy the content and permissions of the file `source` to `target`, no faster than the
+    /// bandwidth allowed.
+    ///
+    /// Only an unpaced copy carries over the alternate data streams of a file on Windows.
+    pub fn copy_file(&self, source: &Path, target: &Path) -> io::Result<u64> {
+        let bandwidth = match self.bandwidth {
+            Some(bandwidth) => bandwidth,
+            None => return fs::copy(source, target),
+        };
+
+        let mut reader = File::open(source)?;
+        let mut writer = File::create(target)?;
+
+        let copied = copy_paced(&mut reader, &mut writer, bandwidth)?;
+        fs::set_permissions(target, reader.metadata()?.permissions())?;
+
+        Ok(copied)
+    }
+}
+
+/// Copy everything `reader` gives to `writer`, sleeping whenever it is ahead of `bandwidth`
+/// bytes a second.
+fn copy_paced<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    bandwidth: u64,
+) -> io::Result<u64> {
+    let started = Instant::now();
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut copied = 0;
+
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+
+        writer.write_all(&buf[..n])?;
+        copied += n as u64;
+
+        let due = Duration::from_secs_f64(copied as f64 / bandwidth as f64);
+
+        if let Some(ahead) = due.checked_sub(started.elapsed()) {
+            thread::sleep(ahead);
+        }
+    }
+
+    writer.flush()?;
+
+    Ok(copied)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use crate::throttle::{copy_paced, Throttle};
+
+    #[test]
+    fn test_wait() {
+        let throttle = Throttle::new(Some(50), None);
+        let started = Instant::now();
+
+        for _ in 0..6 {
+            throttle.wait();
+        }
+
+        // the first rename starts right away and each after it 20ms after the one before
+        assert!(started.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_copy_paced() -> Result<(), Box<dyn std::error::Error>> {
+        let content = vec![7u8; 100 * 1024];
+        let mut copied = vec![];
+        let started = Instant::now();
+
+        assert_eq!(
+            content.len() as u64,
+            copy_paced(&mut content.as_slice(), &mut copied, 1024 * 1024)?
+        );
+        assert_eq!(content, copied);
+        assert!(started.elapsed() >= Duration::from_millis(90));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,664 @@
+//! Reviews the plan interactively given the 'tui' subcommand: every rename is shown in a tree
+//! of the directories it is beneath, where it can be turned on or off and have its target
+//! edited before anything is renamed.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use convert_path::plan::{Rename, RenamePlan};
+
+use crate::snapshot::Identity;
+use crate::spill::Batches;
+
+/// A line of the tree, which is either a planned rename or a directory one is beneath.
+struct Row {
+    path: PathBuf,
+    depth: usize,
+
+    /// The index of the rename of `path`, if it is renamed itself.
+    rename: Option<usize>,
+
+    has_children: bool,
+}
+
+/// What the user decided to do with the plan.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Decision {
+    Execute,
+    Quit,
+}
+
+struct Review {
+    renames: Vec<Rename>,
+    enabled: Vec<bool>,
+    root: PathBuf,
+    rows: Vec<Row>,
+    collapsed: HashSet<usize>,
+
+    /// The index of the selected row among the visible ones.
+    cursor: usize,
+
+    /// The name being typed while a target is edited.
+    editing: Option<String>,
+    confirming: bool,
+    message: Option<String>,
+}
+
+impl Review {
+    fn new(plan: RenamePlan) -> Review {
+        let renames = plan.renames().to_vec();
+        let enabled = vec![true; renames.len()];
+
+        let changed: Vec<usize> = (0..renames.len())
+            .filter(|i| !renames[*i].is_noop())
+            .collect();
+
+        let mut root = changed
+            .first()
+            .and_then(|i| renames[*i].source.parent())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        for i in &changed {
+            while !renames[*i].source.starts_with(&root) {
+                root = root.parent().map(Path::to_path_buf).unwrap_or_default();
+            }
+        }
+
+        // paths are ordered by their components, so each directory comes right before
+        // everything beneath it
+        let mut tree: BTreeMap<PathBuf, Option<usize>> = BTreeMap::new();
+
+        for i in changed {
+            tree.insert(renames[i].source.clone(), Some(i));
+
+            for ancestor in renames[i].source.ancestors().skip(1) {
+                if ancestor == root || !ancestor.starts_with(&root) {
+                    break;
+                }
+
+                tree.entry(ancestor.to_path_buf()).or_insert(None);
+            }
+        }
+
+        let base = root.components().count();
+
+        let mut rows: Vec<Row> = tree
+            .into_iter()
+            .map(|(path, rename)| Row {
+                depth: path.components().count() - base - 1,
+                path,
+                rename,
+                has_children: false,
+            })
+            .collect();
+
+        for i in 1..rows.len() {
+            if rows[i].depth > rows[i - 1].depth {
+                rows[i - 1].has_children = true;
+            }
+        }
+
+        Review {
+            renames,
+            enabled,
+            root,
+            rows,
+            collapsed: HashSet::new(),
+            cursor: 0,
+            editing: None,
+            confirming: false,
+            message: None,
+        }
+    }
+
+    /// The index of every row which is not beneath a collapsed directory.
+    fn visible(&self) -> Vec<usize> {
+        let mut visible = vec![];
+        let mut hidden_below: Option<usize> = None;
+
+        for (i, row) in self.rows.iter().enumerate() {
+            match hidden_below {
+                Some(depth) if row.depth > depth => continue,
+                _ => hidden_below = None,
+            }
+
+            visible.push(i);
+
+            if self.collapsed.contains(&i) {
+                hidden_below = Some(row.depth);
+            }
+        }
+
+        visible
+    }
+
+    fn selected(&self) -> Option<usize> {
+        self.visible().get(self.cursor).copied()
+    }
+
+    /// The rows beneath the row at `i`, not including itself.
+    fn subtree(&self, i: usize) -> std::ops::Range<usize> {
+        let depth = self.rows[i].depth;
+        let end = self.rows[i + 1..]
+            .iter()
+            .position(|row| row.depth <= depth)
+            .map_or(self.rows.len(), |n| i + 1 + n);
+
+        i + 1..end
+    }
+
+    fn enabled_len(&self) -> usize {
+        (0..self.renames.len())
+            .filter(|i| self.enabled[*i] && !self.renames[*i].is_noop())
+            .count()
+    }
+
+    fn move_cursor(&mut self, offset: isize) {
+        let len = self.visible().len();
+
+        if len > 0 {
+            self.cursor = self.cursor.saturating_add_signed(offset).min(len - 1);
+        }
+    }
+
+    /// Turn the selected rename on or off, or every rename beneath the selected directory.
+    fn toggle(&mut self) {
+        let i = match self.selected() {
+            Some(i) => i,
+            None => return,
+        };
+
+        match self.rows[i].rename {
+            Some(rename) => self.enabled[rename] = !self.enabled[rename],
+            None => {
+                let beneath: Vec<usize> = self
+                    .subtree(i)
+                    .filter_map(|row| self.rows[row].rename)
+                    .collect();
+                let enable = !beneath.iter().all(|rename| self.enabled[*rename]);
+
+                for rename in beneath {
+                    self.enabled[rename] = enable;
+                }
+            }
+        }
+    }
+
+    fn toggle_all(&mut self) {
+        let all: Vec<usize> = self.rows.iter().filter_map(|row| row.rename).collect();
+        let enable = !all.iter().all(|rename| self.enabled[*rename]);
+
+        for rename in all {
+            self.enabled[rename] = enable;
+        }
+    }
+
+    fn collapse(&mut self) {
+        let i = match self.selected() {
+            Some(i) => i,
+            None => return,
+        };
+
+        if self.rows[i].has_children && !self.collapsed.contains(&i) {
+            self.collapsed.insert(i);
+            return;
+        }
+
+        // otherwise move to the parent, which is the closest shallower row above
+        let depth = self.rows[i].depth;
+        let visible = self.visible();
+
+        if let Some(parent) = visible[..self.cursor]
+            .iter()
+            .rposition(|row| self.rows[*row].depth < depth)
+        {
+            self.cursor = parent;
+        }
+    }
+
+    fn expand(&mut self) {
+        if let Some(i) = self.selected() {
+            self.collapsed.remove(&i);
+        }
+    }
+
+    fn start_edit(&mut self) {
+        let rename = match self.selected().and_then(|i| self.rows[i].rename) {
+            Some(rename) => rename,
+            None => {
+                self.message = Some(String::from("only renamed paths can be edited"));
+                return;
+            }
+        };
+
+        let name = self.renames[rename]
+            .target
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        self.editing = Some(name);
+    }
+
+    /// Give the selected rename a target named `name` beside its current one, unless it
+    /// would not be a single valid name or would take the place of another path.
+    fn set_target(&mut self, name: &str) -> Result<(), String> {
+        let rename = match self.selected().and_then(|i| self.rows[i].rename) {
+            Some(rename) => rename,
+            None => return Ok(()),
+        };
+
+        if name.is_empty()
+            || name == "."
+            || name == ".."
+            || name.chars().any(std::path::is_separator)
+        {
+            return Err(format!("'{}' is not a valid name", name));
+        }
+
+        let source = &self.renames[rename].source;
+        let target = match self.renames[rename].target.parent() {
+            Some(parent) => parent.join(name),
+            None => PathBuf::from(name),
+        };
+
+        if target == *source {
+            self.renames[rename].target = target;
+            self.enabled[rename] = false;
+
+            return Err(String::from(
+                "the name is unchanged, so the rename was turned off",
+            ));
+        }
+
+        if let Some(other) = (0..self.renames.len())
+            .find(|i| *i != rename && self.enabled[*i] && self.renames[*i].target == target)
+        {
+            return Err(format!(
+                "'{}' is already the target of '{}'",
+                target.display(),
+                self.renames[other].source.display()
+            ));
+        }
+
+        // a target differing only in case may be the source itself on case-insensitive
+        // filesystems
+        if fs::symlink_metadata(&target).is_ok() && Identity::of(&target) != Identity::of(source) {
+            return Err(format!("'{}' already exists", target.display()));
+        }
+
+        self.renames[rename].target = target;
+        self.enabled[rename] = true;
+
+        Ok(())
+    }
+
+    fn handle(&mut self, code: KeyCode) -> Option<Decision> {
+        if let Some(name) = self.editing.as_mut() {
+            match code {
+                KeyCode::Char(c) => name.push(c),
+                KeyCode::Backspace => {
+                    name.pop();
+                }
+                KeyCode::Esc => self.editing = None,
+                KeyCode::Enter => {
+                    let name = self.editing.take().unwrap();
+                    self.message = self.set_target(&name).err();
+                }
+                _ => {}
+            }
+
+            return None;
+        }
+
+        if self.confirming {
+            self.confirming = false;
+            self.message = None;
+
+            return (code == KeyCode::Char('y')).then_some(Decision::Execute);
+        }
+
+        self.message = None;
+
+        match code {
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(1),
+            KeyCode::PageUp => self.move_cursor(-10),
+            KeyCode::PageDown => self.move_cursor(10),
+            KeyCode::Home | KeyCode::Char('g') => self.cursor = 0,
+            KeyCode::End | KeyCode::Char('G') => self.move_cursor(isize::MAX),
+            KeyCode::Left | KeyCode::Char('h') => self.collapse(),
+            KeyCode::Right | KeyCode::Char('l') => self.expand(),
+            KeyCode::Char(' ') => self.toggle(),
+            KeyCode::Char('a') => self.toggle_all(),
+            KeyCode::Char('e') | KeyCode::Enter => self.start_edit(),
+            KeyCode::Char('x') => {
+                self.confirming = true;
+                self.message = Some(format!("perform {} rename(s)? [y/N]", self.enabled_len()));
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return Some(Decision::Quit),
+            _ => {}
+        }
+
+        None
+    }
+
+    fn line(&self, i: usize) -> Line<'_> {
+        let row = &self.rows[i];
+        let mut spans = vec![Span::raw("  ".repeat(row.depth))];
+
+        spans.push(Span::raw(match row.has_children {
+            true if self.collapsed.contains(&i) => "▸ ",
+            true => "▾ ",
+            false => "  ",
+        }));
+
+        let name = row
+            .path
+            .file_name()
+            .unwrap_or(row.path.as_os_str())
+            .to_string_lossy();
+
+        match row.rename {
+            Some(rename) => {
+                let enabled = self.enabled[rename];
+                let rename = &self.renames[rename];
+                let style = if enabled {
+                    Style::default()
+                } else {
+                    Style::default().fg(Color::DarkGray)
+                };
+
+                // targets beside their source are shown by name alone
+                let target = if rename.target.parent() == rename.source.parent() {
+                    rename
+                        .target
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                } else {
+                    rename.target.to_string_lossy()
+                };
+
+                spans.push(Span::styled(if enabled { "[x] " } else { "[ ] " }, style));
+                spans.push(Span::styled(name, style));
+                spans.push(Span::styled(" -> ", style));
+                spans.push(Span::styled(
+                    target,
+                    if enabled {
+                        style.fg(Color::Green)
+                    } else {
+                        style
+                    },
+                ));
+            }
+            None => spans.push(Span::styled(
+                name,
+                Style::default().add_modifier(Modifier::BOLD),
+            )),
+        }
+
+        Line::from(spans)
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [tree, footer] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(2)]).areas(frame.area());
+
+        let items: Vec<ListItem> = self
+            .visible()
+            .into_iter()
+            .map(|i| ListItem::new(self.line(i)))
+            .collect();
+
+        let title = match self.root.as_os_str().is_empty() {
+            true => String::from(" ccpath "),
+            false => format!(" ccpath: {} ", self.root.display()),
+        };
+
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+        let mut state = ListState::default().with_selected(Some(self.cursor));
+        frame.render_stateful_widget(list, tree, &mut state);
+
+        let status = match (&self.editing, &self.message) {
+            (Some(name), _) => format!("new name: {}", name),
+            (None, Some(message)) => message.clone(),
+            (None, None) => format!(
+                "{} of {} rename(s) enabled",
+                self.enabled_len(),
+                self.rows.iter().filter(|row| row.rename.is_some()).count()
+            ),
+        };
+
+        let help = "↑↓ move  ←→ fold  space toggle  a toggle all  e edit  x execute  q quit";
+
+        frame.render_widget(
+            Paragraph::new(vec![
+                Line::from(status),
+                Line::styled(help, Style::default().fg(Color::DarkGray)),
+            ]),
+            footer,
+        );
+    }
+
+    /// The reviewed plan, keeping the order of the original.
+    fn into_plan(self) -> RenamePlan {
+        let mut plan = RenamePlan::new();
+
+        for (rename, enabled) in self.renames.into_iter().zip(self.enabled) {
+            if enabled {
+                plan.push(rename.source, rename.target);
+            }
+        }
+
+        plan
+    }
+}
+
+fn run(terminal: &mut DefaultTerminal, review: &mut Review) -> io::Result<Decision> {
+    loop {
+        terminal.draw(|frame| review.draw(frame))?;
+
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            // the terminal is raw, so interrupting has to be handled here
+            if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+                return Ok(Decision::Quit);
+            }
+
+            if let Some(decision) = review.handle(key.code) {
+                return Ok(decision);
+            }
+        }
+    }
+}
+
+/// Let the user review the plan, returning it as reviewed or nothing if they quit instead.
+pub fn review(batches: Batches) -> io::Result<Option<Batches>> {
+    let (plan, snapshot) = match batches {
+        Batches::InMemory(plan, snapshot) => (plan, snapshot),
+        Batches::Spilled(_) => {
+            return Err(io::Error::other(
+                "the plan is too large to review, raise '--spill-after' to review it anyway",
+            ))
+        }
+    };
+
+    if plan.renames().iter().all(Rename::is_noop) {
+        return Ok(Some(Batches::InMemory(plan, snapshot)));
+    }
+
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err(io::Error::other("reviewing the plan requires a terminal"));
+    }
+
+    let mut review = Review::new(plan);
+
+    let mut terminal = ratatui::try_init()?;
+    let decision = run(&mut terminal, &mut review);
+    ratatui::try_restore()?;
+
+    // sources are unchanged, so the snapshot taken while planning still applies
+    match decision? {
+        Decision::Execute => Ok(Some(Batches::InMemory(review.into_plan(), snapshot))),
+        Decision::Quit => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use ratatui::backend::TestBackend;
+    use ratatui::crossterm::event::KeyCode;
+    use ratatui::Terminal;
+
+    use convert_path::plan::RenamePlan;
+
+    use crate::tui::{Decision, Review};
+
+    fn review() -> Review {
+        let mut plan = RenamePlan::new();
+
+        plan.push(
+            PathBuf::from("/nonexistent/Some Dir/A File"),
+            PathBuf::from("/nonexistent/Some Dir/a_file"),
+        );
+        plan.push(
+            PathBuf::from("/nonexistent/Some Dir/b_file"),
+            PathBuf::from("/nonexistent/Some Dir/b_file"),
+        );
+        plan.push(
+            PathBuf::from("/nonexistent/Some Dir"),
+            PathBuf::from("/nonexistent/some_dir"),
+        );
+        plan.push(
+            PathBuf::from("/nonexistent/Other/C File"),
+            PathBuf::from("/nonexistent/Other/c_file"),
+        );
+
+        Review::new(plan)
+    }
+
+    #[test]
+    fn test_tree() {
+        let review = review();
+
+        let rows: Vec<(&str, usize)> = review
+            .rows
+            .iter()
+            .map(|row| (row.path.to_str().unwrap(), row.depth))
+            .collect();
+
+        assert_eq!(PathBuf::from("/nonexistent"), review.root);
+        assert_eq!(
+            vec![
+                ("/nonexistent/Other", 0),
+                ("/nonexistent/Other/C File", 1),
+                ("/nonexistent/Some Dir", 0),
+                ("/nonexistent/Some Dir/A File", 1),
+            ],
+            rows
+        );
+    }
+
+    #[test]
+    fn test_toggle_and_edit() {
+        let mut review = review();
+
+        // turning off a directory which is not renamed itself turns off everything beneath it
+        review.handle(KeyCode::Char(' '));
+        assert_eq!(vec![true, true, true, false], review.enabled);
+
+        review.handle(KeyCode::Left);
+        assert_eq!(vec![0, 2, 3], review.visible());
+
+        review.handle(KeyCode::Down);
+        review.handle(KeyCode::Down);
+        review.handle(KeyCode::Char('e'));
+
+        for _ in 0.."a_file".len() {
+            review.handle(KeyCode::Backspace);
+        }
+
+        for c in "b_file".chars() {
+            review.handle(KeyCode::Char(c));
+        }
+
+        // the new name must not be taken by a path or another target
+        review.handle(KeyCode::Enter);
+        assert_eq!(
+            Some("'/nonexistent/Some Dir/b_file' is already the target of '/nonexistent/Some Dir/b_file'"),
+            review.message.as_deref()
+        );
+
+        review.handle(KeyCode::Char('e'));
+        review.handle(KeyCode::Backspace);
+        review.handle(KeyCode::Char('2'));
+        review.handle(KeyCode::Enter);
+        assert_eq!(None, review.message);
+
+        review.handle(KeyCode::Char('x'));
+        assert_eq!(Some(Decision::Execute), review.handle(KeyCode::Char('y')));
+
+        let targets: Vec<PathBuf> = review
+            .into_plan()
+            .renames()
+            .iter()
+            .map(|rename| rename.target.clone())
+            .collect();
+
+        assert_eq!(
+            vec![
+                PathBuf::from("/nonexistent/Some Dir/a_fil2"),
+                PathBuf::from("/nonexistent/Some Dir/b_file"),
+                PathBuf::from("/nonexistent/some_dir"),
+            ],
+            targets
+        );
+    }
+
+    #[test]
+    fn test_draw() -> Result<(), Box<dyn std::error::Error>> {
+        let review = review();
+        let mut terminal = Terminal::new(TestBackend::new(60, 8))?;
+
+        terminal.draw(|frame| review.draw(frame))?;
+
+        let buffer = terminal.backend().buffer();
+        let lines: Vec<String> = (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect();
+
+        assert_eq!("│▾ Other", lines[1].trim_end_matches('│').trim_end());
+        assert_eq!(
+            "│    [x] C File -> c_file",
+            lines[2].trim_end_matches('│').trim_end()
+        );
+        assert_eq!("3 of 3 rename(s) enabled", lines[6]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+//! Submits renames to the kernel in batches through io_uring rather than making one
+//! syscall per rename.
+
+use std::ffi::CString;
+use std::io;
+use std::mem::ManuallyDrop;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use io_uring::{opcode, types, IoUring};
+
+use convert_path::plan::Rename;
+
+/// The number of renames submitted to the kernel at once.
+const RING_ENTRIES: u32 = 256;
+
+pub struct Ring {
+    ring: IoUring,
+
+    /// Whether submitting failed, after which the ring is never submitted to again so the
+    /// entries left in its queue are never performed.
+    is_broken: bool,
+}
+
+impl Ring {
+    /// Set up a new ring, failing where io_uring is unsupported or forbidden (ie by seccomp).
+    pub fn new() -> io::Result<Ring> {
+        Ok(Ring {
+            ring: IoUring::new(RING_ENTRIES)?,
+            is_broken: false,
+        })
+    }
+
+    /// Perform every rename, returning the result of each in the same order.
+    ///
+    /// The kernel may complete the renames in any order, so none of them may depend on
+    /// another (ie by renaming a directory containing another source).
+    pub fn rename_all(&mut self, renames: &[&Rename]) -> io::Result<Vec<io::Result<()>>> {
+        if self.is_broken {
+            return Err(io::Error::other("io_uring failed earlier in the run"));
+        }
+
+        let paths = renames
+            .iter()
+            .map(|rename| Ok((c_path(&rename.source)?, c_path(&rename.target)?)))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        // the entries point into the paths, so they are leaked rather than freed when an error
+        // leaves entries with the kernel which may still read them
+        let paths = ManuallyDrop::new(paths);
+
+        let mut results: Vec<io::Result<()>> = Vec::with_capacity(paths.len());
+
+        for (offset, chunk) in paths.chunks(RING_ENTRIES as usize).enumerate() {
+            let base = offset * RING_ENTRIES as usize;
+            let mut chunk_results: Vec<Option<io::Result<()>>> =
+                (0..chunk.len()).map(|_| None).collect();
+
+            for (i, (source, target)) in chunk.iter().enumerate() {
+                let entry = opcode::RenameAt::new(
+                    types::Fd(libc::AT_FDCWD),
+                    source.as_ptr(),
+                    types::Fd(libc::AT_FDCWD),
+                    target.as_ptr(),
+                )
+                .build()
+                .user_data((base + i) as u64);
+
+                // the paths outlive the entry since they are freed only once every entry completed
+                if unsafe { self.ring.submission().push(&entry) }.is_err() {
+                    self.is_broken = true;
+                    return Err(io::Error::other("io_uring submission queue is full"));
+                }
+            }
+
+            let mut remaining = chunk.len();
+
+            while remaining > 0 {
+                match self.ring.submit_and_wait(1) {
+                    Ok(_) => {}
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => {
+                        self.is_broken = true;
+                        return Err(err);
+                    }
+                };
+
+                for cqe in self.ring.completion() {
+                    let i = cqe.user_data() as usize - base;
+
+                    chunk_results[i] = Some(if cqe.result() < 0 {
+                        Err(io::Error::from_raw_os_error(-cqe.result()))
+                    } else {
+                        Ok(())
+                    });
+
+                    remaining -= 1;
+                }
+            }
+
+            results.extend(chunk_results.into_iter().map(Option::unwrap));
+        }
+
+        // every entry completed, so none of them reads the paths any longer
+        drop(ManuallyDrop::into_inner(paths));
+
+        Ok(results)
+    }
+}
+
+fn c_path(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))
+}
@@ -0,0 +1,91 @@
+//! Detects whether the volume holding a directory distinguishes names by case, as most Linux
+//! filesystems do, or not, as APFS and NTFS do by default.
+//!
+//! Names differing only by case then name the same entry, so a rename changing only the case
+//! of a name is a genuine change rather than a conflict with an existing entry, while two
+//! targets differing only by case collide.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::snapshot::Identity;
+
+/// The most entries of a directory looked at for a name to tell its volume by.
+const PROBED_ENTRIES: usize = 16;
+
+/// Whether names differing only by case name the same entry on the volume holding `dir`, or
+/// none if neither `dir` nor any of its first entries has a name with a letter to tell by.
+pub fn is_case_insensitive(dir: &Path) -> Option<bool> {
+    let entries = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .take(PROBED_ENTRIES)
+        .map(|entry| entry.path());
+
+    let dir = fs::canonicalize(dir).ok();
+
+    entries.chain(dir).find_map(|path| probe(&path))
+}
+
+/// Tell whether the volume holding `path` ignores case by whether `path` with the case of its
+/// name swapped names the same entry, if its name has a letter.
+fn probe(path: &Path) -> Option<bool> {
+    let name = path.file_name()?.to_str()?;
+    let swapped = swap_case(name)?;
+    let identity = Identity::of(path)?;
+
+    Some(Identity::of(&path.with_file_name(swapped)) == Some(identity))
+}
+
+/// `name` with the case of its ASCII letters swapped, if it has any.
+fn swap_case(name: &str) -> Option<PathBuf> {
+    if !name.bytes().any(|b| b.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    let swapped: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_lowercase() {
+                c.to_ascii_uppercase()
+            } else {
+                c.to_ascii_lowercase()
+            }
+        })
+        .collect();
+
+    Some(PathBuf::from(swapped))
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::volume::{is_case_insensitive, swap_case};
+
+    #[test]
+    fn test_swap_case() {
+        assert_eq!(Some("sOME fILE.TXT".into()), swap_case("Some File.txt"));
+        assert_eq!(None, swap_case("2024-01"));
+    }
+
+    #[test]
+    fn test_is_case_insensitive() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let probed = dir.path().join("Some Dir");
+
+        fs::create_dir(&probed)?;
+        fs::write(probed.join("Some File"), "")?;
+
+        // whichever the volume of the temporary directory is, it is told by its entries
+        let swapped = probed.join("sOME fILE");
+        assert_eq!(Some(swapped.exists()), is_case_insensitive(&probed));
+
+        let unnamed = dir.path().join("2024");
+        fs::create_dir(&unnamed)?;
+        assert_eq!(None, is_case_insensitive(&unnamed));
+
+        Ok(())
+    }
+}
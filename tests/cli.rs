@@ -1,12 +1,23 @@
+// the oldest tests borrow their arguments, which newer versions of clippy flag
+#![allow(clippy::needless_borrow, clippy::needless_borrows_for_generic_args)]
+
 use std::fs::{self, File};
 use std::path::{Path, PathBuf};
 
 use assert_cmd::Command;
 
-use predicates::prelude::predicate;
+use predicates::prelude::{predicate, PredicateBooleanExt};
 
 use tempfile::TempDir;
 
+/// The ccpath binary, showing its messages in English whatever the locale the tests run in.
+fn ccpath() -> Result<Command, Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("ccpath")?;
+    cmd.env("LC_ALL", "C");
+
+    Ok(cmd)
+}
+
 fn setup(files: &[&Path], dirs: &[&Path]) -> Result<TempDir, Box<dyn std::error::Error>> {
     let env_dir = tempfile::tempdir()?;
     let env_dir_path = env_dir.path();
@@ -43,7 +54,7 @@ fn test_basename() -> Result<(), Box<dyn std::error::Error>> {
         .join("Parent Dir")
         .join("some_child.txt");
 
-    let mut cmd = Command::cargo_bin("ccpath")?;
+    let mut cmd = ccpath()?;
     cmd.args(&["--basename", "snake", target_path.to_str().unwrap()]);
     cmd.assert().success();
 
@@ -65,7 +76,7 @@ fn test_full_path() -> Result<(), Box<dyn std::error::Error>> {
         .join("parent_dir")
         .join("some_child.txt");
 
-    let mut cmd = Command::cargo_bin("ccpath")?;
+    let mut cmd = ccpath()?;
     cmd.args(&[
         "--full-path",
         "--prefix",
@@ -93,7 +104,7 @@ fn test_full_with_parents_pre_exist() -> Result<(), Box<dyn std::error::Error>>
         .join("parent_dir")
         .join("some_child.txt");
 
-    let mut cmd = Command::cargo_bin("ccpath")?;
+    let mut cmd = ccpath()?;
     cmd.args(&[
         "--verbose",
         "--full-path",
@@ -122,7 +133,7 @@ fn test_full_with_parents_no_exist() -> Result<(), Box<dyn std::error::Error>> {
         .join("parent_dir")
         .join("some_child.txt");
 
-    let mut cmd = Command::cargo_bin("ccpath")?;
+    let mut cmd = ccpath()?;
     cmd.args(&[
         "--full-path",
         "--prefix",
@@ -138,6 +149,86 @@ fn test_full_with_parents_no_exist() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn test_full_remove_empty() -> Result<(), Box<dyn std::error::Error>> {
+    let target_path = Path::new("Parent Dir/Some Child.txt");
+    let empty_dir = Path::new("Empty Dir");
+
+    let dir = setup(&[target_path], &[empty_dir])?;
+
+    let target_path = PathBuf::from(dir.path()).join(target_path);
+    let empty_dir = PathBuf::from(dir.path()).join(empty_dir);
+
+    let expected_path = PathBuf::from(dir.path())
+        .join("parent_dir")
+        .join("some_child.txt");
+
+    let mut cmd = ccpath()?;
+    cmd.args([
+        "--remove-empty",
+        "--full-path",
+        "--prefix",
+        dir.path().to_str().unwrap(),
+        "snake",
+        target_path.to_str().unwrap(),
+    ]);
+    cmd.assert().success();
+
+    assert!(!dir.path().join("Parent Dir").exists());
+    assert!(expected_path.exists());
+    assert!(empty_dir.exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_full_no_remove_empty() -> Result<(), Box<dyn std::error::Error>> {
+    let target_path = Path::new("Parent Dir/Some Child.txt");
+
+    let dir = setup(&[target_path], &[])?;
+
+    let target_path = PathBuf::from(dir.path()).join(target_path);
+
+    let mut cmd = ccpath()?;
+    cmd.args([
+        "--full-path",
+        "--prefix",
+        dir.path().to_str().unwrap(),
+        "snake",
+        target_path.to_str().unwrap(),
+    ]);
+    cmd.assert().success();
+
+    assert!(dir.path().join("Parent Dir").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_full_auto_prefix() -> Result<(), Box<dyn std::error::Error>> {
+    let first_path = Path::new("Upload Dir/Parent Dir/Some Child.txt");
+    let second_path = Path::new("Upload Dir/Other Child.txt");
+
+    let dir = setup(&[first_path, second_path], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.args([
+        "--full-path",
+        "--auto-prefix",
+        "snake",
+        dir.path().join(first_path).to_str().unwrap(),
+        dir.path().join(second_path).to_str().unwrap(),
+    ]);
+    cmd.assert().success();
+
+    // only the components beneath the directory containing both are converted
+    let upload_dir = dir.path().join("Upload Dir");
+    assert!(upload_dir.join("parent_dir/some_child.txt").exists());
+    assert!(upload_dir.join("other_child.txt").exists());
+
+    Ok(())
+}
+
 #[test]
 fn test_overwrite() -> Result<(), Box<dyn std::error::Error>> {
     let target_path = Path::new("Some File.txt");
@@ -148,7 +239,7 @@ fn test_overwrite() -> Result<(), Box<dyn std::error::Error>> {
     let target_path = dir.path().join(target_path);
     let existing_path = dir.path().join(existing_path);
 
-    let mut cmd = Command::cargo_bin("ccpath")?;
+    let mut cmd = ccpath()?;
     cmd.args(&[
         "--full-path",
         "--prefix",
@@ -174,7 +265,7 @@ fn test_overwrite_no_clobber() -> Result<(), Box<dyn std::error::Error>> {
     let target_path = dir.path().join(target_path);
     let existing_path = dir.path().join(existing_path);
 
-    let mut cmd = Command::cargo_bin("ccpath")?;
+    let mut cmd = ccpath()?;
     cmd.args(&[
         "--verbose",
         "--no-clobber",
@@ -186,7 +277,7 @@ fn test_overwrite_no_clobber() -> Result<(), Box<dyn std::error::Error>> {
     ]);
     cmd.assert()
         .success()
-        .stdout(predicate::str::contains(format!(
+        .stderr(predicate::str::contains(format!(
             "file '{}' already exists",
             existing_path.display()
         )));
@@ -197,6 +288,171 @@ fn test_overwrite_no_clobber() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn test_same_dir() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(
+        &[
+            Path::new("Some Dir/Other Dir/Some File"),
+            Path::new("Some Dir/Other Dir/Another File"),
+        ],
+        &[],
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "--verbose",
+        "--full-path",
+        "--same-dir",
+        "snake",
+        "Some Dir/Other Dir/Some File",
+        "Some Dir/Other Dir/Another File",
+    ]);
+    cmd.assert().success().stdout(
+        "'Some Dir/Other Dir/Some File' -> 'Some Dir/Other Dir/some_file'\n\
+         'Some Dir/Other Dir/Another File' -> 'Some Dir/Other Dir/another_file'\n\
+         'Some Dir/Other Dir' -> 'Some Dir/other_dir'\n\
+         'Some Dir' -> 'some_dir'\n",
+    );
+
+    // the old directories were renamed rather than left behind
+    assert_eq!(1, fs::read_dir(dir.path())?.count());
+    assert!(dir.path().join("some_dir/other_dir/some_file").exists());
+    assert!(dir.path().join("some_dir/other_dir/another_file").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_merge_dirs() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(
+        &[
+            Path::new("Some Photos/Beach Day.jpg"),
+            Path::new("Some Photos/Shared.jpg"),
+            Path::new("some-photos/Hike.jpg"),
+            Path::new("some-photos/shared.jpg"),
+        ],
+        &[],
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--recursive", "snake", "Some Photos", "some-photos"]);
+    cmd.assert().code(3);
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "--recursive",
+        "--merge-dirs",
+        "--no-clobber",
+        "snake",
+        "Some Photos",
+        "some-photos",
+    ]);
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("leaving 1 path(s)"));
+
+    assert!(dir.path().join("some_photos/beach_day.jpg").exists());
+    assert!(dir.path().join("some_photos/hike.jpg").exists());
+    assert!(dir.path().join("some_photos/shared.jpg").exists());
+
+    // only the file whose name was taken is left behind
+    assert!(!dir.path().join("Some Photos").exists());
+    assert_eq!(1, fs::read_dir(dir.path().join("some-photos"))?.count());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_hard_links() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Backup/Some File")], &[])?;
+    let backup = dir.path().join("Backup");
+
+    fs::write(backup.join("Some File"), "content")?;
+    fs::hard_link(backup.join("Some File"), backup.join("some-file"))?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--recursive", "snake", "Backup"]);
+    cmd.assert().success();
+
+    // both names of the file converge on the one it is converted to
+    let backup = dir.path().join("backup");
+    assert_eq!(1, fs::read_dir(&backup)?.count());
+    assert_eq!("content", fs::read_to_string(backup.join("some_file"))?);
+
+    Ok(())
+}
+
+#[test]
+fn test_flatten() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(
+        &[
+            Path::new("Scans/2021/Jan/IMG 001.png"),
+            Path::new("Scans/2022/IMG 002.png"),
+            Path::new("Scans/2022/img 001.png"),
+        ],
+        &[],
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--recursive", "--flatten", "scans_inbox", "snake", "Scans"]);
+    cmd.assert().code(3);
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "--recursive",
+        "--no-clobber",
+        "--flatten",
+        "scans_inbox",
+        "snake",
+        "Scans",
+    ]);
+    cmd.assert().success();
+
+    assert!(dir.path().join("scans_inbox/img_001.png").exists());
+    assert!(dir.path().join("scans_inbox/img_002.png").exists());
+    assert!(dir.path().join("Scans/2021/Jan/IMG 001.png").exists());
+    assert!(dir.path().join("Scans/2022").is_dir());
+
+    Ok(())
+}
+
+#[test]
+fn test_template() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(
+        &[
+            Path::new("Some Dir/IMG 001.png"),
+            Path::new("Some Dir/IMG 002.png"),
+        ],
+        &[],
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "--template",
+        "{parent}_{stem}_{n:03}",
+        "snake",
+        "Some Dir/IMG 001.png",
+        "Some Dir/IMG 002.png",
+    ]);
+    cmd.assert().success();
+
+    let renamed = dir.path().join("Some Dir");
+    assert!(renamed.join("some_dir_img_001_001.png").exists());
+    assert!(renamed.join("some_dir_img_002_002.png").exists());
+
+    let mut cmd = ccpath()?;
+    cmd.args(["--template", "{stem}_{bogus}", "snake", "."]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown token '{bogus}'"));
+
+    Ok(())
+}
+
 #[test]
 fn test_recursive() -> Result<(), Box<dyn std::error::Error>> {
     let parent_dir = Path::new("Parent Dir");
@@ -213,7 +469,7 @@ fn test_recursive() -> Result<(), Box<dyn std::error::Error>> {
     let grand_child_file = dir.path().join(grand_child_file);
     let another_grand_child_file = dir.path().join(another_grand_child_file);
 
-    let mut cmd = Command::cargo_bin("ccpath")?;
+    let mut cmd = ccpath()?;
     cmd.args(&[
         "--recursive",
         "--prefix",
@@ -267,7 +523,7 @@ fn test_dir_no_recursive() -> Result<(), Box<dyn std::error::Error>> {
     let child_file = dir.path().join(child_file);
     let grand_child_file = dir.path().join(grand_child_file);
 
-    let mut cmd = Command::cargo_bin("ccpath")?;
+    let mut cmd = ccpath()?;
     cmd.args(&[
         "--prefix",
         dir.path().to_str().unwrap(),
@@ -301,7 +557,7 @@ fn test_dir_no_recursive() -> Result<(), Box<dyn std::error::Error>> {
 
 #[test]
 fn test_no_path() -> Result<(), Box<dyn std::error::Error>> {
-    let mut cmd = Command::cargo_bin("ccpath")?;
+    let mut cmd = ccpath()?;
 
     cmd.arg("snake");
 
@@ -312,7 +568,7 @@ fn test_no_path() -> Result<(), Box<dyn std::error::Error>> {
 
 #[test]
 fn test_unsupported_convention() -> Result<(), Box<dyn std::error::Error>> {
-    let mut cmd = Command::cargo_bin("ccpath")?;
+    let mut cmd = ccpath()?;
 
     cmd.arg("unsupported convention").arg("/some/path");
 
@@ -325,7 +581,7 @@ fn test_unsupported_convention() -> Result<(), Box<dyn std::error::Error>> {
 
 #[test]
 fn test_no_convention() -> Result<(), Box<dyn std::error::Error>> {
-    let mut cmd = Command::cargo_bin("ccpath")?;
+    let mut cmd = ccpath()?;
 
     cmd.assert().failure();
 
@@ -334,7 +590,7 @@ fn test_no_convention() -> Result<(), Box<dyn std::error::Error>> {
 
 #[test]
 fn test_basename_mutually_exclusive_mode_group() -> Result<(), Box<dyn std::error::Error>> {
-    let mut cmd = Command::cargo_bin("ccpath")?;
+    let mut cmd = ccpath()?;
 
     cmd.arg("--basename").arg("full-path");
 
@@ -345,9 +601,2480 @@ fn test_basename_mutually_exclusive_mode_group() -> Result<(), Box<dyn std::erro
 
 #[test]
 fn test_path_no_exist() -> Result<(), Box<dyn std::error::Error>> {
-    let mut cmd = Command::cargo_bin("ccpath")?;
+    let mut cmd = ccpath()?;
     cmd.args(&["snake", "/path/does/not/exist"]);
     cmd.assert().failure();
 
     Ok(())
 }
+
+#[test]
+fn test_batch_collision() -> Result<(), Box<dyn std::error::Error>> {
+    let first_path = Path::new("Some File.txt");
+    let second_path = Path::new("Some-File.txt");
+
+    let dir = setup(&[first_path, second_path], &[])?;
+
+    let first_path = dir.path().join(first_path);
+    let second_path = dir.path().join(second_path);
+
+    let mut cmd = ccpath()?;
+    cmd.args([
+        "snake",
+        first_path.to_str().unwrap(),
+        second_path.to_str().unwrap(),
+    ]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("would all be renamed to"));
+
+    assert!(first_path.exists());
+    assert!(second_path.exists());
+    assert!(!dir.path().join("some_file.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_batch_collision_no_clobber() -> Result<(), Box<dyn std::error::Error>> {
+    let first_path = Path::new("Some File.txt");
+    let second_path = Path::new("Some-File.txt");
+
+    let dir = setup(&[first_path, second_path], &[])?;
+
+    let first_path = dir.path().join(first_path);
+    let second_path = dir.path().join(second_path);
+
+    let mut cmd = ccpath()?;
+    cmd.args([
+        "--no-clobber",
+        "snake",
+        first_path.to_str().unwrap(),
+        second_path.to_str().unwrap(),
+    ]);
+    cmd.assert().success();
+
+    assert!(!first_path.exists());
+    assert!(second_path.exists());
+    assert!(dir.path().join("some_file.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_already_conforming() -> Result<(), Box<dyn std::error::Error>> {
+    let target_path = Path::new("some_file.txt");
+
+    let dir = setup(&[target_path], &[])?;
+
+    let target_path = dir.path().join(target_path);
+
+    let mut cmd = ccpath()?;
+    cmd.args(["--verbose", "snake", target_path.to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("->").not())
+        .stderr(predicate::str::contains("1 already conforming"));
+
+    assert!(target_path.exists());
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_unwritable_precheck() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let first_path = Path::new("Writable Dir/Some File.txt");
+    let second_path = Path::new("Read Only Dir/Some File.txt");
+
+    let dir = setup(&[first_path, second_path], &[])?;
+
+    let first_path = dir.path().join(first_path);
+    let second_path = dir.path().join(second_path);
+    let read_only_dir = second_path.parent().unwrap();
+
+    fs::set_permissions(read_only_dir, fs::Permissions::from_mode(0o555))?;
+
+    // privileged users can write regardless of the permission bits
+    if File::create(read_only_dir.join("probe")).is_ok() {
+        return Ok(());
+    }
+
+    let mut cmd = ccpath()?;
+    cmd.args([
+        "snake",
+        first_path.to_str().unwrap(),
+        second_path.to_str().unwrap(),
+    ]);
+    let assert = cmd.assert();
+
+    fs::set_permissions(read_only_dir, fs::Permissions::from_mode(0o755))?;
+
+    assert
+        .failure()
+        .stderr(predicate::str::contains("1 path(s) cannot be renamed"))
+        .stderr(predicate::str::contains(second_path.to_str().unwrap()));
+
+    assert!(first_path.exists());
+    assert!(second_path.exists());
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_recursive_follow_symlinks_loop() -> Result<(), Box<dyn std::error::Error>> {
+    let parent_dir = Path::new("Parent Dir");
+    let child_file = parent_dir.join("Child File.txt");
+
+    let dir = setup(&[child_file.as_path()], &[])?;
+
+    let parent_dir = dir.path().join(parent_dir);
+    std::os::unix::fs::symlink(&parent_dir, parent_dir.join("Loop Link"))?;
+
+    let mut cmd = ccpath()?;
+    cmd.args([
+        "--recursive",
+        "--follow-symlinks",
+        "snake",
+        parent_dir.to_str().unwrap(),
+    ]);
+
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("skipping symlink loop"));
+
+    let new_parent_dir = dir.path().join("parent_dir");
+
+    assert!(new_parent_dir.join("child_file.txt").exists());
+    assert!(fs::symlink_metadata(new_parent_dir.join("loop_link"))?
+        .file_type()
+        .is_symlink());
+
+    Ok(())
+}
+
+#[test]
+fn test_escaping_target() -> Result<(), Box<dyn std::error::Error>> {
+    let target_path = Path::new("-..");
+
+    let dir = setup(&[target_path], &[])?;
+
+    let target_path = dir.path().join(target_path);
+
+    let mut cmd = ccpath()?;
+    cmd.args(["snake", target_path.to_str().unwrap()]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("would move it outside of"));
+
+    assert!(target_path.exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_recursive_home_requires_force() -> Result<(), Box<dyn std::error::Error>> {
+    let child_file = Path::new("home/Child File.txt");
+
+    let dir = setup(&[child_file], &[])?;
+
+    let home_dir = dir.path().join("home");
+    let child_file = dir.path().join(child_file);
+
+    let mut cmd = ccpath()?;
+    cmd.env("HOME", &home_dir)
+        .write_stdin("")
+        .args(["--recursive", "snake", home_dir.to_str().unwrap()]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("without '--force'"));
+
+    assert!(child_file.exists());
+
+    let mut cmd = ccpath()?;
+    cmd.env("HOME", &home_dir).args([
+        "--recursive",
+        "--force",
+        "snake",
+        home_dir.to_str().unwrap(),
+    ]);
+    cmd.assert().success();
+
+    assert!(home_dir.join("child_file.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_full_source_vanished() -> Result<(), Box<dyn std::error::Error>> {
+    let parent_dir = Path::new("Parent Dir");
+    let child_file = parent_dir.join("Child File.txt");
+
+    let dir = setup(&[child_file.as_path()], &[])?;
+
+    let parent_dir = dir.path().join(parent_dir);
+    let child_file = dir.path().join(child_file);
+
+    let mut cmd = ccpath()?;
+    cmd.args([
+        "--full-path",
+        "--prefix",
+        dir.path().to_str().unwrap(),
+        "snake",
+        parent_dir.to_str().unwrap(),
+        child_file.to_str().unwrap(),
+    ]);
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("vanished since the plan was made"));
+
+    assert!(dir
+        .path()
+        .join("parent_dir")
+        .join("Child File.txt")
+        .exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_sanitize_windows_reserved() -> Result<(), Box<dyn std::error::Error>> {
+    let target_path = Path::new("Con.txt");
+
+    let dir = setup(&[target_path], &[])?;
+
+    let target_path = dir.path().join(target_path);
+
+    let mut cmd = ccpath()?;
+    cmd.args([
+        "--sanitize",
+        "windows",
+        "--sanitize-mode",
+        "reject",
+        "snake",
+        target_path.to_str().unwrap(),
+    ]);
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("is a reserved device name"));
+
+    assert!(target_path.exists());
+
+    let mut cmd = ccpath()?;
+    cmd.args([
+        "--sanitize",
+        "windows",
+        "snake",
+        target_path.to_str().unwrap(),
+    ]);
+    cmd.assert().success();
+
+    assert!(!target_path.exists());
+    assert!(dir.path().join("con_.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_sanitize_mode_requires_platform() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Con.txt")], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "--dry-run",
+        "--sanitize-mode",
+        "reject",
+        "snake",
+        "Con.txt",
+    ]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--sanitize <PLATFORM>|--target-fs <FS>",
+    ));
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "--dry-run",
+        "--target-fs",
+        "fat32",
+        "--sanitize-mode",
+        "adjust",
+        "snake",
+        "Con.txt",
+    ]);
+    cmd.assert().success();
+
+    Ok(())
+}
+
+#[test]
+fn test_sanitize_keep() -> Result<(), Box<dyn std::error::Error>> {
+    let first_path = Path::new("Con.txt");
+    let second_path = Path::new("Some File.txt");
+
+    let dir = setup(&[first_path, second_path], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--sanitize", "windows", "keep", "Con.txt", "Some File.txt"]);
+    cmd.assert().success();
+
+    assert!(dir.path().join("Con_.txt").exists());
+    assert!(dir.path().join(second_path).exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_target_fs_fat32() -> Result<(), Box<dyn std::error::Error>> {
+    let first_path = Path::new("Some File.txt");
+    let second_path = Path::new("some file.TXT");
+
+    let dir = setup(&[first_path, second_path], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "--target-fs",
+        "fat32",
+        "snake",
+        "Some File.txt",
+        "some file.TXT",
+    ]);
+    cmd.assert()
+        .code(3)
+        .stderr(predicate::str::contains("would all be renamed"));
+
+    assert!(dir.path().join(first_path).exists());
+    assert!(dir.path().join(second_path).exists());
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--target-fs", "fat32", "snake", "Some File.txt"]);
+    cmd.assert().success();
+
+    assert!(dir.path().join("some_file.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_transform() -> Result<(), Box<dyn std::error::Error>> {
+    let target_path = Path::new("Café Menu (2024).PDF");
+
+    let dir = setup(&[target_path], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "--transform",
+        "slugify",
+        "--transform",
+        "snake",
+        "--transform",
+        "lowercase-ext",
+        "keep",
+        "Café Menu (2024).PDF",
+    ]);
+    cmd.assert().success();
+
+    assert!(dir.path().join("cafe_menu_2024.pdf").exists());
+
+    let mut cmd = ccpath()?;
+    cmd.args(["--transform", "shout", "snake", "Some File"]);
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains("Unsupported transform 'shout'"));
+
+    Ok(())
+}
+
+#[test]
+fn test_config_rules() -> Result<(), Box<dyn std::error::Error>> {
+    let doc_path = Path::new("docs").join("Some Guide.md");
+    let script_path = Path::new("scripts").join("Build Site.sh");
+    let other_path = Path::new("Other File.txt");
+
+    let dir = setup(&[doc_path.as_path(), script_path.as_path(), other_path], &[])?;
+
+    fs::write(
+        dir.path().join(".ccpath.toml"),
+        "[[rules]]\nglob = \"docs/**\"\ninto = \"kebab\"\n\n\
+         [[rules]]\nglob = \"*.txt\"\ninto = \"keep\"\n",
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "snake",
+        doc_path.to_str().unwrap(),
+        script_path.to_str().unwrap(),
+        other_path.to_str().unwrap(),
+    ]);
+    cmd.assert().success();
+
+    assert!(dir.path().join("docs/some-guide.md").exists());
+    assert!(dir.path().join("scripts/build_site.sh").exists());
+    assert!(dir.path().join(other_path).exists());
+
+    let config = dir.path().join("invalid.toml");
+    fs::write(&config, "[[rules]]\nglob = \"*\"\ninto = \"shout\"\n")?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--config", config.to_str().unwrap(), "snake", "scripts"]);
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains("Unsupported naming convention 'shout'"));
+
+    Ok(())
+}
+
+#[test]
+fn test_config_init() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(
+        &[
+            Path::new("Some Dir/Other File"),
+            Path::new("Some Dir/target/Built File"),
+        ],
+        &[],
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "config",
+        "init",
+        "--project",
+        "--recursive",
+        "--exclude",
+        "target",
+        "kebab",
+    ]);
+    cmd.assert().success();
+
+    let config = fs::read_to_string(dir.path().join(".ccpath.toml"))?;
+    assert!(config.contains("recursive = true\n"));
+    assert!(config.contains("exclude = [\"target\"]\n"));
+    assert!(config.contains("# into = \"kebab\"\n"));
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["config", "init", "--project"]);
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains("already exists"));
+
+    // the defaults of the config apply without being given again
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args(["kebab", "Some Dir"]);
+    cmd.assert().success();
+
+    assert!(dir.path().join("some-dir/other-file").exists());
+    assert!(dir.path().join("some-dir/target/Built File").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_rules_stdin() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some File"), Path::new("Other File")], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--rules", "-", "snake", "Some File", "Other File"])
+        .write_stdin(r#"{"rules": [{"glob": "Other*", "into": "kebab"}]}"#);
+    cmd.assert().success();
+
+    assert!(dir.path().join("some_file").exists());
+    assert!(dir.path().join("other-file").exists());
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--rules", "-", "snake", "some_file"])
+        .write_stdin(r#"{"rules": {}}"#);
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains("invalid rules in stdin"));
+
+    Ok(())
+}
+
+#[test]
+fn test_dry_run_by_default() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some File")], &[])?;
+    fs::write(dir.path().join(".ccpath.toml"), "dry-run-by-default = true\n")?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args(["snake", "Some File"]);
+    cmd.assert()
+        .success()
+        .stdout("'Some File' -> 'some_file'\n")
+        .stderr(predicate::str::contains("--execute"));
+
+    assert!(dir.path().join("Some File").exists());
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--execute", "snake", "Some File"]);
+    cmd.assert().success().stdout("");
+
+    assert!(dir.path().join("some_file").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_check() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some File.txt"), Path::new("other_file.txt")], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "--check",
+        "--list-offenders",
+        "snake",
+        "Some File.txt",
+        "other_file.txt",
+    ]);
+    cmd.assert()
+        .code(10)
+        .stdout("Some File.txt\n")
+        .stderr(predicate::str::contains("1 already conforming, 1 not conforming"));
+
+    assert!(dir.path().join("Some File.txt").exists());
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--check", "snake", "other_file.txt"]);
+    cmd.assert().success();
+
+    Ok(())
+}
+
+#[test]
+fn test_check_collisions_only() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(
+        &[
+            Path::new("Some File"),
+            Path::new("Some-File"),
+            Path::new("Other File"),
+        ],
+        &[],
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "--check-collisions-only",
+        "snake",
+        "Some File",
+        "Some-File",
+        "Other File",
+    ]);
+    cmd.assert()
+        .code(3)
+        .stdout("'Some File', 'Some-File' would all be renamed to 'some_file'\n");
+
+    assert!(dir.path().join("Some File").exists());
+    assert!(dir.path().join("Other File").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_sample() -> Result<(), Box<dyn std::error::Error>> {
+    let names: Vec<String> = (0..20).map(|i| format!("Some File {}", i)).collect();
+    let paths: Vec<&Path> = names.iter().map(Path::new).collect();
+    let dir = setup(&paths, &[])?;
+
+    let sample = || -> Result<String, Box<dyn std::error::Error>> {
+        let mut cmd = ccpath()?;
+        cmd.current_dir(dir.path())
+            .args(["--sample", "5", "--seed", "42", "snake"])
+            .args(&names);
+        let output = cmd.assert().success().get_output().stdout.clone();
+
+        Ok(String::from_utf8(output)?)
+    };
+
+    let first = sample()?;
+
+    assert_eq!(5, first.lines().count());
+    assert!(first.lines().all(|line| line.starts_with("'Some File ")));
+    assert_eq!(first, sample()?);
+
+    for name in &names {
+        assert!(dir.path().join(name).exists());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_walk_filters() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(
+        &[
+            Path::new("Some Dir/Some File.txt"),
+            Path::new("Some Dir/Deep Dir/Deep File.txt"),
+            Path::new(".Hidden Dir/Hidden File.txt"),
+            Path::new("Vendor Dir/Vendor File.txt"),
+        ],
+        &[],
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "--recursive",
+        "--no-hidden",
+        "--exclude",
+        "Vendor*",
+        "--max-depth",
+        "2",
+        "--type",
+        "file",
+        "snake",
+        ".",
+    ]);
+    cmd.assert().success();
+
+    assert!(dir.path().join("Some Dir/some_file.txt").exists());
+    assert!(dir.path().join("Some Dir/Deep Dir/Deep File.txt").exists());
+    assert!(dir.path().join(".Hidden Dir/Hidden File.txt").exists());
+    assert!(dir.path().join("Vendor Dir/Vendor File.txt").exists());
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--exclude", "*", "snake", "Some Dir"]);
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn test_exclude_ext() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(
+        &[
+            Path::new("Some Dir/Some File.txt"),
+            Path::new("Some Dir/Some Movie.MKV"),
+            Path::new("Some Dir/Some Backup.tar.gz"),
+        ],
+        &[],
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "--recursive",
+        "--exclude-ext",
+        "mkv,tar.gz",
+        "snake",
+        "Some Dir",
+    ]);
+    cmd.assert().success();
+
+    assert!(dir.path().join("some_dir/some_file.txt").exists());
+    assert!(dir.path().join("some_dir/Some Movie.MKV").exists());
+    assert!(dir.path().join("some_dir/Some Backup.tar.gz").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_exclude_from() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(
+        &[
+            Path::new("Some Dir/Some File.txt"),
+            Path::new("Some Dir/Build Output/Some Object.o"),
+            Path::new("Some Dir/Node Modules/Some Package.js"),
+            Path::new("Some Dir/Other File.tmp"),
+        ],
+        &[],
+    )?;
+
+    fs::create_dir_all(dir.path().join("config/ccpath"))?;
+    fs::write(dir.path().join("config/ccpath/ignore"), "Node Modules/\n")?;
+    fs::write(
+        dir.path().join("excludes"),
+        "# scratch files\n- *.tmp\n/Build Output/\n",
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .env("XDG_CONFIG_HOME", dir.path().join("config"))
+        .args([
+            "--recursive",
+            "--exclude-from",
+            "excludes",
+            "snake",
+            "Some Dir",
+        ]);
+    cmd.assert().success();
+
+    assert!(dir.path().join("some_dir/some_file.txt").exists());
+    assert!(dir.path().join("some_dir/Build Output/Some Object.o").exists());
+    assert!(dir.path().join("some_dir/Node Modules/Some Package.js").exists());
+    assert!(dir.path().join("some_dir/Other File.tmp").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_git_tracked_only() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(
+        &[
+            Path::new("Some Repo/Some File.rs"),
+            Path::new("Some Repo/Build Output/Some Object.o"),
+            Path::new("Some Repo/Untracked File.txt"),
+        ],
+        &[],
+    )?;
+
+    let repo = dir.path().join("Some Repo");
+
+    for args in [&["init", "--quiet"][..], &["add", "Some File.rs"]] {
+        std::process::Command::new("git")
+            .arg("-C")
+            .arg(&repo)
+            .args(args)
+            .output()?;
+    }
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(&repo)
+        .args(["--recursive", "--git-tracked-only", "snake", "."]);
+    cmd.assert().success();
+
+    assert!(repo.join("some_file.rs").exists());
+    assert!(repo.join("Build Output/Some Object.o").exists());
+    assert!(repo.join("Untracked File.txt").exists());
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--recursive", "--git-tracked-only", "snake", "."]);
+    cmd.assert().code(1);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_special_files() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some Dir/Some File")], &[])?;
+    let _listener =
+        std::os::unix::net::UnixListener::bind(dir.path().join("Some Dir/Some Socket"))?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "--recursive",
+        "--special-files",
+        "error",
+        "snake",
+        "Some Dir",
+    ]);
+    cmd.assert().code(1).stderr(predicate::str::contains(
+        "'Some Dir/Some Socket' is a socket",
+    ));
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--recursive", "--verbose", "snake", "Some Dir"]);
+    cmd.assert().success().stderr(predicate::str::contains(
+        "(1 special file(s) left as they are)",
+    ));
+
+    assert!(dir.path().join("some_dir/some_file").exists());
+    assert!(dir.path().join("some_dir/Some Socket").exists());
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "--recursive",
+        "--special-files",
+        "rename",
+        "snake",
+        "some_dir",
+    ]);
+    cmd.assert().success();
+
+    assert!(dir.path().join("some_dir/some_socket").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_extensions() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(
+        &[
+            Path::new("v1.2.3 Release Notes"),
+            Path::new("Some Archive.TAR.GZ"),
+            Path::new("Nightly.Backup"),
+        ],
+        &[],
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "--extensions",
+        "known",
+        "--known-extension",
+        "backup",
+        "snake",
+        "v1.2.3 Release Notes",
+        "Some Archive.TAR.GZ",
+        "Nightly.Backup",
+    ]);
+    cmd.assert().success();
+
+    assert!(dir.path().join("v_1.2.3_release_notes").exists());
+    assert!(dir.path().join("some_archive.TAR.GZ").exists());
+    assert!(dir.path().join("nightly.Backup").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_wordless() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(
+        &[
+            Path::new("___.txt"),
+            Path::new("+++"),
+            Path::new("Some File"),
+        ],
+        &[],
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--wordless", "warn", "kebab", "___.txt"]);
+    cmd.assert().success().stderr(predicate::str::contains(
+        "'___.txt' has no letters or digits",
+    ));
+
+    assert!(dir.path().join(".txt").exists());
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--wordless", "error", "snake", "+++", "Some File"]);
+    cmd.assert().stderr(predicate::str::contains(
+        "name '+++' has no letters or digits to convert",
+    ));
+
+    assert!(dir.path().join("+++").exists());
+    assert!(dir.path().join("some_file").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_conventions() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = ccpath()?;
+    cmd.args(["conventions", "Some File.txt"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("title  Some File.txt\n"))
+        .stdout(predicate::str::contains("camel  someFile.txt\n"))
+        .stdout(predicate::str::contains("SNAKE  SOME_FILE.txt\n"))
+        .stdout(predicate::str::contains("keep   Some File.txt\n"));
+
+    Ok(())
+}
+
+#[test]
+fn test_doctor() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(
+        &[
+            Path::new("docs/some_file.md"),
+            Path::new("docs/other-file.md"),
+            Path::new("Bad:Name"),
+        ],
+        &[],
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args(["doctor", "."]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "'./Bad:Name' contains the forbidden character ':' on windows",
+        ))
+        .stdout(predicate::str::contains(
+            "'./docs' mixes the conventions kebab (1), snake (1)",
+        ))
+        .stderr(predicate::str::contains("4 paths examined, 3 issues found"));
+
+    assert!(dir.path().join("Bad:Name").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_doctor_histogram() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(
+        &[
+            Path::new("docs/some_file.md"),
+            Path::new("docs/other_file.md"),
+            Path::new("docs/Third File.md"),
+            Path::new("Bad:Name"),
+        ],
+        &[],
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["doctor", "--histogram", "--json", "."]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+
+    let histograms: serde_json::Value = serde_json::from_slice(&output)?;
+    assert_eq!(
+        serde_json::json!({
+            "directories": [
+                {"path": ".", "conventions": {}, "ambiguous": 1, "none": 1},
+                {"path": "./docs", "conventions": {"title": 1, "snake": 2}, "ambiguous": 0, "none": 0},
+            ],
+            "overall": {"conventions": {"title": 1, "snake": 2}, "ambiguous": 1, "none": 1},
+        }),
+        histograms
+    );
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args(["doctor", "--json", "."]);
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn test_normalize_input() -> Result<(), Box<dyn std::error::Error>> {
+    let target_path = Path::new("Some File.txt");
+    let target_dir = Path::new("Some Dir");
+
+    let dir = setup(&[target_path], &[target_dir])?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--verbose", "snake", "./Some File.txt", "Some Dir/"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("'Some File.txt' -> 'some_file.txt'"))
+        .stdout(predicate::str::contains("'Some Dir' -> 'some_dir'"));
+
+    assert!(dir.path().join("some_file.txt").exists());
+    assert!(dir.path().join("some_dir").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_prefix_mismatch() -> Result<(), Box<dyn std::error::Error>> {
+    let target_path = Path::new("Parent Dir/Some Child.txt");
+
+    let dir = setup(&[target_path], &[])?;
+
+    let target_path = dir.path().join(target_path);
+    let wrong_prefix = dir.path().join("Wrong Dir");
+
+    let mut cmd = ccpath()?;
+    cmd.args([
+        "--full-path",
+        "--prefix",
+        wrong_prefix.to_str().unwrap(),
+        "--prefix-mismatch",
+        "error",
+        "snake",
+        target_path.to_str().unwrap(),
+    ]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("does not match"));
+
+    assert!(target_path.exists());
+
+    let mut cmd = ccpath()?;
+    cmd.args([
+        "--dry-run",
+        "--full-path",
+        "--prefix",
+        wrong_prefix.to_str().unwrap(),
+        "snake",
+        target_path.to_str().unwrap(),
+    ]);
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("converting the full path"));
+
+    Ok(())
+}
+
+#[test]
+fn test_log_file() -> Result<(), Box<dyn std::error::Error>> {
+    let target_path = Path::new("Some File.txt");
+
+    let dir = setup(&[target_path], &[])?;
+
+    let target_path = dir.path().join(target_path);
+    let log_path = dir.path().join("run.log");
+
+    let mut cmd = ccpath()?;
+    cmd.args([
+        "--log-file",
+        log_path.to_str().unwrap(),
+        "snake",
+        target_path.to_str().unwrap(),
+    ]);
+    cmd.assert().success().stdout("").stderr("");
+
+    let log = fs::read_to_string(log_path)?;
+
+    assert!(log.contains("event=rename"));
+    assert!(log.contains("outcome=renamed"));
+    assert!(log.contains("event=summary renamed=1"));
+
+    Ok(())
+}
+
+#[test]
+fn test_log_json() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some File.txt")], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--log", "json", "snake", "Some File.txt", "Missing File"]);
+    cmd.assert()
+        .code(2)
+        .stdout("")
+        .stderr(predicate::str::contains(r#""level":"ERROR""#))
+        .stderr(predicate::str::contains("no such file or directory"));
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--log", "json", "snake", "Some File.txt"]);
+    cmd.assert()
+        .success()
+        .stdout("")
+        .stderr(predicate::str::contains(
+            r#""level":"INFO","event":"plan","renames":1}"#,
+        ))
+        .stderr(predicate::str::contains(
+            r#""event":"rename","source":"Some File.txt","target":"some_file.txt","outcome":"renamed"}"#,
+        ))
+        .stderr(predicate::str::contains(r#""event":"summary","renamed":1,"#));
+
+    Ok(())
+}
+
+#[test]
+fn test_audit() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some File.txt")], &[])?;
+    fs::write(dir.path().join("Some File.txt"), "content")?;
+
+    // the report can't be sealed without a key
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--audit", "audit.json", "snake", "Some File.txt"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--audit-key <FILE>"));
+
+    fs::write(dir.path().join("audit.key"), "secret")?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "--audit",
+        "audit.json",
+        "--audit-key",
+        "audit.key",
+        "snake",
+        "Some File.txt",
+    ]);
+    cmd.assert().success();
+
+    let audit: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("audit.json"))?)?;
+    let sha = "ed7002b439e9ac845f22357d822bac1444730fbdb6016d3ec9432297b9ec9f73";
+
+    assert_eq!(
+        serde_json::json!([{
+            "source": "Some File.txt",
+            "target": "some_file.txt",
+            "kind": "file",
+            "size": 7,
+            "sha256_before": sha,
+            "sha256_after": sha,
+            "outcome": "renamed",
+        }]),
+        audit["entries"]
+    );
+    assert_eq!(1, audit["count"]);
+    assert!(audit["seal"].is_string());
+
+    Ok(())
+}
+
+#[test]
+fn test_recursive_jobs() -> Result<(), Box<dyn std::error::Error>> {
+    let parent_dir = Path::new("Parent Dir");
+    let files: Vec<PathBuf> = (0..32)
+        .map(|i| parent_dir.join(format!("Child Dir {}", i % 4)).join(format!("Child File {}.txt", i)))
+        .collect();
+    let file_refs: Vec<&Path> = files.iter().map(PathBuf::as_path).collect();
+
+    let dir = setup(&file_refs, &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.args([
+        "--recursive",
+        "--jobs",
+        "4",
+        "--prefix",
+        dir.path().to_str().unwrap(),
+        "snake",
+        dir.path().join(parent_dir).to_str().unwrap(),
+    ]);
+
+    cmd.assert().success();
+
+    for i in 0..32 {
+        let expected = dir
+            .path()
+            .join("parent_dir")
+            .join(format!("child_dir_{}", i % 4))
+            .join(format!("child_file_{}.txt", i));
+
+        assert!(expected.exists());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_jobs_invalid() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = ccpath()?;
+    cmd.args(["--jobs", "0", "snake", "."]);
+
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn test_recursive_spill() -> Result<(), Box<dyn std::error::Error>> {
+    let parent_dir = Path::new("Parent Dir");
+    let files: Vec<PathBuf> = (0..8)
+        .map(|i| parent_dir.join(format!("Child File {}.txt", i)))
+        .collect();
+    let file_refs: Vec<&Path> = files.iter().map(PathBuf::as_path).collect();
+
+    let dir = setup(&file_refs, &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.args([
+        "--recursive",
+        "--spill-after",
+        "3",
+        "--prefix",
+        dir.path().to_str().unwrap(),
+        "snake",
+        dir.path().join(parent_dir).to_str().unwrap(),
+    ]);
+
+    cmd.assert().success();
+
+    for i in 0..8 {
+        assert!(dir
+            .path()
+            .join("parent_dir")
+            .join(format!("child_file_{}.txt", i))
+            .exists());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_spill_collision() -> Result<(), Box<dyn std::error::Error>> {
+    let paths = [
+        Path::new("Some File.txt"),
+        Path::new("Other File.txt"),
+        Path::new("Another File.txt"),
+        Path::new("Some-File.txt"),
+    ];
+
+    let dir = setup(&paths, &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.args(["--spill-after", "1", "snake"]);
+    cmd.args(paths.iter().map(|path| dir.path().join(path)));
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("would all be renamed to"));
+
+    for path in paths {
+        assert!(dir.path().join(path).exists());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_recursive_io_uring() -> Result<(), Box<dyn std::error::Error>> {
+    let parent_dir = Path::new("Parent Dir");
+    let child_dir = parent_dir.join("Child Dir");
+    let child_file = parent_dir.join("Child File.txt");
+    let grand_child_file = child_dir.join("Grand Child File.txt");
+
+    let dir = setup(&[child_file.as_path(), grand_child_file.as_path()], &[])?;
+
+    // builds without io_uring support fall back to standard renames
+    let mut cmd = ccpath()?;
+    cmd.args([
+        "--recursive",
+        "--io-uring",
+        "--prefix",
+        dir.path().to_str().unwrap(),
+        "snake",
+        dir.path().join(parent_dir).to_str().unwrap(),
+    ]);
+
+    cmd.assert().success();
+
+    assert!(dir.path().join("parent_dir/child_file.txt").exists());
+    assert!(dir.path().join("parent_dir/child_dir/grand_child_file.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_stats() -> Result<(), Box<dyn std::error::Error>> {
+    let target_path = Path::new("Parent Dir").join("Some Child.txt");
+
+    let dir = setup(&[&target_path], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.args([
+        "--recursive",
+        "--stats",
+        "snake",
+        dir.path().join("Parent Dir").to_str().unwrap(),
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("stats: walk"))
+        .stderr(predicate::str::contains("stats: convert"))
+        .stderr(predicate::str::contains("2 renames"));
+
+    Ok(())
+}
+
+#[test]
+fn test_recursive_prune_conforming() -> Result<(), Box<dyn std::error::Error>> {
+    let deep_file = Path::new("parent_dir").join("child_dir").join("Deep File.txt");
+
+    let dir = setup(&[deep_file.as_path()], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.args([
+        "--recursive",
+        "snake",
+        dir.path().join("parent_dir").to_str().unwrap(),
+    ]);
+
+    cmd.assert().success();
+
+    assert!(dir.path().join(&deep_file).exists());
+
+    let mut cmd = ccpath()?;
+    cmd.args([
+        "--recursive",
+        "--no-prune-conforming",
+        "snake",
+        dir.path().join("parent_dir").to_str().unwrap(),
+    ]);
+
+    cmd.assert().success();
+
+    assert!(!dir.path().join(&deep_file).exists());
+    assert!(dir.path().join("parent_dir/child_dir/deep_file.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_recursive_incremental() -> Result<(), Box<dyn std::error::Error>> {
+    let child_file = Path::new("Parent Dir").join("Child File.txt");
+
+    let dir = setup(&[child_file.as_path()], &[])?;
+    let cache = tempfile::tempdir()?;
+
+    let run = |path: &Path| -> Result<(), Box<dyn std::error::Error>> {
+        let mut cmd = ccpath()?;
+        cmd.env("XDG_CACHE_HOME", cache.path());
+        cmd.args([
+            "--recursive",
+            "--incremental",
+            "--no-prune-conforming",
+            "snake",
+            path.to_str().unwrap(),
+        ]);
+
+        cmd.assert().success();
+
+        Ok(())
+    };
+
+    run(&dir.path().join("Parent Dir"))?;
+
+    let parent_dir = dir.path().join("parent_dir");
+    assert!(parent_dir.join("child_file.txt").exists());
+
+    // an entry added without changing the directory's modification time is not noticed
+    let modified = fs::metadata(&parent_dir)?.modified()?;
+    File::create(parent_dir.join("Sneaky File.txt"))?;
+    File::open(&parent_dir)?.set_modified(modified)?;
+
+    run(&parent_dir)?;
+    run(&parent_dir)?;
+
+    assert!(parent_dir.join("Sneaky File.txt").exists());
+
+    // an entry added normally is
+    File::create(parent_dir.join("Other File.txt"))?;
+
+    run(&parent_dir)?;
+
+    assert!(parent_dir.join("other_file.txt").exists());
+    assert!(parent_dir.join("sneaky_file.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_files_from() -> Result<(), Box<dyn std::error::Error>> {
+    let first_path = Path::new("Some File.txt");
+    let second_path = Path::new("Some Dir").join("Other File.txt");
+
+    let dir = setup(&[first_path, second_path.as_path()], &[])?;
+
+    let manifest = dir.path().join("manifest");
+    fs::write(
+        &manifest,
+        format!(
+            "{}\n{}\n",
+            dir.path().join(first_path).display(),
+            dir.path().join(&second_path).display()
+        ),
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.args(["--files-from", manifest.to_str().unwrap(), "snake"]);
+
+    cmd.assert().success();
+
+    assert!(dir.path().join("some_file.txt").exists());
+    assert!(dir.path().join("Some Dir/other_file.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_files_from_missing_path() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some File.txt")], &[])?;
+
+    let manifest = dir.path().join("manifest");
+    fs::write(&manifest, dir.path().join("Missing File.txt").to_str().unwrap())?;
+
+    let mut cmd = ccpath()?;
+    cmd.args(["--files-from", manifest.to_str().unwrap(), "snake"]);
+    cmd.arg(dir.path().join("Some File.txt"));
+
+    cmd.assert()
+        .code(2)
+        .stderr(predicate::str::contains("no such file or directory"));
+
+    assert!(dir.path().join("Some File.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_response_file() -> Result<(), Box<dyn std::error::Error>> {
+    let first_path = Path::new("Some File.txt");
+    let second_path = Path::new("Other File.txt");
+
+    let dir = setup(&[first_path, second_path], &[])?;
+
+    let response = dir.path().join("args.txt");
+    fs::write(
+        &response,
+        format!(
+            "--verbose\nsnake\n{}\n{}\n",
+            dir.path().join(first_path).display(),
+            dir.path().join(second_path).display()
+        ),
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.arg(format!("@{}", response.display()));
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("some_file.txt"));
+
+    assert!(dir.path().join("some_file.txt").exists());
+    assert!(dir.path().join("other_file.txt").exists());
+
+    let mut cmd = ccpath()?;
+    cmd.args(["snake", "@missing.txt"]);
+
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains("could not read 'missing.txt'"));
+
+    Ok(())
+}
+
+#[test]
+fn test_backend_unsupported() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = ccpath()?;
+    cmd.args(["--backend", "ftp://host/Some Dir", "snake"]);
+
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains("unsupported backend"));
+
+    Ok(())
+}
+
+#[test]
+fn test_remote_invalid_url() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = ccpath()?;
+    cmd.args(["--remote", "sftp:///Some Dir", "snake"]);
+
+    // builds without sftp support fail before the url is parsed
+    cmd.assert().code(1).stderr(
+        predicate::str::contains("does not name a host")
+            .or(predicate::str::contains("without sftp support")),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_archive() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let archive = dir.path().join("Backup.tar");
+
+    let mut builder = tar::Builder::new(File::create(&archive)?);
+
+    for name in ["Some Dir/Some File.txt", "other_file.txt"].iter() {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        builder.append_data(&mut header, name, "data".as_bytes())?;
+    }
+
+    builder.finish()?;
+
+    let mut cmd = ccpath()?;
+    cmd.args(["archive", "--verbose", "snake"]).arg(&archive);
+
+    cmd.assert().success().stdout(predicate::str::contains(
+        "'Some Dir/Some File.txt' -> 'some_dir/some_file.txt'",
+    ));
+
+    let mut converted = tar::Archive::new(File::open(dir.path().join("Backup.converted.tar"))?);
+    let names: Vec<PathBuf> = converted
+        .entries()?
+        .map(|entry| Ok(entry?.path()?.into_owned()))
+        .collect::<Result<_, std::io::Error>>()?;
+
+    assert_eq!(
+        vec![PathBuf::from("some_dir/some_file.txt"), PathBuf::from("other_file.txt")],
+        names
+    );
+    assert!(archive.exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_fix_references() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some Dir/Some File.md")], &[])?;
+
+    let readme = dir.path().join("readme.txt");
+    fs::write(
+        &readme,
+        "[a](Some Dir/Some File.md) [b](Some Dir) [c](Some Dir/Other File.md)\n",
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.arg("--recursive")
+        .arg("--fix-references")
+        .arg(dir.path().join("*.txt"))
+        .arg("snake")
+        .arg(dir.path().join("Some Dir"));
+
+    cmd.assert().success();
+
+    assert_eq!(
+        "[a](some_dir/some_file.md) [b](some_dir) [c](some_dir/Other File.md)\n",
+        fs::read_to_string(&readme)?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_fix_references_markdown() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some Dir/Some File.md")], &[])?;
+
+    let readme = dir.path().join("readme.md");
+    fs::write(
+        &readme,
+        "[a](Some%20Dir/Some%20File.md#top) [b](<Some Dir/>) `[c](Some%20Dir)` Some Dir\n",
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.arg("--recursive")
+        .arg("--fix-references")
+        .arg(dir.path().join("*.md"))
+        .arg("snake")
+        .arg(dir.path().join("Some Dir"));
+
+    cmd.assert().success();
+
+    assert_eq!(
+        "[a](some_dir/some_file.md#top) [b](<some_dir/>) `[c](Some%20Dir)` Some Dir\n",
+        fs::read_to_string(&readme)?
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_emit_script_sh() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some Dir/Some File.txt")], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.args(["--recursive", "--emit-script", "sh", "snake"])
+        .arg(dir.path().join("Some Dir"));
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+
+    // nothing is renamed until the script is run
+    assert!(dir.path().join("Some Dir/Some File.txt").exists());
+
+    let script = dir.path().join("rename.sh");
+    fs::write(&script, output)?;
+
+    for _ in 0..2 {
+        let status = std::process::Command::new("sh").arg(&script).status()?;
+        assert!(status.success());
+    }
+
+    assert!(dir.path().join("some_dir/some_file.txt").exists());
+    assert!(!dir.path().join("Some Dir").exists());
+
+    // a directory is not moved into one already holding its target name
+    fs::create_dir(dir.path().join("Other Dir"))?;
+
+    let mut cmd = ccpath()?;
+    cmd.args(["--emit-script", "sh", "snake"])
+        .arg(dir.path().join("Other Dir"));
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    fs::write(&script, output)?;
+    fs::create_dir(dir.path().join("other_dir"))?;
+
+    let output = std::process::Command::new("sh").arg(&script).output()?;
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stderr)?.contains("is a directory"));
+
+    assert!(dir.path().join("Other Dir").exists());
+    assert!(!dir.path().join("other_dir/Other Dir").exists());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_emit_script_git() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some File.txt"), Path::new("Readme")], &[])?;
+
+    let git = |args: &[&str]| {
+        std::process::Command::new("git")
+            .args(["-c", "user.name=ccpath", "-c", "user.email=ccpath@localhost"])
+            .args(args)
+            .current_dir(dir.path())
+            .status()
+    };
+
+    // git is not available everywhere the tests are run
+    if !git(&["init", "-q"]).is_ok_and(|status| status.success()) {
+        return Ok(());
+    }
+
+    assert!(git(&["add", "."])?.success());
+    assert!(git(&["commit", "-q", "-m", "init"])?.success());
+
+    let mut cmd = ccpath()?;
+    cmd.args(["--emit-script", "git", "flat", "Some File.txt", "Readme"])
+        .current_dir(dir.path());
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    assert!(String::from_utf8(output.clone())?.contains("rename_case 'Readme' 'readme'"));
+
+    let script = dir.path().join(".git").join("rename.sh");
+    fs::write(&script, output)?;
+
+    let status = std::process::Command::new("sh")
+        .arg(&script)
+        .current_dir(dir.path())
+        .status()?;
+    assert!(status.success());
+
+    let tracked = std::process::Command::new("git")
+        .args(["ls-files"])
+        .current_dir(dir.path())
+        .output()?
+        .stdout;
+
+    assert_eq!("readme\nsomefile.txt\n", String::from_utf8(tracked)?);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_trash() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some File.txt"), Path::new("some_file.txt")], &[])?;
+    fs::write(dir.path().join("some_file.txt"), "displaced")?;
+
+    let data = dir.path().join("data");
+    fs::create_dir(&data)?;
+
+    let mut cmd = ccpath()?;
+    cmd.env("XDG_DATA_HOME", &data)
+        .args(["--trash", "snake"])
+        .arg(dir.path().join("Some File.txt"));
+
+    cmd.assert().success();
+
+    assert!(!dir.path().join("Some File.txt").exists());
+    assert_eq!("", fs::read_to_string(dir.path().join("some_file.txt"))?);
+    assert_eq!(
+        "displaced",
+        fs::read_to_string(data.join("Trash/files/some_file.txt"))?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_notify_without_session() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some File.txt")], &[])?;
+
+    // a run is never failed by a notification which cannot be shown
+    let mut cmd = ccpath()?;
+    cmd.env("DBUS_SESSION_BUS_ADDRESS", "unix:path=/nonexistent/bus")
+        .args(["--notify", "snake"])
+        .arg(dir.path().join("Some File.txt"));
+
+    cmd.assert().success();
+
+    assert!(dir.path().join("some_file.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_syslog() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some File.txt")], &[])?;
+
+    // the audit trail never appears on the console, nor fails a run without a syslog daemon
+    let mut cmd = ccpath()?;
+    cmd.args(["--syslog", "snake"])
+        .arg(dir.path().join("Some File.txt"));
+
+    cmd.assert().success().stdout("").stderr("");
+
+    assert!(dir.path().join("some_file.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_stamp() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some File.txt")], &[])?;
+
+    // not every filesystem supports user attributes
+    if xattr::set(dir.path().join("Some File.txt"), "user.ccpath.test", b"").is_err() {
+        return Ok(());
+    }
+
+    let mut cmd = ccpath()?;
+    cmd.args(["--stamp", "snake"])
+        .arg(dir.path().join("Some File.txt"));
+
+    cmd.assert().success();
+
+    let stamped = dir.path().join("some_file.txt");
+    assert_eq!(
+        Some(b"snake:some_file.txt".to_vec()),
+        xattr::get(&stamped, "user.ccpath.convention")?
+    );
+
+    // the stamp no longer holds once something else renames the path
+    fs::rename(&stamped, dir.path().join("Some File.txt"))?;
+
+    let mut cmd = ccpath()?;
+    cmd.args(["--stamp", "snake"])
+        .arg(dir.path().join("Some File.txt"));
+
+    cmd.assert().success();
+
+    assert!(stamped.exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_invalid_option_value() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = ccpath()?;
+
+    // values are checked before the paths are
+    cmd.args(["--sanitize-mode=ignore", "--sanitize=windows", "snake", "/some/path"]);
+
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains("invalid value 'ignore'"))
+        .stderr(predicate::str::contains("[possible values: adjust, reject]"));
+
+    Ok(())
+}
+
+#[test]
+fn test_explain() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("some HTMLFile.txt")], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.args(["--explain", "--dry-run", "snake"])
+        .arg(dir.path().join("some HTMLFile.txt"));
+
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "'some HTMLFile.txt' -> 'some_html_file.txt'",
+        ))
+        .stderr(predicate::str::contains("'File' split at end of acronym"))
+        .stderr(predicate::str::contains("the extension '.txt' was kept as is"));
+
+    Ok(())
+}
+
+#[test]
+fn test_output_diff() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some Dir/Some File.txt")], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--recursive", "--output", "diff", "snake", "Some Dir"]);
+
+    cmd.assert().success().stdout(
+        "--- current\n\
+         +++ planned\n\
+         @@ -1,2 +1,2 @@\n\
+         -Some Dir/\n\
+         -Some Dir/Some File.txt\n\
+         +some_dir/\n\
+         +some_dir/some_file.txt\n",
+    );
+
+    // nothing is renamed
+    assert!(dir.path().join("Some Dir/Some File.txt").exists());
+
+    // a spilled plan is too large to sort in memory
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "--recursive",
+        "--spill-after",
+        "1",
+        "--output",
+        "diff",
+        "snake",
+        "Some Dir",
+    ]);
+
+    cmd.assert()
+        .code(9)
+        .stderr(predicate::str::contains("raise '--spill-after'"));
+
+    Ok(())
+}
+
+#[test]
+fn test_output_plan() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some Dir/Some File.txt")], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--recursive", "--output", "plan", "snake", "Some Dir"]);
+
+    cmd.assert().success().stdout(format!(
+        "version = 1\n\
+         tool = \"ccpath {}\"\n\
+         \n\
+         [[renames]]\n\
+         source = \"Some Dir/Some File.txt\"\n\
+         target = \"Some Dir/some_file.txt\"\n\
+         kind = \"file\"\n\
+         sha256 = \"e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855\"\n\
+         \n\
+         [[renames]]\n\
+         source = \"Some Dir\"\n\
+         target = \"some_dir\"\n\
+         kind = \"dir\"\n",
+        env!("CARGO_PKG_VERSION")
+    ));
+
+    assert!(dir.path().join("Some Dir/Some File.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_plan_and_apply() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(
+        &[Path::new("Some Dir/Some File.txt"), Path::new("Other File")],
+        &[],
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["plan", "--recursive", "snake", "Some Dir"]);
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    fs::write(dir.path().join("plan.json"), &output)?;
+
+    assert!(String::from_utf8(output.clone())?.starts_with("{\"version\": 1, "));
+    assert!(dir.path().join("Some Dir/Some File.txt").exists());
+
+    // the convention may be given by '--into' as with a run
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["plan", "--recursive", "--into", "snake", "Some Dir"]);
+    cmd.assert().success().stdout(output);
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args(["apply", "plan.json"]);
+    cmd.assert().success();
+
+    assert!(dir.path().join("some_dir/some_file.txt").exists());
+
+    // a plan is not applied to sources which changed since it was planned
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--output", "plan", "snake", "Other File"]);
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    fs::write(dir.path().join("plan.toml"), &output)?;
+    fs::write(dir.path().join("Other File"), "changed")?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args(["apply", "plan.toml"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("1 source(s) changed"));
+
+    assert!(dir.path().join("Other File").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_tui_requires_terminal() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some File")], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args(["tui", "snake", "Some File"]);
+
+    cmd.assert()
+        .failure()
+        .code(1)
+        .stderr("Error: could not review the plan: reviewing the plan requires a terminal\n");
+
+    assert!(dir.path().join("Some File").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_replay() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(
+        &[
+            Path::new("staging/Some Dir/Some File"),
+            Path::new("production/Some Dir/Some File"),
+        ],
+        &[],
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path().join("staging")).args([
+        "--log-file",
+        "../staging.log",
+        "--recursive",
+        "snake",
+        "Some Dir",
+    ]);
+    cmd.assert().success();
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["replay", "--root", "production", "staging.log"]);
+    cmd.assert().success();
+
+    assert!(dir.path().join("production/some_dir/some_file").exists());
+
+    // the renamed paths no longer exist, so nothing is replayed twice
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["replay", "--root", "production", "staging.log"]);
+    cmd.assert().failure().code(2);
+
+    Ok(())
+}
+
+#[test]
+fn test_root() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(
+        &[
+            Path::new("Restore Root/Some Dir/Some File"),
+            Path::new("Other File"),
+        ],
+        &[],
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "--root",
+        "Restore Root",
+        "--verbose",
+        "--full-path",
+        "snake",
+        "/Some Dir/Some File",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout("'/Some Dir/Some File' -> '/some_dir/some_file'\n");
+
+    assert!(dir.path().join("Restore Root/some_dir/some_file").exists());
+
+    // nothing outside of the root is touched
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--root", "Restore Root", "snake", "../Other File"]);
+    cmd.assert().failure().code(6);
+
+    assert!(dir.path().join("Other File").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_resolve() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some Dir/Some File")], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--log-file", "first.log", "snake", "Some Dir/Some File"]);
+    cmd.assert().success();
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--log-file", "second.log", "kebab", "Some Dir"]);
+    cmd.assert().success();
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "resolve",
+        "--journal",
+        "first.log",
+        "--journal",
+        "second.log",
+        "Some Dir/Some File",
+        "Other File",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout("some-dir/some_file\nOther File\n")
+        .stderr(predicate::str::contains("'Other File' was not renamed"));
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "resolve",
+        "--reverse",
+        "--journal",
+        "first.log",
+        "--journal",
+        "second.log",
+        "some-dir/some_file",
+    ]);
+    cmd.assert().success().stdout("Some Dir/Some File\n");
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_daemon() -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    use std::thread;
+    use std::time::Duration;
+
+    let dir = setup(
+        &[Path::new("Some File.txt"), Path::new("Other File.txt")],
+        &[],
+    )?;
+    let socket = dir.path().join("ccpath.sock");
+
+    let mut daemon = std::process::Command::new(assert_cmd::cargo::cargo_bin("ccpath"))
+        .current_dir(dir.path())
+        .env("LC_ALL", "C")
+        .arg("daemon")
+        .arg("--socket")
+        .arg(&socket)
+        .arg("--metrics-file")
+        .arg(dir.path().join("ccpath.prom"))
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+
+    let stream = (0..500)
+        .find_map(|_| {
+            UnixStream::connect(&socket)
+                .map_err(|_| thread::sleep(Duration::from_millis(10)))
+                .ok()
+        })
+        .ok_or("the daemon never listened")?;
+
+    let mut writer = stream.try_clone()?;
+    let mut lines = BufReader::new(stream).lines();
+    let mut request = |command: &str, path: &Path| -> Result<String, Box<dyn std::error::Error>> {
+        writeln!(
+            writer,
+            r#"{{"command": "{}", "into": "snake", "paths": [{:?}]}}"#,
+            command,
+            path.to_string_lossy()
+        )?;
+
+        Ok(lines.next().ok_or("the daemon hung up")??)
+    };
+
+    let converted = request("convert", &dir.path().join("Some File.txt"));
+    let executed = request("execute", &dir.path().join("Other File.txt"));
+    let relative = request("plan", Path::new("Some File.txt"));
+
+    daemon.kill()?;
+    daemon.wait()?;
+
+    assert!(converted?.contains("some_file.txt"));
+    assert!(executed?.contains(r#""outcome":"renamed""#));
+    assert!(relative?.contains(r#""ok":false"#));
+
+    assert!(dir.path().join("Some File.txt").exists());
+    assert!(dir.path().join("other_file.txt").exists());
+
+    // only the executed request is counted, and none is left in flight
+    let metrics = fs::read_to_string(dir.path().join("ccpath.prom"))?;
+    assert!(metrics.contains(r#"ccpath_paths_total{outcome="renamed"} 1"#));
+    assert!(metrics.contains("ccpath_runs_total 1"));
+    assert!(metrics.contains("ccpath_daemon_requests_in_flight 0"));
+    assert!(metrics.contains("ccpath_daemon_queued_renames 0"));
+
+    Ok(())
+}
+
+#[test]
+fn test_stats_out() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some File"), Path::new("other_file")], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "--stats-out",
+        "stats.json",
+        "snake",
+        "Some File",
+        "other_file",
+    ]);
+
+    // only '--stats' prints the breakdown
+    cmd.assert().success().stderr("");
+
+    let stats = fs::read_to_string(dir.path().join("stats.json"))?;
+
+    assert!(stats.contains("\"renamed\": 1,"));
+    assert!(stats.contains("\"conforming\": 1,"));
+    assert!(stats.contains("\"title\": 1"));
+    assert!(stats.contains("\"snake\": 1"));
+    assert!(stats.contains("\"duration_seconds\": "));
+
+    Ok(())
+}
+
+#[test]
+fn test_metrics_file() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some File"), Path::new("Other File")], &[])?;
+
+    for path in ["Some File", "Other File"] {
+        let mut cmd = ccpath()?;
+        cmd.current_dir(dir.path())
+            .args(["--metrics-file", "ccpath.prom", "snake", path]);
+        cmd.assert().success();
+    }
+
+    let metrics = fs::read_to_string(dir.path().join("ccpath.prom"))?;
+
+    assert!(metrics.contains("ccpath_paths_total{outcome=\"renamed\"} 2\n"));
+    assert!(metrics.contains("ccpath_runs_total 2\n"));
+    assert!(metrics.contains("ccpath_last_run_failed 0\n"));
+    assert!(metrics.contains("ccpath_last_run_timestamp_seconds "));
+
+    Ok(())
+}
+
+#[test]
+fn test_max_errors() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(
+        &[],
+        &[
+            Path::new("Dir A/Some Child"),
+            Path::new("dir_a/other_child"),
+            Path::new("Dir B/Some Child"),
+            Path::new("dir_b/other_child"),
+            Path::new("Dir C"),
+        ],
+    )?;
+
+    // a directory can't replace another which is not empty
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--max-errors", "1", "snake", "Dir A", "Dir B", "Dir C"]);
+    cmd.assert()
+        .code(12)
+        .stderr(predicate::str::contains("stopped after 1 rename(s) failed"));
+
+    assert!(dir.path().join("Dir B").exists());
+    assert!(dir.path().join("Dir C").exists());
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--max-errors", "2", "snake", "Dir A", "Dir C"]);
+    cmd.assert().success();
+
+    assert!(dir.path().join("dir_c").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_localized_messages() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .env("LC_ALL", "")
+        .env("LC_MESSAGES", "ja_JP.UTF-8")
+        .args(["snake", "Missing File"]);
+
+    cmd.assert()
+        .failure()
+        .code(2)
+        .stderr("エラー: 'Missing File' というファイルまたはディレクトリはありません\n");
+
+    // 'C' selects the untranslated messages
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .env("LC_ALL", "C")
+        .env("LC_MESSAGES", "ja_JP.UTF-8")
+        .args(["snake", "Missing File"]);
+
+    cmd.assert()
+        .failure()
+        .stderr("Error: no such file or directory 'Missing File'\n");
+
+    Ok(())
+}
+
+#[test]
+fn test_relative_to() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Deep/Root/Some File")], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "--dry-run",
+        "--relative-to",
+        "Deep/Root",
+        "snake",
+        "Deep/Root/Some File",
+    ]);
+
+    cmd.assert()
+        .success()
+        .stdout("'Some File' -> 'some_file'\n");
+
+    Ok(())
+}
+
+#[test]
+fn test_filter() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some File")], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .write_stdin("Some Dir/Some File.txt\r\nSome File\n")
+        .args(["--filter", "snake"]);
+
+    cmd.assert()
+        .success()
+        .stdout("Some Dir/some_file.txt\nsome_file\n");
+
+    // nothing is renamed
+    assert!(dir.path().join("Some File").exists());
+
+    let mut cmd = ccpath()?;
+    cmd.write_stdin("Some File\0Other\nFile\0")
+        .args(["--filter", "--null", "kebab"]);
+
+    // a newline is part of a path rather than separating paths
+    cmd.assert().success().stdout("some-file\0other\nfile\0");
+
+    Ok(())
+}
+
+#[test]
+fn test_map_file() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(
+        &[
+            Path::new("Some File"),
+            Path::new("A, B"),
+            Path::new("Other"),
+        ],
+        &[],
+    )?;
+
+    fs::write(
+        dir.path().join("renames.csv"),
+        "Some File,renamed\n\"A, B\",Sub Dir/ab\n",
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--map-file", "renames.csv"]);
+    cmd.assert().success();
+
+    assert!(dir.path().join("renamed").exists());
+    assert!(dir.path().join("Sub Dir/ab").exists());
+    assert!(!dir.path().join("Some File").exists());
+
+    // mapped renames are checked for collisions like converted ones
+    fs::write(dir.path().join("renames.tsv"), "renamed\tx\nOther\tx\n")?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--map-file", "renames.tsv"]);
+    cmd.assert().code(3);
+
+    assert!(dir.path().join("renamed").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_into_flag() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some File"), Path::new("Other File")], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["Some File", "--into", "kebab", "Other File"]);
+    cmd.assert().success();
+
+    assert!(dir.path().join("some-file").exists());
+    assert!(dir.path().join("other-file").exists());
+
+    // every positional argument is a path, even one naming a convention
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--into=snake", "snake", "some-file"]);
+    cmd.assert().code(2);
+
+    assert!(dir.path().join("some-file").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_dry_run_impact() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some Dir/Some File")], &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path()).args([
+        "--dry-run",
+        "--flatten",
+        "Flat Dir",
+        "snake",
+        "Some Dir/Some File",
+    ]);
+
+    cmd.assert().success().stderr(predicate::str::contains(
+        "1 file(s) and 0 directory(ies) to rename, 1 directory(ies) to create, 1 to empty",
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_partial_failure() -> Result<(), Box<dyn std::error::Error>> {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let invalid = OsStr::from_bytes(b"Bad \xff Dir");
+    let source = Path::new("Some Dir").join(invalid).join("Some File");
+    let dir = setup(&[&source], &[])?;
+
+    for (action, code) in [("error", 1), ("skip", 0)] {
+        let mut cmd = ccpath()?;
+        cmd.current_dir(dir.path())
+            .args(["--full-path", "--partial-failure", action, "snake"])
+            .arg(&source);
+        cmd.assert().code(code);
+
+        assert!(dir.path().join(&source).exists());
+    }
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--full-path", "--partial-failure", "keep", "snake"])
+        .arg(&source);
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("keeping it as it is"));
+
+    let target = Path::new("some_dir").join(invalid).join("some_file");
+    assert!(dir.path().join(target).exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_component_pattern() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(
+        &[
+            Path::new("Some Dir/DeliberateStyle/Some File"),
+            Path::new("Some Dir/DeliberateStyle/KeepMe"),
+        ],
+        &[],
+    )?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--recursive", "--component-pattern", " ", "snake", "Some Dir"]);
+    cmd.assert().success();
+
+    assert!(dir.path().join("some_dir/DeliberateStyle/some_file").exists());
+    assert!(dir.path().join("some_dir/DeliberateStyle/KeepMe").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_undo() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = setup(&[Path::new("Some Dir/Some File")], &[])?;
+    let data = tempfile::tempdir()?;
+
+    let run = || -> Result<Command, Box<dyn std::error::Error>> {
+        let mut cmd = ccpath()?;
+        cmd.current_dir(dir.path())
+            .env("XDG_DATA_HOME", data.path());
+        Ok(cmd)
+    };
+
+    run()?
+        .args(["--record", "--recursive", "snake", "Some Dir"])
+        .assert()
+        .success();
+    assert!(dir.path().join("some_dir/some_file").exists());
+
+    // the journal is written alongside the log, holding only the run and its renames
+    run()?
+        .args([
+            "--record",
+            "--log-file",
+            "run.log",
+            "--verbose",
+            "kebab",
+            "some_dir",
+        ])
+        .assert()
+        .success();
+    assert!(dir.path().join("some-dir").exists());
+    assert!(fs::read_to_string(dir.path().join("run.log"))?.contains("level=info"));
+
+    let journal = fs::read_dir(data.path().join("ccpath/journal"))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .max()
+        .ok_or("no journal was written")?;
+    let recorded = fs::read_to_string(journal)?;
+    assert!(recorded.contains("event=rename"));
+    assert!(!recorded.contains("level="));
+
+    // each undo reverts the run before the one undone last
+    run()?.arg("undo").assert().success();
+    assert!(dir.path().join("some_dir/some_file").exists());
+
+    run()?.arg("undo").assert().success();
+    assert!(dir.path().join("Some Dir/Some File").exists());
+
+    run()?
+        .arg("undo")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no run left to undo"));
+
+    Ok(())
+}
+
+#[test]
+fn test_interactive() -> Result<(), Box<dyn std::error::Error>> {
+    let names = ["File A", "File B", "File C", "File D"];
+    let dir = setup(&names.map(Path::new), &[])?;
+
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["--interactive", "snake"])
+        .args(names)
+        .write_stdin("y\nn\na\n");
+    cmd.assert().success().stderr(predicate::str::contains(
+        "rename 'File A' to 'file_a'? [y/n/a/q]",
+    ));
+
+    assert!(dir.path().join("file_a").exists());
+    assert!(dir.path().join("File B").exists());
+    assert!(dir.path().join("file_c").exists());
+    assert!(dir.path().join("file_d").exists());
+
+    // quitting skips the rename asked about and every one after it
+    let mut cmd = ccpath()?;
+    cmd.current_dir(dir.path())
+        .args(["-i", "kebab", "file_a", "file_c", "file_d"])
+        .write_stdin("y\nq\n");
+    cmd.assert().success();
+
+    assert!(dir.path().join("file-a").exists());
+    assert!(dir.path().join("file_c").exists());
+    assert!(dir.path().join("file_d").exists());
+
+    Ok(())
+}